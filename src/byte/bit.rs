@@ -7,6 +7,8 @@ pub struct BitStream<'a> {
   data: &'a [u8],
   offset: usize,
   bit_offset: usize,
+  /// Whether emulation prevention bytes are skipped while reading
+  emulation_prevention: bool,
 }
 
 impl<'a> std::fmt::Debug for BitStream<'a> {
@@ -25,6 +27,16 @@ impl<'a> BitStream<'a> {
       data,
       offset: 0,
       bit_offset: 0,
+      emulation_prevention: true,
+    }
+  }
+
+  /// Reads `data` that has no emulation prevention bytes left, a `0x000003` sequence of an
+  /// RBSP is read as is
+  pub fn rbsp(data: &'a [u8]) -> Self {
+    Self {
+      emulation_prevention: false,
+      ..Self::new(data)
     }
   }
 }
@@ -32,7 +44,7 @@ impl<'a> BitStream<'a> {
 impl<'a> Deref for BitStream<'a> {
   type Target = [u8];
   fn deref(&self) -> &Self::Target {
-    &self.data[self.offset..]
+    self.data.get(self.offset..).unwrap_or_default()
   }
 }
 
@@ -52,6 +64,19 @@ impl<'a> BitStream<'a> {
     self.bit_offset < 8 || self.offset < self.data.len()
   }
 
+  /// 7.2 more_rbsp_data(), true if there is more data before the rbsp_stop_one_bit
+  pub fn more_rbsp_data(&self) -> bool {
+    let data = &self.data[std::cmp::min(self.offset, self.data.len())..];
+    let Some(last) = data.iter().rposition(|byte| *byte != 0) else {
+      return false;
+    };
+    if last > 0 {
+      return true;
+    }
+    let stop_bit = 7 - data[0].trailing_zeros() as usize;
+    stop_bit > self.bit_offset
+  }
+
   pub fn skip_trailing_bits(&mut self) -> &mut Self {
     if self.bit_offset != 0 {
       self.bit_offset = 0;
@@ -60,6 +85,11 @@ impl<'a> BitStream<'a> {
     self
   }
 
+  pub fn skip_bytes(&mut self, n: usize) -> &mut Self {
+    self.offset = std::cmp::min(self.offset + n, self.data.len());
+    self
+  }
+
   pub fn byte(&mut self) -> u8 {
     self.bits_into(8)
   }
@@ -100,8 +130,9 @@ impl<'a> BitStream<'a> {
   ) -> T {
     let mut bits = BitIter::new(self.deref(), self.bit_offset);
     let k = bits.position(|bit| bit == 1).unwrap_or_default();
+    // Codes longer than `T` only appear in corrupt streams, their extra bits are dropped
     let x = bits
-      .take(k)
+      .take(k.min(std::mem::size_of::<T>() * 8 - 1))
       .fold(T::from(1), |x, bit| x << 1 | T::from(bit));
     self.consume_bits(k + k + 1);
     x - T::from(1)
@@ -141,29 +172,35 @@ impl<'a> BitStream<'a> {
     self.offset += read_bits >> 3;
   }
 
+  /// Bytes past the end of the data are read as zeros
   fn current_byte(&mut self) -> u8 {
-    if self.offset > 1 && self.data[self.offset - 2..=self.offset] == EPB {
+    if self.emulation_prevention
+      && self.offset > 1
+      && self.data.get(self.offset - 2..=self.offset) == Some(&EPB)
+    {
       self.offset += 1;
     }
-    self.data[self.offset]
+    self.data.get(self.offset).copied().unwrap_or_default()
   }
 
   fn slice(&mut self, bit_size: usize) -> Box<[u8]> {
     let size = (bit_size + 7) >> 3;
     let size = self.offset + size + if bit_size > 8 - self.bit_offset { 1 } else { 0 };
-    if self.offset > 1 {
+    if self.emulation_prevention && self.offset > 1 {
       let mut skipped = 0;
       return (self.offset..size)
         .map(|i| {
-          if self.data[i - 2..=i] == EPB {
+          if self.data.get(i - 2..=i) == Some(&EPB) {
             self.offset += 1;
             skipped += 1;
           }
-          self.data[i + skipped]
+          self.data.get(i + skipped).copied().unwrap_or_default()
         })
         .collect();
     }
-    (&self.data[self.offset..size]).into()
+    (self.offset..size)
+      .map(|i| self.data.get(i).copied().unwrap_or_default())
+      .collect()
   }
 }
 
@@ -208,6 +245,9 @@ impl<'a> Iterator for BitIter<'a> {
 }
 
 pub fn pack_bits<T: LossyFrom<u128>>(data: &[u8], bit_offset: usize, bit_size: usize) -> T {
+  if bit_size == 0 {
+    return T::lossy_from(0);
+  }
   let mut value = u128::from_be_bytes(padded_array_from_slice(data));
   value <<= bit_offset;
   value >>= 128 - bit_size;
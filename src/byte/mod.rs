@@ -30,6 +30,20 @@ pub trait TryFromSlice: Sized {
   fn try_from_slice(slice: &[u8]) -> Result<Self, TryFromSliceError>;
 }
 
+impl TryFromSlice for u64 {
+  const SIZE: usize = 8;
+  fn try_from_slice(slice: &[u8]) -> Result<Self, TryFromSliceError> {
+    Ok(u64::from_be_bytes(slice[..Self::SIZE].try_into()?))
+  }
+}
+
+impl TryFromSlice for i64 {
+  const SIZE: usize = 8;
+  fn try_from_slice(slice: &[u8]) -> Result<Self, TryFromSliceError> {
+    Ok(i64::from_be_bytes(slice[..Self::SIZE].try_into()?))
+  }
+}
+
 impl TryFromSlice for u32 {
   const SIZE: usize = 4;
   fn try_from_slice(slice: &[u8]) -> Result<Self, TryFromSliceError> {
@@ -13,6 +13,13 @@ pub struct CLIArgs {
   pub width: i32,
   pub end: SeekPosition,
   pub step: SeekPosition,
+  pub extract: String,
+  pub insert_aud: bool,
+  pub extract_audio: String,
+  pub extract_wav: String,
+  pub extract_cover_art: String,
+  pub extract_subtitles: String,
+  pub track_id: Option<u32>,
 }
 
 impl CLIArgs {
@@ -33,6 +40,16 @@ impl CLIArgs {
         SeekPosition::TimeBase(0) => SeekPosition::TimeBase(1),
         n => n,
       },
+      extract: Self::find_arg(&args, "-x"),
+      insert_aud: Self::find_flag(&args, "-aud"),
+      extract_audio: Self::find_arg(&args, "-xa"),
+      extract_wav: Self::find_arg(&args, "-xw"),
+      extract_cover_art: Self::find_arg(&args, "-xc"),
+      extract_subtitles: Self::find_arg(&args, "-xs"),
+      track_id: match Self::find_arg(&args, "-t") {
+        0 => None,
+        id => Some(id),
+      },
     })
   }
 
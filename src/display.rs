@@ -43,6 +43,43 @@ impl Display for Duration {
   }
 }
 
+/// Seconds since midnight, January 1, 1904 UTC, the epoch of QuickTime and ISO BMFF timestamps
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Date(pub u64);
+
+impl Date {
+  /// Days between the 1904 epoch and the Unix epoch
+  const UNIX_EPOCH_DAYS: i64 = 24_107;
+}
+
+impl Display for Date {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if self.0 == 0 {
+      return write!(f, "Unknown");
+    }
+    let seconds = self.0 % 86_400;
+    // Converts days since the Unix epoch into a proleptic Gregorian calendar date
+    let z = (self.0 / 86_400) as i64 - Self::UNIX_EPOCH_DAYS + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+      (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    write!(
+      f,
+      "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+      seconds / 3_600,
+      (seconds % 3_600) / 60,
+      seconds % 60
+    )
+  }
+}
+
 impl Deref for Duration {
   type Target = time::Duration;
   fn deref(&self) -> &Self::Target {
@@ -38,8 +38,48 @@ fn main() {
   LOG_FILE_PTR.store(&mut log_file as *mut _, Ordering::SeqCst);
 
   let start_time = Instant::now();
+  if !args.extract.is_empty() {
+    let samples = unwrap!(
+      Ok Video::extract_annex_b(&args.filepath, &args.extract, args.insert_aud, args.track_id),
+      Err "Could not extract video track"
+    );
+    log!(ok@"Extracted {samples} samples to {} in {:?}", args.extract, Instant::now() - start_time);
+    return;
+  }
+  if !args.extract_audio.is_empty() {
+    let frames = unwrap!(
+      Ok Video::extract_adts(&args.filepath, &args.extract_audio, args.track_id, args.seek_position, args.end),
+      Err "Could not extract audio track"
+    );
+    log!(ok@"Extracted {frames} frames to {} in {:?}", args.extract_audio, Instant::now() - start_time);
+    return;
+  }
+  if !args.extract_wav.is_empty() {
+    let bytes = unwrap!(
+      Ok Video::extract_wav(&args.filepath, &args.extract_wav, args.track_id),
+      Err "Could not extract audio track"
+    );
+    log!(ok@"Extracted {bytes} bytes of audio to {} in {:?}", args.extract_wav, Instant::now() - start_time);
+    return;
+  }
+  if !args.extract_cover_art.is_empty() {
+    let (format, bytes) = unwrap!(
+      Ok Video::extract_cover_art(&args.filepath, &args.extract_cover_art),
+      Err "Could not extract cover art"
+    );
+    log!(ok@"Extracted {bytes} bytes of {format} cover art to {} in {:?}", args.extract_cover_art, Instant::now() - start_time);
+    return;
+  }
+  if !args.extract_subtitles.is_empty() {
+    let cues = unwrap!(
+      Ok Video::extract_subtitles(&args.filepath, &args.extract_subtitles, args.track_id),
+      Err "Could not extract subtitles"
+    );
+    log!(ok@"Extracted {cues} subtitle cues to {} in {:?}", args.extract_subtitles, Instant::now() - start_time);
+    return;
+  }
   let video = unwrap!(
-    Ok Video::open(&args.filepath),
+    Ok Video::open(&args.filepath, args.seek_position, args.track_id),
     Err "Could not open video"
   );
   let end_time = Instant::now();
@@ -0,0 +1,48 @@
+use super::*;
+
+/// MSB first reader of a raw_data_block, unlike `BitStream` it doesn't skip emulation
+/// prevention bytes and reports reads past the end of the block as errors
+pub struct BitReader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+
+impl<'a> BitReader<'a> {
+  pub fn new(data: &'a [u8]) -> Self {
+    Self { data, position: 0 }
+  }
+
+  pub fn bits_left(&self) -> usize {
+    self.data.len() * 8 - self.position
+  }
+
+  /// Reads up to 32 bits
+  pub fn bits(&mut self, n: u8) -> AacResult<u32> {
+    let end = self.position + n as usize;
+    if end > self.data.len() * 8 {
+      return Err(AacError::EndOfData);
+    }
+    let value = self.data[self.position / 8..end.div_ceil(8)]
+      .iter()
+      .fold(0u64, |value, &byte| value << 8 | byte as u64);
+    let value = value >> ((8 - end % 8) % 8) & ((1u64 << n) - 1);
+    self.position = end;
+    Ok(value as u32)
+  }
+
+  pub fn bit(&mut self) -> AacResult<bool> {
+    Ok(self.bits(1)? != 0)
+  }
+
+  pub fn skip(&mut self, n: usize) -> AacResult {
+    if n > self.bits_left() {
+      return Err(AacError::EndOfData);
+    }
+    self.position += n;
+    Ok(())
+  }
+
+  pub fn byte_align(&mut self) {
+    self.position = ((self.position + 7) & !7).min(self.data.len() * 8);
+  }
+}
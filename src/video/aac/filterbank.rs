@@ -0,0 +1,231 @@
+use super::ics::*;
+use std::f64::consts::PI;
+
+/// Rising halves of the long and short windows of each window shape
+struct Windows {
+  long: [Box<[f32]>; 2],
+  short: [Box<[f32]>; 2],
+}
+
+impl Windows {
+  fn new() -> Self {
+    Self {
+      long: [Self::sine(2048), Self::kaiser_bessel_derived(2048, 4.)],
+      short: [Self::sine(256), Self::kaiser_bessel_derived(256, 6.)],
+    }
+  }
+
+  /// 4.6.11.3.2 Sine window of length `n`
+  fn sine(n: usize) -> Box<[f32]> {
+    (0..n / 2)
+      .map(|i| (PI / n as f64 * (i as f64 + 0.5)).sin() as f32)
+      .collect()
+  }
+
+  /// 4.6.11.3.2 Kaiser-Bessel derived window of length `n`
+  fn kaiser_bessel_derived(n: usize, alpha: f64) -> Box<[f32]> {
+    let kaiser = (0..=n / 2)
+      .map(|i| {
+        let x = (i as f64 - n as f64 / 4.) / (n as f64 / 4.);
+        Self::bessel_i0(PI * alpha * (1. - x * x).sqrt())
+      })
+      .collect::<Vec<_>>();
+    let total = kaiser.iter().sum::<f64>();
+    let mut sum = 0.;
+    kaiser[..n / 2]
+      .iter()
+      .map(|w| {
+        sum += w;
+        (sum / total).sqrt() as f32
+      })
+      .collect()
+  }
+
+  /// Zeroth order modified Bessel function of the first kind
+  fn bessel_i0(x: f64) -> f64 {
+    let mut term = 1.;
+    let mut sum = 1.;
+    for k in 1..50 {
+      term *= x / (2. * k as f64);
+      sum += term * term;
+    }
+    sum
+  }
+}
+
+/// Inverse MDCT of `n / 2` coefficients into `n` samples computed through a DCT-IV, which in
+/// turn uses an `n / 4` point complex FFT
+struct Imdct {
+  n: usize,
+  /// exp(-iπ(4k + 1) / 2n) pre-twiddle of the DCT-IV
+  pre_twiddle: Box<[(f32, f32)]>,
+  /// exp(-iπk / (n / 2)) post-twiddle of the DCT-IV
+  post_twiddle: Box<[(f32, f32)]>,
+  /// exp(-2πik / (n / 4)) FFT twiddle factors
+  fft_twiddle: Box<[(f32, f32)]>,
+  /// Bit reversal permutation of the FFT input
+  bit_reverse: Box<[usize]>,
+}
+
+impl Imdct {
+  fn new(n: usize) -> Self {
+    let m = n / 2;
+    let fft_size = m / 2;
+    let bits = fft_size.trailing_zeros();
+    let exp = |angle: f64| (angle.cos() as f32, angle.sin() as f32);
+    Self {
+      n,
+      pre_twiddle: (0..fft_size)
+        .map(|k| exp(-PI * (4 * k + 1) as f64 / (4 * m) as f64))
+        .collect(),
+      post_twiddle: (0..fft_size)
+        .map(|k| exp(-PI * k as f64 / m as f64))
+        .collect(),
+      fft_twiddle: (0..fft_size / 2)
+        .map(|k| exp(-2. * PI * k as f64 / fft_size as f64))
+        .collect(),
+      bit_reverse: (0..fft_size)
+        .map(|i| i.reverse_bits() >> (usize::BITS - bits))
+        .collect(),
+    }
+  }
+
+  /// Writes the `n` samples of `spectrum` scaled by 2 / n to `output`
+  fn transform(&self, spectrum: &[f32], output: &mut [f32]) {
+    let m = self.n / 2;
+    let fft_size = m / 2;
+    let mut z = vec![(0f32, 0f32); fft_size];
+    for k in 0..fft_size {
+      let (re, im) = (spectrum[2 * k], spectrum[m - 1 - 2 * k]);
+      let (c, s) = self.pre_twiddle[k];
+      z[self.bit_reverse[k]] = (re * c - im * s, re * s + im * c);
+    }
+    self.fft(&mut z);
+
+    // DCT-IV of the spectrum
+    let scale = 2. / self.n as f32;
+    let mut u = vec![0f32; m];
+    for k in 0..fft_size {
+      let (re, im) = z[k];
+      let (c, s) = self.post_twiddle[k];
+      u[2 * k] = (re * c - im * s) * scale;
+      u[m - 1 - 2 * k] = -(re * s + im * c) * scale;
+    }
+
+    // The IMDCT output is the DCT-IV output extended through its symmetries
+    let half = m / 2;
+    output[..half].copy_from_slice(&u[half..]);
+    for i in half..m + half {
+      output[i] = -u[m + half - 1 - i];
+    }
+    for i in m + half..self.n {
+      output[i] = -u[i - m - half];
+    }
+  }
+
+  /// In place radix-2 FFT of bit reversed input
+  fn fft(&self, z: &mut [(f32, f32)]) {
+    let size = z.len();
+    let mut length = 2;
+    while length <= size {
+      let stride = size / length;
+      for start in (0..size).step_by(length) {
+        for k in 0..length / 2 {
+          let (c, s) = self.fft_twiddle[k * stride];
+          let (re, im) = z[start + k + length / 2];
+          let odd = (re * c - im * s, re * s + im * c);
+          let even = z[start + k];
+          z[start + k] = (even.0 + odd.0, even.1 + odd.1);
+          z[start + k + length / 2] = (even.0 - odd.0, even.1 - odd.1);
+        }
+      }
+      length *= 2;
+    }
+  }
+}
+
+/// 4.6.11 Filterbank, turns the spectrum of a channel into 1024 time domain samples
+pub struct FilterBank {
+  windows: Windows,
+  long: Imdct,
+  short: Imdct,
+}
+
+impl FilterBank {
+  pub fn new() -> Self {
+    Self {
+      windows: Windows::new(),
+      long: Imdct::new(2048),
+      short: Imdct::new(256),
+    }
+  }
+
+  /// Windows the inverse transform of `spectrum` and overlap-adds it to `overlap`, the first
+  /// 1024 samples are written to `output` and the rest kept in `overlap` for the next frame
+  pub fn synthesize(
+    &self,
+    spectrum: &[f32; 1024],
+    info: &IcsInfo,
+    previous_shape: bool,
+    overlap: &mut [f32; 1024],
+    output: &mut [f32],
+  ) {
+    let shape = info.window_shape as usize;
+    let previous_shape = previous_shape as usize;
+    // The rising half uses the shape of the previous frame and the falling half the current one
+    let long_previous = &self.windows.long[previous_shape];
+    let long_current = &self.windows.long[shape];
+    let short_previous = &self.windows.short[previous_shape];
+    let short_current = &self.windows.short[shape];
+    let mut samples = [0f32; 2048];
+
+    match info.window_sequence {
+      WindowSequence::EightShort => {
+        let mut window = [0f32; 256];
+        for w in 0..MAX_WINDOWS {
+          self
+            .short
+            .transform(&spectrum[w * 128..(w + 1) * 128], &mut window);
+          let rising = if w == 0 {
+            short_previous
+          } else {
+            short_current
+          };
+          let offset = 448 + w * 128;
+          for i in 0..128 {
+            samples[offset + i] += window[i] * rising[i];
+            samples[offset + 128 + i] += window[128 + i] * short_current[127 - i];
+          }
+        }
+      }
+      sequence => {
+        self.long.transform(spectrum, &mut samples);
+        if sequence == WindowSequence::LongStop {
+          samples[..448].fill(0.);
+          for i in 0..128 {
+            samples[448 + i] *= short_previous[i];
+          }
+        } else {
+          for i in 0..1024 {
+            samples[i] *= long_previous[i];
+          }
+        }
+        if sequence == WindowSequence::LongStart {
+          for i in 0..128 {
+            samples[1472 + i] *= short_current[127 - i];
+          }
+          samples[1600..].fill(0.);
+        } else {
+          for i in 0..1024 {
+            samples[1024 + i] *= long_current[1023 - i];
+          }
+        }
+      }
+    }
+
+    for i in 0..1024 {
+      output[i] = samples[i] + overlap[i];
+    }
+    overlap.copy_from_slice(&samples[1024..]);
+  }
+}
@@ -0,0 +1,119 @@
+use super::table::*;
+use super::*;
+use std::sync::OnceLock;
+
+/// Codeword index of a leaf in `Codebook::nodes`
+const LEAF: u16 = 0x8000;
+
+/// Binary decoding tree of a Huffman codebook
+pub struct Codebook {
+  /// Children of every node for bit 0 and 1, either the index of the next node or the
+  /// codeword index with the `LEAF` bit set. The root is node 0 so 0 marks a missing child
+  nodes: Box<[[u16; 2]]>,
+}
+
+impl Codebook {
+  fn new(codewords: &[(u32, u8)]) -> Self {
+    let mut nodes = vec![[0; 2]];
+    for (index, &(codeword, length)) in codewords.iter().enumerate() {
+      let mut node = 0;
+      for i in (1..length).rev() {
+        let bit = (codeword >> i & 1) as usize;
+        if nodes[node][bit] == 0 {
+          nodes[node][bit] = nodes.len() as u16;
+          nodes.push([0; 2]);
+        }
+        node = nodes[node][bit] as usize;
+      }
+      nodes[node][(codeword & 1) as usize] = LEAF | index as u16;
+    }
+    Self {
+      nodes: nodes.into_boxed_slice(),
+    }
+  }
+
+  /// Reads a codeword returning its index in the codebook
+  pub fn decode(&self, bits: &mut BitReader) -> AacResult<usize> {
+    let mut node = 0;
+    loop {
+      match self.nodes[node][bits.bits(1)? as usize] {
+        0 => return Err(AacError::Invalid("Huffman codeword")),
+        child if child & LEAF != 0 => return Ok((child & !LEAF) as usize),
+        child => node = child as usize,
+      }
+    }
+  }
+
+  /// 4.6.2 Decodes a scalefactor difference in the -60..=60 range
+  pub fn scalefactor(bits: &mut BitReader) -> AacResult<i32> {
+    static CODEBOOK: OnceLock<Codebook> = OnceLock::new();
+    let codebook = CODEBOOK.get_or_init(|| Self::new(&SCALEFACTOR_CODEBOOK));
+    Ok(codebook.decode(bits)? as i32 - 60)
+  }
+
+  /// 4.6.3 Decodes the next 4 or 2 quantized coefficients of spectrum codebook `codebook` into
+  /// `values`, returns how many were decoded
+  pub fn spectrum(codebook: u8, bits: &mut BitReader, values: &mut [i32; 4]) -> AacResult<usize> {
+    static CODEBOOKS: OnceLock<[Codebook; 11]> = OnceLock::new();
+    let codebooks = CODEBOOKS.get_or_init(|| {
+      [
+        Self::new(&SPECTRUM_CODEBOOK_1),
+        Self::new(&SPECTRUM_CODEBOOK_2),
+        Self::new(&SPECTRUM_CODEBOOK_3),
+        Self::new(&SPECTRUM_CODEBOOK_4),
+        Self::new(&SPECTRUM_CODEBOOK_5),
+        Self::new(&SPECTRUM_CODEBOOK_6),
+        Self::new(&SPECTRUM_CODEBOOK_7),
+        Self::new(&SPECTRUM_CODEBOOK_8),
+        Self::new(&SPECTRUM_CODEBOOK_9),
+        Self::new(&SPECTRUM_CODEBOOK_10),
+        Self::new(&SPECTRUM_CODEBOOK_11),
+      ]
+    });
+    let (dimension, unsigned, modulo) = match codebook {
+      1 | 2 => (4, false, 3),
+      3 | 4 => (4, true, 3),
+      5 | 6 => (2, false, 9),
+      7 | 8 => (2, true, 8),
+      9 | 10 => (2, true, 13),
+      11 => (2, true, 17),
+      _ => return Err(AacError::Invalid("spectrum codebook")),
+    };
+    let mut index = codebooks[codebook as usize - 1].decode(bits)?;
+    for value in values[..dimension].iter_mut().rev() {
+      *value = (index % modulo) as i32;
+      index /= modulo;
+    }
+    if !unsigned {
+      for value in &mut values[..dimension] {
+        *value -= modulo as i32 / 2;
+      }
+      return Ok(dimension);
+    }
+    for value in &mut values[..dimension] {
+      if *value != 0 && bits.bit()? {
+        *value = -*value;
+      }
+    }
+    if codebook == 11 {
+      for value in &mut values[..dimension] {
+        if value.abs() == 16 {
+          *value = value.signum() * Self::escape(bits)?;
+        }
+      }
+    }
+    Ok(dimension)
+  }
+
+  /// Escape sequence of codebook 11, an N bit prefix of ones followed by an N + 4 bit word
+  fn escape(bits: &mut BitReader) -> AacResult<i32> {
+    let mut n = 4;
+    while bits.bit()? {
+      n += 1;
+      if n > 12 {
+        return Err(AacError::Invalid("escape sequence"));
+      }
+    }
+    Ok((1 << n) + bits.bits(n)? as i32)
+  }
+}
@@ -0,0 +1,347 @@
+use super::table::*;
+use super::*;
+
+/// Maximum number of scalefactor bands of a window, reached by long windows at 32 kHz
+pub const MAX_SFB: usize = 51;
+pub const MAX_WINDOWS: usize = 8;
+
+pub const ZERO_HCB: u8 = 0;
+pub const ESC_HCB: u8 = 11;
+pub const NOISE_HCB: u8 = 13;
+/// Intensity stereo out of phase
+pub const INTENSITY_HCB2: u8 = 14;
+/// Intensity stereo in phase
+pub const INTENSITY_HCB: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSequence {
+  OnlyLong,
+  LongStart,
+  EightShort,
+  LongStop,
+}
+
+/// 4.6.2.3 ics_info
+#[derive(Debug, Clone, Copy)]
+pub struct IcsInfo {
+  pub window_sequence: WindowSequence,
+  /// Kaiser-Bessel derived window if set, sine window otherwise
+  pub window_shape: bool,
+  pub max_sfb: usize,
+  pub num_window_groups: usize,
+  pub window_group_length: [usize; MAX_WINDOWS],
+  pub swb_offset: &'static [u16],
+  pub sampling_frequency_index: usize,
+}
+
+impl IcsInfo {
+  pub fn decode(bits: &mut BitReader, sampling_frequency_index: usize) -> AacResult<Self> {
+    if bits.bit()? {
+      return Err(AacError::Invalid("ics_reserved_bit"));
+    }
+    let window_sequence = match bits.bits(2)? {
+      0 => WindowSequence::OnlyLong,
+      1 => WindowSequence::LongStart,
+      2 => WindowSequence::EightShort,
+      _ => WindowSequence::LongStop,
+    };
+    let window_shape = bits.bit()?;
+    let (long, short) = SWB_OFFSETS[sampling_frequency_index];
+    let mut info = Self {
+      window_sequence,
+      window_shape,
+      max_sfb: 0,
+      num_window_groups: 1,
+      window_group_length: [1, 0, 0, 0, 0, 0, 0, 0],
+      swb_offset: long,
+      sampling_frequency_index,
+    };
+    if window_sequence == WindowSequence::EightShort {
+      info.max_sfb = bits.bits(4)? as usize;
+      info.swb_offset = short;
+      let scale_factor_grouping = bits.bits(7)?;
+      for i in (0..7).rev() {
+        if scale_factor_grouping >> i & 1 != 0 {
+          info.window_group_length[info.num_window_groups - 1] += 1;
+        } else {
+          info.window_group_length[info.num_window_groups] = 1;
+          info.num_window_groups += 1;
+        }
+      }
+    } else {
+      info.max_sfb = bits.bits(6)? as usize;
+      if bits.bit()? {
+        return Err(AacError::Unsupported("prediction"));
+      }
+    }
+    if info.max_sfb > info.num_swb() {
+      return Err(AacError::Invalid("max_sfb"));
+    }
+    Ok(info)
+  }
+
+  pub fn is_eight_short(&self) -> bool {
+    self.window_sequence == WindowSequence::EightShort
+  }
+
+  pub fn num_swb(&self) -> usize {
+    self.swb_offset.len() - 1
+  }
+
+  /// Coefficients of a single window
+  pub fn window_length(&self) -> usize {
+    if self.is_eight_short() {
+      128
+    } else {
+      1024
+    }
+  }
+
+  /// Calls `f` with the group index, the band index and the coefficient range of every
+  /// scalefactor band in every window up to `max_sfb`
+  pub fn for_each_band<F: FnMut(usize, usize, std::ops::Range<usize>)>(&self, mut f: F) {
+    let mut window = 0;
+    for group in 0..self.num_window_groups {
+      for _ in 0..self.window_group_length[group] {
+        let offset = window * self.window_length();
+        for sfb in 0..self.max_sfb {
+          let start = offset + self.swb_offset[sfb] as usize;
+          let end = offset + self.swb_offset[sfb + 1] as usize;
+          f(group, sfb, start..end);
+        }
+        window += 1;
+      }
+    }
+  }
+}
+
+/// 4.6.2.3 individual_channel_stream, decoded into its dequantized spectrum
+pub struct IndividualChannelStream {
+  pub info: IcsInfo,
+  /// Codebook of every scalefactor band in every window group
+  pub sfb_cb: [[u8; MAX_SFB]; MAX_WINDOWS],
+  /// Scalefactor, noise energy or intensity stereo position of every scalefactor band
+  pub scalefactors: [[i32; MAX_SFB]; MAX_WINDOWS],
+  pub tns: Option<TnsData>,
+  /// Dequantized coefficients, short windows are stored one after the other
+  pub spectrum: Box<[f32; 1024]>,
+}
+
+impl IndividualChannelStream {
+  pub fn decode(
+    bits: &mut BitReader,
+    common_info: Option<IcsInfo>,
+    sampling_frequency_index: usize,
+    random_state: &mut u32,
+  ) -> AacResult<Self> {
+    let global_gain = bits.bits(8)? as i32;
+    let info = match common_info {
+      Some(info) => info,
+      None => IcsInfo::decode(bits, sampling_frequency_index)?,
+    };
+    let mut ics = Self {
+      info,
+      sfb_cb: [[ZERO_HCB; MAX_SFB]; MAX_WINDOWS],
+      scalefactors: [[0; MAX_SFB]; MAX_WINDOWS],
+      tns: None,
+      spectrum: Box::new([0.; 1024]),
+    };
+    ics.decode_section_data(bits)?;
+    ics.decode_scale_factor_data(bits, global_gain)?;
+    let pulse = bits
+      .bit()?
+      .then(|| PulseData::decode(bits, &ics.info))
+      .transpose()?;
+    if bits.bit()? {
+      ics.tns = Some(TnsData::decode(bits, &ics.info)?);
+    }
+    if bits.bit()? {
+      return Err(AacError::Unsupported("gain control"));
+    }
+    let mut quantized = [0; 1024];
+    ics.decode_spectral_data(bits, &mut quantized)?;
+    if let Some(pulse) = pulse {
+      pulse.apply(&ics.info, &mut quantized);
+    }
+    ics.dequantize(&quantized, random_state);
+    Ok(ics)
+  }
+
+  /// 4.6.2.3 section_data, runs of scalefactor bands sharing a codebook
+  fn decode_section_data(&mut self, bits: &mut BitReader) -> AacResult {
+    let sect_bits = if self.info.is_eight_short() { 3 } else { 5 };
+    let sect_esc_val = (1 << sect_bits) - 1;
+    for group in 0..self.info.num_window_groups {
+      let mut sfb = 0;
+      while sfb < self.info.max_sfb {
+        let sect_cb = bits.bits(4)? as u8;
+        if sect_cb == 12 {
+          return Err(AacError::Invalid("section codebook"));
+        }
+        let mut sect_len = 0;
+        loop {
+          let sect_len_incr = bits.bits(sect_bits)? as usize;
+          sect_len += sect_len_incr;
+          if sect_len_incr != sect_esc_val {
+            break;
+          }
+        }
+        if sfb + sect_len > self.info.max_sfb {
+          return Err(AacError::Invalid("section length"));
+        }
+        self.sfb_cb[group][sfb..sfb + sect_len].fill(sect_cb);
+        sfb += sect_len;
+      }
+    }
+    Ok(())
+  }
+
+  /// 4.6.2.3 scale_factor_data, differentially coded scalefactors, noise energies and
+  /// intensity positions
+  fn decode_scale_factor_data(&mut self, bits: &mut BitReader, global_gain: i32) -> AacResult {
+    let mut scale_factor = global_gain;
+    let mut noise_energy = global_gain - 90;
+    let mut is_position = 0;
+    let mut noise_pcm_flag = true;
+    for group in 0..self.info.num_window_groups {
+      for sfb in 0..self.info.max_sfb {
+        self.scalefactors[group][sfb] = match self.sfb_cb[group][sfb] {
+          ZERO_HCB => 0,
+          INTENSITY_HCB | INTENSITY_HCB2 => {
+            is_position += Codebook::scalefactor(bits)?;
+            is_position
+          }
+          NOISE_HCB => {
+            noise_energy += if noise_pcm_flag {
+              noise_pcm_flag = false;
+              bits.bits(9)? as i32 - 256
+            } else {
+              Codebook::scalefactor(bits)?
+            };
+            noise_energy
+          }
+          _ => {
+            scale_factor += Codebook::scalefactor(bits)?;
+            if !(0..256).contains(&scale_factor) {
+              return Err(AacError::Invalid("scalefactor"));
+            }
+            scale_factor
+          }
+        };
+      }
+    }
+    Ok(())
+  }
+
+  /// 4.6.3 spectral_data, coefficients of grouped short windows are interleaved by window in
+  /// the bitstream and stored here one window after the other
+  fn decode_spectral_data(&self, bits: &mut BitReader, quantized: &mut [i32; 1024]) -> AacResult {
+    let window_length = self.info.window_length();
+    let mut group_window = 0;
+    for group in 0..self.info.num_window_groups {
+      let group_length = self.info.window_group_length[group];
+      for sfb in 0..self.info.max_sfb {
+        let codebook = self.sfb_cb[group][sfb];
+        if codebook == ZERO_HCB || codebook > ESC_HCB {
+          continue;
+        }
+        let start = self.info.swb_offset[sfb] as usize;
+        let end = self.info.swb_offset[sfb + 1] as usize;
+        for window in group_window..group_window + group_length {
+          let offset = window * window_length;
+          let mut k = start;
+          let mut values = [0; 4];
+          while k < end {
+            let n = Codebook::spectrum(codebook, bits, &mut values)?;
+            quantized[offset + k..offset + k + n].copy_from_slice(&values[..n]);
+            k += n;
+          }
+        }
+      }
+      group_window += group_length;
+    }
+    Ok(())
+  }
+
+  /// 4.6.1.3 Inverse quantization and scaling, bands of the noise codebook are filled with
+  /// random values of the signalled energy (perceptual noise substitution)
+  fn dequantize(&mut self, quantized: &[i32; 1024], random_state: &mut u32) {
+    let spectrum = &mut self.spectrum;
+    let sfb_cb = &self.sfb_cb;
+    let scalefactors = &self.scalefactors;
+    self.info.for_each_band(|group, sfb, band| {
+      let scalefactor = scalefactors[group][sfb];
+      match sfb_cb[group][sfb] {
+        ZERO_HCB | INTENSITY_HCB | INTENSITY_HCB2 => (),
+        NOISE_HCB => {
+          let mut energy = 0.;
+          for coefficient in &mut spectrum[band.clone()] {
+            *random_state = random_state.wrapping_mul(1664525).wrapping_add(1013904223);
+            *coefficient = *random_state as i32 as f32;
+            energy += *coefficient * *coefficient;
+          }
+          let scale = 2f32.powf(0.25 * scalefactor as f32) / energy.sqrt().max(f32::EPSILON);
+          for coefficient in &mut spectrum[band] {
+            *coefficient *= scale;
+          }
+        }
+        _ => {
+          let gain = 2f32.powf(0.25 * (scalefactor - 100) as f32);
+          for k in band {
+            let value = quantized[k];
+            spectrum[k] =
+              value.signum() as f32 * (value.unsigned_abs() as f32).powf(4. / 3.) * gain;
+          }
+        }
+      }
+    });
+  }
+
+  pub fn band_type(&self, group: usize, sfb: usize) -> u8 {
+    self.sfb_cb[group][sfb]
+  }
+
+  pub fn is_intensity(&self, group: usize, sfb: usize) -> bool {
+    matches!(self.sfb_cb[group][sfb], INTENSITY_HCB | INTENSITY_HCB2)
+  }
+}
+
+/// 4.6.5 pulse_data, small amplitudes added to coefficients of long windows
+struct PulseData {
+  pulse_start_sfb: usize,
+  pulses: Box<[(usize, i32)]>,
+}
+
+impl PulseData {
+  fn decode(bits: &mut BitReader, info: &IcsInfo) -> AacResult<Self> {
+    if info.is_eight_short() {
+      return Err(AacError::Invalid("pulse data in short windows"));
+    }
+    let number_pulse = bits.bits(2)? as usize + 1;
+    let pulse_start_sfb = bits.bits(6)? as usize;
+    if pulse_start_sfb >= info.num_swb() {
+      return Err(AacError::Invalid("pulse_start_sfb"));
+    }
+    Ok(Self {
+      pulse_start_sfb,
+      pulses: (0..number_pulse)
+        .map(|_| Ok((bits.bits(5)? as usize, bits.bits(4)? as i32)))
+        .collect::<AacResult<_>>()?,
+    })
+  }
+
+  fn apply(&self, info: &IcsInfo, quantized: &mut [i32; 1024]) {
+    let mut k = info.swb_offset[self.pulse_start_sfb] as usize;
+    for &(offset, amplitude) in &*self.pulses {
+      k += offset;
+      let Some(value) = quantized.get_mut(k) else {
+        return;
+      };
+      if *value > 0 {
+        *value += amplitude;
+      } else {
+        *value -= amplitude;
+      }
+    }
+  }
+}
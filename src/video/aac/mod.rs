@@ -0,0 +1,345 @@
+mod bits;
+mod filterbank;
+mod huffman;
+mod ics;
+mod table;
+mod tns;
+
+use super::atom::AudioSpecificConfig;
+use super::sample::{IndexedSample, SampleError};
+use bits::BitReader;
+use filterbank::FilterBank;
+use huffman::Codebook;
+use ics::*;
+use std::{fs::File, ops::Deref};
+use thiserror::Error;
+use tns::TnsData;
+
+#[derive(Debug, Error)]
+pub enum AacError {
+  #[error(transparent)]
+  Sample(#[from] SampleError),
+  #[error("Unexpected end of AAC raw_data_block")]
+  EndOfData,
+  #[error("Invalid AAC {0}")]
+  Invalid(&'static str),
+  #[error("Unsupported AAC feature {0}")]
+  Unsupported(&'static str),
+  #[error("AAC raw_data_block has more than {0} channels")]
+  Channels(usize),
+}
+
+pub type AacResult<T = ()> = Result<T, AacError>;
+
+/// Interleaved 16-bit PCM decoded from a single AAC access unit, channels are in the order of
+/// the syntactic elements (center, front left, front right, surrounds, LFE)
+#[derive(Debug)]
+pub struct AudioFrame {
+  pub channels: usize,
+  pub samples: Box<[i16]>,
+}
+
+/// Time domain samples of a channel that overlap the next frame
+struct ChannelState {
+  overlap: Box<[f32; 1024]>,
+  window_shape: bool,
+}
+
+/// ISO/IEC 14496-3 4 AAC-LC decoder. HE-AAC streams are decoded without their SBR and PS
+/// extensions, at the sampling rate of the core
+pub struct AacDecoder {
+  config: AudioSpecificConfig,
+  filter_bank: FilterBank,
+  channels: Vec<ChannelState>,
+  random_state: u32,
+}
+
+impl AacDecoder {
+  /// Samples per channel of every frame
+  pub const FRAME_LENGTH: usize = 1024;
+  /// Maximum number of channels of a raw_data_block, those of channel configuration 7
+  const MAX_CHANNELS: usize = 8;
+
+  pub fn new(config: &AudioSpecificConfig) -> AacResult<Self> {
+    if config.audio_object_type != 2 {
+      return Err(AacError::Unsupported(config.profile()));
+    }
+    if config.frame_length_flag {
+      return Err(AacError::Unsupported("960 sample frames"));
+    }
+    if config.sampling_frequency_index > 12 {
+      return Err(AacError::Unsupported("explicit sampling frequency"));
+    }
+    Ok(Self {
+      config: config.clone(),
+      filter_bank: FilterBank::new(),
+      channels: Vec::new(),
+      random_state: 0x1F2E3D4C,
+    })
+  }
+
+  pub fn sample_rate(&self) -> u32 {
+    self.config.sampling_frequency
+  }
+
+  /// Output channel of every WAVE_FORMAT_EXTENSIBLE channel, which are sorted by speaker
+  /// position, and the channel mask of the channel configuration. `None` for layouts given by
+  /// a program_config_element
+  pub fn wav_channel_layout(&self) -> Option<(&'static [usize], u32)> {
+    Some(match self.config.channel_configuration {
+      1 => (&[0], 0x4),
+      2 => (&[0, 1], 0x3),
+      3 => (&[1, 2, 0], 0x7),
+      4 => (&[1, 2, 0, 3], 0x107),
+      5 => (&[1, 2, 0, 3, 4], 0x37),
+      6 => (&[1, 2, 0, 5, 3, 4], 0x3F),
+      // Wide front channels are written as front left and right of center
+      7 => (&[1, 2, 0, 7, 5, 6, 3, 4], 0xFF),
+      _ => return None,
+    })
+  }
+
+  /// Decodes a raw_data_block into 1024 samples per channel
+  pub fn decode(&mut self, data: &[u8]) -> AacResult<AudioFrame> {
+    let mut bits = BitReader::new(data);
+    let mut spectra = Vec::new();
+    loop {
+      match bits.bits(3)? {
+        // single_channel_element and lfe_channel_element
+        0 | 3 => {
+          bits.bits(4)?;
+          spectra.push(self.decode_ics(&mut bits, None)?);
+        }
+        // channel_pair_element
+        1 => {
+          let (left, right) = self.decode_channel_pair(&mut bits)?;
+          spectra.push(left);
+          spectra.push(right);
+        }
+        2 => return Err(AacError::Unsupported("coupling channel element")),
+        // data_stream_element
+        4 => {
+          bits.bits(4)?;
+          let data_byte_align_flag = bits.bit()?;
+          let mut count = bits.bits(8)? as usize;
+          if count == 255 {
+            count += bits.bits(8)? as usize;
+          }
+          if data_byte_align_flag {
+            bits.byte_align();
+          }
+          bits.skip(count * 8)?;
+        }
+        5 => Self::skip_program_config_element(&mut bits)?,
+        // fill_element
+        6 => {
+          let mut count = bits.bits(4)? as usize;
+          if count == 15 {
+            count += bits.bits(8)? as usize - 1;
+          }
+          bits.skip(count * 8)?;
+        }
+        _ => break,
+      }
+      if spectra.len() > Self::MAX_CHANNELS {
+        return Err(AacError::Channels(Self::MAX_CHANNELS));
+      }
+    }
+
+    while self.channels.len() < spectra.len() {
+      self.channels.push(ChannelState {
+        overlap: Box::new([0.; 1024]),
+        window_shape: false,
+      });
+    }
+    let channels = spectra.len();
+    let mut samples = vec![0; channels * Self::FRAME_LENGTH];
+    let mut output = [0f32; Self::FRAME_LENGTH];
+    for (channel, mut ics) in spectra.into_iter().enumerate() {
+      if let Some(tns) = &ics.tns {
+        tns.apply(&ics.info, &mut ics.spectrum);
+      }
+      let state = &mut self.channels[channel];
+      self.filter_bank.synthesize(
+        &ics.spectrum,
+        &ics.info,
+        state.window_shape,
+        &mut state.overlap,
+        &mut output,
+      );
+      state.window_shape = ics.info.window_shape;
+      for (i, sample) in output.iter().enumerate() {
+        samples[i * channels + channel] =
+          sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+      }
+    }
+
+    Ok(AudioFrame {
+      channels,
+      samples: samples.into_boxed_slice(),
+    })
+  }
+
+  fn decode_ics(
+    &mut self,
+    bits: &mut BitReader,
+    common_info: Option<IcsInfo>,
+  ) -> AacResult<IndividualChannelStream> {
+    IndividualChannelStream::decode(
+      bits,
+      common_info,
+      self.config.sampling_frequency_index as usize,
+      &mut self.random_state,
+    )
+  }
+
+  /// 4.6.8.1 M/S stereo and 4.6.8.2 intensity stereo of a channel_pair_element
+  fn decode_channel_pair(
+    &mut self,
+    bits: &mut BitReader,
+  ) -> AacResult<(IndividualChannelStream, IndividualChannelStream)> {
+    bits.bits(4)?;
+    let common_window = bits.bit()?;
+    let mut ms_used = [[false; MAX_SFB]; MAX_WINDOWS];
+    let mut ms_mask_present = 0;
+    let mut common_info = None;
+    if common_window {
+      let info = IcsInfo::decode(bits, self.config.sampling_frequency_index as usize)?;
+      ms_mask_present = bits.bits(2)?;
+      match ms_mask_present {
+        0 => (),
+        1 => {
+          for group in &mut ms_used[..info.num_window_groups] {
+            for used in &mut group[..info.max_sfb] {
+              *used = bits.bit()?;
+            }
+          }
+        }
+        2 => ms_used = [[true; MAX_SFB]; MAX_WINDOWS],
+        _ => return Err(AacError::Invalid("ms_mask_present")),
+      }
+      common_info = Some(info);
+    }
+    let mut left = self.decode_ics(bits, common_info)?;
+    let mut right = self.decode_ics(bits, common_info)?;
+    let Some(info) = common_info else {
+      return Ok((left, right));
+    };
+
+    info.for_each_band(|group, sfb, band| {
+      let right_type = right.band_type(group, sfb);
+      if right.is_intensity(group, sfb) {
+        let scale = 0.5f32.powf(0.25 * right.scalefactors[group][sfb] as f32)
+          * Self::intensity_sign(right_type, ms_mask_present, ms_used[group][sfb]);
+        for k in band {
+          right.spectrum[k] = left.spectrum[k] * scale;
+        }
+      } else if ms_used[group][sfb]
+        && right_type != NOISE_HCB
+        && left.band_type(group, sfb) != NOISE_HCB
+      {
+        for k in band {
+          let (mid, side) = (left.spectrum[k], right.spectrum[k]);
+          left.spectrum[k] = mid + side;
+          right.spectrum[k] = mid - side;
+        }
+      }
+    });
+    Ok((left, right))
+  }
+
+  /// 4.6.8.2.3 `is_intensity * invert_intensity`, the sign is only inverted by `ms_used` when
+  /// it was sent per band, with `ms_mask_present` 2 every band is M/S but not inverted
+  fn intensity_sign(band_type: u8, ms_mask_present: u32, ms_used: bool) -> f32 {
+    let sign = if band_type == INTENSITY_HCB2 { -1. } else { 1. };
+    if ms_mask_present == 1 && ms_used {
+      -sign
+    } else {
+      sign
+    }
+  }
+
+  /// 4.4.1.1 program_config_element, the channel layout it describes isn't used
+  fn skip_program_config_element(bits: &mut BitReader) -> AacResult {
+    bits.bits(4 + 2 + 4)?;
+    let front = bits.bits(4)? as usize;
+    let side = bits.bits(4)? as usize;
+    let back = bits.bits(4)? as usize;
+    let lfe = bits.bits(2)? as usize;
+    let assoc_data = bits.bits(3)? as usize;
+    let valid_cc = bits.bits(4)? as usize;
+    for _ in 0..2 {
+      if bits.bit()? {
+        bits.bits(4)?;
+      }
+    }
+    if bits.bit()? {
+      bits.bits(3)?;
+    }
+    bits.skip((front + side + back) * 5 + (lfe + assoc_data) * 4 + valid_cc * 5)?;
+    bits.byte_align();
+    let comment_field_bytes = bits.bits(8)? as usize;
+    bits.skip(comment_field_bytes * 8)
+  }
+}
+
+/// Decodes the AAC samples of a track one access unit at a time
+pub struct AudioFrameIter<'a> {
+  reader: File,
+  samples: std::slice::Iter<'a, IndexedSample>,
+  decoder: AacDecoder,
+}
+
+impl<'a> AudioFrameIter<'a> {
+  pub fn new(
+    reader: File,
+    samples: &'a [IndexedSample],
+    config: &AudioSpecificConfig,
+  ) -> AacResult<Self> {
+    Ok(Self {
+      reader,
+      samples: samples.iter(),
+      decoder: AacDecoder::new(config)?,
+    })
+  }
+}
+
+impl<'a> Iterator for AudioFrameIter<'a> {
+  type Item = AacResult<AudioFrame>;
+  fn next(&mut self) -> Option<Self::Item> {
+    let sample = self.samples.next()?;
+    Some(
+      sample
+        .read(&mut self.reader)
+        .map_err(AacError::from)
+        .and_then(|sample| self.decoder.decode(&sample)),
+    )
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.samples.size_hint()
+  }
+}
+
+impl<'a> Deref for AudioFrameIter<'a> {
+  type Target = AacDecoder;
+  fn deref(&self) -> &Self::Target {
+    &self.decoder
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn intensity_sign() {
+    assert_eq!(AacDecoder::intensity_sign(INTENSITY_HCB, 0, false), 1.);
+    assert_eq!(AacDecoder::intensity_sign(INTENSITY_HCB2, 0, false), -1.);
+    assert_eq!(AacDecoder::intensity_sign(INTENSITY_HCB, 1, false), 1.);
+    assert_eq!(AacDecoder::intensity_sign(INTENSITY_HCB, 1, true), -1.);
+    assert_eq!(AacDecoder::intensity_sign(INTENSITY_HCB2, 1, true), 1.);
+    assert_eq!(AacDecoder::intensity_sign(INTENSITY_HCB, 2, true), 1.);
+    assert_eq!(AacDecoder::intensity_sign(INTENSITY_HCB2, 2, true), -1.);
+  }
+}
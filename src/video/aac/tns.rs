@@ -0,0 +1,141 @@
+use super::ics::*;
+use super::table::*;
+use super::*;
+use std::f32::consts::FRAC_PI_2;
+
+/// Maximum TNS filter order of AAC-LC long windows, short windows are limited to 7
+const TNS_MAX_ORDER: usize = 12;
+
+/// 4.6.9 Temporal noise shaping filter, `lpc` holds the direct form coefficients after the
+/// leading 1
+#[derive(Debug, Clone, Copy, Default)]
+struct TnsFilter {
+  length: usize,
+  order: usize,
+  direction: bool,
+  lpc: [f32; TNS_MAX_ORDER],
+}
+
+/// 4.6.9.1 tns_data of every window
+pub struct TnsData {
+  filters: [[TnsFilter; 4]; MAX_WINDOWS],
+  n_filt: [usize; MAX_WINDOWS],
+}
+
+impl TnsData {
+  pub fn decode(bits: &mut BitReader, info: &IcsInfo) -> AacResult<Self> {
+    let short = info.is_eight_short();
+    let (num_windows, n_filt_bits, length_bits, order_bits, max_order) = if short {
+      (MAX_WINDOWS, 1, 4, 3, 7)
+    } else {
+      (1, 2, 6, 5, TNS_MAX_ORDER)
+    };
+    let mut tns = Self {
+      filters: [[TnsFilter::default(); 4]; MAX_WINDOWS],
+      n_filt: [0; MAX_WINDOWS],
+    };
+    for window in 0..num_windows {
+      tns.n_filt[window] = bits.bits(n_filt_bits)? as usize;
+      if tns.n_filt[window] == 0 {
+        continue;
+      }
+      let coef_res = bits.bits(1)? as u8 + 3;
+      for filter in &mut tns.filters[window][..tns.n_filt[window]] {
+        filter.length = bits.bits(length_bits)? as usize;
+        filter.order = bits.bits(order_bits)? as usize;
+        if filter.order > max_order {
+          return Err(AacError::Invalid("TNS filter order"));
+        }
+        if filter.order == 0 {
+          continue;
+        }
+        filter.direction = bits.bit()?;
+        let coef_compress = bits.bits(1)? as u8;
+        let coef_bits = coef_res - coef_compress;
+        let mut coefficients = [0.; TNS_MAX_ORDER];
+        for coefficient in &mut coefficients[..filter.order] {
+          *coefficient = Self::dequantize(bits.bits(coef_bits)?, coef_bits, coef_res);
+        }
+        filter.lpc = Self::lpc(&coefficients[..filter.order]);
+      }
+    }
+    Ok(tns)
+  }
+
+  /// 4.6.9.3 Inverse quantization of a `coef_bits` two's complement reflection coefficient
+  fn dequantize(coefficient: u32, coef_bits: u8, coef_res: u8) -> f32 {
+    let coefficient = (coefficient << (32 - coef_bits)) as i32 >> (32 - coef_bits);
+    let half = (1 << (coef_res - 1)) as f32;
+    let iqfac = if coefficient >= 0 {
+      (half - 0.5) / FRAC_PI_2
+    } else {
+      (half + 0.5) / FRAC_PI_2
+    };
+    (coefficient as f32 / iqfac).sin()
+  }
+
+  /// Converts reflection coefficients to direct form LPC coefficients
+  fn lpc(reflection: &[f32]) -> [f32; TNS_MAX_ORDER] {
+    let mut a = [0.; TNS_MAX_ORDER + 1];
+    let mut b = [0.; TNS_MAX_ORDER + 1];
+    a[0] = 1.;
+    for (m, &k) in (1..).zip(reflection) {
+      for i in 1..m {
+        b[i] = a[i] + k * a[m - i];
+      }
+      a[1..m].copy_from_slice(&b[1..m]);
+      a[m] = k;
+    }
+    let mut lpc = [0.; TNS_MAX_ORDER];
+    lpc.copy_from_slice(&a[1..]);
+    lpc
+  }
+
+  /// 4.6.9.3 Runs the all-pole filters over the spectrum of every window
+  pub fn apply(&self, info: &IcsInfo, spectrum: &mut [f32; 1024]) {
+    let (num_windows, max_bands) = if info.is_eight_short() {
+      (
+        MAX_WINDOWS,
+        TNS_MAX_BANDS_SHORT[info.sampling_frequency_index],
+      )
+    } else {
+      (1, TNS_MAX_BANDS_LONG[info.sampling_frequency_index])
+    };
+    let max_band = (max_bands as usize).min(info.max_sfb);
+    for window in 0..num_windows {
+      let spectrum = &mut spectrum[window * info.window_length()..];
+      let mut bottom = info.num_swb();
+      for filter in &self.filters[window][..self.n_filt[window]] {
+        let top = bottom;
+        bottom = top.saturating_sub(filter.length);
+        if filter.order == 0 {
+          continue;
+        }
+        let start = info.swb_offset[bottom.min(max_band)] as usize;
+        let end = info.swb_offset[top.min(max_band)] as usize;
+        if start >= end {
+          continue;
+        }
+        let lpc = &filter.lpc[..filter.order];
+        let mut state = [0.; TNS_MAX_ORDER];
+        let mut filter_sample = |coefficient: &mut f32| {
+          let mut y = *coefficient;
+          for (a, s) in lpc.iter().zip(&state) {
+            y -= a * s;
+          }
+          state.copy_within(0..TNS_MAX_ORDER - 1, 1);
+          state[0] = y;
+          *coefficient = y;
+        };
+        if filter.direction {
+          spectrum[start..end]
+            .iter_mut()
+            .rev()
+            .for_each(&mut filter_sample);
+        } else {
+          spectrum[start..end].iter_mut().for_each(&mut filter_sample);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,98 @@
+mod obu;
+mod sequence;
+
+pub use obu::*;
+pub use sequence::*;
+
+use super::*;
+use crate::ascii::LogDisplay;
+use crate::byte::BitStream;
+use crate::log;
+
+/// AV1 Codec ISO Media File Format Binding 2.3.3 AV1CodecConfigurationRecord
+#[derive(Debug)]
+pub struct Av1CAtom {
+  pub seq_profile: u8,
+  pub seq_level_idx_0: u8,
+  pub seq_tier_0: bool,
+  pub high_bitdepth: bool,
+  pub twelve_bit: bool,
+  pub monochrome: bool,
+  pub chroma_subsampling_x: bool,
+  pub chroma_subsampling_y: bool,
+  /// Sequence header of the configOBUs
+  pub sequence_header: Option<SequenceHeader>,
+}
+
+impl Av1CAtom {
+  pub const TYPE: [u8; 4] = *b"av1C";
+  const HEADER_SIZE: usize = 4;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    if data.len() < Self::HEADER_SIZE {
+      return Err(AtomError::Size(data.len() as u64));
+    }
+    let mut header = BitStream::rbsp(data.next(Self::HEADER_SIZE));
+    // marker and version
+    header.byte();
+    let mut av1c = Self {
+      seq_profile: header.bits_into(3),
+      seq_level_idx_0: header.bits_into(5),
+      seq_tier_0: header.bit_flag(),
+      high_bitdepth: header.bit_flag(),
+      twelve_bit: header.bit_flag(),
+      monochrome: header.bit_flag(),
+      chroma_subsampling_x: header.bit_flag(),
+      chroma_subsampling_y: header.bit_flag(),
+      sequence_header: None,
+    };
+    for (obu, payload) in ObuHeader::split(&data) {
+      if obu.obu_type != ObuHeader::SEQUENCE_HEADER {
+        continue;
+      }
+      match SequenceHeader::decode(&mut BitStream::rbsp(payload)) {
+        Ok(sequence_header) => av1c.sequence_header = Some(sequence_header),
+        Err(e) => log!(err@"#[av1C] {e}"),
+      }
+    }
+    Ok(av1c)
+  }
+
+  /// BitDepth
+  pub fn bit_depth(&self) -> u8 {
+    match (self.high_bitdepth, self.twelve_bit) {
+      (true, true) => 12,
+      (true, false) => 10,
+      _ => 8,
+    }
+  }
+
+  /// Profile, level, tier, chroma format, bit depth and colour description, e.g. `Main, Level
+  /// 5.1, Main Tier, 4:2:0 10-bit, BT.2020 primaries, PQ transfer, BT.2020 NCL matrix, limited
+  /// range`
+  pub fn description(&self) -> String {
+    let level = match self.seq_level_idx_0 {
+      31 => String::from("Max"),
+      idx => format!("{}.{}", 2 + (idx >> 2), idx & 3),
+    };
+    let tier = if self.seq_tier_0 { "High" } else { "Main" };
+    let chroma_format = chroma_format(
+      self.monochrome,
+      self.chroma_subsampling_x,
+      self.chroma_subsampling_y,
+    );
+    let mut description = format!(
+      "{}, Level {level}, {tier} Tier, {chroma_format} {}-bit",
+      profile_name(self.seq_profile),
+      self.bit_depth()
+    );
+    if let Some(colour) = self
+      .sequence_header
+      .as_ref()
+      .and_then(|sequence_header| sequence_header.color_config.colour())
+    {
+      description += &format!(", {colour}");
+    }
+    description
+  }
+}
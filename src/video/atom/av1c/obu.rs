@@ -0,0 +1,69 @@
+/// AV1 5.3.2 OBU header
+#[derive(Debug, Clone, Copy)]
+pub struct ObuHeader {
+  pub forbidden_bit: bool,
+  pub obu_type: u8,
+  pub extension_flag: bool,
+  pub has_size_field: bool,
+  /// temporal_id of the OBU extension, 0 without it
+  pub temporal_id: u8,
+  /// spatial_id of the OBU extension, 0 without it
+  pub spatial_id: u8,
+}
+
+impl ObuHeader {
+  pub const SEQUENCE_HEADER: u8 = 1;
+  pub const FRAME_HEADER: u8 = 3;
+  pub const FRAME: u8 = 6;
+
+  pub fn new(header: u8, extension: u8) -> Self {
+    let extension_flag = (header >> 2) & 1 != 0;
+    Self {
+      forbidden_bit: header & 0x80 != 0,
+      obu_type: (header >> 3) & 0xF,
+      extension_flag,
+      has_size_field: (header >> 1) & 1 != 0,
+      temporal_id: if extension_flag { extension >> 5 } else { 0 },
+      spatial_id: if extension_flag {
+        (extension >> 3) & 0b11
+      } else {
+        0
+      },
+    }
+  }
+
+  /// Splits `data` in the low overhead bitstream format into its OBUs and their payloads. An
+  /// OBU without obu_size extends to the end of `data`, stops at a truncated OBU
+  pub fn split(data: &[u8]) -> impl Iterator<Item = (Self, &[u8])> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+      let header = Self::new(
+        *data.get(offset)?,
+        data.get(offset + 1).copied().unwrap_or_default(),
+      );
+      let mut start = offset + 1 + header.extension_flag as usize;
+      let size = if header.has_size_field {
+        let (size, length) = leb128(data.get(start..)?)?;
+        start += length;
+        size as usize
+      } else {
+        data.len().checked_sub(start)?
+      };
+      let payload = data.get(start..start.checked_add(size)?)?;
+      offset = start + size;
+      Some((header, payload))
+    })
+  }
+}
+
+/// 4.10.5 leb128(), returns the value and the number of bytes it takes
+pub fn leb128(data: &[u8]) -> Option<(u64, usize)> {
+  let mut value = 0;
+  for (i, byte) in data.iter().take(8).enumerate() {
+    value |= ((byte & 0x7F) as u64) << (i * 7);
+    if byte & 0x80 == 0 {
+      return Some((value, i + 1));
+    }
+  }
+  None
+}
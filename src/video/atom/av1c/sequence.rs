@@ -0,0 +1,332 @@
+use super::*;
+use crate::video::codec::ColourInfo;
+
+/// AV1 5.5 Sequence header OBU, the elements that frame headers depend on
+#[derive(Debug, Clone)]
+pub struct SequenceHeader {
+  pub reduced_still_picture_header: bool,
+  /// equal_picture_interval of the timing info, false without timing info
+  pub equal_picture_interval: bool,
+  pub decoder_model_info: Option<DecoderModelInfo>,
+  pub operating_points: Box<[OperatingPoint]>,
+  pub frame_width_bits_minus_1: u8,
+  pub frame_height_bits_minus_1: u8,
+  pub max_frame_width_minus_1: u32,
+  pub max_frame_height_minus_1: u32,
+  /// delta_frame_id_length_minus_2 and additional_frame_id_length_minus_1 when
+  /// frame_id_numbers_present_flag is set
+  pub frame_id_numbers: Option<(u8, u8)>,
+  pub enable_interintra_compound: bool,
+  pub enable_masked_compound: bool,
+  pub enable_warped_motion: bool,
+  pub enable_dual_filter: bool,
+  pub enable_order_hint: bool,
+  pub enable_jnt_comp: bool,
+  pub enable_ref_frame_mvs: bool,
+  /// 0, 1 or SELECT_SCREEN_CONTENT_TOOLS
+  pub seq_force_screen_content_tools: u8,
+  /// 0, 1 or SELECT_INTEGER_MV
+  pub seq_force_integer_mv: u8,
+  /// OrderHintBits
+  pub order_hint_bits: u8,
+  pub enable_superres: bool,
+  pub enable_cdef: bool,
+  pub enable_restoration: bool,
+  pub color_config: ColorConfig,
+  pub film_grain_params_present: bool,
+}
+
+impl SequenceHeader {
+  pub const SELECT_SCREEN_CONTENT_TOOLS: u8 = 2;
+  pub const SELECT_INTEGER_MV: u8 = 2;
+
+  /// Decodes the payload of a sequence header OBU
+  pub fn decode(data: &mut BitStream) -> AtomResult<Self> {
+    let seq_profile = data.bits_into(3);
+    if seq_profile > 2 {
+      return Err(AtomError::ParameterSet("seq_profile", seq_profile as u32));
+    }
+    // still_picture
+    data.bit_flag();
+    let reduced_still_picture_header = data.bit_flag();
+    let mut equal_picture_interval = false;
+    let mut decoder_model_info = None;
+    let operating_points = if reduced_still_picture_header {
+      // seq_level_idx[0]
+      data.bits_into::<u8>(5);
+      Box::new([OperatingPoint {
+        idc: 0,
+        decoder_model_present: false,
+      }]) as Box<[_]>
+    } else {
+      if data.bit_flag() {
+        equal_picture_interval = timing_info(data);
+        decoder_model_info = data.bit_flag().then(|| DecoderModelInfo::decode(data));
+      }
+      let initial_display_delay_present_flag = data.bit_flag();
+      let operating_points_cnt_minus_1 = data.bits_into::<u8>(5);
+      (0..=operating_points_cnt_minus_1)
+        .map(|_| {
+          let idc = data.bits_into(12);
+          let seq_level_idx = data.bits_into::<u8>(5);
+          // seq_tier
+          if seq_level_idx > 7 {
+            data.bit_flag();
+          }
+          let decoder_model_present = match &decoder_model_info {
+            Some(info) if data.bit_flag() => {
+              // decoder_buffer_delay, encoder_buffer_delay and low_delay_mode_flag
+              let n = info.buffer_delay_length_minus_1 as usize + 1;
+              data.bits_into::<u128>(2 * n + 1);
+              true
+            }
+            _ => false,
+          };
+          // initial_display_delay_minus_1
+          if initial_display_delay_present_flag && data.bit_flag() {
+            data.bits_into::<u8>(4);
+          }
+          OperatingPoint {
+            idc,
+            decoder_model_present,
+          }
+        })
+        .collect()
+    };
+
+    let frame_width_bits_minus_1 = data.bits_into(4);
+    let frame_height_bits_minus_1 = data.bits_into(4);
+    let max_frame_width_minus_1 = data.bits_into(frame_width_bits_minus_1 as usize + 1);
+    let max_frame_height_minus_1 = data.bits_into(frame_height_bits_minus_1 as usize + 1);
+    let frame_id_numbers = (!reduced_still_picture_header && data.bit_flag())
+      .then(|| (data.bits_into(4), data.bits_into(3)));
+    // use_128x128_superblock, enable_filter_intra and enable_intra_edge_filter
+    data.bits_into::<u8>(3);
+
+    let mut header = Self {
+      reduced_still_picture_header,
+      equal_picture_interval,
+      decoder_model_info,
+      operating_points,
+      frame_width_bits_minus_1,
+      frame_height_bits_minus_1,
+      max_frame_width_minus_1,
+      max_frame_height_minus_1,
+      frame_id_numbers,
+      enable_interintra_compound: false,
+      enable_masked_compound: false,
+      enable_warped_motion: false,
+      enable_dual_filter: false,
+      enable_order_hint: false,
+      enable_jnt_comp: false,
+      enable_ref_frame_mvs: false,
+      seq_force_screen_content_tools: Self::SELECT_SCREEN_CONTENT_TOOLS,
+      seq_force_integer_mv: Self::SELECT_INTEGER_MV,
+      order_hint_bits: 0,
+      enable_superres: false,
+      enable_cdef: false,
+      enable_restoration: false,
+      color_config: ColorConfig::default(),
+      film_grain_params_present: false,
+    };
+    if !reduced_still_picture_header {
+      header.enable_interintra_compound = data.bit_flag();
+      header.enable_masked_compound = data.bit_flag();
+      header.enable_warped_motion = data.bit_flag();
+      header.enable_dual_filter = data.bit_flag();
+      header.enable_order_hint = data.bit_flag();
+      if header.enable_order_hint {
+        header.enable_jnt_comp = data.bit_flag();
+        header.enable_ref_frame_mvs = data.bit_flag();
+      }
+      if !data.bit_flag() {
+        header.seq_force_screen_content_tools = data.bit();
+      }
+      header.seq_force_integer_mv = if header.seq_force_screen_content_tools > 0 {
+        if data.bit_flag() {
+          Self::SELECT_INTEGER_MV
+        } else {
+          data.bit()
+        }
+      } else {
+        Self::SELECT_INTEGER_MV
+      };
+      if header.enable_order_hint {
+        header.order_hint_bits = data.bits_into::<u8>(3) + 1;
+      }
+    }
+    header.enable_superres = data.bit_flag();
+    header.enable_cdef = data.bit_flag();
+    header.enable_restoration = data.bit_flag();
+    header.color_config = ColorConfig::decode(data, seq_profile);
+    header.film_grain_params_present = data.bit_flag();
+    Ok(header)
+  }
+
+  /// idLen of the frame ids, 0 when frame_id_numbers_present_flag is not set
+  pub fn id_len(&self) -> usize {
+    self
+      .frame_id_numbers
+      .map(|(delta, additional)| delta as usize + additional as usize + 3)
+      .unwrap_or_default()
+  }
+
+  /// The decoder model signals frame_presentation_time in the frame headers
+  pub fn temporal_point_info_present(&self) -> bool {
+    self.decoder_model_info.is_some() && !self.equal_picture_interval
+  }
+}
+
+/// AV1 profile of `seq_profile`
+pub fn profile_name(seq_profile: u8) -> &'static str {
+  match seq_profile {
+    0 => "Main",
+    1 => "High",
+    2 => "Professional",
+    _ => "Unknown",
+  }
+}
+
+/// AV1 5.5.3 timing_info, returns equal_picture_interval
+fn timing_info(data: &mut BitStream) -> bool {
+  // num_units_in_display_tick and time_scale
+  data.bits_into::<u64>(64);
+  let equal_picture_interval = data.bit_flag();
+  if equal_picture_interval {
+    // num_ticks_per_picture_minus_1, uvlc() is coded like an unsigned exponential Golomb code
+    data.exponential_golomb::<u32>();
+  }
+  equal_picture_interval
+}
+
+/// AV1 5.5.4 decoder_model_info
+#[derive(Debug, Clone)]
+pub struct DecoderModelInfo {
+  pub buffer_delay_length_minus_1: u8,
+  pub buffer_removal_time_length_minus_1: u8,
+  pub frame_presentation_time_length_minus_1: u8,
+}
+
+impl DecoderModelInfo {
+  fn decode(data: &mut BitStream) -> Self {
+    let buffer_delay_length_minus_1 = data.bits_into(5);
+    // num_units_in_decoding_tick
+    data.bits_into::<u32>(32);
+    Self {
+      buffer_delay_length_minus_1,
+      buffer_removal_time_length_minus_1: data.bits_into(5),
+      frame_presentation_time_length_minus_1: data.bits_into(5),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct OperatingPoint {
+  /// operating_point_idc, the temporal layers in the low 8 bits and the spatial layers above
+  pub idc: u16,
+  /// decoder_model_present_for_this_op, operating_parameters_info follows when set
+  pub decoder_model_present: bool,
+}
+
+/// AV1 5.5.2 color_config
+#[derive(Debug, Clone, Default)]
+pub struct ColorConfig {
+  pub color_description_present_flag: bool,
+  pub color_primaries: u8,
+  pub transfer_characteristics: u8,
+  pub matrix_coefficients: u8,
+  pub color_range: bool,
+  pub subsampling_x: bool,
+  pub subsampling_y: bool,
+  pub chroma_sample_position: u8,
+  pub separate_uv_delta_q: bool,
+}
+
+impl ColorConfig {
+  /// CP_BT_709
+  const CP_BT_709: u8 = 1;
+  /// TC_SRGB
+  const TC_SRGB: u8 = 13;
+  /// MC_IDENTITY
+  const MC_IDENTITY: u8 = 0;
+  /// CP_UNSPECIFIED, TC_UNSPECIFIED and MC_UNSPECIFIED
+  const UNSPECIFIED: u8 = 2;
+
+  fn decode(data: &mut BitStream, seq_profile: u8) -> Self {
+    let high_bitdepth = data.bit_flag();
+    let bit_depth = match (seq_profile, high_bitdepth) {
+      (2, true) if data.bit_flag() => 12,
+      (_, true) => 10,
+      _ => 8,
+    };
+    let mono_chrome = seq_profile != 1 && data.bit_flag();
+    let color_description_present_flag = data.bit_flag();
+    let (color_primaries, transfer_characteristics, matrix_coefficients) =
+      if color_description_present_flag {
+        (data.byte(), data.byte(), data.byte())
+      } else {
+        (Self::UNSPECIFIED, Self::UNSPECIFIED, Self::UNSPECIFIED)
+      };
+    let mut config = Self {
+      color_description_present_flag,
+      color_primaries,
+      transfer_characteristics,
+      matrix_coefficients,
+      color_range: true,
+      subsampling_x: true,
+      subsampling_y: true,
+      chroma_sample_position: 0,
+      separate_uv_delta_q: false,
+    };
+    if mono_chrome {
+      config.color_range = data.bit_flag();
+      return config;
+    }
+    if color_primaries == Self::CP_BT_709
+      && transfer_characteristics == Self::TC_SRGB
+      && matrix_coefficients == Self::MC_IDENTITY
+    {
+      config.subsampling_x = false;
+      config.subsampling_y = false;
+    } else {
+      config.color_range = data.bit_flag();
+      match seq_profile {
+        0 => (),
+        1 => {
+          config.subsampling_x = false;
+          config.subsampling_y = false;
+        }
+        _ if bit_depth == 12 => {
+          config.subsampling_x = data.bit_flag();
+          config.subsampling_y = config.subsampling_x && data.bit_flag();
+        }
+        _ => config.subsampling_y = false,
+      }
+      if config.subsampling_x && config.subsampling_y {
+        config.chroma_sample_position = data.bits_into(2);
+      }
+    }
+    config.separate_uv_delta_q = data.bit_flag();
+    config
+  }
+
+  /// Colour description, `None` when it is not present
+  pub fn colour(&self) -> Option<ColourInfo> {
+    self.color_description_present_flag.then_some(ColourInfo {
+      colour_primaries: self.color_primaries,
+      transfer_characteristics: self.transfer_characteristics,
+      matrix_coefficients: self.matrix_coefficients,
+      full_range: self.color_range,
+    })
+  }
+}
+
+/// Chroma format of the subsampling flags, e.g. `4:2:0`
+pub fn chroma_format(mono_chrome: bool, subsampling_x: bool, subsampling_y: bool) -> &'static str {
+  match (mono_chrome, subsampling_x, subsampling_y) {
+    (true, ..) => "4:0:0",
+    (_, true, true) => "4:2:0",
+    (_, true, false) => "4:2:2",
+    _ => "4:4:4",
+  }
+}
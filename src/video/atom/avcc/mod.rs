@@ -9,6 +9,9 @@ pub use vui::*;
 use super::*;
 use crate::byte::BitStream;
 
+/// Raw parameter set NAL units, including the header byte and emulation prevention bytes
+pub type ParameterSetUnits = Box<[Box<[u8]>]>;
+
 #[derive(Debug)]
 pub struct AvcCAtom {
   pub configuration_version: u8,
@@ -20,6 +23,10 @@ pub struct AvcCAtom {
   pub sps: SequenceParameterSet,
   pub num_pps: u8,
   pub pps: PictureParameterSet,
+  /// Raw SPS NAL units, emulation prevention bytes included
+  pub sps_units: ParameterSetUnits,
+  /// Raw PPS NAL units, emulation prevention bytes included
+  pub pps_units: ParameterSetUnits,
 }
 
 impl AvcCAtom {
@@ -27,6 +34,7 @@ impl AvcCAtom {
   pub fn decode(mut data: AtomData) -> Self {
     let mut bit_data;
     let chroma_format_idc;
+    let (sps_units, pps_units) = Self::parameter_set_units(&data[5..]);
     Self {
       configuration_version: data.byte(),
       profile_indication: data.byte(),
@@ -42,6 +50,29 @@ impl AvcCAtom {
       },
       num_pps: bit_data.byte(),
       pps: PictureParameterSet::decode(&mut bit_data, chroma_format_idc),
+      sps_units,
+      pps_units,
+    }
+  }
+
+  /// Splits the length prefixed SPS and PPS arrays of the decoder configuration record
+  fn parameter_set_units(data: &[u8]) -> (ParameterSetUnits, ParameterSetUnits) {
+    fn units(data: &[u8], offset: &mut usize, count: usize) -> ParameterSetUnits {
+      (0..count)
+        .map_while(|_| {
+          let length = u16::from_be_bytes(data.get(*offset..*offset + 2)?.try_into().ok()?);
+          let unit = data.get(*offset + 2..*offset + 2 + length as usize)?;
+          *offset += 2 + length as usize;
+          Some(unit.into())
+        })
+        .collect()
     }
+    let mut offset = 1;
+    let num_sps = data.first().map(|n| n & 0b0001_1111).unwrap_or_default();
+    let sps_units = units(data, &mut offset, num_sps as usize);
+    let num_pps = data.get(offset).copied().unwrap_or_default();
+    offset += 1;
+    let pps_units = units(data, &mut offset, num_pps as usize);
+    (sps_units, pps_units)
   }
 }
@@ -28,12 +28,32 @@ pub struct PictureParameterSet {
 
 impl PictureParameterSet {
   pub fn decode(data: &mut BitStream, chroma_format_idc: u16) -> Self {
+    let length = data.next_into();
+    let header = data.byte();
+    Self::decode_rbsp(length, header, data, chroma_format_idc)
+  }
+
+  /// Reads the `seq_parameter_set_id` of a PPS RBSP, the referenced SPS is needed to decode the
+  /// rest of it
+  pub fn seq_parameter_set_id(rbsp: &[u8]) -> u16 {
+    let mut data = BitStream::new(rbsp);
+    data.exponential_golomb::<u16>();
+    data.exponential_golomb()
+  }
+
+  /// Decodes a PPS NAL unit, `data` starts right after the NAL unit `header`
+  pub fn decode_rbsp(
+    length: u16,
+    header: u8,
+    data: &mut BitStream,
+    chroma_format_idc: u16,
+  ) -> Self {
     let num_slice_groups_minus1;
     Self {
-      length: data.next_into(),
-      forbidden_zero_bit: data.bit(),
-      nal_ref_idc: data.bits(2),
-      nal_unit_type: data.bits(5),
+      length,
+      forbidden_zero_bit: header >> 7,
+      nal_ref_idc: (header >> 5) & 0b11,
+      nal_unit_type: header & 0x1F,
       id: data.exponential_golomb(),
       seq_parameter_set_id: data.exponential_golomb(),
       entropy_coding_mode_flag: data.bit_flag(),
@@ -67,7 +87,7 @@ pub struct ExtraRbspData {
 
 impl ExtraRbspData {
   pub fn new(data: &mut BitStream, chroma_format_idc: u16) -> Option<Self> {
-    data.has_bits().then(|| {
+    data.more_rbsp_data().then(|| {
       let transform_8x8_mode;
       Self {
         transform_8x8_mode_flag: {
@@ -40,6 +40,13 @@ pub struct SequenceParameterSet {
 
 impl SequenceParameterSet {
   pub fn decode(data: &mut BitStream) -> Self {
+    let length = data.next_into();
+    let header = data.byte();
+    Self::decode_rbsp(length, header, data)
+  }
+
+  /// Decodes an SPS NAL unit, `data` starts right after the NAL unit `header`
+  pub fn decode_rbsp(length: u16, header: u8, data: &mut BitStream) -> Self {
     let pic_order_cnt_type;
     let frame_mbs_only_flag;
     let profile_idc;
@@ -50,10 +57,10 @@ impl SequenceParameterSet {
     let mut bit_depth_chroma_minus8 = 0;
     let mut qpprime_y_zero_transform_bypass_flag = false;
     Self {
-      length: data.next_into(),
-      forbidden_zero_bit: data.bit(),
-      nal_ref_idc: data.bits(2),
-      nal_unit_type: data.bits(5),
+      length,
+      forbidden_zero_bit: header >> 7,
+      nal_ref_idc: (header >> 5) & 0b11,
+      nal_unit_type: header & 0x1F,
       profile_idc: {
         profile_idc = data.byte();
         profile_idc
@@ -121,6 +128,34 @@ impl SequenceParameterSet {
   }
 }
 
+impl SequenceParameterSet {
+  /// 7.4.2.1.1 Frame width and height in luma samples after applying the cropping rectangle
+  pub fn frame_size(&self) -> (u16, u16) {
+    let frame_height_in_mbs =
+      (2 - self.frame_mbs_only_flag as u16) * (self.pic_height_in_map_units_minus1 + 1);
+    let width = (self.pic_width_in_mbs_minus1 + 1) * 16;
+    let height = frame_height_in_mbs * 16;
+    let Some(crop) = &self.frame_cropping else {
+      return (width, height);
+    };
+    let chroma_array_type = if self.separate_color_plane_flag {
+      0
+    } else {
+      self.chroma_format_idc
+    };
+    let (crop_unit_x, crop_unit_y) = match chroma_array_type {
+      0 => (1, 2 - self.frame_mbs_only_flag as u16),
+      1 => (2, 2 * (2 - self.frame_mbs_only_flag as u16)),
+      2 => (2, 2 - self.frame_mbs_only_flag as u16),
+      _ => (1, 2 - self.frame_mbs_only_flag as u16),
+    };
+    (
+      width.saturating_sub(crop_unit_x * (crop.left + crop.right)),
+      height.saturating_sub(crop_unit_y * (crop.top + crop.bottom)),
+    )
+  }
+}
+
 #[derive(Debug)]
 pub struct PicOrderCntTypeOne {
   pub delta_pic_order_always_zero_flag: bool,
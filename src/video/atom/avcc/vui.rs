@@ -1,4 +1,5 @@
 use crate::byte::BitStream;
+use crate::video::codec::ColourInfo;
 
 #[derive(Debug)]
 pub struct VuiParameters {
@@ -93,6 +94,19 @@ impl VideoSignalType {
       color_description: ColorDescription::decode(data.bit_flag(), data),
     })
   }
+
+  /// Colour of the video signal, unspecified without a colour description
+  pub fn colour(&self) -> ColourInfo {
+    let description = self.color_description.as_ref();
+    let field =
+      |field: fn(&ColorDescription) -> u8| description.map_or(ColourInfo::UNSPECIFIED, field);
+    ColourInfo {
+      colour_primaries: field(|description| description.primaries),
+      transfer_characteristics: field(|description| description.transfer_characteristics),
+      matrix_coefficients: field(|description| description.matrix_coefficients),
+      full_range: self.video_full_range_flag,
+    }
+  }
 }
 
 #[derive(Debug)]
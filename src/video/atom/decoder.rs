@@ -126,6 +126,15 @@ impl AtomData {
     Ok(T::try_from_slice(&self.data[s..self.offset])?)
   }
 
+  /// Reads a 64 bit field on version 1 atoms and a 32 bit one otherwise
+  pub fn next_versioned(&mut self, version: u8) -> AtomResult<u64> {
+    if version == 1 {
+      self.next_into()
+    } else {
+      Ok(self.next_into::<u32>()? as u64)
+    }
+  }
+
   pub fn next(&mut self, size: usize) -> &[u8] {
     let s = self.offset;
     self.offset += size;
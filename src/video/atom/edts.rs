@@ -37,31 +37,111 @@ impl AtomDecoder for ElstAtom {
   const NAME: [u8; 4] = *b"elst";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
     let mut data = atom.read_data(decoder)?;
+    let version = data.version();
     Ok(Self {
-      version: data.version(),
+      version,
       flags: data.flags(),
       number_of_entries: data.next_into()?,
       edit_list_table: data
-        .chunks(12)
-        .map(|data| ElstItem::from_bytes(AtomData::new(data, atom.offset)))
+        .chunks(if version == 1 { 20 } else { 12 })
+        .map(|data| ElstItem::from_bytes(AtomData::new(data, atom.offset), version))
         .collect::<AtomResult<_>>()?,
     })
   }
 }
 
+impl ElstAtom {
+  /// Maps a presentation `time` to media time, both in `media_timescale` units. Times inside
+  /// an empty edit map to the start of the following edit, returns `None` past the last edit
+  pub fn media_time(&self, time: i64, movie_timescale: u32, media_timescale: u32) -> Option<i64> {
+    let mut start = 0i64;
+    for edit in &*self.edit_list_table {
+      let duration = edit.duration(movie_timescale, media_timescale);
+      if !edit.is_empty() {
+        let offset = (time - start).max(0);
+        if offset < duration {
+          return Some(edit.media_time + (offset as f64 * edit.media_rate as f64) as i64);
+        }
+      }
+      start = start.saturating_add(duration);
+    }
+    None
+  }
+
+  /// Maps a `media_time` to its first presentation time, both in `media_timescale` units,
+  /// returns `None` if the edit list trims it
+  pub fn presentation_time(
+    &self,
+    media_time: i64,
+    movie_timescale: u32,
+    media_timescale: u32,
+  ) -> Option<i64> {
+    let mut start = 0i64;
+    for edit in &*self.edit_list_table {
+      let duration = edit.duration(movie_timescale, media_timescale);
+      if !edit.is_empty() {
+        let offset = media_time - edit.media_time;
+        if edit.is_dwell() {
+          if offset == 0 {
+            return Some(start);
+          }
+        } else {
+          let offset = (offset as f64 / edit.media_rate as f64) as i64;
+          if (0..duration).contains(&offset) {
+            return Some(start + offset);
+          }
+        }
+      }
+      start = start.saturating_add(duration);
+    }
+    None
+  }
+
+  /// Total duration of the edits in `media_timescale` units
+  pub fn duration(&self, movie_timescale: u32, media_timescale: u32) -> i64 {
+    self.edit_list_table.iter().fold(0i64, |duration, edit| {
+      duration.saturating_add(edit.duration(movie_timescale, media_timescale))
+    })
+  }
+}
+
 #[derive(Debug, Default)]
 pub struct ElstItem {
-  pub track_duration: u32,
-  pub media_time: i32,
+  pub track_duration: u64,
+  pub media_time: i64,
   pub media_rate: f32,
 }
 
 impl ElstItem {
-  pub fn from_bytes(mut data: AtomData) -> AtomResult<Self> {
+  pub fn from_bytes(mut data: AtomData, version: u8) -> AtomResult<Self> {
     Ok(Self {
-      track_duration: data.next_into()?,
-      media_time: data.next_into()?,
+      track_duration: data.next_versioned(version)?,
+      media_time: if version == 1 {
+        data.next_into()?
+      } else {
+        data.next_into::<i32>()? as i64
+      },
       media_rate: data.fixed_point_16()?,
     })
   }
+
+  /// Edit that doesn't present any media
+  pub fn is_empty(&self) -> bool {
+    self.media_time == -1
+  }
+
+  /// Edit that presents the media at `media_time` for its whole duration
+  pub fn is_dwell(&self) -> bool {
+    self.media_rate == 0.
+  }
+
+  /// Duration of the edit in `media_timescale` units, a zero `track_duration` extends the edit
+  /// to the end of the media
+  pub fn duration(&self, movie_timescale: u32, media_timescale: u32) -> i64 {
+    if self.track_duration == 0 {
+      return i64::MAX;
+    }
+    (self.track_duration as u128 * media_timescale as u128 / movie_timescale.max(1) as u128)
+      .min(i64::MAX as u128) as i64
+  }
 }
@@ -0,0 +1,326 @@
+use super::*;
+use crate::ascii::LogDisplay;
+use crate::byte::BitStream;
+use crate::log;
+
+/// ISO/IEC 14496-14 3.1.2 elementary stream descriptor atom
+#[derive(Debug, Default)]
+pub struct EsdsAtom {
+  pub es_descriptor: EsDescriptor,
+}
+
+impl EsdsAtom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    let (tag, descriptor) = Self::descriptor(data.reserved(4))?;
+    if tag != EsDescriptor::TAG {
+      return Err(AtomError::DescriptorTag(EsDescriptor::TAG, tag));
+    }
+    Ok(Self {
+      es_descriptor: EsDescriptor::decode(descriptor)?,
+    })
+  }
+
+  /// ISO/IEC 14496-1 8.3.3 Reads the tag and expandable size of a descriptor, returns the tag
+  /// and the descriptor payload
+  fn descriptor(data: &mut AtomData) -> AtomResult<(u8, AtomData)> {
+    if data.is_empty() {
+      return Err(AtomError::Size(0));
+    }
+    let tag = data.byte();
+    let mut size = 0usize;
+    for _ in 0..4 {
+      if data.is_empty() {
+        return Err(AtomError::Size(size as u64));
+      }
+      let byte = data.byte();
+      size = size << 7 | (byte & 0x7F) as usize;
+      if byte & 0x80 == 0 {
+        break;
+      }
+    }
+    if size > data.len() {
+      return Err(AtomError::Size(size as u64));
+    }
+    Ok((tag, AtomData::new(data.next(size), 0)))
+  }
+}
+
+/// ISO/IEC 14496-1 7.2.6.5 ES_Descriptor
+#[derive(Debug, Default)]
+pub struct EsDescriptor {
+  pub decoder_config: Option<DecoderConfigDescriptor>,
+  /// SLConfigDescriptor predefined value
+  pub sl_config: Option<u8>,
+}
+
+impl EsDescriptor {
+  pub const TAG: u8 = 0x03;
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    // ES_ID
+    data.next_into::<u16>()?;
+    let flags = data.byte();
+    // dependsOn_ES_ID
+    if flags & 0x80 != 0 {
+      data.next_into::<u16>()?;
+    }
+    // URLstring
+    if flags & 0x40 != 0 {
+      let length = data.byte() as usize;
+      if length > data.len() {
+        return Err(AtomError::Size(length as u64));
+      }
+      data.reserved(length);
+    }
+    // OCR_ES_Id
+    if flags & 0x20 != 0 {
+      data.next_into::<u16>()?;
+    }
+    let mut es = Self::default();
+    while !data.is_empty() {
+      match EsdsAtom::descriptor(&mut data)? {
+        (DecoderConfigDescriptor::TAG, descriptor) => {
+          es.decoder_config = Some(DecoderConfigDescriptor::decode(descriptor)?)
+        }
+        (0x06, descriptor) => es.sl_config = descriptor.first().copied(),
+        (tag, _) => log!(warn@"#[esds] Unused descriptor {tag:#04x}"),
+      }
+    }
+    Ok(es)
+  }
+}
+
+/// ISO/IEC 14496-1 7.2.6.6 DecoderConfigDescriptor
+#[derive(Debug, Default)]
+pub struct DecoderConfigDescriptor {
+  pub object_type_indication: u8,
+  /// Raw DecoderSpecificInfo
+  pub decoder_specific_info: Box<[u8]>,
+  /// Parsed DecoderSpecificInfo of MPEG-4 and MPEG-2 AAC streams
+  pub audio_specific_config: Option<AudioSpecificConfig>,
+}
+
+impl DecoderConfigDescriptor {
+  pub const TAG: u8 = 0x04;
+  const HEADER_SIZE: usize = 13;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    if data.len() < Self::HEADER_SIZE {
+      return Err(AtomError::Size(data.len() as u64));
+    }
+    let mut config = Self {
+      object_type_indication: data.byte(),
+      ..Default::default()
+    };
+    // streamType, upStream, bufferSizeDB, maxBitrate and avgBitrate
+    data.reserved(12);
+    while !data.is_empty() {
+      match EsdsAtom::descriptor(&mut data)? {
+        (0x05, descriptor) => config.decoder_specific_info = (*descriptor).into(),
+        (tag, _) => log!(warn@"#[esds] Unused decoder config descriptor {tag:#04x}"),
+      }
+    }
+    if config.is_aac() {
+      config.audio_specific_config = AudioSpecificConfig::decode(&config.decoder_specific_info);
+    }
+    Ok(config)
+  }
+
+  /// MPEG-4 Audio or MPEG-2 AAC Main, LC and SSR
+  pub fn is_aac(&self) -> bool {
+    matches!(self.object_type_indication, 0x40 | 0x66 | 0x67 | 0x68)
+  }
+}
+
+/// ISO/IEC 14496-3 1.6.2.1 AudioSpecificConfig
+#[derive(Debug, Default, Clone)]
+pub struct AudioSpecificConfig {
+  pub audio_object_type: u8,
+  pub sampling_frequency_index: u8,
+  pub sampling_frequency: u32,
+  pub channel_configuration: u8,
+  /// 5 when SBR is signalled either explicitly or through the backward compatible extension
+  pub extension_audio_object_type: u8,
+  pub extension_sampling_frequency: Option<u32>,
+  /// `None` when SBR is not signalled, in which case it may still be present implicitly
+  pub sbr_present: Option<bool>,
+  pub ps_present: Option<bool>,
+  pub frame_length_flag: bool,
+  pub depends_on_core_coder: bool,
+  pub core_coder_delay: u16,
+  pub extension_flag: bool,
+}
+
+impl AudioSpecificConfig {
+  pub const SAMPLING_FREQUENCIES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+  ];
+
+  /// Returns `None` if `data` ends before the channel configuration
+  pub fn decode(data: &[u8]) -> Option<Self> {
+    let padded = [data, &[0; 8]].concat();
+    let mut data = AscReader {
+      data: BitStream::new(&padded),
+      bits_left: data.len() * 8,
+    };
+    let mut audio_object_type = data.audio_object_type()?;
+    let (sampling_frequency_index, sampling_frequency) = data.sampling_frequency()?;
+    let mut config = Self {
+      sampling_frequency_index,
+      sampling_frequency,
+      channel_configuration: data.bits(4)? as u8,
+      ..Default::default()
+    };
+    if audio_object_type == 5 || audio_object_type == 29 {
+      config.extension_audio_object_type = 5;
+      config.sbr_present = Some(true);
+      config.ps_present = (audio_object_type == 29).then_some(true);
+      config.extension_sampling_frequency = Some(data.sampling_frequency()?.1);
+      audio_object_type = data.audio_object_type()?;
+      if audio_object_type == 22 {
+        data.bits(4)?;
+      }
+    }
+    config.audio_object_type = audio_object_type;
+
+    if !matches!(
+      audio_object_type,
+      1 | 2 | 3 | 4 | 6 | 7 | 17 | 19 | 20 | 21 | 22 | 23
+    ) {
+      return Some(config);
+    }
+    config.decode_ga_specific_config(&mut data);
+    if config.channel_configuration == 0 {
+      return Some(config);
+    }
+    if matches!(audio_object_type, 17 | 19 | 20 | 21 | 22 | 23) {
+      data.bits(2);
+    }
+    config.decode_sync_extension(&mut data);
+    Some(config)
+  }
+
+  /// 4.4.1 GASpecificConfig, the program_config_element is not parsed
+  fn decode_ga_specific_config(&mut self, data: &mut AscReader) -> Option<()> {
+    self.frame_length_flag = data.bits(1)? != 0;
+    self.depends_on_core_coder = data.bits(1)? != 0;
+    if self.depends_on_core_coder {
+      self.core_coder_delay = data.bits(14)? as u16;
+    }
+    self.extension_flag = data.bits(1)? != 0;
+    if self.channel_configuration == 0 {
+      return None;
+    }
+    if self.audio_object_type == 6 || self.audio_object_type == 20 {
+      data.bits(3)?;
+    }
+    if self.extension_flag {
+      if self.audio_object_type == 22 {
+        data.bits(16)?;
+      }
+      if matches!(self.audio_object_type, 17 | 19 | 20 | 23) {
+        data.bits(3)?;
+      }
+      data.bits(1)?;
+    }
+    Some(())
+  }
+
+  /// Backward compatible SBR and PS signalling appended after the core config
+  fn decode_sync_extension(&mut self, data: &mut AscReader) -> Option<()> {
+    if self.extension_audio_object_type == 5 || data.bits_left < 16 || data.bits(11)? != 0x2B7 {
+      return None;
+    }
+    if data.audio_object_type()? != 5 {
+      return None;
+    }
+    let sbr_present = data.bits(1)? != 0;
+    self.sbr_present = Some(sbr_present);
+    if !sbr_present {
+      return None;
+    }
+    self.extension_audio_object_type = 5;
+    self.extension_sampling_frequency = Some(data.sampling_frequency()?.1);
+    if data.bits_left >= 12 && data.bits(11)? == 0x548 {
+      self.ps_present = Some(data.bits(1)? != 0);
+    }
+    Some(())
+  }
+
+  /// Output sampling frequency, doubled by SBR
+  pub fn output_sampling_frequency(&self) -> u32 {
+    self
+      .extension_sampling_frequency
+      .filter(|_| self.sbr_present == Some(true))
+      .unwrap_or(self.sampling_frequency)
+  }
+
+  /// Number of output channels of the channel configuration, parametric stereo outputs two
+  pub fn channels(&self) -> Option<u8> {
+    if self.ps_present == Some(true) {
+      return Some(2);
+    }
+    match self.channel_configuration {
+      1..=6 => Some(self.channel_configuration),
+      7 | 12 | 14 => Some(8),
+      11 => Some(7),
+      13 => Some(24),
+      _ => None,
+    }
+  }
+
+  pub fn profile(&self) -> &'static str {
+    if self.ps_present == Some(true) {
+      return "HE-AACv2";
+    }
+    if self.sbr_present == Some(true) {
+      return "HE-AAC";
+    }
+    match self.audio_object_type {
+      1 => "AAC Main",
+      2 => "AAC-LC",
+      3 => "AAC SSR",
+      4 => "AAC LTP",
+      6 => "AAC Scalable",
+      17 => "ER AAC-LC",
+      23 => "AAC-LD",
+      32 => "MPEG-1 Layer 1",
+      33 => "MPEG-1 Layer 2",
+      34 => "MPEG-1 Layer 3",
+      39 => "AAC-ELD",
+      42 => "USAC",
+      _ => "Unknown",
+    }
+  }
+}
+
+/// Bit reader bounded to the AudioSpecificConfig length
+struct AscReader<'a> {
+  data: BitStream<'a>,
+  bits_left: usize,
+}
+
+impl<'a> AscReader<'a> {
+  fn bits(&mut self, n: usize) -> Option<u32> {
+    self.bits_left = self.bits_left.checked_sub(n)?;
+    Some(self.data.bits_into(n))
+  }
+
+  fn audio_object_type(&mut self) -> Option<u8> {
+    match self.bits(5)? {
+      31 => Some(32 + self.bits(6)? as u8),
+      audio_object_type => Some(audio_object_type as u8),
+    }
+  }
+
+  fn sampling_frequency(&mut self) -> Option<(u8, u32)> {
+    let index = self.bits(4)? as u8;
+    let frequency = match index {
+      0xF => self.bits(24)?,
+      index => AudioSpecificConfig::SAMPLING_FREQUENCIES
+        .get(index as usize)
+        .copied()
+        .unwrap_or_default(),
+    };
+    Some((index, frequency))
+  }
+}
@@ -0,0 +1,248 @@
+mod pps;
+mod ptl;
+mod sps;
+mod vps;
+mod vui;
+
+pub use pps::*;
+pub use ptl::*;
+pub use sps::*;
+pub use vps::*;
+pub use vui::*;
+
+use super::*;
+use crate::ascii::LogDisplay;
+use crate::byte::BitStream;
+use crate::log;
+
+/// ISO/IEC 14496-15 8.3.3.1 HEVCDecoderConfigurationRecord
+#[derive(Debug)]
+pub struct HvcCAtom {
+  /// General profile, tier and level, without sub-layers
+  pub profile_tier_level: ProfileTierLevel,
+  pub chroma_format_idc: u8,
+  pub bit_depth_luma_minus8: u8,
+  pub nal_length_size_minus_one: u8,
+  pub nal_arrays: Box<[NalArray]>,
+  pub vps: Box<[VideoParameterSet]>,
+  pub sps: Box<[HevcSequenceParameterSet]>,
+  pub pps: Box<[HevcPictureParameterSet]>,
+}
+
+impl HvcCAtom {
+  pub const TYPE: [u8; 4] = *b"hvcC";
+  const HEADER_SIZE: usize = 23;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    if data.len() < Self::HEADER_SIZE {
+      return Err(AtomError::Size(data.len() as u64));
+    }
+    // configurationVersion
+    data.reserved(1);
+    let general_profile = LayerProfile::decode(&mut BitStream::new(data.next(11)));
+    let general_level_idc = data.byte();
+    // min_spatial_segmentation_idc and parallelismType
+    data.reserved(3);
+    let chroma_format_idc = data.byte() & 0b11;
+    let bit_depth_luma_minus8 = data.byte() & 0b111;
+    // bit_depth_chroma_minus8 and avgFrameRate
+    data.reserved(3);
+    // constantFrameRate, numTemporalLayers and temporalIdNested share the byte
+    let nal_length_size_minus_one = data.byte() & 0b11;
+    let mut hvcc = Self {
+      profile_tier_level: ProfileTierLevel {
+        general_profile: Some(general_profile),
+        general_level_idc,
+      },
+      chroma_format_idc,
+      bit_depth_luma_minus8,
+      nal_length_size_minus_one,
+      nal_arrays: Self::nal_arrays(&data),
+      vps: Box::default(),
+      sps: Box::default(),
+      pps: Box::default(),
+    };
+    hvcc.decode_parameter_sets();
+    Ok(hvcc)
+  }
+
+  /// Splits the length prefixed NAL unit arrays, a truncated array keeps the complete units
+  fn nal_arrays(data: &[u8]) -> Box<[NalArray]> {
+    let Some(&num_of_arrays) = data.first() else {
+      return Box::default();
+    };
+    let data = &data[1..];
+    let mut offset = 0;
+    (0..num_of_arrays)
+      .map_while(|_| {
+        // array_completeness and NAL_unit_type come before numNalus
+        let num_nalus = u16::from_be_bytes(data.get(offset + 1..offset + 3)?.try_into().ok()?);
+        offset += 3;
+        let units = (0..num_nalus)
+          .map_while(|_| {
+            let length = u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?);
+            let unit = data.get(offset + 2..offset + 2 + length as usize)?;
+            offset += 2 + length as usize;
+            Some(unit.into())
+          })
+          .collect();
+        Some(NalArray { units })
+      })
+      .collect()
+  }
+
+  /// Decodes the VPS, SPS and PPS NAL units of every array, units that fail to decode are
+  /// logged and skipped
+  fn decode_parameter_sets(&mut self) {
+    let (mut vps, mut sps, mut pps) = (Vec::new(), Vec::new(), Vec::new());
+    for unit in self.nal_arrays.iter().flat_map(|array| array.units.iter()) {
+      let Some((header, rbsp)) = HevcNalUnitHeader::split_rbsp(unit) else {
+        log!(err@"#[hvcC] NAL unit is too short {unit:?}");
+        continue;
+      };
+      let mut data = BitStream::rbsp(&rbsp);
+      let result = match header.nal_unit_type {
+        HevcNalUnitHeader::VPS_NUT => {
+          VideoParameterSet::decode_rbsp(&mut data).map(|set| vps.push(set))
+        }
+        HevcNalUnitHeader::SPS_NUT => {
+          HevcSequenceParameterSet::decode_rbsp(&mut data).map(|set| sps.push(set))
+        }
+        HevcNalUnitHeader::PPS_NUT => {
+          HevcPictureParameterSet::decode_rbsp(&mut data, self.chroma_format_idc)
+            .map(|set| pps.push(set))
+        }
+        _ => Ok(()),
+      };
+      if let Err(e) = result {
+        log!(err@"#[hvcC] {e}");
+      }
+    }
+    self.vps = vps.into_boxed_slice();
+    self.sps = sps.into_boxed_slice();
+    self.pps = pps.into_boxed_slice();
+  }
+}
+
+impl HvcCAtom {
+  /// Profile, tier, level, chroma format and bit depth, e.g. `Main 10, Main Tier, Level 5.1,
+  /// 4:2:0 10-bit`
+  pub fn description(&self) -> String {
+    let profile_tier_level = self
+      .sps
+      .first()
+      .map(|sps| &sps.profile_tier_level)
+      .unwrap_or(&self.profile_tier_level);
+    let tier = profile_tier_level
+      .general_profile
+      .as_ref()
+      .map(LayerProfile::tier_name)
+      .unwrap_or("Main");
+    let (major, minor) = profile_tier_level.level();
+    let (chroma_format, bit_depth) = match self.sps.first() {
+      Some(sps) => (sps.chroma_format(), sps.bit_depth_luma()),
+      None => (
+        ["4:0:0", "4:2:0", "4:2:2", "4:4:4"][self.chroma_format_idc as usize],
+        self.bit_depth_luma_minus8 + 8,
+      ),
+    };
+    format!(
+      "{}, {tier} Tier, Level {major}.{minor}, {chroma_format} {bit_depth}-bit",
+      profile_tier_level.profile_name()
+    )
+  }
+}
+
+/// The NAL unit type of the array is read from the units themselves
+#[derive(Debug)]
+pub struct NalArray {
+  /// Raw NAL units, emulation prevention bytes included
+  pub units: ParameterSetUnits,
+}
+
+/// 7.3.1.2 Two byte HEVC NAL unit header
+#[derive(Debug, Clone, Copy)]
+pub struct HevcNalUnitHeader {
+  pub forbidden_zero_bit: bool,
+  pub nal_unit_type: u8,
+  pub nuh_layer_id: u8,
+  pub nuh_temporal_id_plus1: u8,
+}
+
+impl HevcNalUnitHeader {
+  pub const VPS_NUT: u8 = 32;
+  pub const SPS_NUT: u8 = 33;
+  pub const PPS_NUT: u8 = 34;
+  pub const EOS_NUT: u8 = 36;
+
+  pub fn new(header: [u8; 2]) -> Self {
+    Self {
+      forbidden_zero_bit: header[0] & 0x80 != 0,
+      nal_unit_type: (header[0] >> 1) & 0x3F,
+      nuh_layer_id: (header[0] & 1) << 5 | header[1] >> 3,
+      nuh_temporal_id_plus1: header[1] & 0b111,
+    }
+  }
+
+  /// Coded slice segments of non-IRAP pictures are types 0 to 9 and those of IRAP pictures
+  /// 16 to 21, the other VCL types are reserved
+  pub fn is_slice_segment(&self) -> bool {
+    matches!(self.nal_unit_type, 0..=9 | 16..=21)
+  }
+
+  /// Intra random access point pictures: BLA, IDR and CRA
+  pub fn is_irap(&self) -> bool {
+    (16..=23).contains(&self.nal_unit_type)
+  }
+
+  /// IDR_W_RADL and IDR_N_LP
+  pub fn is_idr(&self) -> bool {
+    matches!(self.nal_unit_type, 19 | 20)
+  }
+
+  /// BLA_W_LP, BLA_W_RADL and BLA_N_LP
+  pub fn is_bla(&self) -> bool {
+    matches!(self.nal_unit_type, 16..=18)
+  }
+
+  pub fn is_cra(&self) -> bool {
+    self.nal_unit_type == 21
+  }
+
+  /// Random access skipped leading pictures, RASL_N and RASL_R
+  pub fn is_rasl(&self) -> bool {
+    matches!(self.nal_unit_type, 8 | 9)
+  }
+
+  /// Random access decodable leading pictures, RADL_N and RADL_R
+  pub fn is_radl(&self) -> bool {
+    matches!(self.nal_unit_type, 6 | 7)
+  }
+
+  /// Sub-layer non-reference pictures, the even types up to RSV_VCL_N14
+  pub fn is_sub_layer_non_reference(&self) -> bool {
+    self.nal_unit_type <= 14 && self.nal_unit_type.is_multiple_of(2)
+  }
+
+  /// TemporalId
+  pub fn temporal_id(&self) -> u8 {
+    self.nuh_temporal_id_plus1.saturating_sub(1)
+  }
+
+  /// Splits `unit` into its header and RBSP. Emulation prevention bytes are removed up front
+  /// since exponential Golomb codes are read from the raw bytes
+  pub fn split_rbsp(unit: &[u8]) -> Option<(Self, Vec<u8>)> {
+    let header = Self::new(unit.get(..2)?.try_into().ok()?);
+    let mut rbsp = Vec::with_capacity(unit.len());
+    let mut zeros = 0;
+    for &byte in &unit[2..] {
+      if zeros >= 2 && byte == 0x03 {
+        zeros = 0;
+        continue;
+      }
+      zeros = if byte == 0 { zeros + 1 } else { 0 };
+      rbsp.push(byte);
+    }
+    Some((header, rbsp))
+  }
+}
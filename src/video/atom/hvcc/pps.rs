@@ -0,0 +1,264 @@
+use super::*;
+
+/// 7.3.2.3 HEVC picture parameter set
+#[derive(Debug)]
+pub struct HevcPictureParameterSet {
+  pub id: u8,
+  pub seq_parameter_set_id: u8,
+  pub dependent_slice_segments_enabled_flag: bool,
+  pub output_flag_present_flag: bool,
+  pub num_extra_slice_header_bits: u8,
+  pub sign_data_hiding_enabled_flag: bool,
+  pub cabac_init_present_flag: bool,
+  pub num_ref_idx_l0_default_active_minus1: u8,
+  pub num_ref_idx_l1_default_active_minus1: u8,
+  pub init_qp_minus26: i16,
+  pub constrained_intra_pred_flag: bool,
+  pub transform_skip_enabled_flag: bool,
+  /// `diff_cu_qp_delta_depth`, `None` when `cu_qp_delta_enabled_flag` is 0
+  pub diff_cu_qp_delta_depth: Option<u8>,
+  pub cb_qp_offset: i16,
+  pub cr_qp_offset: i16,
+  pub slice_chroma_qp_offsets_present_flag: bool,
+  pub weighted_pred_flag: bool,
+  pub weighted_bipred_flag: bool,
+  pub transquant_bypass_enabled_flag: bool,
+  pub tiles: Option<TileInfo>,
+  pub entropy_coding_sync_enabled_flag: bool,
+  pub loop_filter_across_slices_enabled_flag: bool,
+  pub deblocking_filter_control: Option<DeblockingFilterControl>,
+  pub scaling_list: Option<ScalingListData>,
+  pub lists_modification_present_flag: bool,
+  pub log2_parallel_merge_level_minus2: u8,
+  pub slice_segment_header_extension_present_flag: bool,
+  pub range_extension: PpsRangeExtension,
+}
+
+impl HevcPictureParameterSet {
+  /// Decodes a PPS NAL unit, `data` starts right after the NAL unit header
+  pub fn decode_rbsp(data: &mut BitStream, chroma_format_idc: u8) -> AtomResult<Self> {
+    let id = data.exponential_golomb::<u16>();
+    if id > 63 {
+      return Err(AtomError::ParameterSet(
+        "pps_pic_parameter_set_id",
+        id as u32,
+      ));
+    }
+    let seq_parameter_set_id = data.exponential_golomb::<u16>();
+    if seq_parameter_set_id > 15 {
+      return Err(AtomError::ParameterSet(
+        "pps_seq_parameter_set_id",
+        seq_parameter_set_id as u32,
+      ));
+    }
+    let dependent_slice_segments_enabled_flag = data.bit_flag();
+    let output_flag_present_flag = data.bit_flag();
+    let num_extra_slice_header_bits = data.bits_into(3);
+    let sign_data_hiding_enabled_flag = data.bit_flag();
+    let cabac_init_present_flag = data.bit_flag();
+    let num_ref_idx_l0_default_active_minus1 = data.exponential_golomb::<u16>().min(14) as u8;
+    let num_ref_idx_l1_default_active_minus1 = data.exponential_golomb::<u16>().min(14) as u8;
+    let init_qp_minus26 = data.signed_exponential_golomb();
+    let constrained_intra_pred_flag = data.bit_flag();
+    let transform_skip_enabled_flag = data.bit_flag();
+    let diff_cu_qp_delta_depth = data
+      .bit_flag()
+      .then(|| data.exponential_golomb::<u16>() as u8);
+    let cb_qp_offset = data.signed_exponential_golomb();
+    let cr_qp_offset = data.signed_exponential_golomb();
+    let slice_chroma_qp_offsets_present_flag = data.bit_flag();
+    let weighted_pred_flag = data.bit_flag();
+    let weighted_bipred_flag = data.bit_flag();
+    let transquant_bypass_enabled_flag = data.bit_flag();
+    let tiles_enabled_flag = data.bit_flag();
+    let entropy_coding_sync_enabled_flag = data.bit_flag();
+    let tiles = if tiles_enabled_flag {
+      Some(TileInfo::decode(data)?)
+    } else {
+      None
+    };
+    let loop_filter_across_slices_enabled_flag = data.bit_flag();
+    let deblocking_filter_control = DeblockingFilterControl::decode(data.bit_flag(), data);
+    let scaling_list = data
+      .bit_flag()
+      .then(|| ScalingListData::decode(data, chroma_format_idc));
+    let lists_modification_present_flag = data.bit_flag();
+    let log2_parallel_merge_level_minus2 = data.exponential_golomb::<u16>() as u8;
+    let slice_segment_header_extension_present_flag = data.bit_flag();
+    let mut range_extension = PpsRangeExtension::default();
+    if data.bit_flag() {
+      let pps_range_extension_flag = data.bit_flag();
+      data.bits_into::<u8>(7);
+      if pps_range_extension_flag {
+        range_extension = PpsRangeExtension::decode(data, transform_skip_enabled_flag)?;
+      }
+    }
+
+    Ok(Self {
+      id: id as u8,
+      seq_parameter_set_id: seq_parameter_set_id as u8,
+      dependent_slice_segments_enabled_flag,
+      output_flag_present_flag,
+      num_extra_slice_header_bits,
+      sign_data_hiding_enabled_flag,
+      cabac_init_present_flag,
+      num_ref_idx_l0_default_active_minus1,
+      num_ref_idx_l1_default_active_minus1,
+      init_qp_minus26,
+      constrained_intra_pred_flag,
+      transform_skip_enabled_flag,
+      diff_cu_qp_delta_depth,
+      cb_qp_offset,
+      cr_qp_offset,
+      slice_chroma_qp_offsets_present_flag,
+      weighted_pred_flag,
+      weighted_bipred_flag,
+      transquant_bypass_enabled_flag,
+      tiles,
+      entropy_coding_sync_enabled_flag,
+      loop_filter_across_slices_enabled_flag,
+      deblocking_filter_control,
+      scaling_list,
+      lists_modification_present_flag,
+      log2_parallel_merge_level_minus2,
+      slice_segment_header_extension_present_flag,
+      range_extension,
+    })
+  }
+}
+
+#[derive(Debug)]
+pub struct TileInfo {
+  pub num_tile_columns_minus1: u16,
+  pub num_tile_rows_minus1: u16,
+  /// Explicit column widths and row heights in CTBs, `None` for uniform spacing. The last
+  /// column and row take the remaining CTBs
+  pub column_width_minus1: Option<Box<[u16]>>,
+  pub row_height_minus1: Option<Box<[u16]>>,
+  pub loop_filter_across_tiles_enabled_flag: bool,
+}
+
+impl TileInfo {
+  /// A.4.2 level 6.2 allows at most 20 tile columns and 22 tile rows
+  const MAX_TILE_COLUMNS: u16 = 20;
+  const MAX_TILE_ROWS: u16 = 22;
+
+  pub fn decode(data: &mut BitStream) -> AtomResult<Self> {
+    let num_tile_columns_minus1 = data.exponential_golomb::<u16>();
+    let num_tile_rows_minus1 = data.exponential_golomb::<u16>();
+    if num_tile_columns_minus1 >= Self::MAX_TILE_COLUMNS {
+      return Err(AtomError::ParameterSet(
+        "num_tile_columns_minus1",
+        num_tile_columns_minus1 as u32,
+      ));
+    }
+    if num_tile_rows_minus1 >= Self::MAX_TILE_ROWS {
+      return Err(AtomError::ParameterSet(
+        "num_tile_rows_minus1",
+        num_tile_rows_minus1 as u32,
+      ));
+    }
+    let uniform_spacing_flag = data.bit_flag();
+    let (column_width_minus1, row_height_minus1) = if uniform_spacing_flag {
+      (None, None)
+    } else {
+      (
+        Some(
+          (0..num_tile_columns_minus1)
+            .map(|_| data.exponential_golomb())
+            .collect(),
+        ),
+        Some(
+          (0..num_tile_rows_minus1)
+            .map(|_| data.exponential_golomb())
+            .collect(),
+        ),
+      )
+    };
+    Ok(Self {
+      num_tile_columns_minus1,
+      num_tile_rows_minus1,
+      column_width_minus1,
+      row_height_minus1,
+      loop_filter_across_tiles_enabled_flag: data.bit_flag(),
+    })
+  }
+}
+
+#[derive(Debug)]
+pub struct DeblockingFilterControl {
+  pub deblocking_filter_override_enabled_flag: bool,
+  pub pps_deblocking_filter_disabled_flag: bool,
+  pub beta_offset_div2: i8,
+  pub tc_offset_div2: i8,
+}
+
+impl DeblockingFilterControl {
+  pub fn decode(
+    deblocking_filter_control_present_flag: bool,
+    data: &mut BitStream,
+  ) -> Option<Self> {
+    deblocking_filter_control_present_flag.then(|| {
+      let deblocking_filter_override_enabled_flag = data.bit_flag();
+      let pps_deblocking_filter_disabled_flag = data.bit_flag();
+      let (beta_offset_div2, tc_offset_div2) = if pps_deblocking_filter_disabled_flag {
+        (0, 0)
+      } else {
+        (
+          data.signed_exponential_golomb::<i16>().clamp(-6, 6) as i8,
+          data.signed_exponential_golomb::<i16>().clamp(-6, 6) as i8,
+        )
+      };
+      Self {
+        deblocking_filter_override_enabled_flag,
+        pps_deblocking_filter_disabled_flag,
+        beta_offset_div2,
+        tc_offset_div2,
+      }
+    })
+  }
+}
+
+/// 7.3.2.3.2 pps_range_extension, every value is 0 when absent
+#[derive(Debug, Default)]
+pub struct PpsRangeExtension {
+  pub log2_max_transform_skip_block_size_minus2: u8,
+  pub cross_component_prediction_enabled_flag: bool,
+  pub diff_cu_chroma_qp_offset_depth: u8,
+  /// `cb_qp_offset_list` and `cr_qp_offset_list`, empty when
+  /// `chroma_qp_offset_list_enabled_flag` is 0
+  pub chroma_qp_offset_list: Box<[(i8, i8)]>,
+  pub log2_sao_offset_scale_luma: u8,
+  pub log2_sao_offset_scale_chroma: u8,
+}
+
+impl PpsRangeExtension {
+  pub fn decode(data: &mut BitStream, transform_skip_enabled_flag: bool) -> AtomResult<Self> {
+    let mut extension = Self::default();
+    if transform_skip_enabled_flag {
+      extension.log2_max_transform_skip_block_size_minus2 = data.exponential_golomb::<u16>() as u8;
+    }
+    extension.cross_component_prediction_enabled_flag = data.bit_flag();
+    if data.bit_flag() {
+      extension.diff_cu_chroma_qp_offset_depth = data.exponential_golomb::<u16>() as u8;
+      let chroma_qp_offset_list_len_minus1 = data.exponential_golomb::<u16>();
+      if chroma_qp_offset_list_len_minus1 > 5 {
+        return Err(AtomError::ParameterSet(
+          "chroma_qp_offset_list_len_minus1",
+          chroma_qp_offset_list_len_minus1 as u32,
+        ));
+      }
+      extension.chroma_qp_offset_list = (0..=chroma_qp_offset_list_len_minus1)
+        .map(|_| {
+          (
+            data.signed_exponential_golomb::<i16>() as i8,
+            data.signed_exponential_golomb::<i16>() as i8,
+          )
+        })
+        .collect();
+    }
+    extension.log2_sao_offset_scale_luma = data.exponential_golomb::<u16>() as u8;
+    extension.log2_sao_offset_scale_chroma = data.exponential_golomb::<u16>() as u8;
+    Ok(extension)
+  }
+}
@@ -0,0 +1,113 @@
+use crate::byte::BitStream;
+
+/// 7.3.3 profile_tier_level, the sub-layer profiles and levels are read past
+#[derive(Debug)]
+pub struct ProfileTierLevel {
+  pub general_profile: Option<LayerProfile>,
+  pub general_level_idc: u8,
+}
+
+impl ProfileTierLevel {
+  pub fn decode(
+    profile_present_flag: bool,
+    max_num_sub_layers_minus1: u8,
+    data: &mut BitStream,
+  ) -> Self {
+    let general_profile = profile_present_flag.then(|| LayerProfile::decode(data));
+    let general_level_idc = data.byte();
+    let present_flags = (0..max_num_sub_layers_minus1)
+      .map(|_| (data.bit_flag(), data.bit_flag()))
+      .collect::<Vec<_>>();
+    if max_num_sub_layers_minus1 > 0 {
+      for _ in max_num_sub_layers_minus1..8 {
+        data.bits_into::<u8>(2);
+      }
+    }
+    for (sub_layer_profile_present_flag, sub_layer_level_present_flag) in present_flags {
+      if sub_layer_profile_present_flag {
+        LayerProfile::decode(data);
+      }
+      if sub_layer_level_present_flag {
+        data.byte();
+      }
+    }
+    Self {
+      general_profile,
+      general_level_idc,
+    }
+  }
+
+  /// A.3 Profile name of the general layer
+  pub fn profile_name(&self) -> &'static str {
+    self
+      .general_profile
+      .as_ref()
+      .map(LayerProfile::profile_name)
+      .unwrap_or("Unknown")
+  }
+
+  /// A.4.1 Level number, `general_level_idc` is 30 times the level
+  pub fn level(&self) -> (u8, u8) {
+    (self.general_level_idc / 30, self.general_level_idc % 30 / 3)
+  }
+}
+
+/// Profile of a layer, the source and constraint flags that follow the compatibility flags
+/// are read past
+#[derive(Debug)]
+pub struct LayerProfile {
+  pub tier_flag: bool,
+  pub profile_idc: u8,
+  pub profile_compatibility_flags: u32,
+}
+
+impl LayerProfile {
+  pub fn decode(data: &mut BitStream) -> Self {
+    // profile_space
+    data.bits_into::<u8>(2);
+    let profile = Self {
+      tier_flag: data.bit_flag(),
+      profile_idc: data.bits_into(5),
+      profile_compatibility_flags: data.bits_into(32),
+    };
+    // progressive_source_flag, interlaced_source_flag, non_packed_constraint_flag,
+    // frame_only_constraint_flag, 43 constraint flags and the inbld flag
+    data.bits_into::<u64>(48);
+    profile
+  }
+
+  /// `profile_idc`, or the first profile it is compatible with when not signalled
+  pub fn profile(&self) -> u8 {
+    match self.profile_idc {
+      0 => (1..32)
+        .find(|j| self.profile_compatibility_flags & (1 << (31 - j)) != 0)
+        .unwrap_or_default(),
+      profile_idc => profile_idc,
+    }
+  }
+
+  pub fn profile_name(&self) -> &'static str {
+    match self.profile() {
+      1 => "Main",
+      2 => "Main 10",
+      3 => "Main Still Picture",
+      4 => "Format Range Extensions",
+      5 => "High Throughput",
+      6 => "Multiview Main",
+      7 => "Scalable Main",
+      8 => "3D Main",
+      9 => "Screen Content Coding",
+      10 => "Scalable Format Range Extensions",
+      11 => "High Throughput Screen Content Coding",
+      _ => "Unknown",
+    }
+  }
+
+  pub fn tier_name(&self) -> &'static str {
+    if self.tier_flag {
+      "High"
+    } else {
+      "Main"
+    }
+  }
+}
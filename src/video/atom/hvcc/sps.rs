@@ -0,0 +1,511 @@
+use super::*;
+
+/// 7.3.2.2 HEVC sequence parameter set
+#[derive(Debug)]
+pub struct HevcSequenceParameterSet {
+  pub profile_tier_level: ProfileTierLevel,
+  pub id: u8,
+  pub chroma_format_idc: u8,
+  pub separate_colour_plane_flag: bool,
+  pub pic_width_in_luma_samples: u32,
+  pub pic_height_in_luma_samples: u32,
+  pub bit_depth_luma_minus8: u8,
+  pub bit_depth_chroma_minus8: u8,
+  pub log2_max_pic_order_cnt_lsb_minus4: u8,
+  pub sub_layer_ordering: Box<[SubLayerOrdering]>,
+  pub log2_min_luma_coding_block_size_minus3: u8,
+  pub log2_diff_max_min_luma_coding_block_size: u8,
+  pub log2_min_luma_transform_block_size_minus2: u8,
+  pub log2_diff_max_min_luma_transform_block_size: u8,
+  pub max_transform_hierarchy_depth_inter: u8,
+  pub max_transform_hierarchy_depth_intra: u8,
+  pub scaling_list_enabled_flag: bool,
+  /// `None` when scaling lists are disabled or the default lists are used
+  pub scaling_list: Option<ScalingListData>,
+  pub amp_enabled_flag: bool,
+  pub sample_adaptive_offset_enabled_flag: bool,
+  pub pcm: Option<PcmParameters>,
+  pub short_term_ref_pic_sets: Box<[ShortTermRefPicSet]>,
+  pub long_term_ref_pics: Option<LongTermRefPics>,
+  pub temporal_mvp_enabled_flag: bool,
+  pub strong_intra_smoothing_enabled_flag: bool,
+  pub vui_parameters: Option<HevcVuiParameters>,
+  pub range_extension: SpsRangeExtension,
+}
+
+impl HevcSequenceParameterSet {
+  pub const MAX_SHORT_TERM_REF_PIC_SETS: u8 = 64;
+  pub const MAX_LONG_TERM_REF_PICS: u8 = 32;
+
+  /// Decodes an SPS NAL unit, `data` starts right after the NAL unit header
+  pub fn decode_rbsp(data: &mut BitStream) -> AtomResult<Self> {
+    // sps_video_parameter_set_id
+    data.bits_into::<u8>(4);
+    let max_sub_layers_minus1 = data.bits_into::<u8>(3).min(6);
+    // sps_temporal_id_nesting_flag
+    data.bit_flag();
+    let profile_tier_level = ProfileTierLevel::decode(true, max_sub_layers_minus1, data);
+    let id = data.exponential_golomb::<u16>();
+    if id > 15 {
+      return Err(AtomError::ParameterSet(
+        "sps_seq_parameter_set_id",
+        id as u32,
+      ));
+    }
+    let chroma_format_idc = data.exponential_golomb::<u16>();
+    if chroma_format_idc > 3 {
+      return Err(AtomError::ParameterSet(
+        "chroma_format_idc",
+        chroma_format_idc as u32,
+      ));
+    }
+    let separate_colour_plane_flag = chroma_format_idc == 3 && data.bit_flag();
+    let pic_width_in_luma_samples = data.exponential_golomb();
+    let pic_height_in_luma_samples = data.exponential_golomb();
+    skip_window_offsets(data.bit_flag(), data);
+    let bit_depth_luma_minus8 = data.exponential_golomb::<u16>().min(8) as u8;
+    let bit_depth_chroma_minus8 = data.exponential_golomb::<u16>().min(8) as u8;
+    let log2_max_pic_order_cnt_lsb_minus4 = data.exponential_golomb::<u16>().min(12) as u8;
+    let sub_layer_ordering = SubLayerOrdering::decode(max_sub_layers_minus1, data);
+    let log2_min_luma_coding_block_size_minus3 = data.exponential_golomb::<u16>() as u8;
+    let log2_diff_max_min_luma_coding_block_size = data.exponential_golomb::<u16>() as u8;
+    let log2_min_luma_transform_block_size_minus2 = data.exponential_golomb::<u16>() as u8;
+    let log2_diff_max_min_luma_transform_block_size = data.exponential_golomb::<u16>() as u8;
+    let max_transform_hierarchy_depth_inter = data.exponential_golomb::<u16>() as u8;
+    let max_transform_hierarchy_depth_intra = data.exponential_golomb::<u16>() as u8;
+    let scaling_list_enabled_flag = data.bit_flag();
+    let scaling_list = (scaling_list_enabled_flag && data.bit_flag())
+      .then(|| ScalingListData::decode(data, chroma_format_idc as u8));
+    let amp_enabled_flag = data.bit_flag();
+    let sample_adaptive_offset_enabled_flag = data.bit_flag();
+    let pcm = PcmParameters::decode(data.bit_flag(), data);
+
+    let num_short_term_ref_pic_sets = data.exponential_golomb::<u16>();
+    if num_short_term_ref_pic_sets > Self::MAX_SHORT_TERM_REF_PIC_SETS as u16 {
+      return Err(AtomError::ParameterSet(
+        "num_short_term_ref_pic_sets",
+        num_short_term_ref_pic_sets as u32,
+      ));
+    }
+    let mut short_term_ref_pic_sets = Vec::with_capacity(num_short_term_ref_pic_sets as usize);
+    for _ in 0..num_short_term_ref_pic_sets {
+      let set = ShortTermRefPicSet::decode(
+        data,
+        &short_term_ref_pic_sets,
+        num_short_term_ref_pic_sets as usize,
+      )?;
+      short_term_ref_pic_sets.push(set);
+    }
+    let long_term_ref_pics = if data.bit_flag() {
+      Some(LongTermRefPics::decode(
+        data,
+        log2_max_pic_order_cnt_lsb_minus4 + 4,
+      )?)
+    } else {
+      None
+    };
+    let temporal_mvp_enabled_flag = data.bit_flag();
+    let strong_intra_smoothing_enabled_flag = data.bit_flag();
+    let vui_parameters = HevcVuiParameters::decode(data.bit_flag(), max_sub_layers_minus1, data);
+    let mut range_extension = SpsRangeExtension::default();
+    if data.bit_flag() {
+      let sps_range_extension_flag = data.bit_flag();
+      data.bits_into::<u8>(7);
+      if sps_range_extension_flag {
+        range_extension = SpsRangeExtension::decode(data);
+      }
+    }
+
+    Ok(Self {
+      profile_tier_level,
+      id: id as u8,
+      chroma_format_idc: chroma_format_idc as u8,
+      separate_colour_plane_flag,
+      pic_width_in_luma_samples,
+      pic_height_in_luma_samples,
+      bit_depth_luma_minus8,
+      bit_depth_chroma_minus8,
+      log2_max_pic_order_cnt_lsb_minus4,
+      sub_layer_ordering,
+      log2_min_luma_coding_block_size_minus3,
+      log2_diff_max_min_luma_coding_block_size,
+      log2_min_luma_transform_block_size_minus2,
+      log2_diff_max_min_luma_transform_block_size,
+      max_transform_hierarchy_depth_inter,
+      max_transform_hierarchy_depth_intra,
+      scaling_list_enabled_flag,
+      scaling_list,
+      amp_enabled_flag,
+      sample_adaptive_offset_enabled_flag,
+      pcm,
+      short_term_ref_pic_sets: short_term_ref_pic_sets.into_boxed_slice(),
+      long_term_ref_pics,
+      temporal_mvp_enabled_flag,
+      strong_intra_smoothing_enabled_flag,
+      vui_parameters,
+      range_extension,
+    })
+  }
+}
+
+impl HevcSequenceParameterSet {
+  /// 6.2 SubWidthC and SubHeightC
+  pub fn chroma_subsampling(&self) -> (u32, u32) {
+    match self.chroma_format_idc {
+      1 => (2, 2),
+      2 => (2, 1),
+      _ => (1, 1),
+    }
+  }
+
+  pub fn bit_depth_luma(&self) -> u8 {
+    self.bit_depth_luma_minus8 + 8
+  }
+
+  pub fn bit_depth_chroma(&self) -> u8 {
+    self.bit_depth_chroma_minus8 + 8
+  }
+
+  pub fn chroma_format(&self) -> &'static str {
+    match self.chroma_format_idc {
+      0 => "4:0:0",
+      1 => "4:2:0",
+      2 => "4:2:2",
+      _ => "4:4:4",
+    }
+  }
+
+  pub fn log2_min_cb_size(&self) -> u8 {
+    self.log2_min_luma_coding_block_size_minus3 + 3
+  }
+
+  pub fn log2_ctb_size(&self) -> u8 {
+    self.log2_min_cb_size() + self.log2_diff_max_min_luma_coding_block_size
+  }
+}
+
+/// Conformance cropping window of the SPS and default display window of the VUI, the left,
+/// right, top and bottom offsets
+pub fn skip_window_offsets(window_flag: bool, data: &mut BitStream) {
+  if window_flag {
+    for _ in 0..4 {
+      data.exponential_golomb::<u32>();
+    }
+  }
+}
+
+/// DPB size and reordering of a temporal sub-layer, shared by the VPS and the SPS
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubLayerOrdering {
+  pub max_dec_pic_buffering_minus1: u8,
+  pub max_num_reorder_pics: u8,
+  pub max_latency_increase_plus1: u32,
+}
+
+impl SubLayerOrdering {
+  /// Sub-layers without signalled values share the ones of the highest sub-layer
+  pub fn decode(max_sub_layers_minus1: u8, data: &mut BitStream) -> Box<[Self]> {
+    let sub_layer_ordering_info_present_flag = data.bit_flag();
+    let first = if sub_layer_ordering_info_present_flag {
+      0
+    } else {
+      max_sub_layers_minus1
+    };
+    let mut ordering = (first..=max_sub_layers_minus1)
+      .map(|_| Self {
+        max_dec_pic_buffering_minus1: data.exponential_golomb::<u16>().min(15) as u8,
+        max_num_reorder_pics: data.exponential_golomb::<u16>().min(15) as u8,
+        max_latency_increase_plus1: data.exponential_golomb(),
+      })
+      .collect::<Vec<_>>();
+    if !sub_layer_ordering_info_present_flag {
+      ordering.resize(max_sub_layers_minus1 as usize + 1, ordering[0]);
+    }
+    ordering.into_boxed_slice()
+  }
+}
+
+#[derive(Debug)]
+pub struct PcmParameters {
+  pub sample_bit_depth_luma_minus1: u8,
+  pub sample_bit_depth_chroma_minus1: u8,
+  pub log2_min_pcm_luma_coding_block_size_minus3: u8,
+  pub log2_diff_max_min_pcm_luma_coding_block_size: u8,
+  pub loop_filter_disabled_flag: bool,
+}
+
+impl PcmParameters {
+  pub fn decode(pcm_enabled_flag: bool, data: &mut BitStream) -> Option<Self> {
+    pcm_enabled_flag.then(|| Self {
+      sample_bit_depth_luma_minus1: data.bits_into(4),
+      sample_bit_depth_chroma_minus1: data.bits_into(4),
+      log2_min_pcm_luma_coding_block_size_minus3: data.exponential_golomb::<u16>() as u8,
+      log2_diff_max_min_pcm_luma_coding_block_size: data.exponential_golomb::<u16>() as u8,
+      loop_filter_disabled_flag: data.bit_flag(),
+    })
+  }
+}
+
+/// 7.3.7 st_ref_pic_set, stored as the derived delta POC lists of 7.4.8
+#[derive(Debug, Default, Clone)]
+pub struct ShortTermRefPicSet {
+  /// Negative POC deltas, closest picture first
+  pub delta_poc_s0: Box<[i32]>,
+  pub used_by_curr_pic_s0: Box<[bool]>,
+  /// Positive POC deltas, closest picture first
+  pub delta_poc_s1: Box<[i32]>,
+  pub used_by_curr_pic_s1: Box<[bool]>,
+}
+
+impl ShortTermRefPicSet {
+  /// Decodes the set at index `sets.len()`. Sets are predicted from one of `sets`, the set
+  /// of a slice header is at index `num_short_term_ref_pic_sets`
+  pub fn decode(
+    data: &mut BitStream,
+    sets: &[Self],
+    num_short_term_ref_pic_sets: usize,
+  ) -> AtomResult<Self> {
+    let index = sets.len();
+    let inter_ref_pic_set_prediction_flag = index != 0 && data.bit_flag();
+    if !inter_ref_pic_set_prediction_flag {
+      let num_negative_pics = data.exponential_golomb::<u16>();
+      let num_positive_pics = data.exponential_golomb::<u16>();
+      if num_negative_pics > 16 || num_positive_pics > 16 {
+        return Err(AtomError::ParameterSet(
+          "num_delta_pocs",
+          (num_negative_pics + num_positive_pics) as u32,
+        ));
+      }
+      let mut deltas = |count: u16, sign: i32| {
+        let mut poc = 0;
+        (0..count)
+          .map(|_| {
+            poc += sign * (data.exponential_golomb::<u32>() as i32 + 1);
+            (poc, data.bit_flag())
+          })
+          .unzip::<_, _, Vec<_>, Vec<_>>()
+      };
+      let (delta_poc_s0, used_by_curr_pic_s0) = deltas(num_negative_pics, -1);
+      let (delta_poc_s1, used_by_curr_pic_s1) = deltas(num_positive_pics, 1);
+      return Ok(Self {
+        delta_poc_s0: delta_poc_s0.into(),
+        used_by_curr_pic_s0: used_by_curr_pic_s0.into(),
+        delta_poc_s1: delta_poc_s1.into(),
+        used_by_curr_pic_s1: used_by_curr_pic_s1.into(),
+      });
+    }
+
+    let delta_idx_minus1 = if index == num_short_term_ref_pic_sets {
+      data.exponential_golomb::<u16>() as usize
+    } else {
+      0
+    };
+    let Some(reference) = index
+      .checked_sub(delta_idx_minus1 + 1)
+      .and_then(|i| sets.get(i))
+    else {
+      return Err(AtomError::ParameterSet(
+        "delta_idx_minus1",
+        delta_idx_minus1 as u32,
+      ));
+    };
+    let delta_rps_sign = data.bit_flag();
+    let abs_delta_rps_minus1 = data.exponential_golomb::<u32>() as i32;
+    let delta_rps = (1 - 2 * delta_rps_sign as i32) * (abs_delta_rps_minus1 + 1);
+    // used_by_curr_pic_flag and use_delta_flag of every reference picture and of the
+    // reference set itself, last
+    let flags = (0..=reference.num_delta_pocs())
+      .map(|_| {
+        let used_by_curr_pic_flag = data.bit_flag();
+        (
+          used_by_curr_pic_flag,
+          used_by_curr_pic_flag || data.bit_flag(),
+        )
+      })
+      .collect::<Vec<_>>();
+    let num_negative = reference.delta_poc_s0.len();
+    let s0 = reference.delta_poc_s0.iter().zip(&flags);
+    let s1 = reference.delta_poc_s1.iter().zip(&flags[num_negative..]);
+    // Offset by delta_rps like the other pictures, the reference set itself has a delta of 0
+    let own = std::iter::once((&0, &flags[reference.num_delta_pocs()]));
+
+    let (delta_poc_s0, used_by_curr_pic_s0) = s1
+      .clone()
+      .rev()
+      .chain(own.clone())
+      .chain(s0.clone())
+      .map(|(poc, flags)| (poc + delta_rps, flags))
+      .filter(|&(poc, &(_, use_delta_flag))| poc < 0 && use_delta_flag)
+      .map(|(poc, &(used, _))| (poc, used))
+      .unzip::<_, _, Vec<_>, Vec<_>>();
+    let (delta_poc_s1, used_by_curr_pic_s1) = s0
+      .rev()
+      .chain(own)
+      .chain(s1)
+      .map(|(poc, flags)| (poc + delta_rps, flags))
+      .filter(|&(poc, &(_, use_delta_flag))| poc > 0 && use_delta_flag)
+      .map(|(poc, &(used, _))| (poc, used))
+      .unzip::<_, _, Vec<_>, Vec<_>>();
+    if delta_poc_s0.len() > 16 || delta_poc_s1.len() > 16 {
+      return Err(AtomError::ParameterSet(
+        "num_delta_pocs",
+        (delta_poc_s0.len() + delta_poc_s1.len()) as u32,
+      ));
+    }
+    Ok(Self {
+      delta_poc_s0: delta_poc_s0.into(),
+      used_by_curr_pic_s0: used_by_curr_pic_s0.into(),
+      delta_poc_s1: delta_poc_s1.into(),
+      used_by_curr_pic_s1: used_by_curr_pic_s1.into(),
+    })
+  }
+
+  pub fn num_delta_pocs(&self) -> usize {
+    self.delta_poc_s0.len() + self.delta_poc_s1.len()
+  }
+}
+
+#[derive(Debug)]
+pub struct LongTermRefPics {
+  pub lt_ref_pic_poc_lsb_sps: Box<[u32]>,
+  pub used_by_curr_pic_lt_sps_flag: Box<[bool]>,
+}
+
+impl LongTermRefPics {
+  pub fn decode(data: &mut BitStream, log2_max_pic_order_cnt_lsb: u8) -> AtomResult<Self> {
+    let num_long_term_ref_pics_sps = data.exponential_golomb::<u16>();
+    if num_long_term_ref_pics_sps > HevcSequenceParameterSet::MAX_LONG_TERM_REF_PICS as u16 {
+      return Err(AtomError::ParameterSet(
+        "num_long_term_ref_pics_sps",
+        num_long_term_ref_pics_sps as u32,
+      ));
+    }
+    let (lt_ref_pic_poc_lsb_sps, used_by_curr_pic_lt_sps_flag) = (0..num_long_term_ref_pics_sps)
+      .map(|_| {
+        (
+          data.bits_into::<u32>(log2_max_pic_order_cnt_lsb as usize),
+          data.bit_flag(),
+        )
+      })
+      .unzip::<_, _, Vec<_>, Vec<_>>();
+    Ok(Self {
+      lt_ref_pic_poc_lsb_sps: lt_ref_pic_poc_lsb_sps.into(),
+      used_by_curr_pic_lt_sps_flag: used_by_curr_pic_lt_sps_flag.into(),
+    })
+  }
+}
+
+/// 7.3.2.2.2 sps_range_extension, every flag is 0 when absent
+#[derive(Debug, Default)]
+pub struct SpsRangeExtension {
+  pub transform_skip_rotation_enabled_flag: bool,
+  pub transform_skip_context_enabled_flag: bool,
+  pub implicit_rdpcm_enabled_flag: bool,
+  pub explicit_rdpcm_enabled_flag: bool,
+  pub extended_precision_processing_flag: bool,
+  pub intra_smoothing_disabled_flag: bool,
+  pub high_precision_offsets_enabled_flag: bool,
+  pub persistent_rice_adaptation_enabled_flag: bool,
+  pub cabac_bypass_alignment_enabled_flag: bool,
+}
+
+impl SpsRangeExtension {
+  pub fn decode(data: &mut BitStream) -> Self {
+    Self {
+      transform_skip_rotation_enabled_flag: data.bit_flag(),
+      transform_skip_context_enabled_flag: data.bit_flag(),
+      implicit_rdpcm_enabled_flag: data.bit_flag(),
+      explicit_rdpcm_enabled_flag: data.bit_flag(),
+      extended_precision_processing_flag: data.bit_flag(),
+      intra_smoothing_disabled_flag: data.bit_flag(),
+      high_precision_offsets_enabled_flag: data.bit_flag(),
+      persistent_rice_adaptation_enabled_flag: data.bit_flag(),
+      cabac_bypass_alignment_enabled_flag: data.bit_flag(),
+    }
+  }
+}
+
+#[rustfmt::skip]
+const DEFAULT_SCALING_LIST_INTRA: [u8; 64] = [
+  16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 17, 16, 17, 16, 17, 18,
+  17, 18, 18, 17, 18, 21, 19, 20, 21, 20, 19, 21, 24, 22, 22, 24,
+  24, 22, 22, 24, 25, 25, 27, 30, 27, 25, 25, 29, 31, 35, 35, 31,
+  29, 36, 41, 44, 41, 36, 47, 54, 54, 47, 65, 70, 65, 88, 88, 115,
+];
+#[rustfmt::skip]
+const DEFAULT_SCALING_LIST_INTER: [u8; 64] = [
+  16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 17, 17, 17, 17, 17, 18,
+  18, 18, 18, 18, 18, 20, 20, 20, 20, 20, 20, 20, 24, 24, 24, 24,
+  24, 24, 24, 24, 25, 25, 25, 25, 25, 25, 25, 28, 28, 28, 28, 28,
+  28, 33, 33, 33, 33, 33, 41, 41, 41, 41, 54, 54, 54, 71, 71, 91,
+];
+
+/// 7.3.4 scaling_list_data, lists are kept in up-right diagonal coefficient order
+#[derive(Debug, Clone)]
+pub struct ScalingListData {
+  /// `[size_id][matrix_id]`, 4x4 lists only use the first 16 coefficients
+  pub lists: [[[u8; 64]; 6]; 4],
+  /// DC coefficients of the 16x16 and 32x32 lists
+  pub dc_coef: [[u8; 6]; 2],
+}
+
+impl Default for ScalingListData {
+  /// 7.4.5 Table 7-5 and 7-6 default scaling lists
+  fn default() -> Self {
+    let mut lists = [[[16; 64]; 6]; 4];
+    for size_lists in &mut lists[1..] {
+      for (matrix_id, list) in size_lists.iter_mut().enumerate() {
+        *list = match matrix_id {
+          0..=2 => DEFAULT_SCALING_LIST_INTRA,
+          _ => DEFAULT_SCALING_LIST_INTER,
+        };
+      }
+    }
+    Self {
+      lists,
+      dc_coef: [[16; 6]; 2],
+    }
+  }
+}
+
+impl ScalingListData {
+  pub fn decode(data: &mut BitStream, chroma_format_idc: u8) -> Self {
+    let mut scaling = Self::default();
+    for size_id in 0..4 {
+      let step = if size_id == 3 { 3 } else { 1 };
+      for matrix_id in (0..6usize).step_by(step) {
+        let scaling_list_pred_mode_flag = data.bit_flag();
+        if !scaling_list_pred_mode_flag {
+          let delta = data.exponential_golomb::<u16>() as usize * step;
+          // A zero delta keeps the default list
+          if let Some(reference) = matrix_id.checked_sub(delta).filter(|_| delta != 0) {
+            scaling.lists[size_id][matrix_id] = scaling.lists[size_id][reference];
+            if size_id > 1 {
+              scaling.dc_coef[size_id - 2][matrix_id] = scaling.dc_coef[size_id - 2][reference];
+            }
+          }
+          continue;
+        }
+        let mut next_coef = 8;
+        let coef_num = 64.min(1 << (4 + (size_id << 1)));
+        if size_id > 1 {
+          next_coef = (data.signed_exponential_golomb::<i16>() + 8).clamp(1, 255);
+          scaling.dc_coef[size_id - 2][matrix_id] = next_coef as u8;
+        }
+        for coef in &mut scaling.lists[size_id][matrix_id][..coef_num] {
+          let delta = data.signed_exponential_golomb::<i16>();
+          next_coef = (next_coef + delta + 256).rem_euclid(256);
+          *coef = next_coef as u8;
+        }
+      }
+    }
+    // 7.3.4 32x32 chroma lists of 4:4:4 streams are inferred from the 16x16 ones
+    if chroma_format_idc == 3 {
+      for matrix_id in [1, 2, 4, 5] {
+        scaling.lists[3][matrix_id] = scaling.lists[2][matrix_id];
+        scaling.dc_coef[1][matrix_id] = scaling.dc_coef[0][matrix_id];
+      }
+    }
+    scaling
+  }
+}
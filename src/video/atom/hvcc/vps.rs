@@ -0,0 +1,52 @@
+use super::*;
+
+/// 7.3.2.1 HEVC video parameter set, only the id is kept since decoding uses the SPS. The
+/// layer sets and HRD parameters are checked, extensions are not parsed
+#[derive(Debug)]
+pub struct VideoParameterSet {
+  pub id: u8,
+}
+
+impl VideoParameterSet {
+  /// Decodes a VPS NAL unit, `data` starts right after the NAL unit header
+  pub fn decode_rbsp(data: &mut BitStream) -> AtomResult<Self> {
+    let id = data.bits_into(4);
+    // vps_base_layer_internal_flag, vps_base_layer_available_flag and vps_max_layers_minus1
+    data.byte();
+    let max_sub_layers_minus1 = data.bits_into::<u8>(3).min(6);
+    // vps_temporal_id_nesting_flag and vps_reserved_0xffff_16bits
+    data.bits_into::<u32>(17);
+    ProfileTierLevel::decode(true, max_sub_layers_minus1, data);
+    SubLayerOrdering::decode(max_sub_layers_minus1, data);
+    let max_layer_id = data.bits_into::<u8>(6);
+    let num_layer_sets_minus1 = data.exponential_golomb::<u16>();
+    if num_layer_sets_minus1 > 1023 {
+      return Err(AtomError::ParameterSet(
+        "vps_num_layer_sets_minus1",
+        num_layer_sets_minus1 as u32,
+      ));
+    }
+    // layer_id_included_flag of every layer set after the first
+    for _ in 0..num_layer_sets_minus1 {
+      data.bits_into::<u64>(max_layer_id as usize + 1);
+    }
+    let timing_info_present_flag = data.bit_flag();
+    skip_timing_info(timing_info_present_flag, data);
+    if timing_info_present_flag {
+      let num_hrd_parameters = data.exponential_golomb::<u16>();
+      if num_hrd_parameters > num_layer_sets_minus1 + 1 {
+        return Err(AtomError::ParameterSet(
+          "vps_num_hrd_parameters",
+          num_hrd_parameters as u32,
+        ));
+      }
+      for i in 0..num_hrd_parameters {
+        // hrd_layer_set_idx
+        data.exponential_golomb::<u16>();
+        let cprms_present_flag = i == 0 || data.bit_flag();
+        skip_hrd_parameters(cprms_present_flag, max_sub_layers_minus1, data);
+      }
+    }
+    Ok(Self { id })
+  }
+}
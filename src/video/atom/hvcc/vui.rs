@@ -0,0 +1,133 @@
+use super::*;
+
+/// E.2.1 HEVC VUI parameters, the aspect ratio, video signal and chroma location syntax is
+/// shared with H.264. Only the video signal type is kept, the other elements are read past
+/// since the SPS extensions follow the VUI
+#[derive(Debug)]
+pub struct HevcVuiParameters {
+  pub video_signal_type: Option<VideoSignalType>,
+}
+
+impl HevcVuiParameters {
+  pub fn decode(
+    vui_parameters_present_flag: bool,
+    max_sub_layers_minus1: u8,
+    data: &mut BitStream,
+  ) -> Option<Self> {
+    vui_parameters_present_flag.then(|| {
+      let aspect_ratio_idc = if data.bit_flag() { data.byte() } else { 0 };
+      SampleAspectRatio::decode(aspect_ratio_idc, data);
+      // overscan_appropriate_flag
+      if data.bit_flag() {
+        data.bit_flag();
+      }
+      let video_signal_type = VideoSignalType::decode(data.bit_flag(), data);
+      ChromaLocInfo::decode(data.bit_flag(), data);
+      // neutral_chroma_indication_flag, field_seq_flag and frame_field_info_present_flag
+      data.bits_into::<u8>(3);
+      skip_window_offsets(data.bit_flag(), data);
+      let timing_info_present_flag = data.bit_flag();
+      skip_timing_info(timing_info_present_flag, data);
+      if timing_info_present_flag && data.bit_flag() {
+        skip_hrd_parameters(true, max_sub_layers_minus1, data);
+      }
+      skip_bitstream_restriction(data.bit_flag(), data);
+      Self { video_signal_type }
+    })
+  }
+}
+
+/// Timing information of the VPS and the VUI
+pub fn skip_timing_info(timing_info_present_flag: bool, data: &mut BitStream) {
+  if timing_info_present_flag {
+    // num_units_in_tick and time_scale
+    data.bits_into::<u64>(64);
+    if data.bit_flag() {
+      data.exponential_golomb::<u32>();
+    }
+  }
+}
+
+/// E.2.2 hrd_parameters
+pub fn skip_hrd_parameters(
+  common_inf_present_flag: bool,
+  max_num_sub_layers_minus1: u8,
+  data: &mut BitStream,
+) {
+  let mut nal_hrd_parameters_present_flag = false;
+  let mut vcl_hrd_parameters_present_flag = false;
+  let mut sub_pic_hrd_params_present_flag = false;
+  if common_inf_present_flag {
+    nal_hrd_parameters_present_flag = data.bit_flag();
+    vcl_hrd_parameters_present_flag = data.bit_flag();
+    if nal_hrd_parameters_present_flag || vcl_hrd_parameters_present_flag {
+      sub_pic_hrd_params_present_flag = data.bit_flag();
+      if sub_pic_hrd_params_present_flag {
+        // tick_divisor_minus2, du_cpb_removal_delay_increment_length_minus1,
+        // sub_pic_cpb_params_in_pic_timing_sei_flag and dpb_output_delay_du_length_minus1
+        data.bits_into::<u32>(19);
+      }
+      // bit_rate_scale and cpb_size_scale
+      data.byte();
+      if sub_pic_hrd_params_present_flag {
+        // cpb_size_du_scale
+        data.bits_into::<u8>(4);
+      }
+      // initial_cpb_removal_delay_length_minus1, au_cpb_removal_delay_length_minus1 and
+      // dpb_output_delay_length_minus1
+      data.bits_into::<u16>(15);
+    }
+  }
+  for _ in 0..=max_num_sub_layers_minus1 {
+    let fixed_pic_rate_general_flag = data.bit_flag();
+    let fixed_pic_rate_within_cvs_flag = fixed_pic_rate_general_flag || data.bit_flag();
+    if fixed_pic_rate_within_cvs_flag {
+      // elemental_duration_in_tc_minus1
+      data.exponential_golomb::<u16>();
+    }
+    let low_delay_hrd_flag = !fixed_pic_rate_within_cvs_flag && data.bit_flag();
+    let cpb_cnt_minus1 = if low_delay_hrd_flag {
+      0
+    } else {
+      data.exponential_golomb::<u16>().min(31)
+    };
+    let present = [
+      nal_hrd_parameters_present_flag,
+      vcl_hrd_parameters_present_flag,
+    ];
+    for _ in present.into_iter().filter(|&present| present) {
+      for _ in 0..=cpb_cnt_minus1 {
+        skip_sub_layer_hrd_parameters(sub_pic_hrd_params_present_flag, data);
+      }
+    }
+  }
+}
+
+/// E.2.3 sub_layer_hrd_parameters for a single CPB
+fn skip_sub_layer_hrd_parameters(sub_pic_hrd_params_present_flag: bool, data: &mut BitStream) {
+  // bit_rate_value_minus1 and cpb_size_value_minus1, followed by cpb_size_du_value_minus1
+  // and bit_rate_du_value_minus1
+  let values = if sub_pic_hrd_params_present_flag {
+    4
+  } else {
+    2
+  };
+  for _ in 0..values {
+    data.exponential_golomb::<u32>();
+  }
+  // cbr_flag
+  data.bit_flag();
+}
+
+fn skip_bitstream_restriction(bitstream_restriction_flag: bool, data: &mut BitStream) {
+  if bitstream_restriction_flag {
+    // tiles_fixed_structure_flag, motion_vectors_over_pic_boundaries_flag and
+    // restricted_ref_pic_lists_flag
+    data.bits_into::<u8>(3);
+    // min_spatial_segmentation_idc, max_bytes_per_pic_denom, max_bits_per_min_cu_denom,
+    // log2_max_mv_length_horizontal and log2_max_mv_length_vertical
+    for _ in 0..5 {
+      data.exponential_golomb::<u16>();
+    }
+  }
+}
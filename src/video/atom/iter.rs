@@ -26,14 +26,31 @@ impl<'a> Iterator for AtomDataIter<'a> {
     (self.start + HEADER_SIZE < self.end).then(|| {
       let s = self.start as usize;
       let e = s + HEADER_SIZE as usize;
-      decode_header(&self.data[s..e]).and_then(|(atom_size, atom_type)| {
-        let offset = self.start + HEADER_SIZE + self.reader_offset;
-        self.start += atom_size as u64;
+      let data = self.data;
+      let atom = decode_header(&data[s..e]).and_then(|(atom_size, atom_type)| {
+        let (atom_size, header_size) = atom_size_64(
+          atom_size,
+          || {
+            Ok(u64::from_be_bytes(
+              data.get(e..e + 8).unwrap_or_default().try_into()?,
+            ))
+          },
+          self.end - self.start,
+        )?;
+        let offset = self.start + header_size + self.reader_offset;
+        // A truncated last atom ends with the data, a huge largesize would overflow `start`
+        let atom_end = atom_size.min(self.end - self.start);
+        let data_end = s + atom_end as usize;
+        self.start += atom_end;
         Ok((
-          Atom::new(atom_size, atom_type, offset)?,
-          &self.data[s + 8..s + atom_size as usize],
+          Atom::new(atom_size - header_size, atom_type, offset, header_size)?,
+          &data[s + header_size as usize..data_end],
         ))
-      })
+      });
+      if atom.is_err() {
+        self.start = self.end;
+      }
+      atom
     })
   }
 }
@@ -63,11 +80,60 @@ impl<'a, R: Read + Seek> Iterator for AtomIter<'a, R> {
     (self.start + HEADER_SIZE < self.end).then(|| {
       self.reader.seek(SeekFrom::Start(self.start))?;
       self.reader.read_exact(&mut self.buffer)?;
-      decode_header(&self.buffer).and_then(|(atom_size, atom_type)| {
-        let offset = self.start + HEADER_SIZE;
-        self.start += atom_size as u64;
-        Atom::new(atom_size, atom_type, offset)
-      })
+      let atom = decode_header(&self.buffer).and_then(|(atom_size, atom_type)| {
+        let (atom_size, header_size) = atom_size_64(
+          atom_size,
+          || {
+            let mut largesize = [0; 8];
+            self.reader.read_exact(&mut largesize)?;
+            Ok(u64::from_be_bytes(largesize))
+          },
+          self.end - self.start,
+        )?;
+        let offset = self.start + header_size;
+        self.start += atom_size;
+        Atom::new(atom_size - header_size, atom_type, offset, header_size)
+      });
+      if atom.is_err() {
+        self.start = self.end;
+      }
+      atom
     })
   }
 }
+
+/// Resolves the size field of an atom header into the full atom size and the header size.
+/// A size of 1 means the actual size is stored in the 64 bit `largesize` field right after the
+/// name and a size of 0 means the atom extends to the end of its container
+fn atom_size_64<F: FnOnce() -> AtomResult<u64>>(
+  size: u32,
+  largesize: F,
+  remaining: u64,
+) -> AtomResult<(u64, u64)> {
+  let (atom_size, header_size) = match size {
+    0 => (remaining, HEADER_SIZE),
+    1 => (largesize()?, HEADER_SIZE + 8),
+    size => (size as u64, HEADER_SIZE),
+  };
+  if atom_size < header_size {
+    return Err(AtomError::Size(atom_size));
+  }
+  Ok((atom_size, header_size))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn data_iter_truncated_largesize() {
+    let mut data = [0, 0, 0, 1, b'f', b'r', b'e', b'e'].to_vec();
+    data.extend(u64::MAX.to_be_bytes());
+    data.extend([0; 4]);
+    let mut atoms = AtomDataIter::new(&data, 0);
+    let (atom, payload) = atoms.next().unwrap().unwrap();
+    assert_eq!(*atom.name, *b"free");
+    assert_eq!(payload, [0; 4]);
+    assert!(atoms.next().is_none());
+  }
+}
@@ -39,10 +39,10 @@ pub struct MdhdAtom {
   pub atom: Atom,
   pub version: u8,
   pub flags: [u8; 3],
-  pub creation_time: u32,
-  pub modification_time: u32,
+  pub creation_time: u64,
+  pub modification_time: u64,
   pub timescale: u32,
-  pub duration: u32,
+  pub duration: u64,
   pub language: Str<3>,
   pub quality: u16,
 }
@@ -51,14 +51,15 @@ impl AtomDecoder for MdhdAtom {
   const NAME: [u8; 4] = *b"mdhd";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
     let mut data = atom.read_data(decoder)?;
+    let version = data.version();
     Ok(Self {
       atom,
-      version: data.version(),
+      version,
       flags: data.flags(),
-      creation_time: data.next_into()?,
-      modification_time: data.next_into()?,
+      creation_time: data.next_versioned(version)?,
+      modification_time: data.next_versioned(version)?,
       timescale: data.next_into()?,
-      duration: data.next_into()?,
+      duration: data.next_versioned(version)?,
       language: Str(unpack_language_code(data.next(2))?),
       quality: data.next_into()?,
     })
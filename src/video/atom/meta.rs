@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
 use super::*;
@@ -39,7 +40,9 @@ impl MetaAtom {
 }
 
 impl MetaAtom {
-  pub fn tags(&mut self) -> AtomResult<HashMap<Rc<str>, Rc<str>>> {
+  /// Values of the `ilst` items by key, the `keys` entry for the `mdta` handler and the item
+  /// type for the `mdir` handler, `----:mean:name` for freeform items
+  pub fn tags(&mut self) -> AtomResult<HashMap<Rc<str>, DataValue>> {
     match &*self.hdlr.handler_type {
       b"mdta" => {
         let Some(keys) = &mut self.keys else {
@@ -57,7 +60,7 @@ impl MetaAtom {
               .key_values
               .get(index)
               .ok_or_else(|| AtomError::MetaKeyValue(index, format!("{:?}", keys.key_values)))
-              .map(|key| (key.value.clone(), item.data.value.clone()))
+              .map(|key| (key.value.clone(), item.value().clone()))
           })
           .collect::<AtomResult<_>>()
       }
@@ -69,13 +72,29 @@ impl MetaAtom {
           values
             .items
             .iter()
-            .map(|item| (item.atom.name.into(), item.data.value.clone()))
+            .map(|item| (item.key(), item.value().clone()))
             .collect(),
         )
       }
       hdlr => Err(AtomError::MetaHandler(Str(*hdlr))),
     }
   }
+
+  /// First image of the `covr` item of the `mdir` handler
+  pub fn cover_art(&self) -> Option<&DataValue> {
+    if *self.hdlr.handler_type != *b"mdir" {
+      return None;
+    }
+    self
+      .ilst
+      .as_ref()?
+      .items
+      .iter()
+      .filter(|item| *item.atom.name == *b"covr")
+      .flat_map(|item| &*item.data)
+      .map(|data| &data.value)
+      .find(|value| value.image().is_some())
+  }
 }
 
 #[derive(Debug, Default)]
@@ -175,49 +194,249 @@ impl IlstAtom {
   }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct IlstItem {
   pub atom: Atom,
   pub index: u32,
-  pub data: DataAtom,
+  /// `mean` reverse DNS domain of `----` freeform items
+  pub mean: Option<Box<str>>,
+  /// `name` of `----` freeform items
+  pub name: Option<Box<str>>,
+  /// Every `data` atom of the item, `covr` may hold several images
+  pub data: Box<[DataAtom]>,
 }
 
 impl IlstItem {
   fn new(atom: Atom, data: AtomData) -> AtomResult<Self> {
+    let (mut mean, mut name, mut values) = (None, None, Vec::new());
+    for child in data.atoms() {
+      match child {
+        Ok((child, data)) => match &*child.name {
+          b"data" => values.push(DataAtom::new(AtomData::new(data, child.offset))?),
+          b"mean" => mean = Some(Self::full_string(data)),
+          b"name" => name = Some(Self::full_string(data)),
+          _ => log!(warn@"#[ilst] Unused atom {child:#?}"),
+        },
+        Err(e) => log!(err@"#[ilst] {e}"),
+      }
+    }
+    if values.is_empty() {
+      return Err(AtomError::IlstData);
+    }
+    if matches!(&*atom.name, b"trkn" | b"disk") {
+      for data in &mut values {
+        if let DataValue::Binary(bytes) = &data.value {
+          if let [_, _, n0, n1, t0, t1, ..] = **bytes {
+            data.value =
+              DataValue::Position(u16::from_be_bytes([n0, n1]), u16::from_be_bytes([t0, t1]));
+          }
+        }
+      }
+    }
     Ok(Self {
       atom,
       index: u32::from_be_bytes(*atom.name),
-      data: DataAtom::new(AtomData::new(
-        data.atoms().next().ok_or(AtomError::IlstData)??.1,
-        atom.offset,
-      ))?,
+      mean,
+      name,
+      data: values.into_boxed_slice(),
     })
   }
+
+  /// String of the `mean` and `name` full atoms
+  fn full_string(data: &[u8]) -> Box<str> {
+    String::from_utf8_lossy(data.get(4..).unwrap_or_default()).into()
+  }
+
+  /// Item type, `----:mean:name` for freeform items
+  pub fn key(&self) -> Rc<str> {
+    match (&self.mean, &self.name) {
+      (Some(mean), Some(name)) => format!("{}:{mean}:{name}", self.atom.name).into(),
+      _ => self.atom.name.into(),
+    }
+  }
+
+  pub fn value(&self) -> &DataValue {
+    &self.data[0].value
+  }
 }
 
 #[derive(Debug)]
 pub struct DataAtom {
   pub type_indicator: [u8; 4],
   pub locale_indicator: [u8; 4],
-  pub value: Rc<str>,
-}
-
-impl Default for DataAtom {
-  fn default() -> Self {
-    Self {
-      type_indicator: [0; 4],
-      locale_indicator: [0; 4],
-      value: "".into(),
-    }
-  }
+  pub value: DataValue,
 }
 
 impl DataAtom {
   fn new(mut data: AtomData) -> AtomResult<Self> {
+    let type_indicator = data.next_into()?;
     Ok(Self {
-      type_indicator: data.next_into()?,
+      type_indicator,
       locale_indicator: data.next_into()?,
-      value: std::str::from_utf8(&data).unwrap_or_default().into(),
+      value: DataValue::new(u32::from_be_bytes(type_indicator) & 0xFF_FFFF, &data),
     })
   }
 }
+
+/// Value of a `data` atom decoded by its well-known type
+#[derive(Debug, Clone)]
+pub enum DataValue {
+  Utf8(Rc<str>),
+  Utf16(Rc<str>),
+  Integer(i64),
+  Unsigned(u64),
+  Float32(f32),
+  Float64(f64),
+  Jpeg(Rc<[u8]>),
+  Png(Rc<[u8]>),
+  Bmp(Rc<[u8]>),
+  /// Number and total of the `trkn` and `disk` items
+  Position(u16, u16),
+  Binary(Rc<[u8]>),
+}
+
+impl DataValue {
+  const IMPLICIT: u32 = 0;
+  const UTF8: u32 = 1;
+  const UTF16: u32 = 2;
+  const UTF8_SORT: u32 = 4;
+  const UTF16_SORT: u32 = 5;
+  const JPEG: u32 = 13;
+  const PNG: u32 = 14;
+  const BE_SIGNED: u32 = 21;
+  const BE_UNSIGNED: u32 = 22;
+  const BE_FLOAT32: u32 = 23;
+  const BE_FLOAT64: u32 = 24;
+  const BMP: u32 = 27;
+  /// 8, 16, 32 and 64-bit signed integers
+  const SIGNED_SIZED: [u32; 4] = [65, 66, 67, 74];
+  /// 8, 16, 32 and 64-bit unsigned integers
+  const UNSIGNED_SIZED: [u32; 4] = [75, 76, 77, 78];
+
+  pub fn new(well_known_type: u32, data: &[u8]) -> Self {
+    let integer = matches!(data.len(), 1..=4 | 8);
+    let unsigned = || {
+      data
+        .iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+    };
+    match well_known_type {
+      Self::UTF8 | Self::UTF8_SORT => Self::Utf8(String::from_utf8_lossy(data).into()),
+      Self::UTF16 | Self::UTF16_SORT => Self::Utf16(
+        String::from_utf16_lossy(
+          &data
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>(),
+        )
+        .into(),
+      ),
+      Self::JPEG => Self::Jpeg(data.into()),
+      Self::PNG => Self::Png(data.into()),
+      Self::BMP => Self::Bmp(data.into()),
+      Self::BE_SIGNED if integer => {
+        let shift = 64 - 8 * data.len();
+        Self::Integer(((unsigned() << shift) as i64) >> shift)
+      }
+      Self::BE_UNSIGNED if integer => Self::Unsigned(unsigned()),
+      Self::BE_FLOAT32 if data.len() == 4 => Self::Float32(f32::from_bits(unsigned() as u32)),
+      Self::BE_FLOAT64 if data.len() == 8 => Self::Float64(f64::from_bits(unsigned())),
+      t if Self::SIGNED_SIZED.contains(&t) && integer => Self::new(Self::BE_SIGNED, data),
+      t if Self::UNSIGNED_SIZED.contains(&t) && integer => Self::new(Self::BE_UNSIGNED, data),
+      // Images of `covr` items are often stored without a type
+      Self::IMPLICIT if data.starts_with(&[0xFF, 0xD8, 0xFF]) => Self::Jpeg(data.into()),
+      Self::IMPLICIT if data.starts_with(b"\x89PNG") => Self::Png(data.into()),
+      _ => Self::Binary(data.into()),
+    }
+  }
+
+  /// File extension and data of image values
+  pub fn image(&self) -> Option<(&'static str, &[u8])> {
+    match self {
+      Self::Jpeg(data) => Some(("jpg", data)),
+      Self::Png(data) => Some(("png", data)),
+      Self::Bmp(data) => Some(("bmp", data)),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for DataValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Utf8(value) | Self::Utf16(value) => write!(f, "{value}"),
+      Self::Integer(value) => write!(f, "{value}"),
+      Self::Unsigned(value) => write!(f, "{value}"),
+      Self::Float32(value) => write!(f, "{value}"),
+      Self::Float64(value) => write!(f, "{value}"),
+      Self::Jpeg(data) => write!(f, "JPEG image ({} bytes)", data.len()),
+      Self::Png(data) => write!(f, "PNG image ({} bytes)", data.len()),
+      Self::Bmp(data) => write!(f, "BMP image ({} bytes)", data.len()),
+      Self::Position(number, 0) => write!(f, "{number}"),
+      Self::Position(number, total) => write!(f, "{number}/{total}"),
+      Self::Binary(data) => write!(f, "{} bytes", data.len()),
+    }
+  }
+}
+
+/// Description of the iTunes `mdir` item types
+pub fn item_name(key: &str) -> Option<&'static str> {
+  Some(match key {
+    "©nam" => "Title",
+    "©ART" => "Artist",
+    "aART" => "Album Artist",
+    "©alb" => "Album",
+    "©wrt" => "Composer",
+    "©day" => "Date",
+    "©gen" | "gnre" => "Genre",
+    "©cmt" => "Comment",
+    "©too" => "Encoder",
+    "©lyr" => "Lyrics",
+    "©grp" => "Grouping",
+    "desc" => "Description",
+    "ldes" => "Long Description",
+    "cprt" => "Copyright",
+    "trkn" => "Track",
+    "disk" => "Disc",
+    "tmpo" => "Tempo",
+    "cpil" => "Compilation",
+    "covr" => "Cover Art",
+    "tvsh" => "TV Show",
+    "tven" => "TV Episode ID",
+    "tvsn" => "TV Season",
+    "tves" => "TV Episode",
+    "stik" => "Media Kind",
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn data_value() {
+    let value = |well_known_type, data: &[u8]| DataValue::new(well_known_type, data).to_string();
+    assert_eq!(value(1, "Title ©".as_bytes()), "Title ©");
+    assert_eq!(value(2, &[0, b'h', 0xD8, 0x3D, 0xDE, 0x00]), "h😀");
+    assert_eq!(value(21, &[0xFF, 0xFE]), "-2");
+    assert_eq!(value(65, &[0x80]), "-128");
+    assert_eq!(value(74, &[0xFF; 8]), "-1");
+    assert_eq!(value(22, &[0xFF, 0xFE]), "65534");
+    assert_eq!(value(76, &[0x01, 0x00]), "256");
+    assert_eq!(value(23, &1.5f32.to_be_bytes()), "1.5");
+    assert_eq!(value(24, &(-0.25f64).to_be_bytes()), "-0.25");
+    assert_eq!(value(21, &[0; 5]), "5 bytes");
+    assert_eq!(value(23, &[0; 8]), "8 bytes");
+  }
+
+  #[test]
+  fn data_value_image() {
+    let jpeg = [0xFF, 0xD8, 0xFF, 0xE0];
+    assert_eq!(DataValue::new(13, &jpeg).image(), Some(("jpg", &jpeg[..])));
+    assert_eq!(DataValue::new(0, &jpeg).image(), Some(("jpg", &jpeg[..])));
+    assert_eq!(DataValue::new(0, b"\x89PNG\r\n").image().unwrap().0, "png");
+    assert_eq!(DataValue::new(27, b"BM").image().unwrap().0, "bmp");
+    assert_eq!(DataValue::new(0, b"BM").image(), None);
+  }
+}
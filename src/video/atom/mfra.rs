@@ -0,0 +1,55 @@
+use super::*;
+use crate::ascii::LogDisplay;
+use crate::log;
+use std::fmt;
+
+/// Movie fragment random access, fragments are demuxed in file order so only the number of
+/// random access points of every track is kept
+#[derive(Debug, Default)]
+pub struct MfraAtom {
+  pub tfra: Box<[TfraAtom]>,
+}
+
+impl MfraAtom {
+  pub fn new<R: Read + Seek>(mut atom: Atom, reader: &mut R) -> AtomResult<Self> {
+    let data = atom.read_data(reader)?;
+    let mut tfra = Vec::new();
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          b"tfra" => tfra.push(TfraAtom::new(AtomData::new(data, atom.offset))?),
+          // Only needed to find the mfra box from the end of the file
+          b"mfro" => (),
+          _ => log!(warn@"#[mfra] Unused atom {atom:#?}"),
+        },
+        Err(e) => log!(err@"#[mfra] {e}"),
+      }
+    }
+
+    Ok(Self {
+      tfra: tfra.into_boxed_slice(),
+    })
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct TfraAtom {
+  pub track_id: u32,
+  pub number_of_entries: u32,
+}
+
+impl TfraAtom {
+  pub fn new(mut data: AtomData) -> AtomResult<Self> {
+    Ok(Self {
+      track_id: data.reserved(4).next_into()?,
+      // length_size_of_traf_num, length_size_of_trun_num and length_size_of_sample_num
+      number_of_entries: data.reserved(4).next_into()?,
+    })
+  }
+}
+
+impl fmt::Display for TfraAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} (track #{})", self.number_of_entries, self.track_id)
+  }
+}
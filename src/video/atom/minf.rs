@@ -108,6 +108,7 @@ impl AtomDecoder for SmhdAtom {
 pub struct GmhdAtom {
   pub gmin: EncodedAtom,
   pub text: EncodedAtom,
+  pub tmcd: EncodedAtom,
 }
 
 impl AtomDecoder for GmhdAtom {
@@ -119,6 +120,7 @@ impl AtomDecoder for GmhdAtom {
         Ok(atom) => match &*atom.name {
           b"gmin" => gmhd.gmin = EncodedAtom::Encoded(atom),
           b"text" => gmhd.text = EncodedAtom::Encoded(atom),
+          b"tmcd" => gmhd.tmcd = EncodedAtom::Encoded(atom),
           _ => log!(warn@"#[gmhd] Unused atom {atom:#?}"),
         },
         Err(e) => log!(err@"#[gmhd] {e}"),
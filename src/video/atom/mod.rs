@@ -1,30 +1,46 @@
+mod av1c;
 mod avcc;
 mod decoder;
 mod edts;
+mod esds;
+mod hvcc;
 mod iter;
 mod mdat;
 mod mdia;
 mod meta;
+mod mfra;
 mod minf;
+mod moof;
 mod moov;
 mod root;
+mod sidx;
 mod stbl;
 mod stsd;
 mod trak;
+mod visual;
+mod vpcc;
 
+pub use av1c::*;
 pub use avcc::*;
 pub use decoder::*;
 pub use edts::*;
+pub use esds::*;
+pub use hvcc::*;
 pub use iter::*;
 pub use mdat::*;
 pub use mdia::*;
 pub use meta::*;
+pub use mfra::*;
 pub use minf::*;
+pub use moof::*;
 pub use moov::*;
 pub use root::*;
+pub use sidx::*;
 pub use stbl::*;
 pub use stsd::*;
 pub use trak::*;
+pub use visual::*;
+pub use vpcc::*;
 
 use super::Decoder;
 use crate::byte::Str;
@@ -67,41 +83,39 @@ pub enum AtomError {
   NoMinfHandler,
   #[error("MetaAtom is missing handler")]
   NoMetaHandler,
+  #[error("Invalid atom size {0}")]
+  Size(u64),
+  #[error("Expected descriptor tag {0:#04x} got {1:#04x}")]
+  DescriptorTag(u8, u8),
+  #[error("Invalid parameter set {0} {1}")]
+  ParameterSet(&'static str, u32),
 }
 
 pub type AtomResult<T = ()> = Result<T, AtomError>;
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Atom {
-  pub size: u32,
+  pub size: u64,
   pub name: Str<4>,
   pub offset: u64,
+  pub header_size: u64,
 }
 
 impl Atom {
-  fn new(size: u32, name: &[u8], offset: u64) -> AtomResult<Self> {
+  /// `size` is the size of the atom data, without the header
+  fn new(size: u64, name: &[u8], offset: u64, header_size: u64) -> AtomResult<Self> {
     Ok(Self {
-      size: if size <= HEADER_SIZE as u32 {
-        size
-      } else {
-        size - HEADER_SIZE as u32
-      },
+      size,
       name: Str::try_from(name)?,
       offset,
+      header_size,
     })
   }
 
   pub fn read_data<R: Read + Seek>(&mut self, reader: &mut R) -> AtomResult<AtomData> {
     reader.seek(SeekFrom::Start(self.offset))?;
-    let data = if self.size == 0 {
-      let mut data = Vec::new();
-      reader.read_to_end(&mut data)?;
-      data
-    } else {
-      let mut data = vec![0; self.size as usize];
-      reader.read_exact(&mut data)?;
-      data
-    };
+    let mut data = vec![0; self.size as usize];
+    reader.read_exact(&mut data)?;
     Ok(AtomData::new(&data, self.offset))
   }
 
@@ -109,7 +123,7 @@ impl Atom {
     &mut self,
     reader: &mut R,
   ) -> AtomResult<AtomData> {
-    if S as u32 > self.size {
+    if S as u64 > self.size {
       return Err(AtomError::NotEnoughData(*self, S));
     }
     let mut data = [0; S];
@@ -119,6 +133,6 @@ impl Atom {
   }
 
   pub fn atoms<'a, R: Read + Seek>(&self, reader: &'a mut R) -> AtomIter<'a, R> {
-    AtomIter::new(reader, self.offset, self.offset + self.size as u64)
+    AtomIter::new(reader, self.offset, self.offset + self.size)
   }
 }
@@ -0,0 +1,208 @@
+use super::*;
+use crate::ascii::LogDisplay;
+use crate::byte::{FromSlice, TryFromSlice};
+use crate::log;
+use std::fs::File;
+
+#[derive(Debug, Default)]
+pub struct MoofAtom {
+  pub atom: Atom,
+  pub traf: Box<[TrafAtom]>,
+}
+
+impl MoofAtom {
+  pub fn new(mut atom: Atom, reader: &mut File) -> AtomResult<Self> {
+    let data = atom.read_data(reader)?;
+    let mut traf = Vec::new();
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          // Fragments are walked in file order, the sequence number is not checked
+          b"mfhd" => (),
+          b"traf" => traf.push(TrafAtom::new(AtomData::new(data, atom.offset))?),
+          _ => log!(warn@"#[moof] Unused atom {atom:#?}"),
+        },
+        Err(e) => log!(err@"#[moof] {e}"),
+      }
+    }
+
+    Ok(Self {
+      atom,
+      traf: traf.into_boxed_slice(),
+    })
+  }
+
+  /// File offset of the first byte of the moof box, used as the base data offset by default
+  pub fn start(&self) -> u64 {
+    self.atom.offset - self.atom.header_size
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct TrafAtom {
+  pub tfhd: TfhdAtom,
+  pub tfdt: Option<TfdtAtom>,
+  pub trun: Box<[TrunAtom]>,
+  /// Fragment local sample group descriptions, referenced by `sbgp` indices above 0x10000
+  pub sgpd: Box<[SgpdAtom]>,
+  pub sbgp: Box<[SbgpAtom]>,
+}
+
+impl TrafAtom {
+  pub fn new(data: AtomData) -> AtomResult<Self> {
+    let mut tfhd = None;
+    let mut tfdt = None;
+    let mut trun = Vec::new();
+    let mut sgpd = Vec::new();
+    let mut sbgp = Vec::new();
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          b"tfhd" => tfhd = Some(TfhdAtom::new(AtomData::new(data, atom.offset))?),
+          b"tfdt" => tfdt = Some(TfdtAtom::new(AtomData::new(data, atom.offset))?),
+          b"trun" => trun.push(TrunAtom::new(AtomData::new(data, atom.offset))?),
+          b"sgpd" => sgpd.push(SgpdAtom::new(atom, AtomData::new(data, atom.offset))?),
+          b"sbgp" => sbgp.push(SbgpAtom::new(atom, AtomData::new(data, atom.offset))?),
+          _ => log!(warn@"#[traf] Unused atom {atom:#?}"),
+        },
+        Err(e) => log!(err@"#[traf] {e}"),
+      }
+    }
+
+    Ok(Self {
+      tfhd: tfhd.ok_or(AtomError::Required(*b"tfhd"))?,
+      tfdt,
+      trun: trun.into_boxed_slice(),
+      sgpd: sgpd.into_boxed_slice(),
+      sbgp: sbgp.into_boxed_slice(),
+    })
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct TfhdAtom {
+  pub track_id: u32,
+  pub base_data_offset: Option<u64>,
+  pub sample_description_index: Option<u32>,
+  pub default_sample_duration: Option<u32>,
+  pub default_sample_size: Option<u32>,
+  pub default_sample_flags: Option<SampleFlags>,
+  pub default_base_is_moof: bool,
+}
+
+impl TfhdAtom {
+  pub fn new(mut data: AtomData) -> AtomResult<Self> {
+    data.version();
+    let tf_flags = u64::from_slice(&data.flags()) as u32;
+    Ok(Self {
+      track_id: data.next_into()?,
+      base_data_offset: (tf_flags & 0x000001 != 0)
+        .then(|| data.next_into())
+        .transpose()?,
+      sample_description_index: (tf_flags & 0x000002 != 0)
+        .then(|| data.next_into())
+        .transpose()?,
+      default_sample_duration: (tf_flags & 0x000008 != 0)
+        .then(|| data.next_into())
+        .transpose()?,
+      default_sample_size: (tf_flags & 0x000010 != 0)
+        .then(|| data.next_into())
+        .transpose()?,
+      default_sample_flags: (tf_flags & 0x000020 != 0)
+        .then(|| data.next_into())
+        .transpose()?,
+      default_base_is_moof: tf_flags & 0x020000 != 0,
+    })
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct TfdtAtom {
+  pub base_media_decode_time: u64,
+}
+
+impl TfdtAtom {
+  pub fn new(mut data: AtomData) -> AtomResult<Self> {
+    let version = data.version();
+    Ok(Self {
+      base_media_decode_time: data.reserved(3).next_versioned(version)?,
+    })
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct TrunAtom {
+  pub data_offset: Option<i32>,
+  pub first_sample_flags: Option<SampleFlags>,
+  pub samples: Box<[TrunSample]>,
+}
+
+impl TrunAtom {
+  pub fn new(mut data: AtomData) -> AtomResult<Self> {
+    let version = data.version();
+    let tr_flags = u64::from_slice(&data.flags()) as u32;
+    let sample_count: u32 = data.next_into()?;
+    Ok(Self {
+      data_offset: (tr_flags & 0x000001 != 0)
+        .then(|| data.next_into())
+        .transpose()?,
+      first_sample_flags: (tr_flags & 0x000004 != 0)
+        .then(|| data.next_into())
+        .transpose()?,
+      samples: (0..sample_count)
+        .map(|_| {
+          Ok(TrunSample {
+            duration: (tr_flags & 0x000100 != 0)
+              .then(|| data.next_into())
+              .transpose()?,
+            size: (tr_flags & 0x000200 != 0)
+              .then(|| data.next_into())
+              .transpose()?,
+            flags: (tr_flags & 0x000400 != 0)
+              .then(|| data.next_into())
+              .transpose()?,
+            composition_time_offset: (tr_flags & 0x000800 != 0)
+              .then(|| {
+                if version == 0 {
+                  data.next_into::<u32>().map(|offset| offset as i32)
+                } else {
+                  data.next_into()
+                }
+              })
+              .transpose()?,
+          })
+        })
+        .collect::<AtomResult<_>>()?,
+    })
+  }
+}
+
+/// Per sample fields of a track run, missing fields fall back to the `tfhd` and `trex` defaults
+#[derive(Debug, Default)]
+pub struct TrunSample {
+  pub duration: Option<u32>,
+  pub size: Option<u32>,
+  pub flags: Option<SampleFlags>,
+  pub composition_time_offset: Option<i32>,
+}
+
+/// ISO/IEC 14496-12 8.8.3.1 sample flags
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SampleFlags(pub u32);
+
+impl SampleFlags {
+  pub fn is_non_sync_sample(&self) -> bool {
+    self.0 & 0x10000 != 0
+  }
+
+  pub fn is_sync(&self) -> bool {
+    !self.is_non_sync_sample()
+  }
+}
+
+impl TryFromSlice for SampleFlags {
+  const SIZE: usize = 4;
+  fn try_from_slice(slice: &[u8]) -> Result<Self, std::array::TryFromSliceError> {
+    u32::try_from_slice(slice).map(Self)
+  }
+}
@@ -10,11 +10,12 @@ pub struct MoovAtom {
   pub trak: Box<[EncodedAtom<TrakAtom>]>,
   pub udta: Option<EncodedAtom<UdtaAtom>>,
   pub meta: Option<MetaAtom>,
+  pub mvex: Option<EncodedAtom<MvexAtom>>,
 }
 
 impl MoovAtom {
   pub fn new(atom: Atom, reader: &mut File) -> AtomResult<Self> {
-    let (mut mvhd, mut udta, mut meta) = (EncodedAtom::Required, None, None);
+    let (mut mvhd, mut udta, mut meta, mut mvex) = (EncodedAtom::Required, None, None, None);
     let mut reader_clone = reader.try_clone()?;
     let trak = atom
       .atoms(&mut reader_clone)
@@ -24,6 +25,7 @@ impl MoovAtom {
             b"mvhd" => mvhd = EncodedAtom::Encoded(atom),
             b"trak" => return Some(EncodedAtom::<TrakAtom>::Encoded(atom)),
             b"udta" => udta = Some(EncodedAtom::Encoded(atom)),
+            b"mvex" => mvex = Some(EncodedAtom::Encoded(atom)),
             b"meta" => {
               meta = Some(
                 atom
@@ -45,6 +47,7 @@ impl MoovAtom {
       trak,
       udta,
       meta: meta.transpose()?,
+      mvex,
     })
   }
 }
@@ -54,10 +57,10 @@ pub struct MvhdAtom {
   pub atom: Atom,
   pub version: u8,
   pub flags: [u8; 3],
-  pub creation_time: u32,
-  pub modification_time: u32,
+  pub creation_time: u64,
+  pub modification_time: u64,
   pub timescale: u32,
-  pub duration: u32,
+  pub duration: u64,
   pub rate: f32,
   pub volume: f32,
   pub matrix: Matrix3x3,
@@ -68,14 +71,15 @@ impl AtomDecoder for MvhdAtom {
   const NAME: [u8; 4] = *b"mvhd";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
     let mut data = atom.read_data(decoder)?;
+    let version = data.version();
     Ok(Self {
       atom,
-      version: data.version(),
+      version,
       flags: data.flags(),
-      creation_time: data.next_into()?,
-      modification_time: data.next_into()?,
+      creation_time: data.next_versioned(version)?,
+      modification_time: data.next_versioned(version)?,
       timescale: data.next_into()?,
-      duration: data.next_into()?,
+      duration: data.next_versioned(version)?,
       rate: data.fixed_point_16()?,
       volume: data.fixed_point_8()?,
       matrix: data.reserved(2).reserved(8).next_into()?,
@@ -84,35 +88,131 @@ impl AtomDecoder for MvhdAtom {
   }
 }
 
+#[derive(Debug, Default)]
+pub struct MvexAtom {
+  pub trex: Box<[TrexAtom]>,
+}
+
+impl AtomDecoder for MvexAtom {
+  const NAME: [u8; 4] = *b"mvex";
+  fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
+    let data = atom.read_data(decoder)?;
+    let mut trex = Vec::new();
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          // Fragment samples carry their own durations
+          b"mehd" => (),
+          b"trex" => trex.push(TrexAtom::new(AtomData::new(data, atom.offset))?),
+          _ => log!(warn@"#[mvex] Unused atom {atom:#?}"),
+        },
+        Err(e) => log!(err@"#[mvex] {e}"),
+      }
+    }
+
+    Ok(Self {
+      trex: trex.into_boxed_slice(),
+    })
+  }
+}
+
+impl MvexAtom {
+  pub fn trex(&self, track_id: u32) -> Option<&TrexAtom> {
+    self.trex.iter().find(|trex| trex.track_id == track_id)
+  }
+}
+
+/// Per track defaults for the samples of movie fragments
+#[derive(Debug, Default)]
+pub struct TrexAtom {
+  pub track_id: u32,
+  pub default_sample_description_index: u32,
+  pub default_sample_duration: u32,
+  pub default_sample_size: u32,
+  pub default_sample_flags: SampleFlags,
+}
+
+impl TrexAtom {
+  pub fn new(mut data: AtomData) -> AtomResult<Self> {
+    Ok(Self {
+      track_id: data.reserved(4).next_into()?,
+      default_sample_description_index: data.next_into()?,
+      default_sample_duration: data.next_into()?,
+      default_sample_size: data.next_into()?,
+      default_sample_flags: data.next_into()?,
+    })
+  }
+}
+
 #[derive(Debug, Default)]
 pub struct UdtaAtom {
   pub metas: Box<[MetaAtom]>,
+  /// QuickTime `©` international text atoms, `©xyz`, `©mak`, `©mod`, `©swr`, `©day`...
+  pub texts: Box<[UserDataText]>,
 }
 
 impl AtomDecoder for UdtaAtom {
   const NAME: [u8; 4] = *b"udta";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
     let data = atom.read_data(decoder)?;
-    Ok(Self {
-      metas: data
-        .atoms()
-        .filter_map(|atom| {
-          match atom {
-            Ok((atom, data)) => match &*atom.name {
-              b"meta" => {
-                let mut data = AtomData::new(data, atom.offset);
-                if decoder.brand.is_isom() {
-                  data.reserved(4);
-                }
-                return Some(MetaAtom::new(atom, data));
-              }
-              _ => log!(warn@"#[udta] Unused atom {atom:#?}"),
-            },
-            Err(e) => log!(err@"#[udta] {e}"),
+    let (mut metas, mut texts) = (Vec::new(), Vec::new());
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          b"meta" => {
+            let mut data = AtomData::new(data, atom.offset);
+            if decoder.brand.is_isom() {
+              data.reserved(4);
+            }
+            metas.push(MetaAtom::new(atom, data)?);
           }
-          None
-        })
-        .collect::<AtomResult<_>>()?,
+          [0xA9, ..] => texts.extend(UserDataText::decode(atom.name, data)),
+          _ => log!(warn@"#[udta] Unused atom {atom:#?}"),
+        },
+        Err(e) => log!(err@"#[udta] {e}"),
+      }
+    }
+    Ok(Self {
+      metas: metas.into_boxed_slice(),
+      texts: texts.into_boxed_slice(),
     })
   }
 }
+
+impl UdtaAtom {
+  /// Value of the first text atom `name`
+  pub fn text(&self, name: &[u8; 4]) -> Option<&str> {
+    self
+      .texts
+      .iter()
+      .find(|text| *text.name == *name)
+      .map(|text| &*text.value)
+  }
+}
+
+/// Entry of a QuickTime user data text atom, a 16-bit size and language code precede each
+/// string
+#[derive(Debug)]
+pub struct UserDataText {
+  pub name: Str<4>,
+  pub value: Box<str>,
+}
+
+impl UserDataText {
+  pub fn decode(name: Str<4>, mut data: &[u8]) -> Vec<Self> {
+    let mut texts = Vec::new();
+    while let [s0, s1, _, _, rest @ ..] = data {
+      let size = u16::from_be_bytes([*s0, *s1]) as usize;
+      let Some(value) = rest.get(..size) else {
+        log!(warn@"#[udta] Text {name} of {size} bytes is truncated");
+        break;
+      };
+      texts.push(Self {
+        name,
+        value: String::from_utf8_lossy(value).trim_end_matches('\0').into(),
+      });
+      data = &rest[size..];
+    }
+    texts
+  }
+}
@@ -8,16 +8,22 @@ use std::{
 #[derive(Debug)]
 pub struct RootAtom {
   pub ftyp: FtypAtom,
-  pub mdat: MdatAtom,
+  pub mdat: Box<[MdatAtom]>,
   pub moov: MoovAtom,
+  pub moof: Box<[MoofAtom]>,
+  pub sidx: Box<[SidxAtom]>,
+  pub mfra: Option<MfraAtom>,
   pub rest: Box<[Atom]>,
 }
 
 impl RootAtom {
   pub fn new(reader: &mut File, size: u64) -> AtomResult<Self> {
     let mut ftyp = None;
-    let mut mdat = None;
+    let mut mdat = Vec::new();
     let mut moov = None;
+    let mut moof = Vec::new();
+    let mut sidx = Vec::new();
+    let mut mfra = None;
     let mut rest = Vec::new();
     let mut atoms = AtomIter::new(reader, 0, size);
 
@@ -25,26 +31,28 @@ impl RootAtom {
       match atom {
         Ok(atom) => match &*atom.name {
           b"ftyp" => ftyp = Some(FtypAtom::new(atom, atoms.reader)?),
-          b"mdat" => {
-            mdat = {
-              let mdat = MdatAtom::new(atom, atoms.reader)?;
-              if mdat.extended_size > u32::MAX as u64 {
-                atoms.start += mdat.extended_size + 7;
-              }
-              Some(mdat)
-            }
-          }
+          b"mdat" => mdat.push(MdatAtom::new(atom)),
           b"moov" => moov = Some(MoovAtom::new(atom, atoms.reader)?),
+          b"moof" => moof.push(MoofAtom::new(atom, atoms.reader)?),
+          b"sidx" => sidx.push(SidxAtom::new(atom, atoms.reader)?),
+          b"mfra" => mfra = Some(MfraAtom::new(atom, atoms.reader)?),
           _ => rest.push(atom),
         },
         Err(e) => log!(err@"#[root] {e}"),
       }
     }
 
+    if mdat.is_empty() {
+      return Err(AtomError::Required(*b"mdat"));
+    }
+
     Ok(Self {
       ftyp: ftyp.ok_or(AtomError::Required(*b"ftyp"))?,
-      mdat: mdat.ok_or(AtomError::Required(*b"mdat"))?,
+      mdat: mdat.into_boxed_slice(),
       moov: moov.ok_or(AtomError::Required(*b"moov"))?,
+      moof: moof.into_boxed_slice(),
+      sidx: sidx.into_boxed_slice(),
+      mfra,
       rest: rest.into_boxed_slice(),
     })
   }
@@ -0,0 +1,57 @@
+use super::*;
+use crate::display::Duration;
+use std::fmt;
+
+/// Segment index, the references are summed up since segments are demuxed through their
+/// `moof` boxes
+#[derive(Debug, Default)]
+pub struct SidxAtom {
+  pub timescale: u32,
+  pub reference_count: u16,
+  /// Sum of the `subsegment_duration` of every reference, in `timescale` units
+  pub duration: u64,
+  /// Sum of the `referenced_size` of every reference in bytes
+  pub size: u64,
+}
+
+impl SidxAtom {
+  pub fn new<R: Read + Seek>(mut atom: Atom, reader: &mut R) -> AtomResult<Self> {
+    let mut data = atom.read_data(reader)?;
+    let version = data.version();
+    // flags and reference_ID
+    let timescale = data.reserved(7).next_into()?;
+    // earliest_presentation_time and first_offset
+    data.next_versioned(version)?;
+    data.next_versioned(version)?;
+    let reference_count = data.reserved(2).next_into()?;
+    let mut sidx = Self {
+      timescale,
+      reference_count,
+      ..Default::default()
+    };
+    for _ in 0..reference_count {
+      let reference: u32 = data.next_into()?;
+      sidx.size += (reference & 0x7FFF_FFFF) as u64;
+      sidx.duration += data.next_into::<u32>()? as u64;
+      // starts_with_SAP, SAP_type and SAP_delta_time
+      data.reserved(4);
+    }
+    Ok(sidx)
+  }
+}
+
+impl fmt::Display for SidxAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let seconds = match self.timescale {
+      0 => 0.,
+      timescale => self.duration as f32 / timescale as f32,
+    };
+    write!(
+      f,
+      "{} references, {} bytes, {}",
+      self.reference_count,
+      self.size,
+      Duration::from_secs_f32(seconds)
+    )
+  }
+}
@@ -3,7 +3,7 @@ use std::marker::PhantomData;
 
 use super::*;
 use crate::ascii::LogDisplay;
-use crate::byte::FromSlice;
+use crate::byte::{FromSlice, TryFromSlice};
 use crate::log;
 
 #[derive(Debug)]
@@ -16,8 +16,8 @@ pub struct StblAtom {
   pub stss: Option<StssAtom>,
   pub stsz: StszAtom,
   pub stco: StcoAtom,
-  pub sgpd: Option<SgpdAtom>,
-  pub sbgp: Option<SbgpAtom>,
+  pub sgpd: Box<[SgpdAtom]>,
+  pub sbgp: Box<[SbgpAtom]>,
 }
 
 impl AtomDecoder for StblAtom {
@@ -30,8 +30,8 @@ impl AtomDecoder for StblAtom {
     let mut stss = None;
     let mut stsz = None;
     let mut stco = None;
-    let mut sgpd = None;
-    let mut sbgp = None;
+    let mut sgpd = Vec::new();
+    let mut sbgp = Vec::new();
     let mut atoms = atom.atoms(decoder);
     while let Some(atom) = atoms.next() {
       match atom {
@@ -43,8 +43,8 @@ impl AtomDecoder for StblAtom {
           b"stss" => stss = Some(StssAtom::decode_unchecked(atom, atoms.reader)?),
           b"stsz" => stsz = Some(StszAtom::decode_unchecked(atom, atoms.reader)?),
           b"stco" | b"co64" => stco = Some(StcoAtom::decode_unchecked(atom, atoms.reader)?),
-          b"sgpd" => sgpd = Some(SgpdAtom::decode_unchecked(atom, atoms.reader)?),
-          b"sbgp" => sbgp = Some(SbgpAtom::decode_unchecked(atom, atoms.reader)?),
+          b"sgpd" => sgpd.push(SgpdAtom::decode_unchecked(atom, atoms.reader)?),
+          b"sbgp" => sbgp.push(SbgpAtom::decode_unchecked(atom, atoms.reader)?),
           _ => log!(warn@"#[stbl] Unused atom {atom:#?}"),
         },
         Err(e) => log!(err@"#[stbl] {e}"),
@@ -60,8 +60,8 @@ impl AtomDecoder for StblAtom {
       stss,
       stsz: stsz.ok_or(AtomError::Required(StszAtom::NAME))?,
       stco: stco.ok_or(AtomError::Required(StcoAtom::NAME))?,
-      sgpd,
-      sbgp,
+      sgpd: sgpd.into_boxed_slice(),
+      sbgp: sbgp.into_boxed_slice(),
     })
   }
 }
@@ -79,7 +79,7 @@ impl SttsAtom {
     Ok(SampleTable::new(
       decoder.file.try_clone()?,
       self.atom.offset + 8,
-      self.atom.offset + self.atom.size as u64,
+      self.atom.offset + self.atom.size,
       8,
     ))
   }
@@ -134,7 +134,7 @@ impl StssAtom {
     Ok(SampleTable::new(
       decoder.file.try_clone()?,
       self.atom.offset + 8,
-      self.atom.offset + self.atom.size as u64,
+      self.atom.offset + self.atom.size,
       4,
     ))
   }
@@ -161,6 +161,20 @@ pub struct CttsAtom {
   pub entry_count: u32,
 }
 
+impl CttsAtom {
+  pub fn composition_offset_table(
+    &self,
+    decoder: &mut Decoder,
+  ) -> AtomResult<SampleTable<CttsItem>> {
+    Ok(SampleTable::new(
+      decoder.file.try_clone()?,
+      self.atom.offset + 8,
+      self.atom.offset + self.atom.size,
+      8,
+    ))
+  }
+}
+
 impl AtomDecoder for CttsAtom {
   const NAME: [u8; 4] = *b"ctts";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
@@ -174,6 +188,31 @@ impl AtomDecoder for CttsAtom {
   }
 }
 
+#[derive(Debug)]
+pub struct CttsItem {
+  pub sample_count: u32,
+  /// Signed in version 1, version 0 offsets are also read as signed since negative offsets
+  /// are common in the wild
+  pub sample_offset: i32,
+}
+
+impl FromSlice for CttsItem {
+  fn from_slice(slice: &[u8]) -> Self {
+    let sample_count =
+      u32::from_be_bytes((&slice[..4]).try_into().expect("Ctts sample_count missing"));
+    let sample_offset = i32::from_be_bytes(
+      (&slice[4..8])
+        .try_into()
+        .expect("Ctts sample_offset missing"),
+    );
+
+    Self {
+      sample_count,
+      sample_offset,
+    }
+  }
+}
+
 #[derive(Debug)]
 pub struct StscAtom {
   pub atom: Atom,
@@ -187,7 +226,7 @@ impl StscAtom {
     Ok(SampleTable::new(
       decoder.file.try_clone()?,
       self.atom.offset + 8,
-      self.atom.offset + self.atom.size as u64,
+      self.atom.offset + self.atom.size,
       12,
     ))
   }
@@ -250,7 +289,7 @@ impl StszAtom {
     Ok(SampleTable::new(
       decoder.file.try_clone()?,
       self.atom.offset + 12,
-      self.atom.offset + self.atom.size as u64,
+      self.atom.offset + self.atom.size,
       4,
     ))
   }
@@ -276,7 +315,7 @@ impl StcoAtom {
     Ok(SampleTable::new(
       decoder.file.try_clone()?,
       atom.offset + 8,
-      atom.offset + atom.size as u64,
+      atom.offset + atom.size,
       if *atom.name == *b"stco" { 4 } else { 8 },
     ))
   }
@@ -307,33 +346,95 @@ pub struct SgpdAtom {
   pub atom: Atom,
   pub version: u8,
   pub flags: [u8; 3],
-  pub grouping_table: u32,
+  pub grouping_type: Str<4>,
   pub default_length: u32,
+  pub default_sample_description_index: u32,
   pub entry_count: u32,
-  pub payload_data: Box<[u16]>,
+  pub entries: Box<[SampleGroupEntry]>,
 }
 
 impl AtomDecoder for SgpdAtom {
   const NAME: [u8; 4] = *b"sgpd";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
-    let mut data = atom.read_data(decoder)?;
+    let data = atom.read_data(decoder)?;
+    Self::new(atom, data)
+  }
+}
+
+impl SgpdAtom {
+  pub fn new(atom: Atom, mut data: AtomData) -> AtomResult<Self> {
+    let version = data.version();
+    let flags = data.flags();
+    let grouping_type: Str<4> = data.next_into()?;
+    let default_length = if version == 1 { data.next_into()? } else { 0 };
+    let default_sample_description_index = if version >= 2 { data.next_into()? } else { 0 };
+    let entry_count: u32 = data.next_into()?;
+    let mut entries = Vec::with_capacity(entry_count as usize);
+    for _ in 0..entry_count {
+      let length = match (version, default_length) {
+        (1, 0) => data.next_into::<u32>()? as usize,
+        (1, length) => length as usize,
+        _ => SampleGroupEntry::size(grouping_type).unwrap_or(data.len()),
+      };
+      if length > data.len() {
+        return Err(AtomError::NotEnoughData(atom, length));
+      }
+      entries.push(SampleGroupEntry::new(atom, grouping_type, data.next(length))?);
+    }
+
     Ok(Self {
       atom,
-      version: data.version(),
-      flags: data.flags(),
-      grouping_table: data.next_into()?,
-      default_length: data.next_into()?,
-      entry_count: data.next_into()?,
-      payload_data: data
-        .chunks(2)
-        .map(|b| {
-          b.try_into()
-            .map(u16::from_be_bytes)
-            .map_err(AtomError::from)
-        })
-        .collect::<AtomResult<_>>()?,
+      version,
+      flags,
+      grouping_type,
+      default_length,
+      default_sample_description_index,
+      entry_count,
+      entries: entries.into_boxed_slice(),
+    })
+  }
+
+  /// Returns the distance of the `roll` group description `index` of a `sbgp`, 0 is no group.
+  /// In a track fragment, indices above 0x10000 refer to the entries of its own `traf_sgpd`
+  pub fn roll_distance(sgpd: &[Self], traf_sgpd: &[Self], index: u32) -> Option<i16> {
+    let (sgpd, index) = match index.checked_sub(0x10000) {
+      Some(index) if index > 0 => (traf_sgpd, index),
+      _ => (sgpd, index),
+    };
+    let sgpd = sgpd.iter().find(|sgpd| *sgpd.grouping_type == *b"roll")?;
+    match sgpd.entries.get((index as usize).checked_sub(1)?)? {
+      SampleGroupEntry::Roll(distance) => Some(*distance),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum SampleGroupEntry {
+  /// Number of samples that must be decoded before (negative) or after (positive)
+  /// the sample for its decoding to be correct
+  Roll(i16),
+  /// Entry of a grouping type whose description is not used, such as `rap `
+  Unknown,
+}
+
+impl SampleGroupEntry {
+  fn new(atom: Atom, grouping_type: Str<4>, data: &[u8]) -> AtomResult<Self> {
+    Ok(match &*grouping_type {
+      b"roll" | b"prol" => Self::Roll(i16::try_from_slice(
+        data.get(..2).ok_or(AtomError::NotEnoughData(atom, 2))?,
+      )?),
+      _ => Self::Unknown,
     })
   }
+
+  fn size(grouping_type: Str<4>) -> Option<usize> {
+    match &*grouping_type {
+      b"roll" | b"prol" => Some(2),
+      b"rap " => Some(1),
+      _ => None,
+    }
+  }
 }
 
 #[derive(Debug)]
@@ -342,27 +443,58 @@ pub struct SbgpAtom {
   pub version: u8,
   pub flags: [u8; 3],
   pub grouping_type: Str<4>,
+  pub grouping_type_parameter: Option<u32>,
   pub entry_count: u32,
-  pub sample_count: u32,
-  pub group_description_index: u32,
+  pub entries: Box<[SbgpItem]>,
+}
+
+impl SbgpAtom {
+  /// 1-based group description index of each sample in decoding order, 0 if the sample is not
+  /// a member of any group of this type. Samples past the last entry are not listed
+  pub fn group_description_indices(&self) -> impl Iterator<Item = u32> + '_ {
+    self.entries.iter().flat_map(|entry| {
+      (0..entry.sample_count).map(move |_| entry.group_description_index)
+    })
+  }
 }
 
 impl AtomDecoder for SbgpAtom {
   const NAME: [u8; 4] = *b"sbgp";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
-    let mut data = atom.read_data_exact::<20, _>(decoder)?;
+    let data = atom.read_data(decoder)?;
+    Self::new(atom, data)
+  }
+}
+
+impl SbgpAtom {
+  pub fn new(atom: Atom, mut data: AtomData) -> AtomResult<Self> {
+    let version = data.version();
     Ok(Self {
       atom,
-      version: data.version(),
+      version,
       flags: data.flags(),
       grouping_type: data.next_into()?,
+      grouping_type_parameter: (version == 1).then(|| data.next_into()).transpose()?,
       entry_count: data.next_into()?,
-      sample_count: data.next_into()?,
-      group_description_index: data.next_into()?,
+      entries: data
+        .chunks_exact(8)
+        .map(|chunk| {
+          Ok(SbgpItem {
+            sample_count: u32::try_from_slice(chunk)?,
+            group_description_index: u32::try_from_slice(&chunk[4..])?,
+          })
+        })
+        .collect::<AtomResult<_>>()?,
     })
   }
 }
 
+#[derive(Debug)]
+pub struct SbgpItem {
+  pub sample_count: u32,
+  pub group_description_index: u32,
+}
+
 #[derive(Debug)]
 pub struct SampleTable<T: FromSlice = u64> {
   pub reader: File,
@@ -418,3 +550,51 @@ impl<T: FromSlice> Iterator for SampleTable<T> {
     self.next()
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Version 1 `roll` description with a default length of 2 and the given distances
+  fn roll_sgpd(distances: &[i16]) -> SgpdAtom {
+    let mut data = vec![1, 0, 0, 0];
+    data.extend(b"roll");
+    data.extend(2u32.to_be_bytes());
+    data.extend((distances.len() as u32).to_be_bytes());
+    for distance in distances {
+      data.extend(distance.to_be_bytes());
+    }
+    SgpdAtom::new(Atom::default(), AtomData::new(&data, 0)).unwrap()
+  }
+
+  #[test]
+  fn roll_distance_of_movie_groups() {
+    let sgpd = [roll_sgpd(&[-1, 3])];
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &[], 0), None);
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &[], 1), Some(-1));
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &[], 2), Some(3));
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &[], 3), None);
+    assert_eq!(SgpdAtom::roll_distance(&[], &[], 1), None);
+  }
+
+  #[test]
+  fn roll_distance_of_fragment_groups() {
+    let sgpd = [roll_sgpd(&[-1])];
+    let traf_sgpd = [roll_sgpd(&[-2, -4])];
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &traf_sgpd, 1), Some(-1));
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &traf_sgpd, 0x10000), None);
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &traf_sgpd, 0x10001), Some(-2));
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &traf_sgpd, 0x10002), Some(-4));
+    assert_eq!(SgpdAtom::roll_distance(&sgpd, &[], 0x10001), None);
+  }
+
+  #[test]
+  fn short_roll_entry() {
+    let mut data = vec![1, 0, 0, 0];
+    data.extend(b"roll");
+    data.extend(1u32.to_be_bytes());
+    data.extend(1u32.to_be_bytes());
+    data.push(0xFF);
+    assert!(SgpdAtom::new(Atom::default(), AtomData::new(&data, 0)).is_err());
+  }
+}
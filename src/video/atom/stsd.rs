@@ -2,6 +2,8 @@ use super::*;
 use crate::ascii::LogDisplay;
 use crate::byte::pascal_string;
 use crate::log;
+use crate::math::fixed_point_to_f32;
+use crate::video::codec::ColourInfo;
 
 #[derive(Debug)]
 pub struct StsdAtom {
@@ -55,7 +57,14 @@ impl StsdCodec {
 #[derive(Debug)]
 pub enum CodecData {
   Avc1(Avc1Atom),
-  Mp4a(Mp4aAtom),
+  Hvc1(Hvc1Atom),
+  Av01(Av01Atom),
+  Vp09(Vp09Atom),
+  Mp4a(SoundDescription),
+  Pcm(PcmAtom),
+  Tx3g(Tx3gAtom),
+  Text(TextDescription),
+  Tmcd(TmcdAtom),
   Unknown(Str<4>),
 }
 
@@ -63,10 +72,64 @@ impl CodecData {
   fn new(hdlr: Str<4>, data: AtomData) -> AtomResult<Self> {
     Ok(match &*hdlr {
       b"avc1" => Self::Avc1(Avc1Atom::decode(data)?),
-      b"mp4a" => Self::Mp4a(Mp4aAtom::decode(data)?),
+      b"hvc1" | b"hev1" => Self::Hvc1(Hvc1Atom::decode(data)?),
+      b"av01" => Self::Av01(Av01Atom::decode(data)?),
+      b"vp09" => Self::Vp09(Vp09Atom::decode(data)?),
+      b"mp4a" => Self::Mp4a(SoundDescription::decode(data)?),
+      b"lpcm" | b"sowt" | b"twos" | b"in24" | b"in32" | b"fl32" | b"fl64" => {
+        Self::Pcm(PcmAtom::decode(hdlr, data)?)
+      }
+      b"tx3g" => Self::Tx3g(Tx3gAtom::decode(data)?),
+      b"text" => Self::Text(TextDescription::decode(data)?),
+      b"tmcd" => Self::Tmcd(TmcdAtom::decode(data)?),
       _ => Self::Unknown(hdlr),
     })
   }
+
+  /// Extension atoms of visual sample entries
+  pub fn visual_extensions(&self) -> Option<&VisualExtensions> {
+    match self {
+      Self::Avc1(avc1) => Some(&avc1.extensions),
+      Self::Hvc1(hvc1) => Some(&hvc1.extensions),
+      Self::Av01(av01) => Some(&av01.extensions),
+      Self::Vp09(vp09) => Some(&vp09.extensions),
+      _ => None,
+    }
+  }
+
+  /// Colour description of the bitstream, from the SPS VUI of H.264 and H.265 and from the
+  /// decoder configuration of AV1 and VP9
+  pub fn bitstream_colour(&self) -> Option<ColourInfo> {
+    match self {
+      Self::Avc1(avc1) => avc1
+        .avcc
+        .sps
+        .vui_parameters
+        .as_ref()?
+        .video_signal_type
+        .as_ref(),
+      Self::Hvc1(hvc1) => hvc1
+        .hvcc
+        .sps
+        .first()?
+        .vui_parameters
+        .as_ref()?
+        .video_signal_type
+        .as_ref(),
+      Self::Av01(av01) => return av01.av1c.sequence_header.as_ref()?.color_config.colour(),
+      Self::Vp09(vp09) => return Some(vp09.vpcc.colour()),
+      _ => None,
+    }
+    .map(VideoSignalType::colour)
+  }
+
+  /// Colour of the samples, the container `colr` atom takes precedence over the bitstream
+  pub fn colour(&self) -> Option<ColourInfo> {
+    match self.visual_extensions() {
+      Some(extensions) => extensions.colour(self.bitstream_colour()),
+      None => self.bitstream_colour(),
+    }
+  }
 }
 
 #[derive(Debug)]
@@ -86,6 +149,7 @@ pub struct Avc1Atom {
   pub depth: i16,
   pub color_table_id: i16,
   pub avcc: AvcCAtom,
+  pub extensions: VisualExtensions,
 }
 
 impl Avc1Atom {
@@ -116,12 +180,333 @@ impl Avc1Atom {
           .ok_or(AtomError::Required(AvcCAtom::TYPE))??;
         AvcCAtom::decode(AtomData::new(data, atom.offset))
       },
+      extensions: VisualExtensions::decode(&data, AvcCAtom::TYPE)?,
+    })
+  }
+}
+
+/// HEVC visual sample entry, `hvc1` keeps every parameter set in `hvcC` while `hev1` may also
+/// carry them in band
+#[derive(Debug)]
+pub struct Hvc1Atom {
+  pub width: u16,
+  pub height: u16,
+  pub compressor_name: Box<str>,
+  pub depth: i16,
+  pub hvcc: HvcCAtom,
+  pub extensions: VisualExtensions,
+}
+
+impl Hvc1Atom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    // Revision level, version, vendor and temporal and spatial quality
+    let width = data.reserved(16).next_into()?;
+    let height = data.next_into()?;
+    // Resolution, data size and frame count
+    let compressor_name = pascal_string(data.reserved(14).next(32));
+    let depth = data.next_into()?;
+    // Color table id
+    data.reserved(2);
+    Ok(Self {
+      width,
+      height,
+      compressor_name,
+      depth,
+      hvcc: {
+        let (atom, data) = data
+          .atoms()
+          .find_map(|res| {
+            res
+              .map(|(atom, data)| (*atom.name == HvcCAtom::TYPE).then_some((atom, data)))
+              .transpose()
+          })
+          .ok_or(AtomError::Required(HvcCAtom::TYPE))??;
+        HvcCAtom::decode(AtomData::new(data, atom.offset))?
+      },
+      extensions: VisualExtensions::decode(&data, HvcCAtom::TYPE)?,
     })
   }
 }
 
+/// AV1 visual sample entry, `av1C` holds the sequence header
 #[derive(Debug)]
-pub struct Mp4aAtom {
+pub struct Av01Atom {
+  pub width: u16,
+  pub height: u16,
+  pub compressor_name: Box<str>,
+  pub depth: i16,
+  pub av1c: Av1CAtom,
+  pub extensions: VisualExtensions,
+}
+
+impl Av01Atom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    // Revision level, version, vendor and temporal and spatial quality
+    let width = data.reserved(16).next_into()?;
+    let height = data.next_into()?;
+    // Resolution, data size and frame count
+    let compressor_name = pascal_string(data.reserved(14).next(32));
+    let depth = data.next_into()?;
+    // Color table id
+    data.reserved(2);
+    Ok(Self {
+      width,
+      height,
+      compressor_name,
+      depth,
+      av1c: {
+        let (atom, data) = data
+          .atoms()
+          .find_map(|res| {
+            res
+              .map(|(atom, data)| (*atom.name == Av1CAtom::TYPE).then_some((atom, data)))
+              .transpose()
+          })
+          .ok_or(AtomError::Required(Av1CAtom::TYPE))??;
+        Av1CAtom::decode(AtomData::new(data, atom.offset))?
+      },
+      extensions: VisualExtensions::decode(&data, Av1CAtom::TYPE)?,
+    })
+  }
+}
+
+/// VP9 visual sample entry, frames carry their own headers and `vpcC` describes the stream
+#[derive(Debug)]
+pub struct Vp09Atom {
+  pub width: u16,
+  pub height: u16,
+  pub compressor_name: Box<str>,
+  pub depth: i16,
+  pub vpcc: VpcCAtom,
+  pub extensions: VisualExtensions,
+}
+
+impl Vp09Atom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    // Revision level, version, vendor and temporal and spatial quality
+    let width = data.reserved(16).next_into()?;
+    let height = data.next_into()?;
+    // Resolution, data size and frame count
+    let compressor_name = pascal_string(data.reserved(14).next(32));
+    let depth = data.next_into()?;
+    // Color table id
+    data.reserved(2);
+    Ok(Self {
+      width,
+      height,
+      compressor_name,
+      depth,
+      vpcc: {
+        let (atom, data) = data
+          .atoms()
+          .find_map(|res| {
+            res
+              .map(|(atom, data)| (*atom.name == VpcCAtom::TYPE).then_some((atom, data)))
+              .transpose()
+          })
+          .ok_or(AtomError::Required(VpcCAtom::TYPE))??;
+        VpcCAtom::decode(AtomData::new(data, atom.offset))?
+      },
+      extensions: VisualExtensions::decode(&data, VpcCAtom::TYPE)?,
+    })
+  }
+}
+
+/// 3GPP timed text sample entry (3GPP TS 26.245)
+#[derive(Debug)]
+pub struct Tx3gAtom {
+  pub default_style: StyleRecord,
+  pub font_table: Box<[FontRecord]>,
+}
+
+impl Tx3gAtom {
+  /// Size of the fields before the `ftab` atom
+  const SIZE: usize = 30;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    if data.len() < Self::SIZE {
+      return Err(AtomError::Size(data.len() as u64));
+    }
+    // Display flags, justification, background color and default text box
+    data.reserved(18);
+    Ok(Self {
+      default_style: StyleRecord::decode(&mut data)?,
+      font_table: match data.atoms().find_map(|atom| match atom {
+        Ok((atom, data)) => (*atom.name == *b"ftab").then_some(data),
+        Err(e) => {
+          log!(err@"#[tx3g] {e}");
+          None
+        }
+      }) {
+        Some(ftab) => FontRecord::decode_table(ftab),
+        None => Box::default(),
+      },
+    })
+  }
+
+  /// Name of the font of the default style
+  pub fn font_name(&self) -> Option<&str> {
+    self
+      .font_table
+      .iter()
+      .find(|font| font.font_id == self.default_style.font_id)
+      .map(|font| &*font.font_name)
+  }
+}
+
+/// QuickTime text sample description
+#[derive(Debug)]
+pub struct TextDescription {
+  pub font_name: Box<str>,
+}
+
+impl TextDescription {
+  /// Size of the fields before the font name
+  const SIZE: usize = 43;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    if data.len() < Self::SIZE {
+      return Err(AtomError::Size(data.len() as u64));
+    }
+    Ok(Self {
+      // Display flags, justification, colors, default text box and font number and face
+      font_name: pascal_string(data.reserved(Self::SIZE)),
+    })
+  }
+}
+
+/// QuickTime timecode sample description, each sample is the 32-bit frame number its
+/// timecode starts from
+#[derive(Debug)]
+pub struct TmcdAtom {
+  pub flags: u32,
+  pub timescale: u32,
+  /// Duration of a frame in `timescale` units
+  pub frame_duration: u32,
+  /// Frames per second of the timecode, 30 for 29.97 fps drop-frame
+  pub number_of_frames: u8,
+  /// Source tape or reel name from the `name` atom
+  pub source_name: Option<Box<str>>,
+}
+
+impl TmcdAtom {
+  /// Size of the fields before the `name` atom
+  const SIZE: usize = 18;
+  pub const DROP_FRAME: u32 = 0x1;
+  pub const MAX_24_HOURS: u32 = 0x2;
+  pub const NEGATIVE_TIMES: u32 = 0x4;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    if data.len() < Self::SIZE {
+      return Err(AtomError::Size(data.len() as u64));
+    }
+    let mut tmcd = Self {
+      flags: data.reserved(4).next_into()?,
+      timescale: data.next_into()?,
+      frame_duration: data.next_into()?,
+      number_of_frames: data.byte(),
+      source_name: None,
+    };
+    data.reserved(1);
+    for atom in data.atoms() {
+      match atom {
+        // The `name` atom holds a 16-bit text size, a language code and the text
+        Ok((atom, data)) if *atom.name == *b"name" => {
+          tmcd.source_name = data.split_first_chunk::<2>().and_then(|(size, data)| {
+            let text = data.get(2..2 + u16::from_be_bytes(*size) as usize)?;
+            Some(String::from_utf8_lossy(text).into())
+          })
+        }
+        Ok((atom, _)) => log!(warn@"#[tmcd] Unused atom {atom:#?}"),
+        Err(e) => log!(err@"#[tmcd] {e}"),
+      }
+    }
+    Ok(tmcd)
+  }
+
+  pub fn is_drop_frame(&self) -> bool {
+    self.flags & Self::DROP_FRAME != 0
+  }
+
+  /// Frame rate given by the timescale and frame duration, 29.97 for NTSC drop-frame
+  pub fn frame_rate(&self) -> f64 {
+    self.timescale as f64 / self.frame_duration.max(1) as f64
+  }
+}
+
+/// Style of the characters `start_char..end_char` of a timed text sample
+#[derive(Debug, Clone, Copy)]
+pub struct StyleRecord {
+  pub start_char: u16,
+  pub end_char: u16,
+  pub font_id: u16,
+  pub face_style_flags: u8,
+}
+
+impl StyleRecord {
+  pub const SIZE: usize = 12;
+  pub const BOLD: u8 = 1;
+  pub const ITALIC: u8 = 2;
+  pub const UNDERLINE: u8 = 4;
+
+  pub fn decode(data: &mut AtomData) -> AtomResult<Self> {
+    let style = Self {
+      start_char: data.next_into()?,
+      end_char: data.next_into()?,
+      font_id: data.next_into()?,
+      face_style_flags: data.byte(),
+    };
+    // Font size and text color
+    data.reserved(5);
+    Ok(style)
+  }
+
+  pub fn is_bold(&self) -> bool {
+    self.face_style_flags & Self::BOLD != 0
+  }
+
+  pub fn is_italic(&self) -> bool {
+    self.face_style_flags & Self::ITALIC != 0
+  }
+
+  pub fn is_underlined(&self) -> bool {
+    self.face_style_flags & Self::UNDERLINE != 0
+  }
+}
+
+/// Entry of the `ftab` font table
+#[derive(Debug)]
+pub struct FontRecord {
+  pub font_id: u16,
+  pub font_name: Box<str>,
+}
+
+impl FontRecord {
+  fn decode_table(data: &[u8]) -> Box<[Self]> {
+    let Some((count, mut data)) = data.split_first_chunk::<2>() else {
+      return Box::default();
+    };
+    let mut fonts = Vec::new();
+    for _ in 0..u16::from_be_bytes(*count) {
+      let [i0, i1, length, rest @ ..] = data else {
+        break;
+      };
+      let Some(name) = rest.get(..*length as usize) else {
+        break;
+      };
+      fonts.push(Self {
+        font_id: u16::from_be_bytes([*i0, *i1]),
+        font_name: String::from_utf8_lossy(name).into(),
+      });
+      data = &rest[*length as usize..];
+    }
+    fonts.into_boxed_slice()
+  }
+}
+
+/// QuickTime sound sample description shared by `mp4a` and the uncompressed formats
+#[derive(Debug)]
+pub struct SoundDescription {
   pub version: u16,
   pub revision_level: u16,
   pub vendor: u32,
@@ -130,19 +515,261 @@ pub struct Mp4aAtom {
   pub compression_id: u16,
   pub packet_size: u16,
   pub sample_rate: f32,
+  pub sound_v2: Option<SoundDescriptionV2>,
+  pub esds: Option<EsdsAtom>,
+  pub chan: Option<ChanAtom>,
+  /// `enda` atom of the `wave` extension, set for little endian `in24`, `in32` and `fl32` data
+  pub little_endian: Option<bool>,
 }
 
-impl Mp4aAtom {
+impl SoundDescription {
   pub fn decode(mut data: AtomData) -> AtomResult<Self> {
-    Ok(Self {
-      version: data.next_into()?,
+    let version = data.next_into()?;
+    let mut sound = Self {
+      version,
       revision_level: data.next_into()?,
       vendor: data.next_into()?,
       number_of_channels: data.next_into()?,
       sample_size: data.next_into()?,
       compression_id: data.next_into()?,
       packet_size: data.next_into()?,
-      sample_rate: data.fixed_point_16()?,
+      // Unsigned 16.16, rates above 32767 Hz would be negative as a signed fixed point
+      sample_rate: fixed_point_to_f32(data.next_into::<u32>()? as f32, 16),
+      sound_v2: (version == 2)
+        .then(|| SoundDescriptionV2::decode(&mut data))
+        .transpose()?,
+      esds: None,
+      chan: None,
+      little_endian: None,
+    };
+    if version == 1 {
+      // Samples per packet and bytes per packet, frame and sample of the version 1 extension
+      data.reserved(16);
+    }
+
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          b"esds" => sound.esds = Some(EsdsAtom::decode(AtomData::new(data, atom.offset))?),
+          b"chan" => sound.chan = Some(ChanAtom::decode(AtomData::new(data, atom.offset))?),
+          b"wave" => sound.decode_wave(AtomData::new(data, atom.offset))?,
+          _ => log!(warn@"#[sound] Unused atom {atom:#?}"),
+        },
+        Err(e) => log!(err@"#[sound] {e}"),
+      }
+    }
+
+    Ok(sound)
+  }
+
+  /// QuickTime sound descriptions nest the `esds` and `enda` atoms inside a `wave` atom
+  fn decode_wave(&mut self, data: AtomData) -> AtomResult {
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          b"esds" => self.esds = Some(EsdsAtom::decode(AtomData::new(data, atom.offset))?),
+          b"enda" => {
+            self.little_endian = Some(AtomData::new(data, atom.offset).next_into::<u16>()? != 0)
+          }
+          b"chan" => self.chan = Some(ChanAtom::decode(AtomData::new(data, atom.offset))?),
+          b"frma" | b"mp4a" | b"\0\0\0\0" => (),
+          _ => log!(warn@"#[wave] Unused atom {atom:#?}"),
+        },
+        Err(e) => log!(err@"#[wave] {e}"),
+      }
+    }
+    Ok(())
+  }
+
+  pub fn audio_specific_config(&self) -> Option<&AudioSpecificConfig> {
+    self
+      .esds
+      .as_ref()?
+      .es_descriptor
+      .decoder_config
+      .as_ref()?
+      .audio_specific_config
+      .as_ref()
+  }
+
+  /// Sample rate from the version 2 sound description, the AudioSpecificConfig or the sample
+  /// entry in that order
+  pub fn output_sample_rate(&self) -> f64 {
+    if let Some(v2) = &self.sound_v2 {
+      return v2.audio_sample_rate;
+    }
+    match self.audio_specific_config() {
+      Some(config) => config.output_sampling_frequency() as f64,
+      None => self.sample_rate as f64,
+    }
+  }
+
+  /// Channel count from the version 2 sound description, the AudioSpecificConfig or the
+  /// sample entry in that order
+  pub fn output_channels(&self) -> u32 {
+    if let Some(v2) = &self.sound_v2 {
+      return v2.number_of_channels;
+    }
+    self
+      .audio_specific_config()
+      .and_then(|config| config.channels())
+      .map(u32::from)
+      .unwrap_or(self.number_of_channels as u32)
+  }
+}
+
+/// QuickTime sound description version 2 extension, the version 0 fields hold fixed values
+#[derive(Debug)]
+pub struct SoundDescriptionV2 {
+  pub audio_sample_rate: f64,
+  pub number_of_channels: u32,
+  pub const_bits_per_channel: u32,
+  pub format_specific_flags: u32,
+}
+
+impl SoundDescriptionV2 {
+  pub fn decode(data: &mut AtomData) -> AtomResult<Self> {
+    // Size of the struct only
+    let audio_sample_rate = f64::from_bits(data.reserved(4).next_into()?);
+    let number_of_channels = data.next_into()?;
+    // Always 0x7F000000
+    let const_bits_per_channel = data.reserved(4).next_into()?;
+    let format_specific_flags = data.next_into()?;
+    // Constant bytes per audio packet and LPCM frames per audio packet
+    data.reserved(8);
+    Ok(Self {
+      audio_sample_rate,
+      number_of_channels,
+      const_bits_per_channel,
+      format_specific_flags,
+    })
+  }
+}
+
+/// QuickTime audio channel layout, mirrors CoreAudio's AudioChannelLayout
+#[derive(Debug)]
+pub struct ChanAtom {
+  pub channel_layout_tag: u32,
+  pub channel_bitmap: u32,
+  /// AudioChannelLabel of the channel descriptions
+  pub channel_labels: Box<[u32]>,
+}
+
+impl ChanAtom {
+  pub const USE_CHANNEL_DESCRIPTIONS: u32 = 0;
+  pub const USE_CHANNEL_BITMAP: u32 = 1 << 16;
+  pub const MONO: u32 = 100 << 16 | 1;
+  pub const STEREO: u32 = 101 << 16 | 2;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    let channel_layout_tag = data.reserved(4).next_into()?;
+    let channel_bitmap = data.next_into()?;
+    let number_channel_descriptions: u32 = data.next_into()?;
+    Ok(Self {
+      channel_layout_tag,
+      channel_bitmap,
+      channel_labels: (0..number_channel_descriptions)
+        .map(|_| {
+          let label = data.next_into()?;
+          // Channel flags and coordinates
+          data.reserved(16);
+          Ok(label)
+        })
+        .collect::<AtomResult<_>>()?,
     })
   }
+
+  /// Speaker positions as a WAVE_FORMAT_EXTENSIBLE channel mask, the CoreAudio channel bits
+  /// follow the same order and label `n` is bit `n - 1`. Returns 0 for layouts that are not
+  /// described by labels, a bitmap, mono or stereo
+  pub fn channel_mask(&self) -> u32 {
+    match self.channel_layout_tag {
+      Self::USE_CHANNEL_DESCRIPTIONS => self
+        .channel_labels
+        .iter()
+        .filter(|label| (1..=18).contains(*label))
+        .fold(0, |mask, label| mask | 1 << (label - 1)),
+      Self::USE_CHANNEL_BITMAP => self.channel_bitmap,
+      Self::MONO => 0b100,
+      Self::STEREO => 0b11,
+      _ => 0,
+    }
+  }
+}
+
+/// Uncompressed audio sample entry, `lpcm`, `sowt`, `twos`, `in24`, `in32`, `fl32` or `fl64`
+#[derive(Debug)]
+pub struct PcmAtom {
+  pub sound: SoundDescription,
+  pub format: PcmFormat,
+}
+
+impl PcmAtom {
+  pub fn decode(data_format: Str<4>, data: AtomData) -> AtomResult<Self> {
+    let sound = SoundDescription::decode(data)?;
+    Ok(Self {
+      format: PcmFormat::new(data_format, &sound),
+      sound,
+    })
+  }
+
+  /// Size of a frame holding one sample of every channel
+  pub fn bytes_per_frame(&self) -> u32 {
+    self.format.bytes_per_sample() as u32 * self.sound.output_channels()
+  }
+}
+
+/// Sample layout of uncompressed audio
+#[derive(Debug, Clone, Copy)]
+pub struct PcmFormat {
+  pub bits_per_sample: u16,
+  pub float: bool,
+  pub big_endian: bool,
+  pub signed: bool,
+}
+
+impl PcmFormat {
+  /// `lpcm` kAudioFormatFlagIsFloat
+  const FLAG_FLOAT: u32 = 1 << 0;
+  /// `lpcm` kAudioFormatFlagIsBigEndian
+  const FLAG_BIG_ENDIAN: u32 = 1 << 1;
+  /// `lpcm` kAudioFormatFlagIsSignedInteger
+  const FLAG_SIGNED_INTEGER: u32 = 1 << 2;
+
+  pub fn new(data_format: Str<4>, sound: &SoundDescription) -> Self {
+    let big_endian = !sound.little_endian.unwrap_or_default();
+    let integer = |bits_per_sample, big_endian| Self {
+      bits_per_sample,
+      float: false,
+      big_endian,
+      signed: true,
+    };
+    let float = |bits_per_sample| Self {
+      bits_per_sample,
+      float: true,
+      big_endian,
+      signed: true,
+    };
+    match &*data_format {
+      b"lpcm" => match &sound.sound_v2 {
+        Some(v2) => Self {
+          bits_per_sample: v2.const_bits_per_channel as u16,
+          float: v2.format_specific_flags & Self::FLAG_FLOAT != 0,
+          big_endian: v2.format_specific_flags & Self::FLAG_BIG_ENDIAN != 0,
+          signed: v2.format_specific_flags & Self::FLAG_SIGNED_INTEGER != 0,
+        },
+        None => integer(sound.sample_size, true),
+      },
+      b"sowt" => integer(sound.sample_size, false),
+      b"in24" => integer(24, big_endian),
+      b"in32" => integer(32, big_endian),
+      b"fl32" => float(32),
+      b"fl64" => float(64),
+      _ => integer(sound.sample_size, true),
+    }
+  }
+
+  pub fn bytes_per_sample(&self) -> u16 {
+    self.bits_per_sample.div_ceil(8)
+  }
 }
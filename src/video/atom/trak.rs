@@ -11,7 +11,7 @@ pub struct TrakAtom {
   pub clip: EncodedAtom,
   pub matt: EncodedAtom,
   pub edts: Option<EncodedAtom<EdtsAtom>>,
-  pub tref: EncodedAtom,
+  pub tref: Option<EncodedAtom<TrefAtom>>,
   pub txas: EncodedAtom,
   pub load: EncodedAtom,
   pub imap: EncodedAtom,
@@ -36,7 +36,7 @@ impl AtomDecoder for TrakAtom {
           b"clip" => trak.clip = EncodedAtom::Encoded(atom),
           b"matt" => trak.matt = EncodedAtom::Encoded(atom),
           b"edts" => trak.edts = Some(EncodedAtom::Encoded(atom)),
-          b"tref" => trak.tref = EncodedAtom::Encoded(atom),
+          b"tref" => trak.tref = Some(EncodedAtom::Encoded(atom)),
           b"txas" => trak.txas = EncodedAtom::Encoded(atom),
           b"load" => trak.load = EncodedAtom::Encoded(atom),
           b"imap" => trak.imap = EncodedAtom::Encoded(atom),
@@ -57,10 +57,10 @@ pub struct TkhdAtom {
   pub atom: Atom,
   pub version: u8,
   pub flags: [u8; 3],
-  pub creation_time: u32,
-  pub modification_time: u32,
+  pub creation_time: u64,
+  pub modification_time: u64,
   pub track_id: u32,
-  pub duration: u32,
+  pub duration: u64,
   pub layer: u16,
   pub alternate_group: u16,
   pub volume: f32,
@@ -73,14 +73,15 @@ impl AtomDecoder for TkhdAtom {
   const NAME: [u8; 4] = *b"tkhd";
   fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
     let mut data = atom.read_data(decoder)?;
+    let version = data.version();
     Ok(Self {
       atom,
-      version: data.version(),
+      version,
       flags: data.flags(),
-      creation_time: data.next_into()?,
-      modification_time: data.next_into()?,
+      creation_time: data.next_versioned(version)?,
+      modification_time: data.next_versioned(version)?,
       track_id: data.next_into()?,
-      duration: data.reserved(4).next_into()?,
+      duration: data.reserved(4).next_versioned(version)?,
       layer: data.reserved(8).next_into()?,
       alternate_group: data.next_into()?,
       volume: data.fixed_point_8()?,
@@ -90,3 +91,47 @@ impl AtomDecoder for TkhdAtom {
     })
   }
 }
+
+/// Track references, each child atom type lists the ids of the referenced tracks
+#[derive(Debug, Default)]
+pub struct TrefAtom {
+  pub references: Box<[TrackReference]>,
+}
+
+impl AtomDecoder for TrefAtom {
+  const NAME: [u8; 4] = *b"tref";
+  fn decode_unchecked(mut atom: Atom, decoder: &mut Decoder) -> AtomResult<Self> {
+    let data = atom.read_data(decoder)?;
+    Ok(Self {
+      references: data
+        .atoms()
+        .filter_map(|atom| match atom {
+          Ok((atom, data)) => Some(TrackReference {
+            reference_type: atom.name,
+            track_ids: data
+              .chunks_exact(4)
+              .map(|id| u32::from_be_bytes([id[0], id[1], id[2], id[3]]))
+              .collect(),
+          }),
+          Err(e) => {
+            log!(err@"#[tref] {e}");
+            None
+          }
+        })
+        .collect(),
+    })
+  }
+}
+
+/// `chap` chapter list, `tmcd` timecode, `hint` hinted media or `cdsc` described track
+/// reference, among others
+#[derive(Debug, Clone)]
+pub struct TrackReference {
+  pub reference_type: Str<4>,
+  pub track_ids: Box<[u32]>,
+}
+
+impl TrackReference {
+  pub const CHAPTER: [u8; 4] = *b"chap";
+  pub const TIMECODE: [u8; 4] = *b"tmcd";
+}
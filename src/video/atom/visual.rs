@@ -0,0 +1,434 @@
+use super::*;
+use crate::ascii::LogDisplay;
+use crate::log;
+use crate::video::codec::ColourInfo;
+use std::fmt;
+
+/// Optional atoms shared by every visual sample entry after its decoder configuration
+#[derive(Debug, Default, Clone)]
+pub struct VisualExtensions {
+  /// A sample entry may carry both a `nclx` description and an ICC profile
+  pub colr: Box<[ColrAtom]>,
+  pub pasp: Option<PaspAtom>,
+  pub clap: Option<ClapAtom>,
+  pub btrt: Option<BtrtAtom>,
+  pub fiel: Option<FielAtom>,
+  /// QuickTime `gama` gamma level
+  pub gama: Option<f32>,
+  pub mdcv: Option<MdcvAtom>,
+  pub clli: Option<ClliAtom>,
+}
+
+impl VisualExtensions {
+  /// Decodes the children of a visual sample entry, `config` is the type of the decoder
+  /// configuration atom which is decoded by the sample entry itself
+  pub fn decode(data: &AtomData, config: [u8; 4]) -> AtomResult<Self> {
+    let mut extensions = Self::default();
+    let mut colr = Vec::new();
+    for atom in data.atoms() {
+      match atom {
+        Ok((atom, data)) => {
+          let mut data = AtomData::new(data, atom.offset);
+          match &*atom.name {
+            b"colr" => colr.push(ColrAtom::decode(data)?),
+            b"pasp" => extensions.pasp = Some(PaspAtom::decode(data)?),
+            b"clap" => extensions.clap = Some(ClapAtom::decode(data)?),
+            b"btrt" => extensions.btrt = Some(BtrtAtom::decode(data)?),
+            b"fiel" => extensions.fiel = Some(FielAtom::decode(data)),
+            b"gama" => extensions.gama = Some(data.fixed_point_16()?),
+            b"mdcv" => extensions.mdcv = Some(MdcvAtom::decode(data)?),
+            b"clli" => extensions.clli = Some(ClliAtom::decode(data)?),
+            name if *name == config => (),
+            _ => log!(warn@"#[visual] Unused atom {atom:#?}"),
+          }
+        }
+        Err(e) => log!(err@"#[visual] {e}"),
+      }
+    }
+    extensions.colr = colr.into_boxed_slice();
+    Ok(extensions)
+  }
+
+  /// Colour description of the first `nclx` or `nclc` atom
+  pub fn colour_description(&self) -> Option<&ColrAtom> {
+    self
+      .colr
+      .iter()
+      .find(|colr| matches!(colr, ColrAtom::Nclx(_) | ColrAtom::Nclc { .. }))
+  }
+
+  /// Colour of the samples, the `colr` atom overrides the `bitstream` colour description
+  /// (ISO/IEC 14496-12 12.1.5). Fields left unspecified by the container and the range of the
+  /// `nclc` atom, which has no range flag, are taken from the bitstream
+  pub fn colour(&self, bitstream: Option<ColourInfo>) -> Option<ColourInfo> {
+    let specified = |container: u8, bitstream: Option<u8>| match (container, bitstream) {
+      (ColourInfo::UNSPECIFIED, Some(bitstream)) => bitstream,
+      (container, _) => container,
+    };
+    let (container, full_range) = match self.colour_description() {
+      None => return bitstream,
+      Some(ColrAtom::Nclx(colour)) => (*colour, Some(colour.full_range)),
+      Some(ColrAtom::Nclc {
+        primaries,
+        transfer_function,
+        matrix,
+      }) => (
+        ColourInfo {
+          colour_primaries: *primaries as u8,
+          transfer_characteristics: *transfer_function as u8,
+          matrix_coefficients: *matrix as u8,
+          full_range: false,
+        },
+        None,
+      ),
+      Some(_) => return bitstream,
+    };
+    Some(ColourInfo {
+      colour_primaries: specified(
+        container.colour_primaries,
+        bitstream.map(|colour| colour.colour_primaries),
+      ),
+      transfer_characteristics: specified(
+        container.transfer_characteristics,
+        bitstream.map(|colour| colour.transfer_characteristics),
+      ),
+      matrix_coefficients: specified(
+        container.matrix_coefficients,
+        bitstream.map(|colour| colour.matrix_coefficients),
+      ),
+      full_range: full_range
+        .or(bitstream.map(|colour| colour.full_range))
+        .unwrap_or_default(),
+    })
+  }
+}
+
+/// Colour information, `nclx` (ISO/IEC 14496-12), `nclc` (QuickTime) or an ICC profile
+#[derive(Debug, Clone)]
+pub enum ColrAtom {
+  Nclx(ColourInfo),
+  /// QuickTime parameter type, the values follow H.273 without a range flag
+  Nclc {
+    primaries: u16,
+    transfer_function: u16,
+    matrix: u16,
+  },
+  /// `prof` unrestricted or `rICC` restricted ICC profile
+  Icc {
+    restricted: bool,
+    profile: Box<[u8]>,
+  },
+  Unknown(Str<4>),
+}
+
+impl ColrAtom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    let colour_type: Str<4> = data.next_into()?;
+    Ok(match &*colour_type {
+      b"nclx" => Self::Nclx(ColourInfo {
+        colour_primaries: data.next_into::<u16>()? as u8,
+        transfer_characteristics: data.next_into::<u16>()? as u8,
+        matrix_coefficients: data.next_into::<u16>()? as u8,
+        full_range: data.byte() & 0x80 != 0,
+      }),
+      b"nclc" => Self::Nclc {
+        primaries: data.next_into()?,
+        transfer_function: data.next_into()?,
+        matrix: data.next_into()?,
+      },
+      b"prof" | b"rICC" => Self::Icc {
+        restricted: *colour_type == *b"rICC",
+        profile: data.to_vec().into_boxed_slice(),
+      },
+      _ => Self::Unknown(colour_type),
+    })
+  }
+}
+
+impl fmt::Display for ColrAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Nclx(colour) => write!(f, "nclx {colour}"),
+      Self::Nclc {
+        primaries,
+        transfer_function,
+        matrix,
+      } => write!(f, "nclc {primaries}-{transfer_function}-{matrix}"),
+      Self::Icc {
+        restricted,
+        profile,
+      } => write!(
+        f,
+        "{} ICC profile ({} bytes)",
+        if *restricted {
+          "Restricted"
+        } else {
+          "Unrestricted"
+        },
+        profile.len()
+      ),
+      Self::Unknown(colour_type) => write!(f, "Unknown colour type {colour_type}"),
+    }
+  }
+}
+
+/// Pixel aspect ratio
+#[derive(Debug, Clone, Copy)]
+pub struct PaspAtom {
+  pub h_spacing: u32,
+  pub v_spacing: u32,
+}
+
+impl PaspAtom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    Ok(Self {
+      h_spacing: data.next_into()?,
+      v_spacing: data.next_into()?,
+    })
+  }
+}
+
+impl fmt::Display for PaspAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}", self.h_spacing, self.v_spacing)
+  }
+}
+
+/// Clean aperture, the size and offset from the centre of the picture are fractions
+#[derive(Debug, Clone, Copy)]
+pub struct ClapAtom {
+  pub clean_aperture_width: (u32, u32),
+  pub clean_aperture_height: (u32, u32),
+  pub horiz_off: (i32, u32),
+  pub vert_off: (i32, u32),
+}
+
+impl ClapAtom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    Ok(Self {
+      clean_aperture_width: (data.next_into()?, data.next_into()?),
+      clean_aperture_height: (data.next_into()?, data.next_into()?),
+      horiz_off: (data.next_into()?, data.next_into()?),
+      vert_off: (data.next_into()?, data.next_into()?),
+    })
+  }
+
+  pub fn width(&self) -> f32 {
+    self.clean_aperture_width.0 as f32 / self.clean_aperture_width.1.max(1) as f32
+  }
+
+  pub fn height(&self) -> f32 {
+    self.clean_aperture_height.0 as f32 / self.clean_aperture_height.1.max(1) as f32
+  }
+
+  pub fn horizontal_offset(&self) -> f32 {
+    self.horiz_off.0 as f32 / self.horiz_off.1.max(1) as f32
+  }
+
+  pub fn vertical_offset(&self) -> f32 {
+    self.vert_off.0 as f32 / self.vert_off.1.max(1) as f32
+  }
+}
+
+impl fmt::Display for ClapAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}x{} offset {:+},{:+}",
+      self.width(),
+      self.height(),
+      self.horizontal_offset(),
+      self.vertical_offset()
+    )
+  }
+}
+
+/// Bit rate of the samples
+#[derive(Debug, Clone, Copy)]
+pub struct BtrtAtom {
+  pub buffer_size_db: u32,
+  pub max_bitrate: u32,
+  pub avg_bitrate: u32,
+}
+
+impl BtrtAtom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    Ok(Self {
+      buffer_size_db: data.next_into()?,
+      max_bitrate: data.next_into()?,
+      avg_bitrate: data.next_into()?,
+    })
+  }
+}
+
+impl fmt::Display for BtrtAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} kb/s average, {} kb/s max, {} bytes buffer",
+      self.avg_bitrate / 1000,
+      self.max_bitrate / 1000,
+      self.buffer_size_db
+    )
+  }
+}
+
+/// QuickTime field handling
+#[derive(Debug, Clone, Copy)]
+pub struct FielAtom {
+  /// 1 for progressive and 2 for interlaced video
+  pub field_count: u8,
+  pub field_ordering: u8,
+}
+
+impl FielAtom {
+  pub fn decode(mut data: AtomData) -> Self {
+    Self {
+      field_count: data.byte(),
+      field_ordering: data.byte(),
+    }
+  }
+}
+
+impl fmt::Display for FielAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.field_count < 2 {
+      return f.write_str("Progressive");
+    }
+    f.write_str(match self.field_ordering {
+      1 => "Interlaced, top field first",
+      6 => "Interlaced, bottom field first",
+      9 => "Interlaced, top field first, fields stored in temporal order",
+      14 => "Interlaced, bottom field first, fields stored in temporal order",
+      _ => "Interlaced",
+    })
+  }
+}
+
+/// SMPTE ST 2086 mastering display colour volume, chromaticities are in 0.00002 units and
+/// luminances in 0.0001 cd/m²
+#[derive(Debug, Clone, Copy)]
+pub struct MdcvAtom {
+  pub display_primaries: [(u16, u16); 3],
+  pub white_point: (u16, u16),
+  pub max_display_mastering_luminance: u32,
+  pub min_display_mastering_luminance: u32,
+}
+
+impl MdcvAtom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    let mut display_primaries = [(0, 0); 3];
+    for primary in &mut display_primaries {
+      *primary = (data.next_into()?, data.next_into()?);
+    }
+    Ok(Self {
+      display_primaries,
+      white_point: (data.next_into()?, data.next_into()?),
+      max_display_mastering_luminance: data.next_into()?,
+      min_display_mastering_luminance: data.next_into()?,
+    })
+  }
+}
+
+impl fmt::Display for MdcvAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let xy = |(x, y): (u16, u16)| format!("({:.4}, {:.4})", x as f32 * 0.00002, y as f32 * 0.00002);
+    let [g, b, r] = self.display_primaries;
+    write!(
+      f,
+      "R{} G{} B{} WP{}, {}-{} cd/m²",
+      xy(r),
+      xy(g),
+      xy(b),
+      xy(self.white_point),
+      self.min_display_mastering_luminance as f32 * 0.0001,
+      self.max_display_mastering_luminance as f32 * 0.0001
+    )
+  }
+}
+
+/// Content light level
+#[derive(Debug, Clone, Copy)]
+pub struct ClliAtom {
+  pub max_content_light_level: u16,
+  pub max_pic_average_light_level: u16,
+}
+
+impl ClliAtom {
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    Ok(Self {
+      max_content_light_level: data.next_into()?,
+      max_pic_average_light_level: data.next_into()?,
+    })
+  }
+}
+
+impl fmt::Display for ClliAtom {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "MaxCLL {} cd/m², MaxFALL {} cd/m²",
+      self.max_content_light_level, self.max_pic_average_light_level
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BT709: ColourInfo = ColourInfo {
+    colour_primaries: 1,
+    transfer_characteristics: 1,
+    matrix_coefficients: 1,
+    full_range: true,
+  };
+
+  fn extensions(colr: ColrAtom) -> VisualExtensions {
+    VisualExtensions {
+      colr: Box::new([colr]),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn colour_without_colr() {
+    assert_eq!(VisualExtensions::default().colour(Some(BT709)), Some(BT709));
+    let icc = extensions(ColrAtom::Icc {
+      restricted: false,
+      profile: Box::default(),
+    });
+    assert_eq!(icc.colour(Some(BT709)), Some(BT709));
+    assert_eq!(icc.colour(None), None);
+  }
+
+  #[test]
+  fn colr_overrides_bitstream() {
+    let nclx = extensions(ColrAtom::Nclx(ColourInfo {
+      colour_primaries: 9,
+      transfer_characteristics: ColourInfo::UNSPECIFIED,
+      matrix_coefficients: 9,
+      full_range: false,
+    }));
+    let colour = ColourInfo {
+      colour_primaries: 9,
+      transfer_characteristics: 1,
+      matrix_coefficients: 9,
+      full_range: false,
+    };
+    assert_eq!(nclx.colour(Some(BT709)), Some(colour));
+
+    let nclc = extensions(ColrAtom::Nclc {
+      primaries: 9,
+      transfer_function: 16,
+      matrix: 9,
+    });
+    let colour = ColourInfo {
+      colour_primaries: 9,
+      transfer_characteristics: 16,
+      matrix_coefficients: 9,
+      full_range: true,
+    };
+    assert_eq!(nclc.colour(Some(BT709)), Some(colour));
+  }
+}
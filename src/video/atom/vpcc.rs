@@ -0,0 +1,76 @@
+use super::*;
+use crate::video::codec::ColourInfo;
+
+/// VP Codec ISO Media File Format Binding VPCodecConfigurationBox
+#[derive(Debug)]
+pub struct VpcCAtom {
+  pub profile: u8,
+  pub level: u8,
+  pub bit_depth: u8,
+  /// 0 and 1 are 4:2:0 with vertical and colocated chroma, 2 is 4:2:2 and 3 is 4:4:4
+  pub chroma_subsampling: u8,
+  pub video_full_range_flag: bool,
+  pub colour_primaries: u8,
+  pub transfer_characteristics: u8,
+  pub matrix_coefficients: u8,
+  pub codec_initialization_data: Box<[u8]>,
+}
+
+impl VpcCAtom {
+  pub const TYPE: [u8; 4] = *b"vpcC";
+  const HEADER_SIZE: usize = 12;
+
+  pub fn decode(mut data: AtomData) -> AtomResult<Self> {
+    if data.len() < Self::HEADER_SIZE {
+      return Err(AtomError::Size(data.len() as u64));
+    }
+    let profile = data.reserved(4).byte();
+    let level = data.byte();
+    let packed = data.byte();
+    let mut vpcc = Self {
+      profile,
+      level,
+      bit_depth: packed >> 4,
+      chroma_subsampling: (packed >> 1) & 0b111,
+      video_full_range_flag: packed & 1 != 0,
+      colour_primaries: data.byte(),
+      transfer_characteristics: data.byte(),
+      matrix_coefficients: data.byte(),
+      codec_initialization_data: Box::default(),
+    };
+    let size = data.next_into::<u16>()? as usize;
+    vpcc.codec_initialization_data = data.next(size.min(data.len())).into();
+    Ok(vpcc)
+  }
+
+  pub fn chroma_format(&self) -> &'static str {
+    match self.chroma_subsampling {
+      0 | 1 => "4:2:0",
+      2 => "4:2:2",
+      _ => "4:4:4",
+    }
+  }
+
+  pub fn colour(&self) -> ColourInfo {
+    ColourInfo {
+      colour_primaries: self.colour_primaries,
+      transfer_characteristics: self.transfer_characteristics,
+      matrix_coefficients: self.matrix_coefficients,
+      full_range: self.video_full_range_flag,
+    }
+  }
+
+  /// Profile, level, chroma format, bit depth and colour description, e.g. `Profile 2, Level
+  /// 5.1, 4:2:0 10-bit, BT.2020 primaries, PQ transfer, BT.2020 NCL matrix, limited range`
+  pub fn description(&self) -> String {
+    format!(
+      "Profile {}, Level {}.{}, {} {}-bit, {}",
+      self.profile,
+      self.level / 10,
+      self.level % 10,
+      self.chroma_format(),
+      self.bit_depth,
+      self.colour()
+    )
+  }
+}
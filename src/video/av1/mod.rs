@@ -0,0 +1,369 @@
+use super::atom::{AtomError, Av1CAtom, ObuHeader, SequenceHeader};
+use crate::byte::BitStream;
+use crate::log;
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Av1Error {
+  #[error(transparent)]
+  Atom(#[from] AtomError),
+  #[error("AV1 obu_forbidden_bit is not 0")]
+  ForbiddenBit,
+  #[error("AV1 frame header before any sequence header")]
+  MissingSequenceHeader,
+}
+
+pub type Av1Result<T = ()> = Result<T, Av1Error>;
+
+/// AV1 6.8.2 frame_type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+  Key,
+  Inter,
+  IntraOnly,
+  Switch,
+}
+
+impl From<u8> for FrameType {
+  fn from(frame_type: u8) -> Self {
+    match frame_type {
+      0 => Self::Key,
+      1 => Self::Inter,
+      2 => Self::IntraOnly,
+      _ => Self::Switch,
+    }
+  }
+}
+
+impl fmt::Display for FrameType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::Key => "KEY_FRAME",
+      Self::Inter => "INTER_FRAME",
+      Self::IntraOnly => "INTRA_ONLY_FRAME",
+      Self::Switch => "SWITCH_FRAME",
+    })
+  }
+}
+
+/// AV1 5.9.6 render_size, the frame size when render_and_frame_size_different is not set
+#[derive(Debug, Clone, Copy)]
+pub struct FrameSize {
+  pub render_width: u32,
+  pub render_height: u32,
+}
+
+/// AV1 5.9.2 uncompressed_header up to the frame size
+#[derive(Debug)]
+pub struct FrameHeader {
+  pub show_existing_frame: bool,
+  pub frame_to_show_map_idx: u8,
+  /// frame_type, or RefFrameType of the shown frame when show_existing_frame is set
+  pub frame_type: FrameType,
+  pub show_frame: bool,
+  pub showable_frame: bool,
+  pub error_resilient_mode: bool,
+  pub disable_cdf_update: bool,
+  pub allow_screen_content_tools: bool,
+  pub force_integer_mv: bool,
+  pub current_frame_id: u32,
+  pub frame_size_override_flag: bool,
+  pub order_hint: u32,
+  pub primary_ref_frame: u8,
+  pub refresh_frame_flags: u8,
+  /// `None` for inter frames whose size is copied from a reference that was not parsed
+  pub frame_size: Option<FrameSize>,
+}
+
+impl FrameHeader {
+  /// PRIMARY_REF_NONE
+  pub const PRIMARY_REF_NONE: u8 = 7;
+  /// allFrames
+  const ALL_FRAMES: u8 = 0xFF;
+  /// REFS_PER_FRAME
+  const REFS_PER_FRAME: usize = 7;
+
+  /// A key frame that is shown right away, decoding can start from it
+  pub fn is_key_frame(&self) -> bool {
+    self.frame_type == FrameType::Key && (self.show_frame || self.show_existing_frame)
+  }
+}
+
+impl fmt::Display for FrameHeader {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.frame_type)?;
+    if let Some(size) = &self.frame_size {
+      write!(f, " {}x{}", size.render_width, size.render_height)?;
+    }
+    if self.show_existing_frame {
+      write!(f, " (shows frame {})", self.frame_to_show_map_idx)
+    } else if !self.show_frame {
+      write!(f, " (hidden)")
+    } else {
+      Ok(())
+    }
+  }
+}
+
+/// Reference frame slots of the frame headers parsed so far
+#[derive(Debug, Clone, Copy)]
+struct RefFrame {
+  frame_type: FrameType,
+  frame_size: Option<FrameSize>,
+}
+
+/// AV1 frame header parser of the OBUs of every sample. Frames are not decoded, only the
+/// sequence header and the reference frame types and sizes are tracked
+#[derive(Debug, Default)]
+pub struct Av1Parser {
+  pub sequence_header: Option<SequenceHeader>,
+  ref_frames: [Option<RefFrame>; 8],
+}
+
+impl Av1Parser {
+  pub fn new(av1c: &Av1CAtom) -> Self {
+    Self {
+      sequence_header: av1c.sequence_header.clone(),
+      ref_frames: [None; 8],
+    }
+  }
+
+  /// Parses the OBUs of a temporal unit, returns the header of every frame it contains
+  pub fn parse_temporal_unit(&mut self, data: &[u8]) -> Av1Result<Vec<FrameHeader>> {
+    let mut frames = Vec::new();
+    for (obu, payload) in ObuHeader::split(data) {
+      if obu.forbidden_bit {
+        return Err(Av1Error::ForbiddenBit);
+      }
+      match obu.obu_type {
+        ObuHeader::SEQUENCE_HEADER => {
+          self.sequence_header = Some(SequenceHeader::decode(&mut BitStream::rbsp(payload))?);
+        }
+        ObuHeader::FRAME_HEADER | ObuHeader::FRAME => {
+          let header = self.frame_header(&obu, &mut BitStream::rbsp(payload))?;
+          log!(File@"AV1 {header:#?}");
+          frames.push(header);
+        }
+        _ => (),
+      }
+    }
+    Ok(frames)
+  }
+
+  /// 5.9.2 uncompressed_header, stops once the frame size is known and updates the reference
+  /// frame slots it refreshes
+  fn frame_header(&mut self, obu: &ObuHeader, data: &mut BitStream) -> Av1Result<FrameHeader> {
+    let Self {
+      sequence_header,
+      ref_frames,
+    } = self;
+    let seq = sequence_header
+      .as_ref()
+      .ok_or(Av1Error::MissingSequenceHeader)?;
+    let id_len = seq.id_len();
+    let mut header = FrameHeader {
+      show_existing_frame: false,
+      frame_to_show_map_idx: 0,
+      frame_type: FrameType::Key,
+      show_frame: true,
+      showable_frame: false,
+      error_resilient_mode: true,
+      disable_cdf_update: false,
+      allow_screen_content_tools: false,
+      force_integer_mv: false,
+      current_frame_id: 0,
+      frame_size_override_flag: false,
+      order_hint: 0,
+      primary_ref_frame: FrameHeader::PRIMARY_REF_NONE,
+      refresh_frame_flags: FrameHeader::ALL_FRAMES,
+      frame_size: None,
+    };
+    let temporal_point_info = |data: &mut BitStream| {
+      if let Some(info) = &seq.decoder_model_info {
+        data.bits_into::<u32>(info.frame_presentation_time_length_minus_1 as usize + 1);
+      }
+    };
+
+    if !seq.reduced_still_picture_header {
+      header.show_existing_frame = data.bit_flag();
+      if header.show_existing_frame {
+        header.frame_to_show_map_idx = data.bits_into(3);
+        if seq.temporal_point_info_present() {
+          temporal_point_info(data);
+        }
+        header.refresh_frame_flags = 0;
+        if id_len > 0 {
+          header.current_frame_id = data.bits_into(id_len);
+        }
+        if let Some(frame) = ref_frames[header.frame_to_show_map_idx as usize] {
+          header.frame_type = frame.frame_type;
+          header.frame_size = frame.frame_size;
+        }
+        // 7.21 Showing a key frame refreshes every slot with it
+        if header.frame_type == FrameType::Key {
+          header.refresh_frame_flags = FrameHeader::ALL_FRAMES;
+          Self::refresh(ref_frames, &header);
+        }
+        return Ok(header);
+      }
+      header.frame_type = FrameType::from(data.bits_into::<u8>(2));
+      header.show_frame = data.bit_flag();
+      if header.show_frame && seq.temporal_point_info_present() {
+        temporal_point_info(data);
+      }
+      header.showable_frame = if header.show_frame {
+        header.frame_type != FrameType::Key
+      } else {
+        data.bit_flag()
+      };
+      header.error_resilient_mode = header.frame_type == FrameType::Switch
+        || (header.frame_type == FrameType::Key && header.show_frame)
+        || data.bit_flag();
+    }
+    let frame_is_intra = matches!(header.frame_type, FrameType::Key | FrameType::IntraOnly);
+
+    header.disable_cdf_update = data.bit_flag();
+    header.allow_screen_content_tools =
+      if seq.seq_force_screen_content_tools == SequenceHeader::SELECT_SCREEN_CONTENT_TOOLS {
+        data.bit_flag()
+      } else {
+        seq.seq_force_screen_content_tools != 0
+      };
+    if header.allow_screen_content_tools {
+      header.force_integer_mv = if seq.seq_force_integer_mv == SequenceHeader::SELECT_INTEGER_MV {
+        data.bit_flag()
+      } else {
+        seq.seq_force_integer_mv != 0
+      };
+    }
+    header.force_integer_mv |= frame_is_intra;
+    if id_len > 0 {
+      header.current_frame_id = data.bits_into(id_len);
+    }
+    header.frame_size_override_flag = header.frame_type == FrameType::Switch
+      || (!seq.reduced_still_picture_header && data.bit_flag());
+    header.order_hint = data.bits_into(seq.order_hint_bits as usize);
+    if !frame_is_intra && !header.error_resilient_mode {
+      header.primary_ref_frame = data.bits_into(3);
+    }
+    if let Some(info) = &seq.decoder_model_info {
+      if data.bit_flag() {
+        for op in &*seq.operating_points {
+          if !op.decoder_model_present {
+            continue;
+          }
+          let in_temporal_layer = (op.idc >> obu.temporal_id) & 1 != 0;
+          let in_spatial_layer = (op.idc >> (obu.spatial_id + 8)) & 1 != 0;
+          if op.idc == 0 || (in_temporal_layer && in_spatial_layer) {
+            data.bits_into::<u32>(info.buffer_removal_time_length_minus_1 as usize + 1);
+          }
+        }
+      }
+    }
+    if !(header.frame_type == FrameType::Switch
+      || (header.frame_type == FrameType::Key && header.show_frame))
+    {
+      header.refresh_frame_flags = data.bits_into(8);
+    }
+    if (!frame_is_intra || header.refresh_frame_flags != FrameHeader::ALL_FRAMES)
+      && header.error_resilient_mode
+      && seq.enable_order_hint
+    {
+      // ref_order_hint of every slot
+      for _ in 0..8 {
+        data.bits_into::<u32>(seq.order_hint_bits as usize);
+      }
+    }
+
+    if frame_is_intra {
+      header.frame_size = Some(Self::frame_size(seq, &header, data));
+    } else {
+      let frame_refs_short_signaling = seq.enable_order_hint && data.bit_flag();
+      if frame_refs_short_signaling {
+        // last_frame_idx and gold_frame_idx
+        data.bits_into::<u8>(6);
+      }
+      let mut ref_frame_idx = [None; FrameHeader::REFS_PER_FRAME];
+      for idx in &mut ref_frame_idx {
+        if !frame_refs_short_signaling {
+          *idx = Some(data.bits_into::<u8>(3));
+        }
+        if let Some((delta_frame_id_length_minus_2, _)) = seq.frame_id_numbers {
+          data.bits_into::<u32>(delta_frame_id_length_minus_2 as usize + 2);
+        }
+      }
+      header.frame_size = if header.frame_size_override_flag && !header.error_resilient_mode {
+        Self::frame_size_with_refs(ref_frames, seq, &header, &ref_frame_idx, data)
+      } else {
+        Some(Self::frame_size(seq, &header, data))
+      };
+    }
+    Self::refresh(ref_frames, &header);
+    Ok(header)
+  }
+
+  /// 5.9.5 frame_size, 5.9.8 superres_params and 5.9.6 render_size
+  fn frame_size(seq: &SequenceHeader, header: &FrameHeader, data: &mut BitStream) -> FrameSize {
+    let (upscaled_width, frame_height) = if header.frame_size_override_flag {
+      (
+        data.bits_into::<u32>(seq.frame_width_bits_minus_1 as usize + 1) + 1,
+        data.bits_into::<u32>(seq.frame_height_bits_minus_1 as usize + 1) + 1,
+      )
+    } else {
+      (
+        seq.max_frame_width_minus_1 + 1,
+        seq.max_frame_height_minus_1 + 1,
+      )
+    };
+    Self::superres_params(seq, data);
+    let (render_width, render_height) = if data.bit_flag() {
+      (data.bits_into::<u32>(16) + 1, data.bits_into::<u32>(16) + 1)
+    } else {
+      (upscaled_width, frame_height)
+    };
+    FrameSize {
+      render_width,
+      render_height,
+    }
+  }
+
+  /// 5.9.7 frame_size_with_refs, `None` when the size is found in a reference frame that is
+  /// unknown
+  fn frame_size_with_refs(
+    ref_frames: &[Option<RefFrame>; 8],
+    seq: &SequenceHeader,
+    header: &FrameHeader,
+    ref_frame_idx: &[Option<u8>],
+    data: &mut BitStream,
+  ) -> Option<FrameSize> {
+    for idx in ref_frame_idx {
+      if data.bit_flag() {
+        let size = ref_frames[(*idx)? as usize]?.frame_size?;
+        Self::superres_params(seq, data);
+        return Some(size);
+      }
+    }
+    Some(Self::frame_size(seq, header, data))
+  }
+
+  /// 5.9.8 superres_params, the downscaled FrameWidth is not needed for the render size
+  fn superres_params(seq: &SequenceHeader, data: &mut BitStream) {
+    // use_superres and coded_denom
+    if seq.enable_superres && data.bit_flag() {
+      data.bits_into::<u8>(3);
+    }
+  }
+
+  /// 7.20 Reference frame update process of the slots in refresh_frame_flags
+  fn refresh(ref_frames: &mut [Option<RefFrame>; 8], header: &FrameHeader) {
+    for (i, slot) in ref_frames.iter_mut().enumerate() {
+      if header.refresh_frame_flags & (1 << i) != 0 {
+        *slot = Some(RefFrame {
+          frame_type: header.frame_type,
+          frame_size: header.frame_size,
+        });
+      }
+    }
+  }
+}
@@ -0,0 +1,259 @@
+use super::atom::{DataValue, UdtaAtom};
+use std::{fmt, rc::Rc};
+
+/// Recording device, place and time of phone and camera footage, from the QuickTime `mdta`
+/// keys or the legacy `udta` text atoms when the keys are missing
+#[derive(Debug, Default)]
+pub struct CameraMetadata {
+  pub make: Option<Box<str>>,
+  pub model: Option<Box<str>>,
+  pub software: Option<Box<str>>,
+  pub creation_date: Option<CaptureDate>,
+  pub location: Option<Location>,
+}
+
+impl CameraMetadata {
+  const KEY_PREFIX: &'static str = "com.apple.quicktime.";
+
+  pub fn new(tags: &[(Rc<str>, DataValue)], udta: Option<&UdtaAtom>) -> Self {
+    let key = |name: &str| {
+      tags.iter().find_map(|(key, value)| {
+        (key.strip_prefix(Self::KEY_PREFIX) == Some(name)).then(|| value.to_string())
+      })
+    };
+    let text = |name: &[u8; 4]| udta.and_then(|udta| udta.text(name)).map(str::to_string);
+    let field = |name, udta_name| key(name).or_else(|| text(udta_name)).map(Box::from);
+    Self {
+      make: field("make", b"\xA9mak"),
+      model: field("model", b"\xA9mod"),
+      software: field("software", b"\xA9swr"),
+      creation_date: key("creationdate")
+        .or_else(|| text(b"\xA9day"))
+        .and_then(|date| CaptureDate::parse(&date)),
+      location: key("location.ISO6709")
+        .or_else(|| text(b"\xA9xyz"))
+        .and_then(|location| Location::parse(&location)),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.make.is_none()
+      && self.model.is_none()
+      && self.software.is_none()
+      && self.creation_date.is_none()
+      && self.location.is_none()
+  }
+}
+
+/// ISO 6709 point, degrees north and east and metres above the reference ellipsoid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+  pub latitude: f64,
+  pub longitude: f64,
+  pub altitude: Option<f64>,
+}
+
+impl Location {
+  /// Parses `±DD.D±DDD.D[±AAA.A][CRSxxx]/`, the integer part of the latitude and longitude
+  /// may also be `DDMM` and `DDDMM` or `DDMMSS` and `DDDMMSS` with a decimal fraction
+  pub fn parse(s: &str) -> Option<Self> {
+    let s = s.trim().trim_end_matches('/');
+    if !s.is_ascii() {
+      return None;
+    }
+    let s = s.find("CRS").map_or(s, |crs| &s[..crs]);
+    let mut components = Vec::new();
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+      if c == '+' || c == '-' {
+        if let Some(start) = start {
+          components.push(&s[start..i]);
+        }
+        start = Some(i);
+      }
+    }
+    components.push(&s[start?..]);
+    let latitude = Self::angle(components.first()?, 2)?;
+    let longitude = Self::angle(components.get(1)?, 3)?;
+    if latitude.abs() > 90. || longitude.abs() > 180. {
+      return None;
+    }
+    Some(Self {
+      latitude,
+      longitude,
+      altitude: components.get(2).and_then(|altitude| altitude.parse().ok()),
+    })
+  }
+
+  /// Signed angle in decimal degrees, `degree_digits` is the length of the degrees field
+  fn angle(s: &str, degree_digits: usize) -> Option<f64> {
+    let (sign, s) = match s.split_at(1) {
+      ("-", s) => (-1., s),
+      ("+", s) => (1., s),
+      _ => return None,
+    };
+    let integer_digits = s.find('.').unwrap_or(s.len());
+    let (degrees, minutes) = s.split_at(degree_digits.min(s.len()));
+    let value = match integer_digits.checked_sub(degree_digits)? {
+      0 => s.parse::<f64>().ok()?,
+      2 => degrees.parse::<f64>().ok()? + minutes.parse::<f64>().ok()? / 60.,
+      4 => {
+        let (minutes, seconds) = minutes.split_at(2);
+        degrees.parse::<f64>().ok()?
+          + minutes.parse::<f64>().ok()? / 60.
+          + seconds.parse::<f64>().ok()? / 3_600.
+      }
+      _ => return None,
+    };
+    Some(sign * value)
+  }
+}
+
+impl fmt::Display for Location {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{:.6}°{} {:.6}°{}",
+      self.latitude.abs(),
+      if self.latitude < 0. { 'S' } else { 'N' },
+      self.longitude.abs(),
+      if self.longitude < 0. { 'W' } else { 'E' },
+    )?;
+    if let Some(altitude) = self.altitude {
+      write!(f, ", {altitude} m")?;
+    }
+    Ok(())
+  }
+}
+
+/// ISO 8601 local date and time with its UTC offset when given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureDate {
+  pub year: u16,
+  pub month: u8,
+  pub day: u8,
+  pub hour: u8,
+  pub minute: u8,
+  pub second: u8,
+  /// Minutes east of UTC, `None` for local times without an offset
+  pub utc_offset: Option<i16>,
+}
+
+impl CaptureDate {
+  /// Parses `YYYY-MM-DD[THH:MM[:SS[.s]]][Z|±HH[:]MM]`, `YYYYMMDDTHHMMSS` and a bare year
+  pub fn parse(s: &str) -> Option<Self> {
+    let s = s.trim();
+    let digits = |s: &str| -> Option<u16> {
+      s.bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| s.parse().ok())
+        .flatten()
+    };
+    let (date, time) = s.split_once(['T', ' ']).unwrap_or((s, ""));
+    let date = date.replace('-', "");
+    if ![4, 6, 8].contains(&date.len()) {
+      return None;
+    }
+    let mut capture = Self {
+      year: digits(date.get(..4)?)?,
+      month: date.get(4..6).map_or(Some(1), digits)? as u8,
+      day: date.get(6..8).map_or(Some(1), digits)? as u8,
+      hour: 0,
+      minute: 0,
+      second: 0,
+      utc_offset: None,
+    };
+    let (time, offset) = match time.find(['Z', '+', '-']) {
+      Some(i) => time.split_at(i),
+      None => (time, ""),
+    };
+    let time = time.split('.').next().unwrap_or_default().replace(':', "");
+    capture.hour = time.get(..2).map_or(Some(0), digits)? as u8;
+    capture.minute = time.get(2..4).map_or(Some(0), digits)? as u8;
+    capture.second = time.get(4..6).map_or(Some(0), digits)? as u8;
+    capture.utc_offset = match offset.split_at(offset.len().min(1)) {
+      ("Z", _) => Some(0),
+      (sign @ ("+" | "-"), offset) => {
+        let offset = offset.replace(':', "");
+        let minutes = digits(offset.get(..2)?)? * 60 + offset.get(2..4).map_or(Some(0), digits)?;
+        Some(if sign == "-" {
+          -(minutes as i16)
+        } else {
+          minutes as i16
+        })
+      }
+      _ => None,
+    };
+    let valid = (1..=12).contains(&capture.month)
+      && (1..=31).contains(&capture.day)
+      && capture.hour < 24
+      && capture.minute < 60
+      && capture.second < 61;
+    valid.then_some(capture)
+  }
+}
+
+impl fmt::Display for CaptureDate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+      self.year, self.month, self.day, self.hour, self.minute, self.second
+    )?;
+    match self.utc_offset {
+      Some(0) => write!(f, " UTC"),
+      Some(offset) => write!(
+        f,
+        " UTC{}{:02}:{:02}",
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+      ),
+      None => Ok(()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn location(latitude: f64, longitude: f64, altitude: Option<f64>) -> Option<Location> {
+    Some(Location {
+      latitude,
+      longitude,
+      altitude,
+    })
+  }
+
+  #[test]
+  fn parse_location() {
+    assert_eq!(
+      Location::parse("+48.8577+002.2950+035.000/"),
+      location(48.8577, 2.295, Some(35.))
+    );
+    assert_eq!(
+      Location::parse("-33.8568+151.2153CRSWGS_84/"),
+      location(-33.8568, 151.2153, None)
+    );
+    assert_eq!(Location::parse("+4830-00230/"), location(48.5, -2.5, None));
+    assert_eq!(
+      Location::parse("+403000-0743000/"),
+      location(40.5, -74.5, None)
+    );
+    assert_eq!(Location::parse("+91.0+000.0/"), None);
+    assert_eq!(Location::parse("48.8577+002.2950/"), None);
+    assert_eq!(Location::parse(""), None);
+  }
+
+  #[test]
+  fn parse_capture_date() {
+    let date = CaptureDate::parse("2024-05-06T07:08:09+0200").unwrap();
+    assert_eq!(date.to_string(), "2024-05-06 07:08:09 UTC+02:00");
+    let date = CaptureDate::parse("20240506T070809Z").unwrap();
+    assert_eq!(date.to_string(), "2024-05-06 07:08:09 UTC");
+    let date = CaptureDate::parse("2024").unwrap();
+    assert_eq!(date.to_string(), "2024-01-01 00:00:00");
+    assert_eq!(CaptureDate::parse("2024-13-01"), None);
+  }
+}
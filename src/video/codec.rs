@@ -1,8 +1,12 @@
 use crate::byte::Str;
+use std::fmt;
 
 #[derive(Debug)]
 pub enum VideoCodec {
   H264,
+  H265,
+  Av1,
+  Vp9,
   Unknown(Str<4>),
   Cinepak,
   Jpeg,
@@ -35,6 +39,9 @@ impl From<Str<4>> for VideoCodec {
   fn from(value: Str<4>) -> Self {
     match &*value {
       b"avc1" => Self::H264,
+      b"hvc1" | b"hev1" => Self::H265,
+      b"av01" => Self::Av1,
+      b"vp09" => Self::Vp9,
       b"cvid" => Self::Cinepak,
       b"jpeg" => Self::Jpeg,
       b"smc " => Self::Graphics,
@@ -64,3 +71,143 @@ impl From<Str<4>> for VideoCodec {
     }
   }
 }
+
+/// ITU-T H.273 colour description shared by the AV1 and VP9 configurations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColourInfo {
+  pub colour_primaries: u8,
+  pub transfer_characteristics: u8,
+  pub matrix_coefficients: u8,
+  pub full_range: bool,
+}
+
+impl ColourInfo {
+  /// Value of a colour field left to the application
+  pub const UNSPECIFIED: u8 = 2;
+
+  /// Luma weights KR and KB of the matrix coefficients, `None` for identity, YCgCo, ICtCp and
+  /// unspecified matrices
+  pub fn luma_weights(&self) -> Option<(f32, f32)> {
+    match self.matrix_coefficients {
+      1 => Some((0.2126, 0.0722)),
+      4 => Some((0.30, 0.11)),
+      5 | 6 => Some((0.299, 0.114)),
+      7 => Some((0.212, 0.087)),
+      9 | 10 => Some((0.2627, 0.0593)),
+      _ => None,
+    }
+  }
+
+  /// Converts a Y'CbCr sample to R'G'B'. Unspecified matrices use BT.709 for HD pictures of
+  /// more than 576 lines and BT.601 otherwise
+  pub fn to_rgb(self, y: u8, cb: u8, cr: u8, height: usize) -> [u8; 3] {
+    let component = |c: u8| match self.full_range {
+      true => c,
+      false => ((c as f32 - 16.) * 255. / 219.).round().clamp(0., 255.) as u8,
+    };
+    // GBR, Y carries G, Cb carries B and Cr carries R
+    if self.matrix_coefficients == 0 {
+      return [component(cr), component(y), component(cb)];
+    }
+    let (y, cb, cr) = if self.full_range {
+      (
+        y as f32 / 255.,
+        (cb as f32 - 128.) / 255.,
+        (cr as f32 - 128.) / 255.,
+      )
+    } else {
+      (
+        (y as f32 - 16.) / 219.,
+        (cb as f32 - 128.) / 224.,
+        (cr as f32 - 128.) / 224.,
+      )
+    };
+    let (r, g, b) = match self.matrix_coefficients {
+      8 => (y - cb + cr, y + cb, y - cb - cr),
+      _ => {
+        let (kr, kb) = self.luma_weights().unwrap_or(if height > 576 {
+          (0.2126, 0.0722)
+        } else {
+          (0.299, 0.114)
+        });
+        let kg = 1. - kr - kb;
+        let r = y + 2. * (1. - kr) * cr;
+        let b = y + 2. * (1. - kb) * cb;
+        (r, (y - kr * r - kb * b) / kg, b)
+      }
+    };
+    [r, g, b].map(|c| (c * 255.).round().clamp(0., 255.) as u8)
+  }
+
+  pub fn primaries_name(&self) -> &'static str {
+    match self.colour_primaries {
+      1 => "BT.709",
+      4 => "BT.470 M",
+      5 => "BT.470 BG",
+      6 => "SMPTE 170M",
+      7 => "SMPTE 240M",
+      8 => "Film",
+      9 => "BT.2020",
+      10 => "SMPTE 428",
+      11 => "DCI-P3",
+      12 => "Display P3",
+      22 => "EBU 3213",
+      _ => "Unspecified",
+    }
+  }
+
+  pub fn transfer_name(&self) -> &'static str {
+    match self.transfer_characteristics {
+      1 | 6 | 14 | 15 => "BT.709",
+      4 => "Gamma 2.2",
+      5 => "Gamma 2.8",
+      7 => "SMPTE 240M",
+      8 => "Linear",
+      11 => "IEC 61966-2-4",
+      13 => "sRGB",
+      16 => "PQ",
+      17 => "SMPTE 428",
+      18 => "HLG",
+      _ => "Unspecified",
+    }
+  }
+
+  pub fn matrix_name(&self) -> &'static str {
+    match self.matrix_coefficients {
+      0 => "Identity",
+      1 => "BT.709",
+      4 => "FCC",
+      5 | 6 => "BT.601",
+      7 => "SMPTE 240M",
+      8 => "YCgCo",
+      9 => "BT.2020 NCL",
+      10 => "BT.2020 CL",
+      14 => "ICtCp",
+      _ => "Unspecified",
+    }
+  }
+}
+
+impl Default for ColourInfo {
+  fn default() -> Self {
+    Self {
+      colour_primaries: Self::UNSPECIFIED,
+      transfer_characteristics: Self::UNSPECIFIED,
+      matrix_coefficients: Self::UNSPECIFIED,
+      full_range: false,
+    }
+  }
+}
+
+impl fmt::Display for ColourInfo {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} primaries, {} transfer, {} matrix, {} range",
+      self.primaries_name(),
+      self.transfer_name(),
+      self.matrix_name(),
+      if self.full_range { "full" } else { "limited" }
+    )
+  }
+}
@@ -1,13 +1,20 @@
+use super::aac::*;
 use super::atom::*;
+use super::av1::{Av1Error, Av1Parser};
 use super::cabac::CabacError;
+use super::hevc::{HevcDecoder, HevcError};
 use super::sample::*;
 use super::slice::dpb::DecodedPictureBuffer;
 use super::slice::*;
+use super::vp9::{Vp9Error, Vp9Parser};
 use crate::byte::{BitStream, Str};
+use crate::ascii::LogDisplay;
 use crate::log;
+use crate::video::codec::ColourInfo;
 use crate::video::frame::Frame;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use thiserror::Error;
 
@@ -25,6 +32,14 @@ pub enum DecoderError {
   MissingConfig,
   #[error(transparent)]
   Cabac(#[from] CabacError),
+  #[error(transparent)]
+  Aac(#[from] AacError),
+  #[error(transparent)]
+  Hevc(#[from] HevcError),
+  #[error(transparent)]
+  Av1(#[from] Av1Error),
+  #[error(transparent)]
+  Vp9(#[from] Vp9Error),
 }
 
 pub type DecoderResult<T = ()> = Result<T, DecoderError>;
@@ -33,6 +48,8 @@ pub type DecoderResult<T = ()> = Result<T, DecoderError>;
 pub enum DecoderBrand {
   QuickTime,
   Isom,
+  /// Raw H.264 byte stream with start code prefixed NAL units
+  AnnexB,
   None,
 }
 
@@ -40,6 +57,30 @@ impl DecoderBrand {
   pub fn is_isom(&self) -> bool {
     matches!(self, DecoderBrand::Isom)
   }
+
+  pub fn is_annex_b(&self) -> bool {
+    matches!(self, DecoderBrand::AnnexB)
+  }
+
+  /// Detects an Annex B byte stream from the first bytes of a file. Files starting with a top
+  /// level atom header are left to `ftyp`, as a 64 bit atom size begins with `00 00 00 01` too
+  fn detect(prefix: &[u8]) -> Self {
+    const ATOMS: [&[u8; 4]; 9] = [
+      b"ftyp", b"moov", b"mdat", b"free", b"wide", b"skip", b"styp", b"sidx", b"moof",
+    ];
+    let is_atom = prefix.get(..4).is_some_and(|size| {
+      let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]);
+      size <= 1 || size >= 8
+    }) && prefix
+      .get(4..8)
+      .is_some_and(|name| ATOMS.iter().any(|atom| *atom == name));
+    let zeros = prefix.iter().take_while(|byte| **byte == 0).count();
+    if !is_atom && zeros >= 2 && prefix.get(zeros) == Some(&1) {
+      Self::AnnexB
+    } else {
+      Self::None
+    }
+  }
 }
 
 impl TryFrom<Str<4>> for DecoderBrand {
@@ -47,7 +88,8 @@ impl TryFrom<Str<4>> for DecoderBrand {
   fn try_from(brand: Str<4>) -> Result<Self, Self::Error> {
     match &*brand {
       b"qt  " => Ok(Self::QuickTime),
-      b"isom" => Ok(Self::Isom),
+      b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"iso8" | b"iso9" | b"mp41" | b"mp42"
+      | b"avc1" | b"dash" | b"msdh" | b"msix" | b"cmfc" | b"cmf2" => Ok(Self::Isom),
       _ => Err(DecoderError::Unsupported(brand)),
     }
   }
@@ -62,12 +104,15 @@ pub struct Decoder {
 
 impl Decoder {
   pub fn open<P: AsRef<Path>>(path: P) -> DecoderResult<Self> {
-    let file = File::open(path)?;
+    let mut file = File::open(path)?;
     let size = file.metadata()?.len();
+    let mut prefix = Vec::with_capacity(32);
+    (&mut file).take(32).read_to_end(&mut prefix)?;
+    file.seek(SeekFrom::Start(0))?;
     Ok(Self {
       size,
       file,
-      brand: DecoderBrand::None,
+      brand: DecoderBrand::detect(&prefix),
     })
   }
 
@@ -84,8 +129,110 @@ impl Decoder {
     Ok(root)
   }
 
-  pub fn decode_sample(&mut self, stbl: &mut StblAtom) -> DecoderResult {
-    let samples = SampleIter::new(self, stbl)?.take(1).enumerate();
+  /// Decodes the first frame presented at or after `time`, in media timescale units, starting
+  /// from the closest random access point before it and writes it to `temp/yuv_frame`.
+  /// Returns the index of the sample of the written frame
+  pub fn seek(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    time: i64,
+  ) -> DecoderResult<Option<usize>> {
+    let Some(sample) = index.sample_presented_from(time) else {
+      log!(File@"SEEK {time} IS PAST THE LAST SAMPLE");
+      return Ok(None);
+    };
+    log!(File@"SEEK {time} => SAMPLE #{} {:?}", sample + 1, index[sample]);
+    self.decode_indexed(stbl, index, sample)
+  }
+
+  fn decode_indexed(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    sample: usize,
+  ) -> DecoderResult<Option<usize>> {
+    if let Some(codec @ CodecData::Hvc1(hvc1)) = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data)
+    {
+      log!(File@"HVCC {:#?}", hvc1.hvcc);
+      let nal_length_size = hvc1.hvcc.nal_length_size_minus_one as usize + 1;
+      let mut decoder = HevcDecoder::new(&hvc1.hvcc);
+      let mut reader = self.file.try_clone()?;
+      let point = index.sync_sample(sample).unwrap_or_default();
+      let mut complete = true;
+      for (i, sample_data) in index.iter().enumerate().take(sample + 1).skip(point) {
+        let sample_data = sample_data.read(&mut reader)?;
+        log!(File@"{:-^100}", format!("SAMPLE #{} ({} bytes)", i + 1, sample_data.len()));
+        for unit in sample_data.raw_units(nal_length_size) {
+          if let Err(e) = decoder.decode_nal(unit) {
+            log!(err@"#[hevc] {e}");
+            complete = false;
+          }
+        }
+      }
+      return match decoder.finish_picture() {
+        Some(picture) if complete => {
+          Self::write_frame(&picture.frame(), codec.colour())?;
+          Ok(Some(sample))
+        }
+        _ => {
+          log!(warn@"#[hevc] Sample #{} could not be decoded", sample + 1);
+          Ok(None)
+        }
+      };
+    }
+    match stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data)
+    {
+      Some(CodecData::Av01(av01)) => {
+        let mut parser = Av1Parser::new(&av01.av1c);
+        return self.parse_frame_headers(index, sample, "av1", |data| {
+          Ok(
+            parser
+              .parse_temporal_unit(data)?
+              .iter()
+              .map(|header| (header.is_key_frame(), header.to_string()))
+              .collect(),
+          )
+        });
+      }
+      Some(CodecData::Vp09(_)) => {
+        let mut parser = Vp9Parser::default();
+        return self.parse_frame_headers(index, sample, "vp9", |data| {
+          Ok(
+            parser
+              .parse_sample(data)?
+              .iter()
+              .map(|header| (header.key_frame, header.to_string()))
+              .collect(),
+          )
+        });
+      }
+      _ => (),
+    }
+    let point = self.random_access_point(stbl, index, sample)?;
+    log!(File@"RANDOM ACCESS POINT {point:?} FOR SAMPLE #{}", sample + 1);
+    let mut reader = self.file.try_clone()?;
+    let samples = index
+      .iter()
+      .enumerate()
+      .skip(point.sample)
+      .map(move |(i, sample)| Ok((i, sample.read(&mut reader)?)));
+    let colour = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .and_then(|d| d.data.colour());
     let Some(CodecData::Avc1(avc1)) = stbl
       .stsd
       .decode(self)?
@@ -94,61 +241,603 @@ impl Decoder {
       .map(|d| &mut d.data) else {
         return Err(DecoderError::MissingConfig)
       };
-    let nal_length_size = avc1.avcc.nal_length_size_minus_one as usize + 1;
+    Self::decode_samples(
+      samples,
+      &mut avc1.avcc,
+      sample,
+      point.recovery_frame_cnt,
+      colour,
+    )
+  }
+
+  /// Decodes `samples` until the frame of the sample at index `sample` is recovered
+  /// and writes it to `temp/yuv_frame`, returns the index of the sample of the written frame
+  fn decode_samples<I: Iterator<Item = SampleResult<(usize, Sample)>>>(
+    samples: I,
+    avcc: &mut AvcCAtom,
+    sample: usize,
+    recovery_frame_cnt: u16,
+    colour: Option<ColourInfo>,
+  ) -> DecoderResult<Option<usize>> {
+    let nal_length_size = avcc.nal_length_size_minus_one as usize + 1;
     let mut dpb = DecodedPictureBuffer::new();
+    let mut recovery = Recovery::new(recovery_frame_cnt);
+
+    let mut random_access = true;
 
-    for (i, sample) in samples {
-      log!(File@"{:-^100}", format!("SAMPLE #{} ({} bytes)", i + 1, sample.len()));
-      for nal in sample.units(nal_length_size) {
+    for sample_data in samples {
+      let (i, sample_data) = sample_data?;
+      log!(File@"{:-^100}", format!("SAMPLE #{} ({} bytes)", i + 1, sample_data.len()));
+      let mut frame = None;
+      for nal in sample_data.units(nal_length_size) {
         let nal = nal?;
-        let msg = format!(
-          "[{:?} idc={}] ({} bytes) => ",
-          nal.unit_type, nal.idc, nal.size
-        );
         match nal.unit_type {
+          NALUnitType::Sei => Self::log_sei(&nal),
+          NALUnitType::NonIDRPicture | NALUnitType::IDRPicture => {
+            if random_access {
+              dpb.random_access = !nal.unit_type.is_idr();
+              random_access = false;
+            }
+            Self::decode_slice(
+              i,
+              &nal,
+              &mut avcc.sps,
+              &mut avcc.pps,
+              &mut dpb,
+              &mut frame,
+              &mut recovery,
+            )?;
+          }
+          _ => log!(File@"{} [UNUSED]", Self::nal_msg(&nal)),
+        }
+      }
+
+      if let Some(frame) = frame {
+        if i >= sample && recovery.is_recovered() {
+          Self::write_frame(&frame, colour)?;
+          return Ok(Some(i));
+        }
+        log!(File@"SAMPLE #{} OUTPUT SUPPRESSED {recovery:?}", i + 1);
+      }
+    }
+    Ok(None)
+  }
+
+  /// Writes `frame` to `temp/yuv_frame` and, converted to RGB with `colour`, to
+  /// `temp/rgb_frame.ppm`
+  fn write_frame(frame: &Frame, colour: Option<ColourInfo>) -> DecoderResult {
+    let colour = colour.unwrap_or_default();
+    log!(File@"OUTPUT COLOUR {colour}");
+    frame.write_to_yuv_file("temp/yuv_frame")?;
+    frame.write_to_ppm_file("temp/rgb_frame.ppm", &colour)?;
+    Ok(())
+  }
+
+  /// Rewrites every sample of `index` as an Annex B byte stream, returns the number of samples
+  /// written
+  pub fn extract_annex_b<W: Write>(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    writer: W,
+    insert_aud: bool,
+  ) -> DecoderResult<usize> {
+    let Some(CodecData::Avc1(avc1)) = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data) else {
+        return Err(DecoderError::MissingConfig)
+      };
+    let nal_length_size = avc1.avcc.nal_length_size_minus_one as usize + 1;
+    let mut writer = AnnexBWriter::new(
+      writer,
+      avc1.avcc.sps_units.clone(),
+      avc1.avcc.pps_units.clone(),
+      insert_aud,
+    );
+
+    let mut reader = self.file.try_clone()?;
+    let mut count = 0;
+    for sample in index.iter() {
+      writer.write_sample(&sample.read(&mut reader)?, nal_length_size)?;
+      count += 1;
+    }
+    writer.flush()?;
+    log!(File@"EXTRACTED {count} SAMPLES TO ANNEX B");
+    Ok(count)
+  }
+
+  /// Writes the AAC samples of the track presented between media times `start` and `end` as
+  /// ADTS frames, returns the number of frames written
+  pub fn extract_adts<W: Write>(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    start: i64,
+    end: i64,
+    writer: W,
+  ) -> DecoderResult<usize> {
+    let Some(CodecData::Mp4a(mp4a)) = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data) else {
+        return Err(DecoderError::MissingConfig)
+      };
+    let config = mp4a
+      .audio_specific_config()
+      .ok_or(DecoderError::MissingConfig)?;
+    log!(File@"ADTS {config:#?}");
+    let mut writer = AdtsWriter::new(writer, config)?;
+    let mut reader = self.file.try_clone()?;
+
+    let mut count = 0;
+    for sample in index.iter().filter(|sample| {
+      sample.composition_time < end && sample.composition_time + sample.duration as i64 > start
+    }) {
+      writer.write_sample(&sample.read(&mut reader)?)?;
+      count += 1;
+    }
+    writer.flush()?;
+    log!(File@"EXTRACTED {count} AAC FRAMES TO ADTS");
+    Ok(count)
+  }
+
+  /// Decodes the AAC samples of `index` one access unit at a time
+  pub fn audio_frames<'a>(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &'a SampleIndex,
+  ) -> DecoderResult<AudioFrameIter<'a>> {
+    let Some(CodecData::Mp4a(mp4a)) = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data) else {
+        return Err(DecoderError::MissingConfig)
+      };
+    let config = mp4a
+      .audio_specific_config()
+      .ok_or(DecoderError::MissingConfig)?;
+    Ok(AudioFrameIter::new(self.file.try_clone()?, index, config)?)
+  }
+
+  /// Decodes the `tx3g` or QuickTime `text` samples of `index`, in index order
+  pub fn text_samples(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+  ) -> DecoderResult<Vec<TextSample>> {
+    let quicktime = match stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data)
+    {
+      Some(CodecData::Tx3g(_)) => false,
+      Some(CodecData::Text(_)) => true,
+      _ => return Err(DecoderError::MissingConfig),
+    };
+    let mut reader = self.file.try_clone()?;
+    index
+      .iter()
+      .map(|sample| Ok(TextSample::decode(&sample.read(&mut reader)?, quicktime)?))
+      .collect()
+  }
+
+  /// Reads the frame number each `tmcd` sample of `index` starts its timecode from
+  pub fn timecode_samples(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+  ) -> DecoderResult<Vec<u32>> {
+    let Some(CodecData::Tmcd(_)) = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data) else {
+        return Err(DecoderError::MissingConfig)
+      };
+    let mut reader = self.file.try_clone()?;
+    index
+      .iter()
+      .map(|sample| {
+        let sample = sample.read(&mut reader)?;
+        let frame = sample
+          .first_chunk::<4>()
+          .ok_or(SampleError::TimecodeSize(sample.len()))?;
+        Ok(u32::from_be_bytes(*frame))
+      })
+      .collect()
+  }
+
+  /// Writes the audio samples of `index` to `writer` as a WAV file, uncompressed samples are
+  /// copied and AAC samples decoded to 16-bit PCM. Returns the number of bytes of audio data
+  /// written
+  pub fn extract_wav<W: Write>(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    writer: W,
+  ) -> DecoderResult<u64> {
+    match stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data)
+    {
+      Some(CodecData::Pcm(_)) => self.extract_pcm_wav(stbl, index, writer),
+      Some(CodecData::Mp4a(_)) => self.extract_aac_wav(stbl, index, writer),
+      _ => Err(DecoderError::MissingConfig),
+    }
+  }
+
+  fn extract_aac_wav<W: Write>(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    writer: W,
+  ) -> DecoderResult<u64> {
+    let frames = self.audio_frames(stbl, index)?;
+    let Some((channel_order, channel_mask)) = frames.wav_channel_layout() else {
+      return Err(AacError::Unsupported("channel configuration").into());
+    };
+    let channels = channel_order.len();
+    let frame_size = AacDecoder::FRAME_LENGTH * channels * 2;
+    let data_size = (index.len() * frame_size) as u64;
+    let format = PcmFormat {
+      bits_per_sample: 16,
+      float: false,
+      big_endian: false,
+      signed: true,
+    };
+    let sample_rate = frames.sample_rate();
+    log!(File@"WAV AAC {channels} CHANNELS {sample_rate} HZ");
+    let mut writer = WavWriter::new(
+      writer,
+      format,
+      channels as u16,
+      sample_rate,
+      channel_mask,
+      data_size,
+    )?;
+
+    let mut block = vec![0; frame_size];
+    for (i, frame) in frames.enumerate() {
+      match frame {
+        Ok(frame) if frame.channels == channels => {
+          for (samples, block) in frame
+            .samples
+            .chunks_exact(channels)
+            .zip(block.chunks_exact_mut(channels * 2))
+          {
+            for (&channel, sample) in channel_order.iter().zip(block.chunks_exact_mut(2)) {
+              sample.copy_from_slice(&samples[channel].to_le_bytes());
+            }
+          }
+        }
+        // Concealed with silence to keep the data chunk size
+        Ok(frame) => {
+          log!(err@"#[aac] Frame {i} has {} channels instead of {channels}", frame.channels);
+          block.fill(0);
+        }
+        Err(e) => {
+          log!(err@"#[aac] Frame {i} {e}");
+          block.fill(0);
+        }
+      }
+      writer.write_samples(&mut block)?;
+    }
+    writer.finish()?;
+    log!(File@"DECODED {} AAC FRAMES TO WAV", index.len());
+    Ok(data_size)
+  }
+
+  fn extract_pcm_wav<W: Write>(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    writer: W,
+  ) -> DecoderResult<u64> {
+    const BLOCK_SIZE: u64 = 1 << 20;
+    let Some(CodecData::Pcm(pcm)) = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data) else {
+        return Err(DecoderError::MissingConfig)
+      };
+    log!(File@"WAV {pcm:#?}");
+    let bytes_per_frame = pcm.bytes_per_frame() as u64;
+    // QuickTime stores uncompressed audio with a constant sample size of 1 where every sample
+    // is a single frame of bytes_per_frame bytes
+    let sample_size = |sample: &IndexedSample| match stbl.stsz.sample_size {
+      1 => bytes_per_frame,
+      _ => sample.size as u64,
+    };
+
+    // Contiguous samples are read together as (offset, size) runs
+    let mut runs: Vec<(u64, u64)> = Vec::new();
+    let mut next_offset = None;
+    for sample in index.iter() {
+      match runs.last_mut() {
+        Some(run) if next_offset == Some(sample.offset) => run.1 += sample_size(sample),
+        _ => runs.push((sample.offset, sample_size(sample))),
+      }
+      next_offset = Some(sample.offset + sample.size as u64);
+    }
+    let data_size = runs.iter().map(|(_, size)| size).sum();
+
+    let mut writer = WavWriter::new(
+      writer,
+      pcm.format,
+      pcm.sound.output_channels() as u16,
+      pcm.sound.output_sample_rate().round() as u32,
+      pcm
+        .sound
+        .chan
+        .as_ref()
+        .map_or(0, |chan| chan.channel_mask()),
+      data_size,
+    )?;
+    let block_size = BLOCK_SIZE - BLOCK_SIZE % bytes_per_frame.max(1);
+    let mut block = vec![0; block_size as usize];
+    for (offset, size) in runs {
+      self.file.seek(SeekFrom::Start(offset))?;
+      let mut remaining = size;
+      while remaining > 0 {
+        let block = &mut block[..remaining.min(block_size) as usize];
+        self.file.read_exact(block)?;
+        writer.write_samples(block)?;
+        remaining -= block.len() as u64;
+      }
+    }
+    writer.finish()?;
+    log!(File@"EXTRACTED {data_size} BYTES OF PCM TO WAV");
+    Ok(data_size)
+  }
+
+  /// Decodes the first frame of an Annex B byte stream, where parameter sets are sent in-band,
+  /// and writes it to `temp/yuv_frame`
+  pub fn decode_annex_b(&mut self) -> DecoderResult<ElementaryStream> {
+    self.file.seek(SeekFrom::Start(0))?;
+
+    let mut sps = HashMap::new();
+    let mut pps = HashMap::new();
+    let mut active_sps = None;
+    let mut dpb = DecodedPictureBuffer::new();
+    let mut recovery = None;
+    let mut access_units = 0;
+    let mut decoding = true;
+
+    for (i, access_unit) in AccessUnitIter::new(&mut self.file).enumerate() {
+      let access_unit = access_unit?;
+      access_units += 1;
+      if !decoding {
+        continue;
+      }
+      log!(File@"{:-^100}", format!("ACCESS UNIT #{} ({} NAL units)", i + 1, access_unit.len()));
+      let mut frame = None;
+      for nal in access_unit.units() {
+        match nal.unit_type {
+          NALUnitType::Sps => {
+            let unit = SequenceParameterSet::decode_rbsp(
+              nal.size as u16,
+              nal.idc << 5 | 7,
+              &mut BitStream::new(nal.rbsp()),
+            );
+            log!(File@"{}{unit:#?}", Self::nal_msg(&nal));
+            active_sps = Some(unit.id);
+            sps.insert(unit.id, unit);
+          }
+          NALUnitType::Pps => {
+            let chroma_format_idc = sps
+              .get(&PictureParameterSet::seq_parameter_set_id(nal.rbsp()))
+              .map_or(1, |sps: &SequenceParameterSet| sps.chroma_format_idc);
+            let unit = PictureParameterSet::decode_rbsp(
+              nal.size as u16,
+              nal.idc << 5 | 8,
+              &mut BitStream::new(nal.rbsp()),
+              chroma_format_idc,
+            );
+            log!(File@"{}{unit:#?}", Self::nal_msg(&nal));
+            pps.insert(unit.id, unit);
+          }
           NALUnitType::Sei => {
-            let mut bit_data = BitStream::new(nal.data);
-            let sei_msg = SeiMessage::decode(nal.size, &mut bit_data);
-            if let SeiPayload::UserDataUnregistered {
-              uuid_iso_iec_11578,
-              data,
-            } = sei_msg.payload
-            {
-              log!(File@"{msg}SEI: (\"{:016x}\", \"{}\")", uuid_iso_iec_11578, String::from_utf8_lossy(&data));
-            } else {
-              log!(File@"{msg}{sei_msg:?}");
+            Self::log_sei(&nal);
+            if recovery.is_none() {
+              let mut bit_data = BitStream::new(nal.rbsp());
+              recovery = SeiMessage::decode_all(nal.size, &mut bit_data)
+                .iter()
+                .find_map(|sei| match sei.payload {
+                  SeiPayload::RecoveryPoint {
+                    recovery_frame_cnt, ..
+                  } => Some(Recovery::new(recovery_frame_cnt)),
+                  _ => None,
+                });
             }
           }
           NALUnitType::NonIDRPicture | NALUnitType::IDRPicture => {
-            let mut slice = Slice::new(nal.data, &nal, &mut avc1.avcc.sps, &mut avc1.avcc.pps);
-            let mut frame = Frame::new(&slice);
-            slice.data(&mut dpb, &mut frame)?;
-            log!(File@"{msg}{:#?}", slice);
-            use std::io::Write;
-            let name = format!("temp/slice/{i}");
-            let mut f = std::fs::File::create(name).expect("SLICE CREATION");
-            f.write_all(
-              format!(
-                "{:#?}\n{:#?}\n{:#?}\n{:#?}",
-                dpb,
-                nal,
-                slice,
-                &slice.macroblocks[..10]
-              )
-              .as_bytes(),
-            )
-            .expect("SLICE SAVING");
-            if i == 0 {
-              frame.write_to_yuv_file("temp/yuv_frame")?;
-            }
+            let mut header = BitStream::new(nal.rbsp());
+            header.exponential_golomb::<u32>();
+            header.exponential_golomb::<u32>();
+            let pps_id: u16 = header.exponential_golomb();
+            let pps = pps.get_mut(&pps_id).ok_or(DecoderError::MissingConfig)?;
+            let sps = sps
+              .get_mut(&pps.seq_parameter_set_id)
+              .ok_or(DecoderError::MissingConfig)?;
+            active_sps = Some(sps.id);
+            let recovery = recovery.get_or_insert_with(|| {
+              dpb.random_access = !access_unit.is_idr();
+              Recovery::new(0)
+            });
+            Self::decode_slice(i, &nal, sps, pps, &mut dpb, &mut frame, recovery)?;
           }
-          _ => log!(File@"{msg} [UNUSED]"),
+          _ => log!(File@"{} [UNUSED]", Self::nal_msg(&nal)),
+        }
+      }
+
+      if let Some(frame) = frame {
+        if recovery
+          .as_ref()
+          .is_some_and(|recovery| recovery.is_recovered())
+        {
+          let colour = active_sps
+            .and_then(|id| sps.get(&id))
+            .and_then(|sps| sps.vui_parameters.as_ref()?.video_signal_type.as_ref())
+            .map(VideoSignalType::colour);
+          Self::write_frame(&frame, colour)?;
+          decoding = false;
+        } else {
+          log!(File@"ACCESS UNIT #{} OUTPUT SUPPRESSED {recovery:?}", i + 1);
         }
       }
     }
+
+    Ok(ElementaryStream {
+      sps: active_sps
+        .and_then(|id| sps.remove(&id))
+        .ok_or(DecoderError::MissingConfig)?,
+      access_units,
+    })
+  }
+
+  fn nal_msg(nal: &NALUnit) -> String {
+    format!(
+      "[{:?} idc={}] ({} bytes) => ",
+      nal.unit_type, nal.idc, nal.size
+    )
+  }
+
+  fn log_sei(nal: &NALUnit) {
+    let msg = Self::nal_msg(nal);
+    let mut bit_data = BitStream::new(nal.rbsp());
+    for sei_msg in &*SeiMessage::decode_all(nal.size, &mut bit_data) {
+      if let SeiPayload::UserDataUnregistered {
+        uuid_iso_iec_11578,
+        data,
+      } = &sei_msg.payload
+      {
+        log!(File@"{msg}SEI: (\"{:016x}\", \"{}\")", uuid_iso_iec_11578, String::from_utf8_lossy(data));
+      } else {
+        log!(File@"{msg}{sei_msg:?}");
+      }
+    }
+  }
+
+  fn decode_slice(
+    i: usize,
+    nal: &NALUnit,
+    sps: &mut SequenceParameterSet,
+    pps: &mut PictureParameterSet,
+    dpb: &mut DecodedPictureBuffer,
+    frame: &mut Option<Frame>,
+    recovery: &mut Recovery,
+  ) -> DecoderResult {
+    let mut slice = Slice::new(nal.data, nal, sps, pps);
+    recovery.update(&slice);
+    let frame = frame.get_or_insert_with(|| Frame::new(&slice));
+    slice.data(dpb, frame)?;
+    log!(File@"{}{:#?}", Self::nal_msg(nal), slice);
+    use std::io::Write;
+    let name = format!("temp/slice/{i}");
+    let mut f = std::fs::File::create(name).expect("SLICE CREATION");
+    f.write_all(
+      format!(
+        "{:#?}\n{:#?}\n{:#?}\n{:#?}",
+        dpb,
+        nal,
+        slice,
+        &slice.macroblocks[..10]
+      )
+      .as_bytes(),
+    )
+    .expect("SLICE SAVING");
     Ok(())
   }
 
+  /// Parses the frame headers of the samples from the closest sync sample to `sample` with
+  /// `parse`, which returns whether every frame is a key frame and its description. The frames
+  /// are reported but not decoded, returns `None`
+  fn parse_frame_headers(
+    &mut self,
+    index: &SampleIndex,
+    sample: usize,
+    codec: &str,
+    mut parse: impl FnMut(&[u8]) -> DecoderResult<Vec<(bool, String)>>,
+  ) -> DecoderResult<Option<usize>> {
+    let mut reader = self.file.try_clone()?;
+    let point = index.sync_sample(sample).unwrap_or_default();
+    for (i, sample_data) in index.iter().enumerate().take(sample + 1).skip(point) {
+      let frames = parse(&sample_data.read(&mut reader)?)?;
+      if i == point && !frames.first().is_some_and(|(key_frame, _)| *key_frame) {
+        log!(warn@"#[{codec}] Sync sample #{} does not start with a key frame", i + 1);
+      }
+      if i == sample {
+        for (_, frame) in &frames {
+          log!(info@"#[{codec}] Sample #{} {frame}", i + 1);
+        }
+      }
+    }
+    log!(warn@"#[{codec}] Sample #{} was parsed but not decoded", sample + 1);
+    Ok(None)
+  }
+
+  /// Finds the closest sample at or before `sample` where decoding can start, either a
+  /// sync sample, a member of a `roll` sample group or a sample carrying a recovery point SEI
+  pub fn random_access_point(
+    &mut self,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    sample: usize,
+  ) -> DecoderResult<RandomAccessPoint> {
+    let mut point = RandomAccessPoint {
+      sample: index.sync_sample(sample).unwrap_or_default(),
+      ..Default::default()
+    };
+    if point.sample == sample {
+      return Ok(point);
+    }
+
+    if index.has_roll_groups() {
+      if let Some((i, distance)) = (point.sample + 1..=sample)
+        .rev()
+        .find_map(|i| index[i].roll_distance.map(|distance| (i, distance)))
+      {
+        point.sample = i;
+        point.recovery_frame_cnt = distance.max(0) as u16;
+      }
+      return Ok(point);
+    }
+
+    let Some(CodecData::Avc1(avc1)) = stbl
+      .stsd
+      .decode(self)?
+      .sample_description_table
+      .first()
+      .map(|d| &d.data) else {
+        return Err(DecoderError::MissingConfig)
+      };
+    let nal_length_size = avc1.avcc.nal_length_size_minus_one as usize + 1;
+    for i in point.sample + 1..=sample {
+      let sample_data = index[i].read(&mut self.file)?;
+      if let Some(recovery_frame_cnt) = sample_data.recovery_point(nal_length_size)? {
+        point.sample = i;
+        point.recovery_frame_cnt = recovery_frame_cnt;
+      }
+    }
+
+    Ok(point)
+  }
+
   pub fn decode_udta_meta<'a>(
     &mut self,
     root: &'a mut RootAtom,
@@ -214,6 +903,62 @@ impl Decoder {
   }
 }
 
+#[derive(Debug)]
+pub struct ElementaryStream {
+  /// Last active sequence parameter set
+  pub sps: SequenceParameterSet,
+  pub access_units: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomAccessPoint {
+  /// Index of the sample where decoding starts
+  pub sample: usize,
+  /// Number of frames, in decoding order, until the output is correct
+  pub recovery_frame_cnt: u16,
+}
+
+/// Tracks the recovery point of a stream decoded from a random access point,
+/// frames are not output until `frame_num` reaches the recovery point
+#[derive(Debug)]
+pub struct Recovery {
+  recovery_frame_cnt: u16,
+  recovery_frame_num: Option<u16>,
+  recovered: bool,
+}
+
+impl Recovery {
+  pub fn new(recovery_frame_cnt: u16) -> Self {
+    Self {
+      recovery_frame_cnt,
+      recovery_frame_num: None,
+      recovered: false,
+    }
+  }
+
+  /// D.2.8 The recovery point is the first picture whose frame_num is
+  /// `(frame_num + recovery_frame_cnt) % MaxFrameNum` of the first decoded picture
+  pub fn update(&mut self, slice: &Slice) {
+    if slice.nal_unit_type.is_idr() {
+      self.recovered = true;
+    }
+    if self.recovered {
+      return;
+    }
+    let max_frame_num = slice.max_frame_num as u32;
+    let recovery_frame_num = *self.recovery_frame_num.get_or_insert(
+      ((slice.frame_num as u32 + self.recovery_frame_cnt as u32) % max_frame_num) as u16,
+    );
+    let distance =
+      (recovery_frame_num as u32 + max_frame_num - slice.frame_num as u32) % max_frame_num;
+    self.recovered = distance == 0 || distance > self.recovery_frame_cnt as u32;
+  }
+
+  pub fn is_recovered(&self) -> bool {
+    self.recovered
+  }
+}
+
 impl Read for Decoder {
   fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
     self.file.read(buf)
@@ -225,3 +970,17 @@ impl Seek for Decoder {
     self.file.seek(pos)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detect_annex_b() {
+    assert!(DecoderBrand::detect(&[0, 0, 0, 1, 0x67, 0x42]).is_annex_b());
+    assert!(DecoderBrand::detect(&[0, 0, 1, 0x09, 0xF0]).is_annex_b());
+    // 64 bit atom size
+    assert!(!DecoderBrand::detect(&[0, 0, 0, 1, b'f', b't', b'y', b'p']).is_annex_b());
+    assert!(!DecoderBrand::detect(&[0, 0, 0, 0x20, b'f', b't', b'y', b'p']).is_annex_b());
+  }
+}
@@ -9,7 +9,7 @@ use std::{
   io::{BufWriter, Write},
 };
 
-use super::slice::Slice;
+use super::{codec::ColourInfo, slice::Slice};
 use crate::math::inverse_raster_scan;
 
 #[derive(Debug)]
@@ -27,11 +27,16 @@ pub struct Frame {
 
 impl Frame {
   pub fn new(slice: &Slice) -> Self {
-    let width_l = slice.pic_width_in_samples_l as usize;
-    let height_l = slice.pic_height_in_samples_l as usize;
-    let width_c = slice.pic_width_in_samples_c as usize;
-    let height_c = slice.pic_height_in_samples_c as usize;
+    Self::with_size(
+      slice.pic_width_in_samples_l as usize,
+      slice.pic_height_in_samples_l as usize,
+      slice.pic_width_in_samples_c as usize,
+      slice.pic_height_in_samples_c as usize,
+    )
+  }
 
+  /// Frame of zero samples with the given luma and chroma sizes
+  pub fn with_size(width_l: usize, height_l: usize, width_c: usize, height_c: usize) -> Self {
     Self {
       luma_data: vec![vec![0; height_l].into(); width_l].into(),
       chroma_cr_data: vec![vec![0; height_c].into(); width_c].into(),
@@ -69,6 +74,33 @@ impl Frame {
     Ok(())
   }
 
+  /// Writes the frame as a binary PPM, converted to RGB with the matrix coefficients and range
+  /// of `colour`
+  pub fn write_to_ppm_file(&self, file_path: &str, colour: &ColourInfo) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(file_path)?);
+    write!(file, "P6\n{} {}\n255\n", self.width_l, self.height_l)?;
+
+    let chroma = |data: &[Box<[u8]>], x: usize, y: usize| {
+      data
+        .get(x * self.width_c / self.width_l.max(1))
+        .and_then(|column| column.get(y * self.height_c / self.height_l.max(1)))
+        .copied()
+        .unwrap_or(128)
+    };
+    for y in 0..self.height_l {
+      for x in 0..self.width_l {
+        file.write_all(&colour.to_rgb(
+          self.luma_data[x][y],
+          chroma(&self.chroma_cb_data, x, y),
+          chroma(&self.chroma_cr_data, x, y),
+          self.height_l,
+        ))?;
+      }
+    }
+
+    Ok(())
+  }
+
   pub fn decode(&mut self, slice: &mut Slice) {
     if slice.mb().mb_type.mode().is_intra_4x4() {
       self.transform_for_4x4_luma_residual_blocks(slice);
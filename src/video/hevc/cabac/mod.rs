@@ -0,0 +1,966 @@
+pub mod residual;
+pub mod table;
+
+use super::{
+  picture::{BlockInfo, MvField, SaoParameters},
+  slice::{coding_unit::*, Slice},
+  HevcError, HevcResult,
+};
+use crate::{
+  math::clamp,
+  video::cabac::consts::{RANGE_TAB_LPS, TRANS_IDX_LPS, TRANS_IDX_MPS},
+};
+use table::*;
+
+/// 9.3 CABAC parsing process of the HEVC slice segment data, the arithmetic decoding engine
+/// and the context variables are the same as H.264 with 9.3.4.3 decisions
+#[derive(Debug, Clone)]
+pub struct CabacContext {
+  /// pStateIdx of every context variable
+  pub p_state_idx: [u8; CTX_IDX_COUNT],
+  /// valMps of every context variable
+  pub val_mps: [u8; CTX_IDX_COUNT],
+  /// StatCoeff of the persistent Rice adaptation for every sbType
+  pub stat_coeff: [u8; 4],
+  /// ivlCurrRange
+  pub range: u16,
+  /// ivlOffset
+  pub offset: u16,
+  pub bin_count: u32,
+}
+
+impl CabacContext {
+  /// 9.3.2 Initializes the context variables and the arithmetic decoding engine at the start
+  /// of the slice segment data
+  pub fn new(slice: &mut Slice) -> HevcResult<Self> {
+    let mut cabac = Self {
+      p_state_idx: [0; CTX_IDX_COUNT],
+      val_mps: [0; CTX_IDX_COUNT],
+      stat_coeff: [0; 4],
+      range: 510,
+      offset: 0,
+      bin_count: 0,
+    };
+    cabac.init_context_variables(slice);
+    cabac.init_decoding_engine(slice)?;
+    Ok(cabac)
+  }
+
+  /// 9.3.2.2 Initialization process for context variables
+  pub fn init_context_variables(&mut self, slice: &Slice) {
+    let slice_qp_y = clamp(slice.slice_qp_y as i32, 0, 51);
+    for (ctx_idx, init) in CTX_INIT_TABLE.iter().enumerate() {
+      let init_value = init[slice.init_type] as i32;
+      let m = (init_value >> 4) * 5 - 45;
+      let n = ((init_value & 15) << 3) - 16;
+      let pre_ctx_state = clamp(((m * slice_qp_y) >> 4) + n, 1, 126);
+      if pre_ctx_state <= 63 {
+        self.p_state_idx[ctx_idx] = (63 - pre_ctx_state) as u8;
+        self.val_mps[ctx_idx] = 0;
+      } else {
+        self.p_state_idx[ctx_idx] = (pre_ctx_state - 64) as u8;
+        self.val_mps[ctx_idx] = 1;
+      }
+    }
+    self.stat_coeff = [0; 4];
+  }
+
+  /// 9.3.2.4 Synchronization of the context variables and Rice statistics with `stored`
+  pub fn sync(&mut self, stored: &Self) {
+    self.p_state_idx = stored.p_state_idx;
+    self.val_mps = stored.val_mps;
+    self.stat_coeff = stored.stat_coeff;
+  }
+
+  /// 9.3.2.5 Initialization process for the arithmetic decoding engine
+  pub fn init_decoding_engine(&mut self, slice: &mut Slice) -> HevcResult {
+    self.range = 510;
+    self.offset = slice.stream.bits_into(9);
+    if self.offset == 510 || self.offset == 511 {
+      return Err(HevcError::Invalid("ivlOffset"));
+    }
+    Ok(())
+  }
+
+  /// 9.3.4.3.2 Arithmetic decoding process for a binary decision
+  pub fn decision(&mut self, slice: &mut Slice, ctx_idx: usize) -> bool {
+    let p_state_idx = self.p_state_idx[ctx_idx] as usize;
+    let q_range_idx = ((self.range >> 6) & 3) as usize;
+    let range_lps = RANGE_TAB_LPS[p_state_idx][q_range_idx] as u16;
+    self.range -= range_lps;
+    let bin_val = if self.offset >= self.range {
+      let bin_val = self.val_mps[ctx_idx] == 0;
+      self.offset -= self.range;
+      self.range = range_lps;
+      if p_state_idx == 0 {
+        self.val_mps[ctx_idx] = 1 - self.val_mps[ctx_idx];
+      }
+      self.p_state_idx[ctx_idx] = TRANS_IDX_LPS[p_state_idx];
+      bin_val
+    } else {
+      self.p_state_idx[ctx_idx] = TRANS_IDX_MPS[p_state_idx];
+      self.val_mps[ctx_idx] != 0
+    };
+    self.renorm(slice);
+    self.bin_count += 1;
+    bin_val
+  }
+
+  /// 9.3.4.3.4 Bypass decoding process for binary decisions
+  pub fn bypass(&mut self, slice: &mut Slice) -> bool {
+    self.offset = (self.offset << 1) | slice.stream.bit() as u16;
+    self.bin_count += 1;
+    if self.offset >= self.range {
+      self.offset -= self.range;
+      true
+    } else {
+      false
+    }
+  }
+
+  /// 9.3.4.3.5 Decoding process for binary decisions before termination
+  pub fn terminate(&mut self, slice: &mut Slice) -> bool {
+    self.range -= 2;
+    self.bin_count += 1;
+    if self.offset >= self.range {
+      true
+    } else {
+      self.renorm(slice);
+      false
+    }
+  }
+
+  /// 9.3.4.3.3 Renormalization process in the arithmetic decoding engine
+  pub fn renorm(&mut self, slice: &mut Slice) {
+    while self.range < 256 {
+      self.range <<= 1;
+      self.offset = (self.offset << 1) | slice.stream.bit() as u16;
+    }
+  }
+
+  /// Fixed-length bypass bins, most significant bit first
+  pub fn bypass_bits(&mut self, slice: &mut Slice, n: u8) -> u32 {
+    (0..n).fold(0, |value, _| (value << 1) | self.bypass(slice) as u32)
+  }
+
+  /// 9.3.3.2 Truncated Rice binarization with cRiceParam 0 of bypass bins
+  pub fn truncated_unary_bypass(&mut self, slice: &mut Slice, c_max: u32) -> u32 {
+    let mut value = 0;
+    while value < c_max && self.bypass(slice) {
+      value += 1;
+    }
+    value
+  }
+
+  /// 9.3.3.3 k-th order exponential Golomb binarization of bypass bins
+  pub fn exp_golomb_bypass(&mut self, slice: &mut Slice, mut k: u8) -> HevcResult<u32> {
+    let mut value = 0u32;
+    while self.bypass(slice) {
+      value += 1 << k;
+      k += 1;
+      if k >= 32 {
+        return Err(HevcError::Invalid("k-th order Exp-Golomb prefix"));
+      }
+    }
+    Ok(value + self.bypass_bits(slice, k))
+  }
+}
+
+impl CabacContext {
+  /// 7.3.8.2 Coding tree unit syntax
+  pub fn coding_tree_unit(&mut self, slice: &mut Slice) -> HevcResult {
+    let log2_ctb_size = slice.picture.log2_ctb_size;
+    let pic_width_in_ctbs = slice.picture.pic_width_in_ctbs;
+    let x_ctb = (slice.ctb_addr_in_rs % pic_width_in_ctbs) << log2_ctb_size;
+    let y_ctb = (slice.ctb_addr_in_rs / pic_width_in_ctbs) << log2_ctb_size;
+    if slice.slice_sao_luma_flag || slice.slice_sao_chroma_flag {
+      self.sao(slice)?;
+    }
+    self.coding_quadtree(slice, x_ctb, y_ctb, log2_ctb_size, 0)
+  }
+
+  /// 7.3.8.3 Sample adaptive offset syntax of the current CTB
+  fn sao(&mut self, slice: &mut Slice) -> HevcResult {
+    let picture = &*slice.picture;
+    let ctb_addr_in_rs = slice.ctb_addr_in_rs;
+    let ctb_addr_in_ts = slice.ctb_addr_in_ts as usize;
+    let pic_width_in_ctbs = picture.pic_width_in_ctbs;
+    let tile_id =
+      |ctb_addr_rs: u32| picture.tile_id[picture.ctb_addr_rs_to_ts[ctb_addr_rs as usize] as usize];
+    let left_ctb = if !ctb_addr_in_rs.is_multiple_of(pic_width_in_ctbs)
+      && ctb_addr_in_rs > slice.slice_addr_rs
+      && picture.tile_id[ctb_addr_in_ts] == tile_id(ctb_addr_in_rs - 1)
+    {
+      Some(ctb_addr_in_rs - 1)
+    } else {
+      None
+    };
+    let up_ctb = if ctb_addr_in_rs >= pic_width_in_ctbs
+      && ctb_addr_in_rs - pic_width_in_ctbs >= slice.slice_addr_rs
+      && picture.tile_id[ctb_addr_in_ts] == tile_id(ctb_addr_in_rs - pic_width_in_ctbs)
+    {
+      Some(ctb_addr_in_rs - pic_width_in_ctbs)
+    } else {
+      None
+    };
+
+    let mut merge_ctb = None;
+    if let Some(left_ctb) = left_ctb {
+      if self.decision(slice, CTXIDX_SAO_MERGE_FLAG) {
+        merge_ctb = Some(left_ctb);
+      }
+    }
+    if let (None, Some(up_ctb)) = (merge_ctb, up_ctb) {
+      if self.decision(slice, CTXIDX_SAO_MERGE_FLAG) {
+        merge_ctb = Some(up_ctb);
+      }
+    }
+    if let Some(merge_ctb) = merge_ctb {
+      slice.picture.sao[ctb_addr_in_rs as usize] = slice.picture.sao[merge_ctb as usize];
+      return Ok(());
+    }
+
+    let mut sao = [SaoParameters::default(); 3];
+    let components = if slice.chroma_array_type != 0 { 3 } else { 1 };
+    for c_idx in 0..components {
+      let (enabled, bit_depth, log2_offset_scale) = if c_idx == 0 {
+        (
+          slice.slice_sao_luma_flag,
+          slice.sps.bit_depth_luma(),
+          slice.pps.range_extension.log2_sao_offset_scale_luma,
+        )
+      } else {
+        (
+          slice.slice_sao_chroma_flag,
+          slice.sps.bit_depth_chroma(),
+          slice.pps.range_extension.log2_sao_offset_scale_chroma,
+        )
+      };
+      if !enabled {
+        continue;
+      }
+      sao[c_idx].type_idx = if c_idx == 2 {
+        sao[1].type_idx
+      } else if !self.decision(slice, CTXIDX_SAO_TYPE_IDX) {
+        0
+      } else if !self.bypass(slice) {
+        1
+      } else {
+        2
+      };
+      if sao[c_idx].type_idx == 0 {
+        continue;
+      }
+      let c_max = (1 << (bit_depth.min(10) - 5)) - 1;
+      let offset_abs = [(); 4].map(|_| self.truncated_unary_bypass(slice, c_max) as i16);
+      let mut offset_sign = [false, false, true, true];
+      if sao[c_idx].type_idx == 1 {
+        for (sign, abs) in offset_sign.iter_mut().zip(offset_abs) {
+          *sign = abs != 0 && self.bypass(slice);
+        }
+        sao[c_idx].band_position = self.bypass_bits(slice, 5) as u8;
+      } else {
+        sao[c_idx].eo_class = if c_idx == 2 {
+          sao[1].eo_class
+        } else {
+          self.bypass_bits(slice, 2) as u8
+        };
+      }
+      for i in 0..4 {
+        let offset = offset_abs[i] << log2_offset_scale;
+        sao[c_idx].offset_val[i + 1] = if offset_sign[i] { -offset } else { offset };
+      }
+    }
+    slice.picture.sao[ctb_addr_in_rs as usize] = sao;
+    Ok(())
+  }
+
+  /// 7.3.8.4 Coding quadtree syntax
+  fn coding_quadtree(
+    &mut self,
+    slice: &mut Slice,
+    x0: u32,
+    y0: u32,
+    log2_cb_size: u8,
+    cqt_depth: u8,
+  ) -> HevcResult {
+    let size = 1 << log2_cb_size;
+    let log2_min_cb_size = slice.sps.log2_min_cb_size();
+    let split_cu_flag = if x0 + size <= slice.picture.width
+      && y0 + size <= slice.picture.height
+      && log2_cb_size > log2_min_cb_size
+    {
+      let ctx_inc = [(-1, 0), (0, -1)]
+        .into_iter()
+        .filter(|&(dx, dy)| {
+          slice
+            .picture
+            .available(x0, y0, x0 as i32 + dx, y0 as i32 + dy)
+            && slice
+              .picture
+              .block((x0 as i32 + dx) as u32, (y0 as i32 + dy) as u32)
+              .ct_depth
+              > cqt_depth
+        })
+        .count();
+      self.decision(slice, CTXIDX_SPLIT_CU_FLAG + ctx_inc)
+    } else {
+      log2_cb_size > log2_min_cb_size
+    };
+    if log2_cb_size >= slice.log2_min_cu_qp_delta_size {
+      slice.start_quantization_group(x0, y0);
+    }
+    if slice.cu_chroma_qp_offset_enabled_flag
+      && log2_cb_size >= slice.log2_min_cu_chroma_qp_offset_size
+    {
+      slice.is_cu_chroma_qp_offset_coded = false;
+    }
+    if split_cu_flag {
+      let half = size >> 1;
+      for (x, y) in [
+        (x0, y0),
+        (x0 + half, y0),
+        (x0, y0 + half),
+        (x0 + half, y0 + half),
+      ] {
+        if x < slice.picture.width && y < slice.picture.height {
+          self.coding_quadtree(slice, x, y, log2_cb_size - 1, cqt_depth + 1)?;
+        }
+      }
+      Ok(())
+    } else {
+      self.coding_unit(slice, x0, y0, log2_cb_size)
+    }
+  }
+
+  /// 7.3.8.5 Coding unit syntax
+  fn coding_unit(&mut self, slice: &mut Slice, x0: u32, y0: u32, log2_cb_size: u8) -> HevcResult {
+    let size = 1 << log2_cb_size;
+    let mut cu = CodingUnit {
+      x0,
+      y0,
+      log2_cb_size,
+      intra_pred_mode_y: [1; 4],
+      ..Default::default()
+    };
+    if slice.pps.transquant_bypass_enabled_flag {
+      cu.cu_transquant_bypass_flag = self.decision(slice, CTXIDX_CU_TRANSQUANT_BYPASS_FLAG);
+    }
+    let cu_skip_flag = !slice.slice_type.is_intra() && {
+      let ctx_inc = [(-1, 0), (0, -1)]
+        .into_iter()
+        .filter(|&(dx, dy)| {
+          slice
+            .picture
+            .available(x0, y0, x0 as i32 + dx, y0 as i32 + dy)
+            && slice
+              .picture
+              .block((x0 as i32 + dx) as u32, (y0 as i32 + dy) as u32)
+              .pred_mode
+              == PredMode::Skip
+        })
+        .count();
+      self.decision(slice, CTXIDX_CU_SKIP_FLAG + ctx_inc)
+    };
+    cu.pred_mode = if cu_skip_flag {
+      PredMode::Skip
+    } else if slice.slice_type.is_intra() || self.decision(slice, CTXIDX_PRED_MODE_FLAG) {
+      PredMode::Intra
+    } else {
+      PredMode::Inter
+    };
+    let mut block = BlockInfo {
+      ct_depth: slice.picture.log2_ctb_size - log2_cb_size,
+      pred_mode: cu.pred_mode,
+      pcm_flag: false,
+      cu_transquant_bypass_flag: cu.cu_transquant_bypass_flag,
+      intra_pred_mode_y: 1,
+      qp_y: 0,
+      mv_field: MvField::default(),
+      cbf_luma: false,
+      bs: [0; 2],
+    };
+    slice.picture.set_blocks(x0, y0, size, size, block);
+
+    let mut pcm_flag = false;
+    if cu_skip_flag {
+      let prediction_unit = self.prediction_unit(slice, &cu, [x0, y0, size, size])?;
+      cu.prediction_units.push(prediction_unit);
+    } else {
+      let log2_min_cb_size = slice.sps.log2_min_cb_size();
+      if !cu.pred_mode.is_intra() || log2_cb_size == log2_min_cb_size {
+        cu.part_mode = self.part_mode(slice, cu.pred_mode, log2_cb_size);
+      }
+      if cu.pred_mode.is_intra() {
+        if let Some(pcm) = slice
+          .sps
+          .pcm
+          .as_ref()
+          .filter(|_| cu.part_mode == PartMode::Part2Nx2N)
+        {
+          let log2_min_pcm_size = pcm.log2_min_pcm_luma_coding_block_size_minus3 + 3;
+          let log2_max_pcm_size =
+            log2_min_pcm_size + pcm.log2_diff_max_min_pcm_luma_coding_block_size;
+          if (log2_min_pcm_size..=log2_max_pcm_size).contains(&log2_cb_size) {
+            pcm_flag = self.terminate(slice);
+          }
+        }
+        if pcm_flag {
+          block.pcm_flag = true;
+          slice.picture.set_blocks(x0, y0, size, size, block);
+          cu.pcm_sample = Some(self.pcm_sample(slice, log2_cb_size)?);
+        } else {
+          self.intra_pred_modes(slice, &mut cu);
+        }
+      } else {
+        for prediction_block in cu.part_mode.prediction_blocks(x0, y0, log2_cb_size) {
+          let prediction_unit = self.prediction_unit(slice, &cu, prediction_block)?;
+          cu.prediction_units.push(prediction_unit);
+        }
+      }
+      if !pcm_flag {
+        let rqt_root_cbf = cu.pred_mode.is_intra()
+          || (cu.part_mode == PartMode::Part2Nx2N && cu.prediction_units[0].merge_flag)
+          || self.decision(slice, CTXIDX_RQT_ROOT_CBF);
+        if rqt_root_cbf {
+          self.transform_tree(
+            slice,
+            &mut cu,
+            [x0, y0],
+            [x0, y0],
+            log2_cb_size,
+            0,
+            0,
+            [[true; 2]; 2],
+          )?;
+        }
+      }
+    }
+
+    cu.qp_y = slice.qp_y();
+    cu.cu_qp_offset_cb = slice.cu_qp_offset_cb;
+    cu.cu_qp_offset_cr = slice.cu_qp_offset_cr;
+    for (x, y, w, h, mode) in cu.intra_partitions() {
+      block.intra_pred_mode_y = mode;
+      block.qp_y = cu.qp_y;
+      slice.picture.set_blocks(x, y, w, h, block);
+    }
+    slice.coding_units.push(cu);
+    Ok(())
+  }
+
+  /// Table 9-43 part_mode binarization
+  fn part_mode(&mut self, slice: &mut Slice, pred_mode: PredMode, log2_cb_size: u8) -> PartMode {
+    if self.decision(slice, CTXIDX_PART_MODE) {
+      return PartMode::Part2Nx2N;
+    }
+    if pred_mode.is_intra() {
+      return PartMode::PartNxN;
+    }
+    let horizontal = self.decision(slice, CTXIDX_PART_MODE + 1);
+    if log2_cb_size == slice.sps.log2_min_cb_size() {
+      if horizontal {
+        PartMode::Part2NxN
+      } else if log2_cb_size == 3 || self.decision(slice, CTXIDX_PART_MODE + 2) {
+        PartMode::PartNx2N
+      } else {
+        PartMode::PartNxN
+      }
+    } else if !slice.sps.amp_enabled_flag || self.decision(slice, CTXIDX_PART_MODE + 3) {
+      if horizontal {
+        PartMode::Part2NxN
+      } else {
+        PartMode::PartNx2N
+      }
+    } else {
+      match (horizontal, self.bypass(slice)) {
+        (true, false) => PartMode::Part2NxnU,
+        (true, true) => PartMode::Part2NxnD,
+        (false, false) => PartMode::PartnLx2N,
+        (false, true) => PartMode::PartnRx2N,
+      }
+    }
+  }
+
+  /// 7.3.8.7 PCM sample syntax, the arithmetic decoding engine restarts after the samples
+  fn pcm_sample(&mut self, slice: &mut Slice, log2_cb_size: u8) -> HevcResult<PcmSample> {
+    let Some(pcm) = slice.sps.pcm.as_ref() else {
+      return Err(HevcError::Invalid("pcm_flag"));
+    };
+    if !slice.stream.is_byte_aligned(0) {
+      return Err(HevcError::Invalid("pcm_alignment_zero_bit"));
+    }
+    let samples = 1 << (log2_cb_size * 2);
+    let luma_bits = pcm.sample_bit_depth_luma_minus1 as usize + 1;
+    let luma = (0..samples)
+      .map(|_| slice.stream.bits_into(luma_bits))
+      .collect();
+    let chroma = if slice.chroma_array_type != 0 {
+      let (sub_width, sub_height) = slice.sps.chroma_subsampling();
+      let chroma_bits = pcm.sample_bit_depth_chroma_minus1 as usize + 1;
+      (0..2 * samples / (sub_width * sub_height))
+        .map(|_| slice.stream.bits_into(chroma_bits))
+        .collect()
+    } else {
+      Box::default()
+    };
+    self.init_decoding_engine(slice)?;
+    Ok(PcmSample { luma, chroma })
+  }
+
+  /// prev_intra_luma_pred_flag, mpm_idx, rem_intra_luma_pred_mode and intra_chroma_pred_mode of
+  /// every partition with the modes derived in 8.4.2 and 8.4.3
+  fn intra_pred_modes(&mut self, slice: &mut Slice, cu: &mut CodingUnit) {
+    let partitions = if cu.part_mode == PartMode::PartNxN {
+      4
+    } else {
+      1
+    };
+    let prev_intra_luma_pred_flags = (0..partitions)
+      .map(|_| self.decision(slice, CTXIDX_PREV_INTRA_LUMA_PRED_FLAG))
+      .collect::<Vec<_>>();
+    let pb_size = (1 << cu.log2_cb_size) / if partitions == 4 { 2 } else { 1 };
+    for (i, prev_intra_luma_pred_flag) in prev_intra_luma_pred_flags.into_iter().enumerate() {
+      let x_pb = cu.x0 + (i as u32 & 1) * pb_size;
+      let y_pb = cu.y0 + (i as u32 >> 1) * pb_size;
+      let candidates = Self::candidate_mode_list(slice, x_pb, y_pb);
+      cu.intra_pred_mode_y[i] = if prev_intra_luma_pred_flag {
+        candidates[self.truncated_unary_bypass(slice, 2) as usize]
+      } else {
+        let mut sorted = candidates;
+        sorted.sort_unstable();
+        let mut mode = self.bypass_bits(slice, 5) as u8;
+        for candidate in sorted {
+          if mode >= candidate {
+            mode += 1;
+          }
+        }
+        mode
+      };
+      let mut block = *slice.picture.block(x_pb, y_pb);
+      block.intra_pred_mode_y = cu.intra_pred_mode_y[i];
+      slice
+        .picture
+        .set_blocks(x_pb, y_pb, pb_size, pb_size, block);
+    }
+    if partitions == 1 {
+      cu.intra_pred_mode_y = [cu.intra_pred_mode_y[0]; 4];
+    }
+
+    let chroma_partitions = match slice.chroma_array_type {
+      0 => 0,
+      3 => partitions,
+      _ => 1,
+    };
+    for i in 0..chroma_partitions {
+      cu.intra_chroma_pred_mode[i] = if !self.decision(slice, CTXIDX_INTRA_CHROMA_PRED_MODE) {
+        4
+      } else {
+        self.bypass_bits(slice, 2) as u8
+      };
+      // 8.4.3 Table 8-2
+      let luma_mode = cu.intra_pred_mode_y[i];
+      let mode = match cu.intra_chroma_pred_mode[i] {
+        4 => luma_mode,
+        mode => {
+          let mode = [0, 26, 10, 1][mode as usize];
+          if mode == luma_mode {
+            34
+          } else {
+            mode
+          }
+        }
+      };
+      cu.intra_pred_mode_c[i] = if slice.chroma_array_type == 2 {
+        INTRA_PRED_MODE_C_422[mode as usize]
+      } else {
+        mode
+      };
+    }
+    if chroma_partitions == 1 {
+      cu.intra_chroma_pred_mode = [cu.intra_chroma_pred_mode[0]; 4];
+      cu.intra_pred_mode_c = [cu.intra_pred_mode_c[0]; 4];
+    }
+  }
+
+  /// 8.4.2 candModeList of the luma prediction block at (`x_pb`, `y_pb`)
+  fn candidate_mode_list(slice: &Slice, x_pb: u32, y_pb: u32) -> [u8; 3] {
+    let picture = &*slice.picture;
+    let candidate = |x_nb: i32, y_nb: i32| {
+      if !picture.available(x_pb, y_pb, x_nb, y_nb) {
+        return 1;
+      }
+      let block = picture.block(x_nb as u32, y_nb as u32);
+      // The above neighbour is not used outside of the current CTB
+      let outside_ctb = y_nb < ((y_pb >> picture.log2_ctb_size) << picture.log2_ctb_size) as i32;
+      if !block.pred_mode.is_intra() || block.pcm_flag || outside_ctb {
+        1
+      } else {
+        block.intra_pred_mode_y
+      }
+    };
+    let a = candidate(x_pb as i32 - 1, y_pb as i32);
+    let b = candidate(x_pb as i32, y_pb as i32 - 1);
+    if a == b {
+      if a < 2 {
+        [0, 1, 26]
+      } else {
+        [a, 2 + ((a + 29) % 32), 2 + ((a - 2 + 1) % 32)]
+      }
+    } else {
+      let c = if a != 0 && b != 0 {
+        0
+      } else if a != 1 && b != 1 {
+        1
+      } else {
+        26
+      };
+      [a, b, c]
+    }
+  }
+
+  /// 7.3.8.6 Prediction unit syntax of the `[x0, y0, width, height]` prediction block
+  fn prediction_unit(
+    &mut self,
+    slice: &mut Slice,
+    cu: &CodingUnit,
+    [x0, y0, width, height]: [u32; 4],
+  ) -> HevcResult<PredictionUnit> {
+    let mut pu = PredictionUnit {
+      x0,
+      y0,
+      width,
+      height,
+      ..Default::default()
+    };
+    pu.merge_flag = cu.pred_mode == PredMode::Skip || self.decision(slice, CTXIDX_MERGE_FLAG);
+    if pu.merge_flag {
+      if slice.max_num_merge_cand > 1 {
+        let c_max = slice.max_num_merge_cand as u32 - 1;
+        pu.merge_idx = if self.decision(slice, CTXIDX_MERGE_IDX) {
+          1 + self.truncated_unary_bypass(slice, c_max - 1) as u8
+        } else {
+          0
+        };
+      }
+      return Ok(pu);
+    }
+    if slice.slice_type.is_bidirectional() {
+      let ct_depth = slice.picture.log2_ctb_size - cu.log2_cb_size;
+      pu.inter_pred_idc = if width + height != 12
+        && self.decision(slice, CTXIDX_INTER_PRED_IDC + ct_depth as usize)
+      {
+        InterPredIdc::PredBi
+      } else if self.decision(slice, CTXIDX_INTER_PRED_IDC + 4) {
+        InterPredIdc::PredL1
+      } else {
+        InterPredIdc::PredL0
+      };
+    }
+    let num_ref_idx_active_minus1 = [
+      slice.num_ref_idx_l0_active_minus1,
+      slice.num_ref_idx_l1_active_minus1,
+    ];
+    for x in 0..2 {
+      let used = match pu.inter_pred_idc {
+        InterPredIdc::PredL0 => x == 0,
+        InterPredIdc::PredL1 => x == 1,
+        InterPredIdc::PredBi => true,
+      };
+      if !used {
+        continue;
+      }
+      if num_ref_idx_active_minus1[x] > 0 {
+        pu.ref_idx[x] = self.ref_idx(slice, num_ref_idx_active_minus1[x]);
+      }
+      if !(x == 1 && slice.mvd_l1_zero_flag && pu.inter_pred_idc == InterPredIdc::PredBi) {
+        pu.mvd[x] = self.mvd_coding(slice)?;
+      }
+      pu.mvp_flag[x] = self.decision(slice, CTXIDX_MVP_FLAG);
+    }
+    Ok(pu)
+  }
+
+  /// ref_idx_lX, truncated Rice with the first two bins context coded
+  fn ref_idx(&mut self, slice: &mut Slice, c_max: u8) -> u8 {
+    let mut value = 0;
+    while value < c_max {
+      let bin = if value < 2 {
+        self.decision(slice, CTXIDX_REF_IDX + value as usize)
+      } else {
+        self.bypass(slice)
+      };
+      if !bin {
+        break;
+      }
+      value += 1;
+    }
+    value
+  }
+
+  /// 7.3.8.9 Motion vector difference syntax
+  fn mvd_coding(&mut self, slice: &mut Slice) -> HevcResult<[i32; 2]> {
+    let greater0 = [(); 2].map(|_| self.decision(slice, CTXIDX_ABS_MVD_GREATER0_FLAG));
+    let greater1 =
+      greater0.map(|greater0| greater0 && self.decision(slice, CTXIDX_ABS_MVD_GREATER1_FLAG));
+    let mut mvd = [0; 2];
+    for i in 0..2 {
+      if !greater0[i] {
+        continue;
+      }
+      let abs = if greater1[i] {
+        self.exp_golomb_bypass(slice, 1)? as i32 + 2
+      } else {
+        1
+      };
+      mvd[i] = if self.bypass(slice) { -abs } else { abs };
+    }
+    Ok(mvd)
+  }
+
+  /// 7.3.8.8 Transform tree syntax, `parent_cbf` holds cbf_cb and cbf_cr of the parent block
+  #[allow(clippy::too_many_arguments)]
+  fn transform_tree(
+    &mut self,
+    slice: &mut Slice,
+    cu: &mut CodingUnit,
+    [x0, y0]: [u32; 2],
+    base: [u32; 2],
+    log2_trafo_size: u8,
+    trafo_depth: u8,
+    blk_idx: u8,
+    parent_cbf: [[bool; 2]; 2],
+  ) -> HevcResult {
+    let sps = slice.sps;
+    let log2_min_tb_size = sps.log2_min_luma_transform_block_size_minus2 + 2;
+    let log2_max_tb_size = log2_min_tb_size + sps.log2_diff_max_min_luma_transform_block_size;
+    let intra_split_flag = cu.pred_mode.is_intra() && cu.part_mode == PartMode::PartNxN;
+    let max_trafo_depth = if cu.pred_mode.is_intra() {
+      sps.max_transform_hierarchy_depth_intra + intra_split_flag as u8
+    } else {
+      sps.max_transform_hierarchy_depth_inter
+    };
+    let inter_split_flag = sps.max_transform_hierarchy_depth_inter == 0
+      && cu.pred_mode == PredMode::Inter
+      && cu.part_mode != PartMode::Part2Nx2N
+      && trafo_depth == 0;
+    let split_transform_flag = if log2_trafo_size <= log2_max_tb_size
+      && log2_trafo_size > log2_min_tb_size
+      && trafo_depth < max_trafo_depth
+      && !(intra_split_flag && trafo_depth == 0)
+    {
+      self.decision(
+        slice,
+        CTXIDX_SPLIT_TRANSFORM_FLAG + 5 - log2_trafo_size as usize,
+      )
+    } else {
+      log2_trafo_size > log2_max_tb_size
+        || (intra_split_flag && trafo_depth == 0)
+        || inter_split_flag
+    };
+
+    // cbf_cb and cbf_cr of the current depth, the 4x4 luma blocks of 4:2:0 and 4:2:2 keep the
+    // flags of their parent for the chroma blocks coded with the last one
+    let chroma_array_type = slice.chroma_array_type;
+    let mut cbf = [[false; 2]; 2];
+    if (log2_trafo_size > 2 && chroma_array_type != 0) || chroma_array_type == 3 {
+      for (c, parent_cbf) in parent_cbf.iter().enumerate() {
+        if trafo_depth == 0 || parent_cbf[0] {
+          cbf[c][0] = self.decision(slice, CTXIDX_CBF_CHROMA + trafo_depth as usize);
+          if chroma_array_type == 2 && (!split_transform_flag || log2_trafo_size == 3) {
+            cbf[c][1] = self.decision(slice, CTXIDX_CBF_CHROMA + trafo_depth as usize);
+          }
+        }
+      }
+    } else if log2_trafo_size == 2 && chroma_array_type != 0 {
+      cbf = parent_cbf;
+    }
+
+    if split_transform_flag {
+      let half = 1 << (log2_trafo_size - 1);
+      for (i, (x, y)) in [
+        (x0, y0),
+        (x0 + half, y0),
+        (x0, y0 + half),
+        (x0 + half, y0 + half),
+      ]
+      .into_iter()
+      .enumerate()
+      {
+        self.transform_tree(
+          slice,
+          cu,
+          [x, y],
+          [x0, y0],
+          log2_trafo_size - 1,
+          trafo_depth + 1,
+          i as u8,
+          cbf,
+        )?;
+      }
+      return Ok(());
+    }
+    let chroma_cbf = log2_trafo_size > 2 || chroma_array_type == 3;
+    let cbf_luma = if cu.pred_mode.is_intra()
+      || trafo_depth != 0
+      || (chroma_cbf && cbf.iter().flatten().any(|cbf| *cbf))
+    {
+      self.decision(slice, CTXIDX_CBF_LUMA + (trafo_depth == 0) as usize)
+    } else {
+      true
+    };
+    let mut tu = TransformUnit {
+      x0,
+      y0,
+      x_base: base[0],
+      y_base: base[1],
+      log2_trafo_size,
+      blk_idx,
+      cbf_luma,
+      cbf_cb: cbf[0],
+      cbf_cr: cbf[1],
+      ..Default::default()
+    };
+    self.transform_unit(slice, cu, &mut tu)?;
+    cu.transform_units.push(tu);
+    Ok(())
+  }
+
+  /// 7.3.8.10 Transform unit syntax
+  fn transform_unit(
+    &mut self,
+    slice: &mut Slice,
+    cu: &CodingUnit,
+    tu: &mut TransformUnit,
+  ) -> HevcResult {
+    let chroma_array_type = slice.chroma_array_type;
+    let log2_trafo_size = tu.log2_trafo_size;
+    let log2_trafo_size_c = if chroma_array_type == 3 {
+      log2_trafo_size
+    } else {
+      (log2_trafo_size - 1).max(2)
+    };
+    // For 4x4 luma blocks the chroma flags are those of the parent, only coded with blkIdx 3
+    let chroma_blocks =
+      chroma_array_type != 0 && (log2_trafo_size > 2 || chroma_array_type == 3 || tu.blk_idx == 3);
+    let cbf_chroma = chroma_array_type != 0 && tu.cbf_cb.iter().chain(&tu.cbf_cr).any(|cbf| *cbf);
+    if !tu.cbf_luma && !cbf_chroma {
+      return Ok(());
+    }
+
+    if slice.pps.diff_cu_qp_delta_depth.is_some() && !slice.is_cu_qp_delta_coded {
+      let mut cu_qp_delta_abs = 0;
+      while cu_qp_delta_abs < 5
+        && self.decision(
+          slice,
+          CTXIDX_CU_QP_DELTA_ABS + (cu_qp_delta_abs > 0) as usize,
+        )
+      {
+        cu_qp_delta_abs += 1;
+      }
+      if cu_qp_delta_abs == 5 {
+        cu_qp_delta_abs += self.exp_golomb_bypass(slice, 0)? as i32;
+      }
+      let cu_qp_delta_sign_flag = cu_qp_delta_abs > 0 && self.bypass(slice);
+      slice.is_cu_qp_delta_coded = true;
+      slice.cu_qp_delta_val = if cu_qp_delta_sign_flag {
+        -cu_qp_delta_abs
+      } else {
+        cu_qp_delta_abs
+      };
+      let half_qp_bd_offset_y = slice.qp_bd_offset_y as i32 / 2;
+      if !(-(26 + half_qp_bd_offset_y)..=25 + half_qp_bd_offset_y).contains(&slice.cu_qp_delta_val)
+      {
+        return Err(HevcError::SliceHeader(
+          "CuQpDeltaVal",
+          slice.cu_qp_delta_val as u32,
+        ));
+      }
+    }
+    if slice.cu_chroma_qp_offset_enabled_flag
+      && cbf_chroma
+      && !cu.cu_transquant_bypass_flag
+      && !slice.is_cu_chroma_qp_offset_coded
+    {
+      let list = &slice.pps.range_extension.chroma_qp_offset_list;
+      let cu_chroma_qp_offset_flag = self.decision(slice, CTXIDX_CU_CHROMA_QP_OFFSET_FLAG);
+      let mut cu_chroma_qp_offset_idx = 0;
+      if cu_chroma_qp_offset_flag {
+        while cu_chroma_qp_offset_idx + 1 < list.len()
+          && self.decision(slice, CTXIDX_CU_CHROMA_QP_OFFSET_IDX)
+        {
+          cu_chroma_qp_offset_idx += 1;
+        }
+      }
+      slice.is_cu_chroma_qp_offset_coded = true;
+      (slice.cu_qp_offset_cb, slice.cu_qp_offset_cr) = match list.get(cu_chroma_qp_offset_idx) {
+        Some(&offsets) if cu_chroma_qp_offset_flag => offsets,
+        _ => (0, 0),
+      };
+    }
+
+    if tu.cbf_luma {
+      let residual = self.residual_coding(slice, cu, tu.x0, tu.y0, log2_trafo_size, 0)?;
+      tu.residuals.push(residual);
+    }
+    if !chroma_blocks {
+      return Ok(());
+    }
+    let (x_c, y_c, log2_size_c) = if log2_trafo_size > 2 || chroma_array_type == 3 {
+      (tu.x0, tu.y0, log2_trafo_size_c)
+    } else {
+      (tu.x_base, tu.y_base, 2)
+    };
+    let partition = cu.partition_index(tu.x0, tu.y0);
+    let cross_component_prediction = slice
+      .pps
+      .range_extension
+      .cross_component_prediction_enabled_flag
+      && tu.cbf_luma
+      && (!cu.pred_mode.is_intra() || cu.intra_chroma_pred_mode[partition] == 4);
+    let sub_blocks = if chroma_array_type == 2 { 2 } else { 1 };
+    for c in 0..2 {
+      if cross_component_prediction && log2_trafo_size_c == log2_trafo_size {
+        tu.res_scale_val[c] = self.cross_comp_pred(slice, c);
+      }
+      let cbf = if c == 0 { tu.cbf_cb } else { tu.cbf_cr };
+      for t_idx in 0..sub_blocks {
+        if cbf[t_idx] {
+          let residual = self.residual_coding(
+            slice,
+            cu,
+            x_c,
+            y_c + ((t_idx as u32) << log2_size_c),
+            log2_size_c,
+            c as u8 + 1,
+          )?;
+          tu.residuals.push(residual);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// 7.3.8.12 Cross-component prediction syntax, returns ResScaleVal
+  fn cross_comp_pred(&mut self, slice: &mut Slice, c: usize) -> i8 {
+    let mut log2_res_scale_abs_plus1 = 0;
+    while log2_res_scale_abs_plus1 < 4
+      && self.decision(
+        slice,
+        CTXIDX_LOG2_RES_SCALE_ABS_PLUS1 + 4 * c + log2_res_scale_abs_plus1,
+      )
+    {
+      log2_res_scale_abs_plus1 += 1;
+    }
+    if log2_res_scale_abs_plus1 == 0 {
+      return 0;
+    }
+    let res_scale_sign_flag = self.decision(slice, CTXIDX_RES_SCALE_SIGN_FLAG + c);
+    let res_scale_val = 1 << (log2_res_scale_abs_plus1 - 1);
+    if res_scale_sign_flag {
+      -res_scale_val
+    } else {
+      res_scale_val
+    }
+  }
+}
@@ -0,0 +1,347 @@
+use super::{table::*, CabacContext};
+use crate::video::hevc::{
+  slice::{coding_unit::*, Slice},
+  HevcError, HevcResult,
+};
+
+impl CabacContext {
+  /// 7.3.8.11 Residual coding syntax of the `1 << log2_trafo_size` transform block of `c_idx`
+  pub fn residual_coding(
+    &mut self,
+    slice: &mut Slice,
+    cu: &CodingUnit,
+    x0: u32,
+    y0: u32,
+    log2_trafo_size: u8,
+    c_idx: u8,
+  ) -> HevcResult<ResidualBlock> {
+    let sps = slice.sps;
+    let pps = slice.pps;
+    let luma = c_idx == 0;
+    let chroma_inc = if luma { 0 } else { 1 };
+    let bypass = cu.cu_transquant_bypass_flag;
+
+    let transform_skip_flag = pps.transform_skip_enabled_flag
+      && !bypass
+      && log2_trafo_size
+        <= pps
+          .range_extension
+          .log2_max_transform_skip_block_size_minus2
+          + 2
+      && self.decision(slice, CTXIDX_TRANSFORM_SKIP_FLAG + chroma_inc);
+    let mut explicit_rdpcm_dir_flag = None;
+    if !cu.pred_mode.is_intra()
+      && sps.range_extension.explicit_rdpcm_enabled_flag
+      && (transform_skip_flag || bypass)
+      && self.decision(slice, CTXIDX_EXPLICIT_RDPCM_FLAG + chroma_inc)
+    {
+      explicit_rdpcm_dir_flag =
+        Some(self.decision(slice, CTXIDX_EXPLICIT_RDPCM_DIR_FLAG + chroma_inc));
+    }
+
+    // 7.4.9.11 scanIdx from the intra prediction mode of the partition
+    let partition = cu.partition_index(x0, y0);
+    let pred_mode_intra = if luma {
+      cu.intra_pred_mode_y[partition]
+    } else {
+      cu.intra_pred_mode_c[partition]
+    };
+    let scan_idx = if cu.pred_mode.is_intra()
+      && (log2_trafo_size == 2 || (log2_trafo_size == 3 && (luma || slice.chroma_array_type == 3)))
+    {
+      match pred_mode_intra {
+        6..=14 => 2,
+        22..=30 => 1,
+        _ => 0,
+      }
+    } else {
+      0
+    };
+
+    // 9.3.4.2.3 last_sig_coeff_x_prefix and last_sig_coeff_y_prefix
+    let (ctx_offset, ctx_shift) = if luma {
+      (
+        3 * (log2_trafo_size - 2) + ((log2_trafo_size - 1) >> 2),
+        (log2_trafo_size + 1) >> 2,
+      )
+    } else {
+      (15, log2_trafo_size - 2)
+    };
+    let c_max = (log2_trafo_size << 1) - 1;
+    let mut last_prefix = [0u8; 2];
+    for (prefix, ctx_base) in last_prefix.iter_mut().zip([
+      CTXIDX_LAST_SIG_COEFF_X_PREFIX,
+      CTXIDX_LAST_SIG_COEFF_Y_PREFIX,
+    ]) {
+      while *prefix < c_max
+        && self.decision(
+          slice,
+          ctx_base + (ctx_offset + (*prefix >> ctx_shift)) as usize,
+        )
+      {
+        *prefix += 1;
+      }
+    }
+    let [mut last_x, mut last_y] = last_prefix.map(|prefix| {
+      if prefix > 3 {
+        let suffix = self.bypass_bits(slice, (prefix >> 1) - 1);
+        (1 << ((prefix >> 1) - 1)) * (2 + (prefix as u32 & 1)) + suffix
+      } else {
+        prefix as u32
+      }
+    });
+    if scan_idx == 2 {
+      (last_x, last_y) = (last_y, last_x);
+    }
+
+    let size = 1usize << log2_trafo_size;
+    let log2_sub_blocks = log2_trafo_size - 2;
+    let sub_blocks = sub_block_scan(log2_sub_blocks, scan_idx);
+    let scan = &SCAN_4X4[scan_idx];
+    let position = |i: usize, n: usize| {
+      let [x_s, y_s] = sub_blocks[i];
+      let [x_p, y_p] = scan[n];
+      (((x_s << 2) + x_p) as u32, ((y_s << 2) + y_p) as u32)
+    };
+    let (last_sub_block, last_scan_pos) = (0..sub_blocks.len())
+      .flat_map(|i| (0..16).map(move |n| (i, n)))
+      .find(|&(i, n)| position(i, n) == (last_x, last_y))
+      .ok_or(HevcError::Invalid("last significant coefficient"))?;
+
+    let transform_skip_context =
+      sps.range_extension.transform_skip_context_enabled_flag && (transform_skip_flag || bypass);
+    let sign_hiding_allowed = pps.sign_data_hiding_enabled_flag
+      && !bypass
+      && !(cu.pred_mode.is_intra()
+        && sps.range_extension.implicit_rdpcm_enabled_flag
+        && transform_skip_flag
+        && (pred_mode_intra == 10 || pred_mode_intra == 26))
+      && explicit_rdpcm_dir_flag.is_none();
+    let persistent_rice_adaptation = sps.range_extension.persistent_rice_adaptation_enabled_flag;
+    let sb_type = 2 * luma as usize + (transform_skip_flag || bypass) as usize;
+
+    let sub_blocks_per_row = 1 << log2_sub_blocks;
+    let mut coded_sub_block_flags = vec![false; sub_blocks_per_row * sub_blocks_per_row];
+    let mut coefficients = vec![0; size * size].into_boxed_slice();
+    let mut greater1_ctx = 1;
+    for i in (0..=last_sub_block).rev() {
+      let [x_s, y_s] = sub_blocks[i].map(|v| v as usize);
+      let mut infer_sb_dc_sig_coeff = false;
+      let coded_sub_block_flag = if i < last_sub_block && i > 0 {
+        let right =
+          x_s + 1 < sub_blocks_per_row && coded_sub_block_flags[y_s * sub_blocks_per_row + x_s + 1];
+        let below = y_s + 1 < sub_blocks_per_row
+          && coded_sub_block_flags[(y_s + 1) * sub_blocks_per_row + x_s];
+        let ctx_inc = (right || below) as usize + 2 * chroma_inc;
+        infer_sb_dc_sig_coeff = true;
+        self.decision(slice, CTXIDX_CODED_SUB_BLOCK_FLAG + ctx_inc)
+      } else {
+        true
+      };
+      coded_sub_block_flags[y_s * sub_blocks_per_row + x_s] = coded_sub_block_flag;
+      if !coded_sub_block_flag {
+        continue;
+      }
+      let prev_csbf = if x_s + 1 < sub_blocks_per_row {
+        coded_sub_block_flags[y_s * sub_blocks_per_row + x_s + 1] as u8
+      } else {
+        0
+      } + if y_s + 1 < sub_blocks_per_row {
+        2 * coded_sub_block_flags[(y_s + 1) * sub_blocks_per_row + x_s] as u8
+      } else {
+        0
+      };
+
+      // sig_coeff_flag in reverse scan order
+      let mut sig_coeff_flags = [false; 16];
+      let first_n = if i == last_sub_block {
+        sig_coeff_flags[last_scan_pos] = true;
+        last_scan_pos as isize - 1
+      } else {
+        15
+      };
+      for n in (0..=first_n).rev() {
+        let n = n as usize;
+        if n == 0 && infer_sb_dc_sig_coeff {
+          sig_coeff_flags[0] = true;
+          break;
+        }
+        let (x_c, y_c) = position(i, n);
+        let sig_ctx = if transform_skip_context {
+          if luma {
+            42
+          } else {
+            16
+          }
+        } else if log2_trafo_size == 2 {
+          CTX_IDX_MAP[((y_c << 2) + x_c) as usize]
+        } else if x_c + y_c == 0 {
+          0
+        } else {
+          let (x_p, y_p) = (x_c & 3, y_c & 3);
+          let sig_ctx = match prev_csbf {
+            0 => {
+              if x_p + y_p == 0 {
+                2
+              } else if x_p + y_p < 3 {
+                1
+              } else {
+                0
+              }
+            }
+            1 => 2u8.saturating_sub(y_p as u8),
+            2 => 2u8.saturating_sub(x_p as u8),
+            _ => 2,
+          };
+          if luma {
+            let sub_block_inc = if i > 0 { 3 } else { 0 };
+            let size_inc = if log2_trafo_size == 3 {
+              if scan_idx == 0 {
+                9
+              } else {
+                15
+              }
+            } else {
+              21
+            };
+            sig_ctx + sub_block_inc + size_inc
+          } else if log2_trafo_size == 3 {
+            sig_ctx + 9
+          } else {
+            sig_ctx + 12
+          }
+        };
+        let ctx_inc = if luma {
+          sig_ctx as usize
+        } else {
+          27 + sig_ctx as usize
+        };
+        sig_coeff_flags[n] = self.decision(slice, CTXIDX_SIG_COEFF_FLAG + ctx_inc);
+        if sig_coeff_flags[n] {
+          infer_sb_dc_sig_coeff = false;
+        }
+      }
+
+      let significant = (0..16).rev().filter(|&n| sig_coeff_flags[n]).collect::<Vec<_>>();
+      let (Some(&last_sig_scan_pos), Some(&first_sig_scan_pos)) = (significant.first(), significant.last()) else {
+        continue;
+      };
+
+      // 9.3.4.2.6 ctxSet of coeff_abs_level_greater1_flag and coeff_abs_level_greater2_flag,
+      // greater1Ctx carries over from the previous sub-block with significant coefficients
+      let mut ctx_set = if i == 0 || !luma { 0 } else { 2 };
+      if greater1_ctx == 0 {
+        ctx_set += 1;
+      }
+      greater1_ctx = 1;
+      let mut greater1_flags = [false; 16];
+      let mut last_greater1_scan_pos = None;
+      for &n in significant.iter().take(8) {
+        let ctx_inc = ctx_set * 4 + greater1_ctx.min(3) + 16 * chroma_inc;
+        greater1_flags[n] = self.decision(slice, CTXIDX_COEFF_ABS_LEVEL_GREATER1_FLAG + ctx_inc);
+        if greater1_flags[n] {
+          greater1_ctx = 0;
+          if last_greater1_scan_pos.is_none() {
+            last_greater1_scan_pos = Some(n);
+          }
+        } else if greater1_ctx > 0 {
+          greater1_ctx += 1;
+        }
+      }
+      let sign_hidden = sign_hiding_allowed && last_sig_scan_pos - first_sig_scan_pos > 3;
+      let mut greater2_flags = [false; 16];
+      if let Some(n) = last_greater1_scan_pos {
+        greater2_flags[n] = self.decision(
+          slice,
+          CTXIDX_COEFF_ABS_LEVEL_GREATER2_FLAG + ctx_set + 4 * chroma_inc,
+        );
+      }
+      let sign_flags = significant
+        .iter()
+        .map(|&n| !(sign_hidden && n == first_sig_scan_pos) && self.bypass(slice))
+        .collect::<Vec<_>>();
+
+      // 9.3.3.11 cRiceParam of coeff_abs_level_remaining
+      let mut rice_param = if persistent_rice_adaptation {
+        self.stat_coeff[sb_type] / 4
+      } else {
+        0
+      };
+      let mut first_remaining = true;
+      let mut sum_abs_level = 0;
+      for (num_sig_coeff, (&n, sign_flag)) in significant.iter().zip(sign_flags).enumerate() {
+        let base_level = 1 + greater1_flags[n] as u32 + greater2_flags[n] as u32;
+        let remaining_threshold = if num_sig_coeff < 8 {
+          if Some(n) == last_greater1_scan_pos {
+            3
+          } else {
+            2
+          }
+        } else {
+          1
+        };
+        let mut abs_level = base_level;
+        if base_level == remaining_threshold {
+          let coeff_abs_level_remaining = self.coeff_abs_level_remaining(slice, rice_param)?;
+          if persistent_rice_adaptation && first_remaining {
+            let stat_coeff = &mut self.stat_coeff[sb_type];
+            if coeff_abs_level_remaining >= 3 << (*stat_coeff / 4) {
+              *stat_coeff += 1;
+            } else if 2 * coeff_abs_level_remaining < 1 << (*stat_coeff / 4) && *stat_coeff > 0 {
+              *stat_coeff -= 1;
+            }
+          }
+          first_remaining = false;
+          abs_level += coeff_abs_level_remaining;
+          if abs_level > 3 << rice_param {
+            rice_param = if persistent_rice_adaptation {
+              rice_param + 1
+            } else {
+              (rice_param + 1).min(4)
+            };
+          }
+        }
+        let mut level = if sign_flag {
+          -(abs_level as i32)
+        } else {
+          abs_level as i32
+        };
+        if sign_hidden {
+          sum_abs_level += abs_level;
+          if n == first_sig_scan_pos && sum_abs_level % 2 == 1 {
+            level = -level;
+          }
+        }
+        let (x_c, y_c) = position(i, n);
+        coefficients[y_c as usize * size + x_c as usize] = level;
+      }
+    }
+
+    Ok(ResidualBlock {
+      c_idx,
+      x0,
+      y0,
+      log2_trafo_size,
+      transform_skip_flag,
+      explicit_rdpcm_dir_flag,
+      coefficients,
+    })
+  }
+
+  /// 9.3.3.11 coeff_abs_level_remaining, a Rice prefix with an exponential Golomb escape
+  fn coeff_abs_level_remaining(&mut self, slice: &mut Slice, rice_param: u8) -> HevcResult<u32> {
+    let mut prefix = 0u8;
+    while self.bypass(slice) {
+      prefix += 1;
+      if prefix > 32 {
+        return Err(HevcError::Invalid("coeff_abs_level_remaining prefix"));
+      }
+    }
+    if prefix <= 3 {
+      Ok(((prefix as u32) << rice_param) + self.bypass_bits(slice, rice_param))
+    } else {
+      let suffix = self.bypass_bits(slice, prefix - 3 + rice_param);
+      Ok((((1 << (prefix - 3)) + 2) << rice_param) + suffix)
+    }
+  }
+}
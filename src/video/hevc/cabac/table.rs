@@ -0,0 +1,313 @@
+// 9.3.2.2 Context index offsets of every syntax element
+pub const CTXIDX_SAO_MERGE_FLAG: usize = 0;
+pub const CTXIDX_SAO_TYPE_IDX: usize = 1;
+pub const CTXIDX_SPLIT_CU_FLAG: usize = 2;
+pub const CTXIDX_CU_TRANSQUANT_BYPASS_FLAG: usize = 5;
+pub const CTXIDX_CU_SKIP_FLAG: usize = 6;
+pub const CTXIDX_PRED_MODE_FLAG: usize = 9;
+pub const CTXIDX_PART_MODE: usize = 10;
+pub const CTXIDX_PREV_INTRA_LUMA_PRED_FLAG: usize = 14;
+pub const CTXIDX_INTRA_CHROMA_PRED_MODE: usize = 15;
+pub const CTXIDX_RQT_ROOT_CBF: usize = 16;
+pub const CTXIDX_MERGE_FLAG: usize = 17;
+pub const CTXIDX_MERGE_IDX: usize = 18;
+pub const CTXIDX_INTER_PRED_IDC: usize = 19;
+pub const CTXIDX_REF_IDX: usize = 24;
+pub const CTXIDX_MVP_FLAG: usize = 26;
+pub const CTXIDX_SPLIT_TRANSFORM_FLAG: usize = 27;
+pub const CTXIDX_CBF_LUMA: usize = 30;
+pub const CTXIDX_CBF_CHROMA: usize = 32;
+pub const CTXIDX_ABS_MVD_GREATER0_FLAG: usize = 37;
+pub const CTXIDX_ABS_MVD_GREATER1_FLAG: usize = 38;
+pub const CTXIDX_CU_QP_DELTA_ABS: usize = 39;
+pub const CTXIDX_TRANSFORM_SKIP_FLAG: usize = 41;
+pub const CTXIDX_LAST_SIG_COEFF_X_PREFIX: usize = 43;
+pub const CTXIDX_LAST_SIG_COEFF_Y_PREFIX: usize = 61;
+pub const CTXIDX_CODED_SUB_BLOCK_FLAG: usize = 79;
+pub const CTXIDX_SIG_COEFF_FLAG: usize = 83;
+pub const CTXIDX_COEFF_ABS_LEVEL_GREATER1_FLAG: usize = 127;
+pub const CTXIDX_COEFF_ABS_LEVEL_GREATER2_FLAG: usize = 151;
+pub const CTXIDX_EXPLICIT_RDPCM_FLAG: usize = 157;
+pub const CTXIDX_EXPLICIT_RDPCM_DIR_FLAG: usize = 159;
+pub const CTXIDX_LOG2_RES_SCALE_ABS_PLUS1: usize = 161;
+pub const CTXIDX_RES_SCALE_SIGN_FLAG: usize = 169;
+pub const CTXIDX_CU_CHROMA_QP_OFFSET_FLAG: usize = 171;
+pub const CTXIDX_CU_CHROMA_QP_OFFSET_IDX: usize = 172;
+
+pub const CTX_IDX_COUNT: usize = 173;
+
+/// 9.3.2.2 initValue of every context for initType 0, 1 and 2, syntax elements absent from I
+/// slices use 154 for initType 0
+#[rustfmt::skip]
+pub const CTX_INIT_TABLE: [[u8; 3]; CTX_IDX_COUNT] = [
+  /* 0 sao_merge_left_flag, sao_merge_up_flag */
+  [153, 153, 153], /*    0 */
+  /* 1 sao_type_idx_luma, sao_type_idx_chroma */
+  [200, 185, 160], /*    1 */
+  /* 2-4 split_cu_flag */
+  [139, 107, 107], /*    2 */
+  [141, 139, 139], /*    3 */
+  [157, 126, 126], /*    4 */
+  /* 5 cu_transquant_bypass_flag */
+  [154, 154, 154], /*    5 */
+  /* 6-8 cu_skip_flag */
+  [154, 197, 197], /*    6 */
+  [154, 185, 185], /*    7 */
+  [154, 201, 201], /*    8 */
+  /* 9 pred_mode_flag */
+  [154, 149, 134], /*    9 */
+  /* 10-13 part_mode */
+  [184, 154, 154], /*   10 */
+  [154, 139, 139], /*   11 */
+  [154, 154, 154], /*   12 */
+  [154, 154, 154], /*   13 */
+  /* 14 prev_intra_luma_pred_flag */
+  [184, 154, 183], /*   14 */
+  /* 15 intra_chroma_pred_mode */
+  [ 63, 152, 152], /*   15 */
+  /* 16 rqt_root_cbf */
+  [154,  79,  79], /*   16 */
+  /* 17 merge_flag */
+  [154, 110, 154], /*   17 */
+  /* 18 merge_idx */
+  [154, 122, 137], /*   18 */
+  /* 19-23 inter_pred_idc */
+  [154,  95,  95], /*   19 */
+  [154,  79,  79], /*   20 */
+  [154,  63,  63], /*   21 */
+  [154,  31,  31], /*   22 */
+  [154,  31,  31], /*   23 */
+  /* 24-25 ref_idx_l0, ref_idx_l1 */
+  [154, 153, 153], /*   24 */
+  [154, 153, 153], /*   25 */
+  /* 26 mvp_l0_flag, mvp_l1_flag */
+  [154, 168, 168], /*   26 */
+  /* 27-29 split_transform_flag */
+  [153, 124, 224], /*   27 */
+  [138, 138, 167], /*   28 */
+  [138,  94, 122], /*   29 */
+  /* 30-31 cbf_luma */
+  [111, 153, 153], /*   30 */
+  [141, 111, 111], /*   31 */
+  /* 32-36 cbf_cb, cbf_cr */
+  [ 94, 149, 149], /*   32 */
+  [138, 107,  92], /*   33 */
+  [182, 167, 167], /*   34 */
+  [154, 154, 154], /*   35 */
+  [154, 154, 154], /*   36 */
+  /* 37 abs_mvd_greater0_flag */
+  [154, 140, 169], /*   37 */
+  /* 38 abs_mvd_greater1_flag */
+  [154, 198, 198], /*   38 */
+  /* 39-40 cu_qp_delta_abs */
+  [154, 154, 154], /*   39 */
+  [154, 154, 154], /*   40 */
+  /* 41-42 transform_skip_flag, luma then chroma */
+  [139, 139, 139], /*   41 */
+  [139, 139, 139], /*   42 */
+  /* 43-60 last_sig_coeff_x_prefix */
+  [110, 125, 125], /*   43 */
+  [110, 110, 110], /*   44 */
+  [124,  94, 124], /*   45 */
+  [125, 110, 110], /*   46 */
+  [140,  95,  95], /*   47 */
+  [153,  79,  94], /*   48 */
+  [125, 125, 125], /*   49 */
+  [127, 111, 111], /*   50 */
+  [140, 110, 111], /*   51 */
+  [109,  78,  79], /*   52 */
+  [111, 110, 125], /*   53 */
+  [143, 111, 126], /*   54 */
+  [127, 111, 111], /*   55 */
+  [111,  95, 111], /*   56 */
+  [ 79,  94,  79], /*   57 */
+  [108, 108, 108], /*   58 */
+  [123, 123, 123], /*   59 */
+  [ 63, 108,  93], /*   60 */
+  /* 61-78 last_sig_coeff_y_prefix */
+  [110, 125, 125], /*   61 */
+  [110, 110, 110], /*   62 */
+  [124,  94, 124], /*   63 */
+  [125, 110, 110], /*   64 */
+  [140,  95,  95], /*   65 */
+  [153,  79,  94], /*   66 */
+  [125, 125, 125], /*   67 */
+  [127, 111, 111], /*   68 */
+  [140, 110, 111], /*   69 */
+  [109,  78,  79], /*   70 */
+  [111, 110, 125], /*   71 */
+  [143, 111, 126], /*   72 */
+  [127, 111, 111], /*   73 */
+  [111,  95, 111], /*   74 */
+  [ 79,  94,  79], /*   75 */
+  [108, 108, 108], /*   76 */
+  [123, 123, 123], /*   77 */
+  [ 63, 108,  93], /*   78 */
+  /* 79-82 coded_sub_block_flag */
+  [ 91, 121, 121], /*   79 */
+  [171, 140, 140], /*   80 */
+  [134,  61,  61], /*   81 */
+  [141, 154, 154], /*   82 */
+  /* 83-126 sig_coeff_flag, the last two with transform_skip_context_enabled_flag */
+  [111, 155, 170], /*   83 */
+  [111, 154, 154], /*   84 */
+  [125, 139, 139], /*   85 */
+  [110, 153, 153], /*   86 */
+  [110, 139, 139], /*   87 */
+  [ 94, 123, 123], /*   88 */
+  [124, 123, 123], /*   89 */
+  [108,  63,  63], /*   90 */
+  [124, 153, 124], /*   91 */
+  [107, 166, 166], /*   92 */
+  [125, 183, 183], /*   93 */
+  [141, 140, 140], /*   94 */
+  [179, 136, 136], /*   95 */
+  [153, 153, 153], /*   96 */
+  [125, 154, 154], /*   97 */
+  [107, 166, 166], /*   98 */
+  [125, 183, 183], /*   99 */
+  [141, 140, 140], /*  100 */
+  [179, 136, 136], /*  101 */
+  [153, 153, 153], /*  102 */
+  [125, 154, 154], /*  103 */
+  [107, 166, 166], /*  104 */
+  [125, 183, 183], /*  105 */
+  [141, 140, 140], /*  106 */
+  [179, 136, 136], /*  107 */
+  [153, 153, 153], /*  108 */
+  [125, 154, 154], /*  109 */
+  [140, 170, 170], /*  110 */
+  [139, 153, 153], /*  111 */
+  [182, 123, 138], /*  112 */
+  [182, 123, 138], /*  113 */
+  [152, 107, 122], /*  114 */
+  [136, 121, 121], /*  115 */
+  [152, 107, 122], /*  116 */
+  [136, 121, 121], /*  117 */
+  [153, 167, 167], /*  118 */
+  [136, 151, 151], /*  119 */
+  [139, 183, 183], /*  120 */
+  [111, 140, 140], /*  121 */
+  [136, 151, 151], /*  122 */
+  [139, 183, 183], /*  123 */
+  [111, 140, 140], /*  124 */
+  [141, 140, 140], /*  125 */
+  [111, 140, 140], /*  126 */
+  /* 127-150 coeff_abs_level_greater1_flag */
+  [140, 154, 154], /*  127 */
+  [ 92, 196, 196], /*  128 */
+  [137, 196, 167], /*  129 */
+  [138, 167, 167], /*  130 */
+  [140, 154, 154], /*  131 */
+  [152, 152, 152], /*  132 */
+  [138, 167, 167], /*  133 */
+  [139, 182, 182], /*  134 */
+  [153, 182, 182], /*  135 */
+  [ 74, 134, 134], /*  136 */
+  [149, 149, 149], /*  137 */
+  [ 92, 136, 136], /*  138 */
+  [139, 153, 153], /*  139 */
+  [107, 121, 121], /*  140 */
+  [122, 136, 136], /*  141 */
+  [152, 137, 122], /*  142 */
+  [140, 169, 169], /*  143 */
+  [179, 194, 208], /*  144 */
+  [166, 166, 166], /*  145 */
+  [182, 167, 167], /*  146 */
+  [140, 154, 154], /*  147 */
+  [227, 167, 152], /*  148 */
+  [122, 137, 167], /*  149 */
+  [197, 182, 182], /*  150 */
+  /* 151-156 coeff_abs_level_greater2_flag */
+  [138, 107, 107], /*  151 */
+  [153, 167, 167], /*  152 */
+  [136,  91,  91], /*  153 */
+  [167, 122, 107], /*  154 */
+  [152, 107, 107], /*  155 */
+  [152, 167, 167], /*  156 */
+  /* 157-158 explicit_rdpcm_flag */
+  [139, 139, 139], /*  157 */
+  [139, 139, 139], /*  158 */
+  /* 159-160 explicit_rdpcm_dir_flag */
+  [139, 139, 139], /*  159 */
+  [139, 139, 139], /*  160 */
+  /* 161-168 log2_res_scale_abs_plus1 */
+  [154, 154, 154], /*  161 */
+  [154, 154, 154], /*  162 */
+  [154, 154, 154], /*  163 */
+  [154, 154, 154], /*  164 */
+  [154, 154, 154], /*  165 */
+  [154, 154, 154], /*  166 */
+  [154, 154, 154], /*  167 */
+  [154, 154, 154], /*  168 */
+  /* 169-170 res_scale_sign_flag */
+  [154, 154, 154], /*  169 */
+  [154, 154, 154], /*  170 */
+  /* 171 cu_chroma_qp_offset_flag */
+  [154, 154, 154], /*  171 */
+  /* 172 cu_chroma_qp_offset_idx */
+  [154, 154, 154], /*  172 */
+];
+
+/// 9.3.4.2.5 ctxIdxMap of sig_coeff_flag in 4x4 transform blocks
+#[rustfmt::skip]
+pub const CTX_IDX_MAP: [u8; 15] = [0, 1, 4, 5, 2, 3, 4, 5, 6, 6, 8, 8, 7, 7, 8];
+
+/// 8.4.3 Table 8-3 IntraPredModeC of 4:2:2 streams from the 4:2:0 mode
+#[rustfmt::skip]
+pub const INTRA_PRED_MODE_C_422: [u8; 35] = [
+  0, 1, 2, 2, 2, 2, 3, 5, 7, 8, 10, 11, 13, 15, 16, 18, 19, 20,
+  21, 22, 23, 23, 24, 24, 25, 25, 26, 27, 27, 28, 28, 29, 29, 30, 31,
+];
+
+// 6.5.3, 6.5.4 and 6.5.5 ScanOrder of up-right diagonal, horizontal and vertical scans as
+// (x, y) positions, indexed by scanIdx
+#[rustfmt::skip]
+pub const SCAN_2X2: [[[u8; 2]; 4]; 3] = [
+  [
+    [0, 0], [0, 1], [1, 0], [1, 1],
+  ],
+  [
+    [0, 0], [1, 0], [0, 1], [1, 1],
+  ],
+  [
+    [0, 0], [0, 1], [1, 0], [1, 1],
+  ],
+];
+
+#[rustfmt::skip]
+pub const SCAN_4X4: [[[u8; 2]; 16]; 3] = [
+  [
+    [0, 0], [0, 1], [1, 0], [0, 2], [1, 1], [2, 0], [0, 3], [1, 2], [2, 1], [3, 0], [1, 3], [2, 2],
+    [3, 1], [2, 3], [3, 2], [3, 3],
+  ],
+  [
+    [0, 0], [1, 0], [2, 0], [3, 0], [0, 1], [1, 1], [2, 1], [3, 1], [0, 2], [1, 2], [2, 2], [3, 2],
+    [0, 3], [1, 3], [2, 3], [3, 3],
+  ],
+  [
+    [0, 0], [0, 1], [0, 2], [0, 3], [1, 0], [1, 1], [1, 2], [1, 3], [2, 0], [2, 1], [2, 2], [2, 3],
+    [3, 0], [3, 1], [3, 2], [3, 3],
+  ],
+];
+
+#[rustfmt::skip]
+pub const SCAN_8X8_DIAGONAL: [[u8; 2]; 64] = [
+  [0, 0], [0, 1], [1, 0], [0, 2], [1, 1], [2, 0], [0, 3], [1, 2], [2, 1], [3, 0], [0, 4], [1, 3],
+  [2, 2], [3, 1], [4, 0], [0, 5], [1, 4], [2, 3], [3, 2], [4, 1], [5, 0], [0, 6], [1, 5], [2, 4],
+  [3, 3], [4, 2], [5, 1], [6, 0], [0, 7], [1, 6], [2, 5], [3, 4], [4, 3], [5, 2], [6, 1], [7, 0],
+  [1, 7], [2, 6], [3, 5], [4, 4], [5, 3], [6, 2], [7, 1], [2, 7], [3, 6], [4, 5], [5, 4], [6, 3],
+  [7, 2], [3, 7], [4, 6], [5, 5], [6, 4], [7, 3], [4, 7], [5, 6], [6, 5], [7, 4], [5, 7], [6, 6],
+  [7, 5], [6, 7], [7, 6], [7, 7],
+];
+
+/// ScanOrder[log2BlockSize][scanIdx] of the sub-blocks of a transform block, horizontal and
+/// vertical scans are only used by 4x4 and 8x8 transform blocks
+pub fn sub_block_scan(log2_block_size: u8, scan_idx: usize) -> &'static [[u8; 2]] {
+  match log2_block_size {
+    0 => &[[0, 0]],
+    1 => &SCAN_2X2[scan_idx],
+    2 => &SCAN_4X4[scan_idx],
+    _ => &SCAN_8X8_DIAGONAL,
+  }
+}
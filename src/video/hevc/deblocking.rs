@@ -0,0 +1,346 @@
+use super::{
+  picture::{BlockInfo, MvField, Picture, Plane},
+  slice::{coding_unit::CodingUnit, Slice},
+  transform::qp_c,
+};
+
+/// 8.7.2.5.3 β′ of Q from 0 to 51
+#[rustfmt::skip]
+const BETA_TABLE: [i32; 52] = [
+  0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+  6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 20, 22, 24,
+  26, 28, 30, 32, 34, 36, 38, 40, 42, 44, 46, 48, 50, 52, 54, 56,
+  58, 60, 62, 64,
+];
+
+/// 8.7.2.5.3 tC′ of Q from 0 to 53
+#[rustfmt::skip]
+const TC_TABLE: [i32; 54] = [
+  0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+  0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 3,
+  3, 3, 3, 4, 4, 4, 5, 5, 6, 6, 7, 8, 9, 10, 11, 13,
+  14, 16, 18, 20, 22, 24,
+];
+
+/// edgeFlags of a transform block edge, prediction block edges are 1
+const TRANSFORM_EDGE: u8 = 2;
+
+/// 8.7.2.4 The prediction blocks use different reference pictures or numbers of motion vectors,
+/// or motion vectors of the same reference picture 4 quarter luma samples apart or more
+fn motion_discontinuity(p: &MvField, q: &MvField) -> bool {
+  let motion = |mv_field: &MvField| {
+    (0..2)
+      .filter(|&x| mv_field.pred_flag[x])
+      .map(|x| (mv_field.ref_poc[x], mv_field.mv[x]))
+      .collect::<Vec<_>>()
+  };
+  let close = |(ref_a, mv_a): (i32, [i32; 2]), (ref_b, mv_b): (i32, [i32; 2])| {
+    ref_a == ref_b && (mv_a[0] - mv_b[0]).abs() < 4 && (mv_a[1] - mv_b[1]).abs() < 4
+  };
+  match (motion(p).as_slice(), motion(q).as_slice()) {
+    (&[p0], &[q0]) => !close(p0, q0),
+    // The motion vectors are compared in both pairings when they refer to the same picture
+    (&[p0, p1], &[q0, q1]) => {
+      !((close(p0, q0) && close(p1, q1)) || (close(p0, q1) && close(p1, q0)))
+    }
+    _ => true,
+  }
+}
+
+/// 8.7.2.4 bS of the edge between the blocks containing the samples p0 and q0
+fn boundary_strength(p: &BlockInfo, q: &BlockInfo, transform_edge: bool) -> u8 {
+  if p.pred_mode.is_intra() || q.pred_mode.is_intra() {
+    2
+  } else if (transform_edge && (p.cbf_luma || q.cbf_luma))
+    || motion_discontinuity(&p.mv_field, &q.mv_field)
+  {
+    1
+  } else {
+    0
+  }
+}
+
+impl Slice<'_> {
+  /// 8.7.2.2 to 8.7.2.4 Marks the luma transform blocks of a decoded coding unit with non-zero
+  /// coefficients and derives the bS of its transform and prediction block edges on the 8x8 luma
+  /// grid. The blocks on the left and on top are decoded first
+  pub fn boundary_strengths(&mut self, cu: &CodingUnit) {
+    for tu in cu.transform_units.iter().filter(|tu| tu.cbf_luma) {
+      let size = 1 << tu.log2_trafo_size;
+      for y in (tu.y0..tu.y0 + size).step_by(4) {
+        for x in (tu.x0..tu.x0 + size).step_by(4) {
+          self.picture.block_mut(x, y).cbf_luma = true;
+        }
+      }
+    }
+    if self.slice_deblocking_filter_disabled_flag {
+      return;
+    }
+
+    // edgeFlags of the vertical and horizontal edges of every 4x4 block of the coding block
+    let blocks = 1 << (cu.log2_cb_size - 2);
+    let mut edge_flags = [vec![0; blocks * blocks], vec![0; blocks * blocks]];
+    let mut mark = |[x, y, width, height]: [u32; 4], flag: u8| {
+      let (x, y) = (((x - cu.x0) >> 2) as usize, ((y - cu.y0) >> 2) as usize);
+      for j in y..y + (height >> 2) as usize {
+        let edge = &mut edge_flags[0][j * blocks + x];
+        *edge = flag.max(*edge);
+      }
+      for i in x..x + (width >> 2) as usize {
+        let edge = &mut edge_flags[1][y * blocks + i];
+        *edge = flag.max(*edge);
+      }
+    };
+    let size = 1 << cu.log2_cb_size;
+    mark([cu.x0, cu.y0, size, size], TRANSFORM_EDGE);
+    for tu in &cu.transform_units {
+      let size = 1 << tu.log2_trafo_size;
+      mark([tu.x0, tu.y0, size, size], TRANSFORM_EDGE);
+    }
+    for pu in &cu.prediction_units {
+      mark([pu.x0, pu.y0, pu.width, pu.height], 1);
+    }
+    // 8.7.2 filterEdgeFlag of the left and top edges of the coding block
+    if cu.x0 == 0 || !self.filter_edge(cu, cu.x0 - 1, cu.y0) {
+      (0..blocks).for_each(|j| edge_flags[0][j * blocks] = 0);
+    }
+    if cu.y0 == 0 || !self.filter_edge(cu, cu.x0, cu.y0 - 1) {
+      edge_flags[1][..blocks].fill(0);
+    }
+
+    for (i, flags) in edge_flags.iter().enumerate() {
+      for (index, &flag) in flags.iter().enumerate() {
+        let x = cu.x0 + (index % blocks) as u32 * 4;
+        let y = cu.y0 + (index / blocks) as u32 * 4;
+        let on_grid = if i == 0 {
+          x.is_multiple_of(8)
+        } else {
+          y.is_multiple_of(8)
+        };
+        if flag == 0 || !on_grid {
+          continue;
+        }
+        let p = self.picture.p_block([x, y], i);
+        let bs = boundary_strength(p, self.picture.block(x, y), flag == TRANSFORM_EDGE);
+        self.picture.block_mut(x, y).bs[i] = bs;
+      }
+    }
+  }
+
+  /// 8.7.2 filterEdgeFlag of the left or top edge of the coding block against the neighbouring
+  /// luma sample at (`x`, `y`) across a slice or a tile boundary
+  fn filter_edge(&self, cu: &CodingUnit, x: u32, y: u32) -> bool {
+    let picture = &*self.picture;
+    let ctb_curr = picture.ctb_addr_rs(cu.x0, cu.y0) as usize;
+    let ctb_nb = picture.ctb_addr_rs(x, y) as usize;
+    let tile_id =
+      |ctb_addr_rs: usize| picture.tile_id[picture.ctb_addr_rs_to_ts[ctb_addr_rs] as usize];
+    (self.slice_loop_filter_across_slices_enabled_flag
+      || picture.ctb_slice_addr[ctb_nb] == picture.ctb_slice_addr[ctb_curr])
+      && (picture.loop_filter_across_tiles_enabled_flag || tile_id(ctb_nb) == tile_id(ctb_curr))
+  }
+}
+
+impl Picture {
+  /// 8.7.2 Deblocking filter process of the decoded picture, the vertical edges of the whole
+  /// picture are filtered first and the horizontal ones use their output
+  pub fn deblocking_filter(&mut self) {
+    // Separately coded colour planes are filtered like luma
+    let luma_planes = if self.chroma_array_type == 0 {
+      self.planes.len()
+    } else {
+      1
+    };
+    for dir in 0..2 {
+      for y in (0..self.height).step_by(4) {
+        for x in (0..self.width).step_by(4) {
+          let bs = self.block(x, y).bs[dir];
+          if bs == 0 {
+            continue;
+          }
+          for plane_index in 0..luma_planes {
+            self.luma_edge_filter(plane_index, [x, y], dir, bs);
+          }
+        }
+      }
+      if self.chroma_array_type != 0 {
+        for c_idx in 1..3 {
+          self.chroma_edge_filter(c_idx, dir);
+        }
+      }
+    }
+  }
+
+  /// Block containing the sample p0 of the vertical (`dir` 0) or horizontal edge at (`x`, `y`)
+  fn p_block(&self, [x, y]: [u32; 2], dir: usize) -> &BlockInfo {
+    if dir == 0 {
+      self.block(x - 1, y)
+    } else {
+      self.block(x, y - 1)
+    }
+  }
+
+  /// 8.7.2.5.7 nDp and nDq are 0 for the samples of PCM coding units without loop filter and of
+  /// coding units with transquant bypass
+  fn filtered(&self, block: &BlockInfo) -> bool {
+    !((block.pcm_flag && self.pcm_loop_filter_disabled_flag) || block.cu_transquant_bypass_flag)
+  }
+
+  /// 8.7.2.5.3 and 8.7.2.5.6 to 8.7.2.5.7 Decisions and filtering of the 4 lines of a luma edge
+  /// segment with the boundary filtering strength `bs` at (`x`, `y`)
+  fn luma_edge_filter(&mut self, plane_index: usize, [x, y]: [u32; 2], dir: usize, bs: u8) {
+    let p_block = *self.p_block([x, y], dir);
+    let q_block = *self.block(x, y);
+    let filter_p = self.filtered(&p_block);
+    let filter_q = self.filtered(&q_block);
+    let parameters = self.loop_filter[self.ctb_addr_rs(x, y) as usize];
+    let plane = &mut self.planes[plane_index];
+    let bit_depth = plane.bit_depth as i32;
+    let max = (1 << bit_depth) - 1;
+
+    let qp_l = (q_block.qp_y as i32 + p_block.qp_y as i32 + 1) >> 1;
+    let q = (qp_l + ((parameters.slice_beta_offset_div2 as i32) << 1)).clamp(0, 51);
+    let beta = BETA_TABLE[q as usize] * (1 << (bit_depth - 8));
+    let q =
+      (qp_l + 2 * (bs as i32 - 1) + ((parameters.slice_tc_offset_div2 as i32) << 1)).clamp(0, 53);
+    let tc = TC_TABLE[q as usize] * (1 << (bit_depth - 8));
+
+    // Location of the sample pi (`i` < 0 as -i - 1) or qi of the line `k`
+    let location = |k: u32, i: i32| {
+      if dir == 0 {
+        ((x as i32 + i) as u32, y + k)
+      } else {
+        (x + k, (y as i32 + i) as u32)
+      }
+    };
+    let line = |plane: &Plane, k: u32| {
+      let p = [0, 1, 2, 3].map(|i| {
+        let (x, y) = location(k, -i - 1);
+        plane.get(x, y) as i32
+      });
+      let q = [0, 1, 2, 3].map(|i| {
+        let (x, y) = location(k, i);
+        plane.get(x, y) as i32
+      });
+      (p, q)
+    };
+
+    // 8.7.2.5.3 Decision process for luma block edges
+    let (p_0, q_0) = line(plane, 0);
+    let (p_3, q_3) = line(plane, 3);
+    let second_derivative = |s: [i32; 4]| (s[2] - 2 * s[1] + s[0]).abs();
+    let dp = second_derivative(p_0) + second_derivative(p_3);
+    let dq = second_derivative(q_0) + second_derivative(q_3);
+    let dpq_0 = second_derivative(p_0) + second_derivative(q_0);
+    let dpq_3 = second_derivative(p_3) + second_derivative(q_3);
+    if dpq_0 + dpq_3 >= beta {
+      return;
+    }
+    // 8.7.2.5.6 Decision process for a luma sample
+    let strong = |p: [i32; 4], q: [i32; 4], dpq: i32| {
+      2 * dpq < (beta >> 2)
+        && (p[3] - p[0]).abs() + (q[0] - q[3]).abs() < (beta >> 3)
+        && (p[0] - q[0]).abs() < ((5 * tc + 1) >> 1)
+    };
+    let strong_filter = strong(p_0, q_0, dpq_0) && strong(p_3, q_3, dpq_3);
+    let side_threshold = (beta + (beta >> 1)) >> 3;
+    let (de_p, de_q) = (dp < side_threshold, dq < side_threshold);
+
+    // 8.7.2.5.7 Filtering process for a luma sample
+    for k in 0..4 {
+      let (p, q) = line(plane, k);
+      let mut p_f = p;
+      let mut q_f = q;
+      if strong_filter {
+        let clip = |s: i32, f: i32| f.clamp(s - 2 * tc, s + 2 * tc);
+        p_f[0] = clip(
+          p[0],
+          (p[2] + 2 * p[1] + 2 * p[0] + 2 * q[0] + q[1] + 4) >> 3,
+        );
+        p_f[1] = clip(p[1], (p[2] + p[1] + p[0] + q[0] + 2) >> 2);
+        p_f[2] = clip(p[2], (2 * p[3] + 3 * p[2] + p[1] + p[0] + q[0] + 4) >> 3);
+        q_f[0] = clip(
+          q[0],
+          (p[1] + 2 * p[0] + 2 * q[0] + 2 * q[1] + q[2] + 4) >> 3,
+        );
+        q_f[1] = clip(q[1], (p[0] + q[0] + q[1] + q[2] + 2) >> 2);
+        q_f[2] = clip(q[2], (p[0] + q[0] + q[1] + 3 * q[2] + 2 * q[3] + 4) >> 3);
+      } else {
+        let delta = (9 * (q[0] - p[0]) - 3 * (q[1] - p[1]) + 8) >> 4;
+        if delta.abs() >= tc * 10 {
+          continue;
+        }
+        let delta = delta.clamp(-tc, tc);
+        p_f[0] = (p[0] + delta).clamp(0, max);
+        q_f[0] = (q[0] - delta).clamp(0, max);
+        if de_p {
+          let delta_p = ((((p[2] + p[0] + 1) >> 1) - p[1] + delta) >> 1).clamp(-(tc >> 1), tc >> 1);
+          p_f[1] = (p[1] + delta_p).clamp(0, max);
+        }
+        if de_q {
+          let delta_q = ((((q[2] + q[0] + 1) >> 1) - q[1] - delta) >> 1).clamp(-(tc >> 1), tc >> 1);
+          q_f[1] = (q[1] + delta_q).clamp(0, max);
+        }
+      }
+      for i in 0..3 {
+        if filter_p {
+          let (x, y) = location(k, -(i as i32) - 1);
+          plane.set(x, y, p_f[i] as u16);
+        }
+        if filter_q {
+          let (x, y) = location(k, i as i32);
+          plane.set(x, y, q_f[i] as u16);
+        }
+      }
+    }
+  }
+
+  /// 8.7.2.5.5 Edge filtering of the chroma component `c_idx` for the edges with bS 2 on its 8x8
+  /// sample grid
+  fn chroma_edge_filter(&mut self, c_idx: usize, dir: usize) {
+    let (sub_width, sub_height) = self.chroma_subsampling();
+    let (width, height) = (self.planes[c_idx].width, self.planes[c_idx].height);
+    let bit_depth = self.planes[c_idx].bit_depth as i32;
+    let max = (1 << bit_depth) - 1;
+    let (step_x, step_y) = if dir == 0 { (8, 1) } else { (1, 8) };
+    for y in (0..height).step_by(step_y) {
+      for x in (0..width).step_by(step_x) {
+        let luma = [x * sub_width, y * sub_height];
+        let q_block = *self.block(luma[0], luma[1]);
+        if q_block.bs[dir] != 2 {
+          continue;
+        }
+        let p_block = *self.p_block(luma, dir);
+        let parameters = self.loop_filter[self.ctb_addr_rs(luma[0], luma[1]) as usize];
+        let qp_i =
+          ((q_block.qp_y as i32 + p_block.qp_y as i32 + 1) >> 1) + self.c_qp_pic_offset[c_idx - 1];
+        let qp_c = qp_c(qp_i, self.chroma_array_type);
+        let q = (qp_c + 2 + ((parameters.slice_tc_offset_div2 as i32) << 1)).clamp(0, 53);
+        let tc = TC_TABLE[q as usize] * (1 << (bit_depth - 8));
+
+        let (p_0, p_1) = if dir == 0 {
+          ((x - 1, y), (x - 2, y))
+        } else {
+          ((x, y - 1), (x, y - 2))
+        };
+        let (q_0, q_1) = if dir == 0 {
+          ((x, y), (x + 1, y))
+        } else {
+          ((x, y), (x, y + 1))
+        };
+        let filter_p = self.filtered(&p_block);
+        let filter_q = self.filtered(&q_block);
+        let plane = &mut self.planes[c_idx];
+        let sample = |(x, y): (u32, u32)| plane.get(x, y) as i32;
+        let (p0, p1, q0, q1) = (sample(p_0), sample(p_1), sample(q_0), sample(q_1));
+        let delta = ((((q0 - p0) << 2) + p1 - q1 + 4) >> 3).clamp(-tc, tc);
+        if filter_p {
+          plane.set(p_0.0, p_0.1, (p0 + delta).clamp(0, max) as u16);
+        }
+        if filter_q {
+          plane.set(q_0.0, q_0.1, (q0 - delta).clamp(0, max) as u16);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,382 @@
+use super::{picture::Picture, slice::header::SliceSegmentHeader, HevcError, HevcResult};
+use crate::{
+  ascii::LogDisplay,
+  log,
+  video::atom::{
+    HevcNalUnitHeader, HevcPictureParameterSet, HevcSequenceParameterSet, SubLayerOrdering,
+  },
+};
+
+/// 8.3.2 Marking of a picture in the decoded picture buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReferenceMarking {
+  #[default]
+  Unused,
+  ShortTerm,
+  LongTerm,
+}
+
+/// Decoded picture kept for reference or output
+#[derive(Debug)]
+pub struct DecodedPicture {
+  pub picture: Picture,
+  pub marking: ReferenceMarking,
+  /// Marked as "needed for output"
+  pub needed_for_output: bool,
+  /// PicLatencyCount
+  pub pic_latency_count: u32,
+}
+
+impl DecodedPicture {
+  /// PicOrderCntVal
+  pub fn poc(&self) -> i32 {
+    self.picture.pic_order_cnt_val
+  }
+
+  pub fn is_long_term(&self) -> bool {
+    self.marking == ReferenceMarking::LongTerm
+  }
+}
+
+/// C.5.2 Decoded picture buffer of the output order decoder, the pictures are marked by the
+/// reference picture sets of 8.3.2 and output by the bumping process
+#[derive(Debug, Default)]
+pub struct DecodedPictureBuffer {
+  pub pictures: Vec<DecodedPicture>,
+  /// PicOrderCntVal of prevTid0Pic
+  pub prev_tid0_pic_order_cnt: i32,
+  /// NoRaslOutputFlag of the last IRAP picture
+  pub no_rasl_output_flag: bool,
+  /// Cleared at the start of the bitstream and after an end of sequence NAL unit
+  pub sequence_started: bool,
+  /// PicOrderCntVal of RefPicSetStCurrBefore, RefPicSetStCurrAfter and RefPicSetLtCurr of the
+  /// current picture
+  pub ref_pic_set_curr: [Vec<i32>; 3],
+  /// DPB size and reordering of the highest sub-layer of the active SPS
+  pub sub_layer_ordering: SubLayerOrdering,
+}
+
+impl DecodedPictureBuffer {
+  /// RASL pictures associated with an IRAP picture that starts decoding reference pictures
+  /// before it and are skipped
+  pub fn skips(&self, nal: &HevcNalUnitHeader) -> bool {
+    nal.is_rasl() && self.no_rasl_output_flag
+  }
+
+  /// Starts the decoding of a picture from the header of its first slice segment: derives its
+  /// PicOrderCntVal, marks the reference pictures and removes or outputs pictures before the
+  /// current one is decoded
+  pub fn start_picture(
+    &mut self,
+    nal: &HevcNalUnitHeader,
+    header: &SliceSegmentHeader,
+    sps: &HevcSequenceParameterSet,
+    pps: &HevcPictureParameterSet,
+  ) -> HevcResult<i32> {
+    let first_picture = !self.sequence_started;
+    self.sequence_started = true;
+    if let Some(ordering) = sps.sub_layer_ordering.last() {
+      self.sub_layer_ordering = *ordering;
+    }
+    let irap = nal.is_irap();
+    if irap {
+      // HandleCraAsBlaFlag is 0, CRA pictures only start a coded video sequence first
+      self.no_rasl_output_flag = nal.is_idr() || nal.is_bla() || first_picture;
+    }
+
+    // 8.3.1 Decoding process for picture order count
+    let max_pic_order_cnt_lsb = 1 << (sps.log2_max_pic_order_cnt_lsb_minus4 + 4);
+    let pic_order_cnt_lsb = header.slice_pic_order_cnt_lsb as i32;
+    let pic_order_cnt_msb = if irap && self.no_rasl_output_flag {
+      0
+    } else {
+      let prev_pic_order_cnt_lsb = self.prev_tid0_pic_order_cnt & (max_pic_order_cnt_lsb - 1);
+      let prev_pic_order_cnt_msb = self.prev_tid0_pic_order_cnt - prev_pic_order_cnt_lsb;
+      if pic_order_cnt_lsb < prev_pic_order_cnt_lsb
+        && prev_pic_order_cnt_lsb - pic_order_cnt_lsb >= max_pic_order_cnt_lsb / 2
+      {
+        prev_pic_order_cnt_msb + max_pic_order_cnt_lsb
+      } else if pic_order_cnt_lsb > prev_pic_order_cnt_lsb
+        && pic_order_cnt_lsb - prev_pic_order_cnt_lsb > max_pic_order_cnt_lsb / 2
+      {
+        prev_pic_order_cnt_msb - max_pic_order_cnt_lsb
+      } else {
+        prev_pic_order_cnt_msb
+      }
+    };
+    let pic_order_cnt_val = pic_order_cnt_msb + pic_order_cnt_lsb;
+    if nal.temporal_id() == 0
+      && !nal.is_rasl()
+      && !nal.is_radl()
+      && !nal.is_sub_layer_non_reference()
+    {
+      self.prev_tid0_pic_order_cnt = pic_order_cnt_val;
+    }
+
+    if irap && self.no_rasl_output_flag {
+      for picture in &mut self.pictures {
+        picture.marking = ReferenceMarking::Unused;
+      }
+    }
+    let missing = self.reference_picture_set(header, pic_order_cnt_val, max_pic_order_cnt_lsb);
+
+    // C.5.2.2 Output and removal of pictures from the DPB
+    if irap && self.no_rasl_output_flag && !first_picture {
+      let no_output_of_prior_pics_flag = nal.is_cra() || header.no_output_of_prior_pics_flag;
+      if !no_output_of_prior_pics_flag {
+        while self.bump() {}
+      }
+      self.pictures.clear();
+    } else {
+      self
+        .pictures
+        .retain(|picture| picture.needed_for_output || picture.marking != ReferenceMarking::Unused);
+      while self.output_needed()
+        || self.pictures.len() > self.sub_layer_ordering.max_dec_pic_buffering_minus1 as usize
+      {
+        if !self.bump() {
+          break;
+        }
+      }
+    }
+
+    // 8.3.3 Generation of unavailable reference pictures
+    for (poc, marking) in missing {
+      log!(warn@"#[hevc] Missing reference picture {poc} generated");
+      let mut picture = Picture::new(sps, pps)?;
+      picture.pic_order_cnt_val = poc;
+      picture.pic_output_flag = false;
+      for plane in picture.planes.iter_mut() {
+        plane.samples.fill(1 << (plane.bit_depth - 1));
+      }
+      self.pictures.push(DecodedPicture {
+        picture,
+        marking,
+        needed_for_output: false,
+        pic_latency_count: 0,
+      });
+    }
+    Ok(pic_order_cnt_val)
+  }
+
+  /// 8.3.2 Decoding process for reference picture set. Marks the pictures of the RPS of the
+  /// current picture and returns the missing pictures it uses, the pictures outside of it are
+  /// no longer used for reference
+  fn reference_picture_set(
+    &mut self,
+    header: &SliceSegmentHeader,
+    pic_order_cnt_val: i32,
+    max_pic_order_cnt_lsb: i32,
+  ) -> Vec<(i32, ReferenceMarking)> {
+    let set = &header.short_term_ref_pic_set;
+    let mut poc_st = [Vec::new(), Vec::new(), Vec::new()];
+    for (deltas, used, curr) in [
+      (&set.delta_poc_s0, &set.used_by_curr_pic_s0, 0),
+      (&set.delta_poc_s1, &set.used_by_curr_pic_s1, 1),
+    ] {
+      for (delta, used) in deltas.iter().zip(used.iter()) {
+        poc_st[if *used { curr } else { 2 }].push(pic_order_cnt_val + delta);
+      }
+    }
+
+    // PocLtCurr and PocLtFoll matched on their least significant bits when the MSB is not
+    // signalled
+    let (mut lt_curr, mut lt_foll) = (Vec::new(), Vec::new());
+    for lt in header.long_term_ref_pics.iter() {
+      let mut poc_lt = lt.poc_lsb_lt as i32;
+      if let Some(delta_poc_msb_cycle_lt) = lt.delta_poc_msb_cycle_lt {
+        poc_lt += pic_order_cnt_val
+          - delta_poc_msb_cycle_lt as i32 * max_pic_order_cnt_lsb
+          - (pic_order_cnt_val & (max_pic_order_cnt_lsb - 1));
+      }
+      let poc = self
+        .pictures
+        .iter()
+        .filter(|picture| picture.marking != ReferenceMarking::Unused)
+        .map(DecodedPicture::poc)
+        .find(|poc| {
+          if lt.delta_poc_msb_cycle_lt.is_some() {
+            *poc == poc_lt
+          } else {
+            poc & (max_pic_order_cnt_lsb - 1) == poc_lt
+          }
+        })
+        .ok_or(poc_lt);
+      if lt.used_by_curr_pic_lt_flag {
+        lt_curr.push(poc);
+      } else {
+        lt_foll.push(poc);
+      }
+    }
+
+    let [st_curr_before, st_curr_after, st_foll] = poc_st;
+    let mut missing = st_curr_before
+      .iter()
+      .chain(&st_curr_after)
+      .filter(|&&poc| {
+        !self
+          .pictures
+          .iter()
+          .any(|picture| picture.poc() == poc && picture.marking == ReferenceMarking::ShortTerm)
+      })
+      .map(|&poc| (poc, ReferenceMarking::ShortTerm))
+      .collect::<Vec<_>>();
+    let lt_curr = lt_curr
+      .into_iter()
+      .map(|poc| {
+        poc.unwrap_or_else(|poc| {
+          missing.push((poc, ReferenceMarking::LongTerm));
+          poc
+        })
+      })
+      .collect::<Vec<_>>();
+    let lt_foll = lt_foll.into_iter().flatten().collect::<Vec<_>>();
+
+    for picture in &mut self.pictures {
+      let poc = picture.poc();
+      picture.marking = if picture.marking == ReferenceMarking::Unused {
+        ReferenceMarking::Unused
+      } else if lt_curr.contains(&poc) || lt_foll.contains(&poc) {
+        ReferenceMarking::LongTerm
+      } else if picture.marking == ReferenceMarking::ShortTerm
+        && (st_curr_before.contains(&poc) || st_curr_after.contains(&poc) || st_foll.contains(&poc))
+      {
+        ReferenceMarking::ShortTerm
+      } else {
+        ReferenceMarking::Unused
+      };
+    }
+    self.ref_pic_set_curr = [st_curr_before, st_curr_after, lt_curr];
+    missing
+  }
+
+  /// 8.3.4 Decoding process for reference picture lists construction, RefPicList0 and
+  /// RefPicList1 of a P or B slice
+  pub fn ref_pic_lists<'a>(
+    &'a self,
+    header: &SliceSegmentHeader,
+  ) -> HevcResult<[Vec<&'a DecodedPicture>; 2]> {
+    if header.slice_type.is_intra() {
+      return Ok(Default::default());
+    }
+    let find = |poc: &i32, marking: ReferenceMarking| {
+      self
+        .pictures
+        .iter()
+        .find(|picture| picture.poc() == *poc && picture.marking == marking)
+        .ok_or(HevcError::Invalid("reference picture set"))
+    };
+    let [st_curr_before, st_curr_after, lt_curr] = &self.ref_pic_set_curr;
+    let before = st_curr_before
+      .iter()
+      .map(|poc| find(poc, ReferenceMarking::ShortTerm))
+      .collect::<HevcResult<Vec<_>>>()?;
+    let after = st_curr_after
+      .iter()
+      .map(|poc| find(poc, ReferenceMarking::ShortTerm))
+      .collect::<HevcResult<Vec<_>>>()?;
+    let long_term = lt_curr
+      .iter()
+      .map(|poc| find(poc, ReferenceMarking::LongTerm))
+      .collect::<HevcResult<Vec<_>>>()?;
+    let num_pic_total_curr = before.len() + after.len() + long_term.len();
+    if num_pic_total_curr == 0 {
+      return Err(HevcError::Invalid("empty reference picture set"));
+    }
+
+    let modification = header.ref_pic_lists_modification.as_ref();
+    let list = |first: &[&'a DecodedPicture],
+                second: &[&'a DecodedPicture],
+                num_ref_idx_active_minus1: u8,
+                list_entry: Option<&[u8]>| {
+      let num_ref_idx_active = num_ref_idx_active_minus1 as usize + 1;
+      // RefPicListTemp0 or RefPicListTemp1 of NumRpsCurrTempList entries
+      let temp = first
+        .iter()
+        .chain(second)
+        .chain(&long_term)
+        .cycle()
+        .take(num_ref_idx_active.max(num_pic_total_curr))
+        .copied()
+        .collect::<Vec<_>>();
+      (0..num_ref_idx_active)
+        .map(|i| temp[list_entry.map_or(i, |entry| entry[i] as usize)])
+        .collect::<Vec<_>>()
+    };
+    let list0 = list(
+      &before,
+      &after,
+      header.num_ref_idx_l0_active_minus1,
+      modification.and_then(|m| m.list_entry_l0.as_deref()),
+    );
+    let list1 = if header.slice_type.is_bidirectional() {
+      list(
+        &after,
+        &before,
+        header.num_ref_idx_l1_active_minus1,
+        modification.and_then(|m| m.list_entry_l1.as_deref()),
+      )
+    } else {
+      Vec::new()
+    };
+    Ok([list0, list1])
+  }
+
+  /// C.5.2.3 Stores the decoded current picture as a short-term reference picture and outputs
+  /// the pictures beyond the reordering and latency limits
+  pub fn store(&mut self, picture: Picture) {
+    for picture in self.pictures.iter_mut().filter(|p| p.needed_for_output) {
+      picture.pic_latency_count += 1;
+    }
+    self.pictures.push(DecodedPicture {
+      needed_for_output: picture.pic_output_flag,
+      picture,
+      marking: ReferenceMarking::ShortTerm,
+      pic_latency_count: 0,
+    });
+    while self.output_needed() {
+      if !self.bump() {
+        break;
+      }
+    }
+  }
+
+  /// More pictures are waiting for output than sps_max_num_reorder_pics, or one of them
+  /// waited SpsMaxLatencyPictures
+  fn output_needed(&self) -> bool {
+    let ordering = &self.sub_layer_ordering;
+    let sps_max_latency_pictures =
+      ordering.max_num_reorder_pics as u32 + ordering.max_latency_increase_plus1 - 1;
+    let waiting = self
+      .pictures
+      .iter()
+      .filter(|picture| picture.needed_for_output);
+    waiting.clone().count() > ordering.max_num_reorder_pics as usize
+      || (ordering.max_latency_increase_plus1 != 0
+        && waiting
+          .into_iter()
+          .any(|picture| picture.pic_latency_count >= sps_max_latency_pictures))
+  }
+
+  /// C.5.2.4 Bumping process, outputs the picture with the smallest PicOrderCntVal and removes
+  /// it when it is not used for reference. Returns false when no picture is waiting for output
+  fn bump(&mut self) -> bool {
+    let Some(index) = self
+      .pictures
+      .iter()
+      .enumerate()
+      .filter(|(_, picture)| picture.needed_for_output)
+      .min_by_key(|(_, picture)| picture.poc())
+      .map(|(index, _)| index)
+    else {
+      return false;
+    };
+    let picture = &mut self.pictures[index];
+    picture.needed_for_output = false;
+    log!(File@"OUTPUT PICTURE POC {}", picture.poc());
+    if picture.marking == ReferenceMarking::Unused {
+      self.pictures.remove(index);
+    }
+    true
+  }
+}
@@ -0,0 +1,246 @@
+use super::{
+  picture::{MvField, Plane},
+  slice::{header::SliceType, Slice},
+  HevcResult,
+};
+
+/// Table 8-12 luma interpolation filter coefficients fL of the quarter sample positions
+#[rustfmt::skip]
+const LUMA_FILTER: [[i32; 8]; 4] = [
+  [0, 0, 0, 64, 0, 0, 0, 0],
+  [-1, 4, -10, 58, 17, -5, 1, 0],
+  [-1, 4, -11, 40, 40, -11, 4, -1],
+  [0, 1, -5, 17, 58, -10, 4, -1],
+];
+
+/// Table 8-13 chroma interpolation filter coefficients fC of the eighth sample positions
+#[rustfmt::skip]
+const CHROMA_FILTER: [[i32; 4]; 8] = [
+  [0, 64, 0, 0],
+  [-2, 58, 10, -2],
+  [-4, 54, 16, -2],
+  [-6, 46, 28, -4],
+  [-4, 36, 36, -4],
+  [-4, 28, 46, -6],
+  [-2, 16, 54, -4],
+  [-2, 10, 58, -2],
+];
+
+/// 8.5.3.3.3 Fractional sample interpolation of the `width` x `height` block of `plane` at the
+/// integer position `[x_int, y_int]` and the fractional offsets `frac`, the reference samples
+/// outside of the picture are padded. Returns the predicted samples at 14-bit precision
+fn interpolate<const N: usize>(
+  plane: &Plane,
+  [x_int, y_int]: [i32; 2],
+  [width, height]: [usize; 2],
+  [x_frac, y_frac]: [usize; 2],
+  filters: &[[i32; N]],
+) -> Vec<i32> {
+  let bit_depth = plane.bit_depth as i32;
+  let shift1 = (bit_depth - 8).min(4);
+  let shift2 = 6;
+  let shift3 = (14 - bit_depth).max(2);
+  let offset = N as i32 / 2 - 1;
+  let sample = |x: i32, y: i32| {
+    plane.get(
+      x.clamp(0, plane.width as i32 - 1) as u32,
+      y.clamp(0, plane.height as i32 - 1) as u32,
+    ) as i32
+  };
+
+  // Rows filtered horizontally, with the rows around the block the vertical filter needs
+  let (first_row, rows) = if y_frac != 0 {
+    (y_int - offset, height + N - 1)
+  } else {
+    (y_int, height)
+  };
+  let mut horizontal = Vec::with_capacity(rows * width);
+  for y in first_row..first_row + rows as i32 {
+    for x in x_int..x_int + width as i32 {
+      horizontal.push(if x_frac != 0 {
+        let filtered = filters[x_frac]
+          .iter()
+          .enumerate()
+          .map(|(i, c)| c * sample(x + i as i32 - offset, y))
+          .sum::<i32>();
+        filtered >> shift1
+      } else {
+        sample(x, y)
+      });
+    }
+  }
+  if y_frac == 0 {
+    if x_frac == 0 {
+      horizontal.iter_mut().for_each(|sample| *sample <<= shift3);
+    }
+    return horizontal;
+  }
+
+  let shift = if x_frac != 0 { shift2 } else { shift1 };
+  let mut pred = Vec::with_capacity(width * height);
+  for y in 0..height {
+    for x in 0..width {
+      let filtered = filters[y_frac]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| c * horizontal[(y + i) * width + x])
+        .sum::<i32>();
+      pred.push(filtered >> shift);
+    }
+  }
+  pred
+}
+
+impl Slice<'_> {
+  /// 8.5.3.3 Decoding process for inter sample prediction, writes the weighted prediction of the
+  /// luma prediction block `[x, y, width, height]` and of its chroma blocks to the picture
+  pub fn inter_sample_prediction(&mut self, pb: [u32; 4], mv_field: &MvField) -> HevcResult {
+    let components = if self.chroma_array_type == 0 { 1 } else { 3 };
+    for c_idx in 0..components {
+      let (sub_width, sub_height) = if c_idx == 0 {
+        (1, 1)
+      } else {
+        self.sps.chroma_subsampling()
+      };
+      let [x_pb, y_pb, width, height] = pb;
+      let (x0, y0) = (x_pb / sub_width, y_pb / sub_height);
+      let size = [(width / sub_width) as usize, (height / sub_height) as usize];
+      let plane_index = self.plane_index(c_idx);
+
+      let mut pred = [None, None];
+      for x in 0..2 {
+        if !mv_field.pred_flag[x] {
+          continue;
+        }
+        let reference = &self.ref_pic(x, mv_field.ref_idx[x])?.picture.planes[plane_index];
+        let [mv_x, mv_y] = mv_field.mv[x];
+        pred[x] = Some(if c_idx == 0 {
+          // Quarter sample luma motion vectors
+          interpolate(
+            reference,
+            [x0 as i32 + (mv_x >> 2), y0 as i32 + (mv_y >> 2)],
+            size,
+            [(mv_x & 3) as usize, (mv_y & 3) as usize],
+            &LUMA_FILTER,
+          )
+        } else {
+          // 8-228 and 8-229 chroma motion vectors in units of 1 / (4 * SubWidthC) and
+          // 1 / (4 * SubHeightC) chroma samples, interpolated at an eighth of a sample
+          let mv_c_x = mv_x * 2 / sub_width as i32;
+          let mv_c_y = mv_y * 2 / sub_height as i32;
+          interpolate(
+            reference,
+            [x0 as i32 + (mv_c_x >> 3), y0 as i32 + (mv_c_y >> 3)],
+            size,
+            [(mv_c_x & 7) as usize, (mv_c_y & 7) as usize],
+            &CHROMA_FILTER,
+          )
+        });
+      }
+
+      let samples = self.weighted_sample_prediction(c_idx, mv_field, pred);
+      let plane = &mut self.picture.planes[plane_index];
+      for (i, sample) in samples.into_iter().enumerate() {
+        plane.set(x0 + (i % size[0]) as u32, y0 + (i / size[0]) as u32, sample);
+      }
+    }
+    Ok(())
+  }
+
+  /// 8.5.3.3.4 Weighted sample prediction of the 14-bit predictions of list 0 and list 1
+  fn weighted_sample_prediction(
+    &self,
+    c_idx: u8,
+    mv_field: &MvField,
+    pred: [Option<Vec<i32>>; 2],
+  ) -> Vec<u16> {
+    let bit_depth = if c_idx == 0 {
+      self.sps.bit_depth_luma()
+    } else {
+      self.sps.bit_depth_chroma()
+    } as i32;
+    let max = (1 << bit_depth) - 1;
+    let weighted_pred_flag = match self.slice_type {
+      SliceType::P => self.pps.weighted_pred_flag,
+      SliceType::B => self.pps.weighted_bipred_flag,
+      SliceType::I => false,
+    };
+    let shift1 = 14 - bit_depth;
+    let clip = |sample: i32| sample.clamp(0, max) as u16;
+
+    let Some(table) = self.pred_weight_table.as_ref().filter(|_| weighted_pred_flag) else {
+      // 8.5.3.3.4.2 Default weighted sample prediction process
+      return match pred {
+        [Some(pred0), Some(pred1)] => {
+          let shift2 = 15 - bit_depth;
+          let offset2 = 1 << (shift2 - 1);
+          pred0
+            .iter()
+            .zip(&pred1)
+            .map(|(p0, p1)| clip((p0 + p1 + offset2) >> shift2))
+            .collect()
+        }
+        [Some(pred), None] | [None, Some(pred)] => {
+          let offset1 = if shift1 > 0 { 1 << (shift1 - 1) } else { 0 };
+          pred.iter().map(|p| clip((p + offset1) >> shift1)).collect()
+        }
+        [None, None] => Vec::new(),
+      };
+    };
+
+    // 8.5.3.3.4.3 Explicit weighted sample prediction process
+    let log2_wd = if c_idx == 0 {
+      table.luma_log2_weight_denom
+    } else {
+      table.chroma_log2_weight_denom
+    } as i32
+      + shift1;
+    let offset_shift = if self.sps.range_extension.high_precision_offsets_enabled_flag {
+      0
+    } else {
+      bit_depth - 8
+    };
+    let [(w0, o0), (w1, o1)] = [0, 1].map(|x| {
+      table.weights[x]
+        .get(mv_field.ref_idx[x] as usize)
+        .filter(|_| mv_field.pred_flag[x])
+        .map(|weight| {
+          let (w, o) = if c_idx == 0 {
+            (weight.luma_weight, weight.luma_offset)
+          } else {
+            (
+              weight.chroma_weight[c_idx as usize - 1],
+              weight.chroma_offset[c_idx as usize - 1],
+            )
+          };
+          (w as i32, (o as i32) << offset_shift)
+        })
+        .unwrap_or_default()
+    });
+    match pred {
+      [Some(pred0), Some(pred1)] => pred0
+        .iter()
+        .zip(&pred1)
+        .map(|(p0, p1)| clip((p0 * w0 + p1 * w1 + ((o0 + o1 + 1) << log2_wd)) >> (log2_wd + 1)))
+        .collect(),
+      [Some(pred), None] | [None, Some(pred)] => {
+        let (w, o) = if mv_field.pred_flag[0] {
+          (w0, o0)
+        } else {
+          (w1, o1)
+        };
+        pred
+          .iter()
+          .map(|p| {
+            if log2_wd >= 1 {
+              clip(((p * w + (1 << (log2_wd - 1))) >> log2_wd) + o)
+            } else {
+              clip(p * w + o)
+            }
+          })
+          .collect()
+      }
+      [None, None] => Vec::new(),
+    }
+  }
+}
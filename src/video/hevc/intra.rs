@@ -0,0 +1,250 @@
+use super::slice::Slice;
+
+/// Table 8-4 intraPredAngle of the angular modes 2 to 34
+#[rustfmt::skip]
+const INTRA_PRED_ANGLE: [i32; 33] = [
+  32, 26, 21, 17, 13, 9, 5, 2, 0, -2, -5, -9, -13, -17, -21, -26,
+  -32, -26, -21, -17, -13, -9, -5, -2, 0, 2, 5, 9, 13, 17, 21, 26, 32,
+];
+
+/// Table 8-5 invAngle of the angular modes 11 to 25
+#[rustfmt::skip]
+const INV_ANGLE: [i32; 15] = [
+  -4096, -1638, -910, -630, -482, -390, -315, -256, -315, -390, -482, -630, -910, -1638, -4096,
+];
+
+const INTRA_PLANAR: u8 = 0;
+const INTRA_DC: u8 = 1;
+const INTRA_ANGULAR10: u8 = 10;
+const INTRA_ANGULAR26: u8 = 26;
+
+/// Reference samples of a `size` block, p[-1][size * 2 - 1] up to p[-1][-1] then p[0][-1] up to
+/// p[size * 2 - 1][-1], in the order of the substitution process
+struct ReferenceSamples {
+  size: usize,
+  samples: Vec<i32>,
+}
+
+impl ReferenceSamples {
+  /// p[-1][-1]
+  fn corner(&self) -> i32 {
+    self.samples[2 * self.size]
+  }
+
+  /// p[-1][y] for y from -1 to size * 2 - 1
+  fn left(&self, y: i32) -> i32 {
+    self.samples[(2 * self.size as i32 - 1 - y) as usize]
+  }
+
+  /// p[x][-1] for x from -1 to size * 2 - 1
+  fn top(&self, x: i32) -> i32 {
+    self.samples[(2 * self.size as i32 + 1 + x) as usize]
+  }
+}
+
+/// 8.4.4.2 Intra sample prediction of the `1 << log2_size` block of colour component `c_idx` at
+/// (`x_tb`, `y_tb`) in component samples, returns the predicted samples in raster order
+pub fn intra_sample_prediction(
+  slice: &Slice,
+  c_idx: u8,
+  x_tb: u32,
+  y_tb: u32,
+  log2_size: u8,
+  pred_mode_intra: u8,
+  cu_transquant_bypass_flag: bool,
+) -> Vec<i32> {
+  let size = 1 << log2_size;
+  let bit_depth = slice.picture.planes[slice.plane_index(c_idx)].bit_depth;
+  let mut p = reference_samples(slice, c_idx, x_tb, y_tb, size);
+
+  // 8.4.4.2.3 Filtering process of neighbouring samples
+  let range_extension = &slice.sps.range_extension;
+  let min_dist_ver_hor = (pred_mode_intra as i32 - 26)
+    .abs()
+    .min((pred_mode_intra as i32 - 10).abs());
+  let intra_hor_ver_dist_thres = match size {
+    8 => 7,
+    16 => 1,
+    _ => 0,
+  };
+  let filter_flag = !range_extension.intra_smoothing_disabled_flag
+    && (c_idx == 0 || slice.chroma_array_type == 3)
+    && pred_mode_intra != INTRA_DC
+    && size != 4
+    && min_dist_ver_hor > intra_hor_ver_dist_thres;
+  if filter_flag {
+    let threshold = 1 << (bit_depth - 5);
+    let last = 2 * size as i32 - 1;
+    let bi_int_flag = slice.sps.strong_intra_smoothing_enabled_flag
+      && c_idx == 0
+      && size == 32
+      && (p.corner() + p.top(last) - 2 * p.top(size as i32 - 1)).abs() < threshold
+      && (p.corner() + p.left(last) - 2 * p.left(size as i32 - 1)).abs() < threshold;
+    p.samples = if bi_int_flag {
+      let (corner, left, top) = (p.corner(), p.left(63), p.top(63));
+      (0..p.samples.len() as i32)
+        .map(|i| match i - 64 {
+          0 => corner,
+          i if i < 0 => ((64 + i) * corner - i * left + 32) >> 6,
+          i => ((64 - i) * corner + i * top + 32) >> 6,
+        })
+        .collect()
+    } else {
+      let samples = &p.samples;
+      (0..samples.len())
+        .map(|i| {
+          if i == 0 || i == samples.len() - 1 {
+            samples[i]
+          } else {
+            (samples[i - 1] + 2 * samples[i] + samples[i + 1] + 2) >> 2
+          }
+        })
+        .collect()
+    };
+  }
+
+  let disable_intra_boundary_filter =
+    range_extension.implicit_rdpcm_enabled_flag && cu_transquant_bypass_flag;
+  let edge_filters = c_idx == 0 && size < 32 && !disable_intra_boundary_filter;
+  let max = (1 << bit_depth) - 1;
+  let mut pred = vec![0; size * size];
+  match pred_mode_intra {
+    // 8.4.4.2.5 Specification of intra prediction mode INTRA_PLANAR
+    INTRA_PLANAR => {
+      let n = size as i32;
+      for y in 0..n {
+        for x in 0..n {
+          pred[(y * n + x) as usize] = ((n - 1 - x) * p.left(y)
+            + (x + 1) * p.top(n)
+            + (n - 1 - y) * p.top(x)
+            + (y + 1) * p.left(n)
+            + n)
+            >> (log2_size + 1);
+        }
+      }
+    }
+    // 8.4.4.2.6 Specification of intra prediction mode INTRA_DC
+    INTRA_DC => {
+      let n = size as i32;
+      let dc_val = ((0..n).map(|i| p.top(i) + p.left(i)).sum::<i32>() + n) >> (log2_size + 1);
+      pred.fill(dc_val);
+      if edge_filters {
+        pred[0] = (p.left(0) + 2 * dc_val + p.top(0) + 2) >> 2;
+        for i in 1..n {
+          pred[i as usize] = (p.top(i) + 3 * dc_val + 2) >> 2;
+          pred[(i * n) as usize] = (p.left(i) + 3 * dc_val + 2) >> 2;
+        }
+      }
+    }
+    // 8.4.4.2.6 Specification of intra prediction mode in the range of INTRA_ANGULAR2 to
+    // INTRA_ANGULAR34
+    _ => {
+      let n = size as i32;
+      let intra_pred_angle = INTRA_PRED_ANGLE[pred_mode_intra as usize - 2];
+      let vertical = pred_mode_intra >= 18;
+      // Main and side references along the prediction direction, ref[x] at x + size
+      let (main, side): (&dyn Fn(i32) -> i32, &dyn Fn(i32) -> i32) = if vertical {
+        (&|x| p.top(x), &|y| p.left(y))
+      } else {
+        (&|y| p.left(y), &|x| p.top(x))
+      };
+      let mut reference = vec![0; 3 * size + 1];
+      for x in 0..=n {
+        reference[(x + n) as usize] = main(x - 1);
+      }
+      if intra_pred_angle < 0 {
+        let inv_angle = INV_ANGLE[pred_mode_intra as usize - 11];
+        if (n * intra_pred_angle) >> 5 < -1 {
+          for x in (n * intra_pred_angle) >> 5..=-1 {
+            reference[(x + n) as usize] = side(-1 + ((x * inv_angle + 128) >> 8));
+          }
+        }
+      } else {
+        for x in n + 1..=2 * n {
+          reference[(x + n) as usize] = main(x - 1);
+        }
+      }
+      for j in 0..n {
+        let i_idx = ((j + 1) * intra_pred_angle) >> 5;
+        let i_fact = ((j + 1) * intra_pred_angle) & 31;
+        for i in 0..n {
+          let a = reference[(i + i_idx + 1 + n) as usize];
+          let sample = if i_fact != 0 {
+            let b = reference[(i + i_idx + 2 + n) as usize];
+            ((32 - i_fact) * a + i_fact * b + 16) >> 5
+          } else {
+            a
+          };
+          // j is the row of vertical modes and the column of horizontal ones
+          let (x, y) = if vertical { (i, j) } else { (j, i) };
+          pred[(y * n + x) as usize] = sample;
+        }
+      }
+      if edge_filters && intra_pred_angle == 0 {
+        for i in 0..n {
+          if pred_mode_intra == INTRA_ANGULAR26 {
+            pred[(i * n) as usize] = (p.top(0) + ((p.left(i) - p.corner()) >> 1)).clamp(0, max);
+          } else if pred_mode_intra == INTRA_ANGULAR10 {
+            pred[i as usize] = (p.left(0) + ((p.top(i) - p.corner()) >> 1)).clamp(0, max);
+          }
+        }
+      }
+    }
+  }
+  pred
+}
+
+/// 8.4.4.2.2 Reference samples of the block with the unavailable ones substituted
+fn reference_samples(
+  slice: &Slice,
+  c_idx: u8,
+  x_tb: u32,
+  y_tb: u32,
+  size: usize,
+) -> ReferenceSamples {
+  let picture = &*slice.picture;
+  let plane = &picture.planes[slice.plane_index(c_idx)];
+  let (sub_width, sub_height) = if c_idx == 0 {
+    (1, 1)
+  } else {
+    slice.sps.chroma_subsampling()
+  };
+  let (x_tb_y, y_tb_y) = (x_tb * sub_width, y_tb * sub_height);
+  let constrained_intra_pred_flag = slice.pps.constrained_intra_pred_flag;
+  let samples = (0..4 * size as i32 + 1)
+    .map(|i| {
+      let (x, y) = if i <= 2 * size as i32 {
+        (-1, 2 * size as i32 - 1 - i)
+      } else {
+        (i - 2 * size as i32 - 1, -1)
+      };
+      let (x_nb, y_nb) = (x_tb as i32 + x, y_tb as i32 + y);
+      let (x_nb_y, y_nb_y) = (x_nb * sub_width as i32, y_nb * sub_height as i32);
+      let available = picture.available(x_tb_y, y_tb_y, x_nb_y, y_nb_y)
+        && (!constrained_intra_pred_flag
+          || picture
+            .block(x_nb_y as u32, y_nb_y as u32)
+            .pred_mode
+            .is_intra());
+      if available {
+        Some(plane.get(x_nb as u32, y_nb as u32) as i32)
+      } else {
+        None
+      }
+    })
+    .collect::<Vec<_>>();
+
+  let samples = match samples.iter().flatten().next() {
+    Some(&first) => samples
+      .iter()
+      .scan(first, |previous, sample| {
+        if let Some(sample) = sample {
+          *previous = *sample;
+        }
+        Some(*previous)
+      })
+      .collect(),
+    None => vec![1 << (plane.bit_depth - 1); samples.len()],
+  };
+  ReferenceSamples { size, samples }
+}
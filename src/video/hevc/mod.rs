@@ -0,0 +1,195 @@
+pub mod cabac;
+pub mod deblocking;
+pub mod dpb;
+pub mod inter;
+pub mod intra;
+pub mod motion;
+pub mod picture;
+pub mod reconstruct;
+pub mod sao;
+pub mod slice;
+pub mod transform;
+
+use super::atom::{
+  AtomError, HevcNalUnitHeader, HevcPictureParameterSet, HevcSequenceParameterSet, HvcCAtom,
+  VideoParameterSet,
+};
+use crate::ascii::LogDisplay;
+use crate::byte::BitStream;
+use crate::log;
+use dpb::DecodedPictureBuffer;
+use picture::Picture;
+use slice::{header::SliceSegmentHeader, Slice};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HevcError {
+  #[error(transparent)]
+  Atom(#[from] AtomError),
+  #[error("HEVC NAL unit is too short")]
+  NalUnit,
+  #[error("HEVC NAL forbidden_zero_bit is not 0")]
+  ForbiddenZeroBit,
+  #[error("Missing HEVC {0} with id {1}")]
+  MissingParameterSet(&'static str, u32),
+  #[error("Invalid HEVC {0}: {1}")]
+  SliceHeader(&'static str, u32),
+  #[error("Invalid HEVC {0}")]
+  Invalid(&'static str),
+  #[error("Unsupported HEVC feature {0}")]
+  Unsupported(&'static str),
+}
+
+pub type HevcResult<T = ()> = Result<T, HevcError>;
+
+/// ITU-T H.265 decoder of the slice segment syntax. Parameter sets of the hvcC are replaced by
+/// those sent in-band with the same id
+#[derive(Debug, Default)]
+pub struct HevcDecoder {
+  pub vps: HashMap<u8, VideoParameterSet>,
+  pub sps: HashMap<u8, HevcSequenceParameterSet>,
+  pub pps: HashMap<u8, HevcPictureParameterSet>,
+  /// Picture of the last decoded slice segment, before the in-loop filters
+  pub picture: Option<Picture>,
+  /// Previously decoded pictures
+  pub dpb: DecodedPictureBuffer,
+}
+
+impl HevcDecoder {
+  pub fn new(hvcc: &HvcCAtom) -> Self {
+    let mut decoder = Self::default();
+    for unit in hvcc.nal_arrays.iter().flat_map(|array| array.units.iter()) {
+      if let Err(e) = decoder.decode_nal(unit) {
+        log!(err@"#[hevc] {e}");
+      }
+    }
+    decoder
+  }
+
+  /// Decodes a NAL unit without its length prefix, slice segments are parsed into the coding
+  /// units of the current picture
+  pub fn decode_nal(&mut self, unit: &[u8]) -> HevcResult {
+    let (header, rbsp) = HevcNalUnitHeader::split_rbsp(unit).ok_or(HevcError::NalUnit)?;
+    if header.forbidden_zero_bit {
+      return Err(HevcError::ForbiddenZeroBit);
+    }
+    // Only the base layer is decoded
+    if header.nuh_layer_id != 0 {
+      log!(File@"{} [UNUSED LAYER]", Self::nal_msg(&header, unit.len()));
+      return Ok(());
+    }
+    let mut data = BitStream::rbsp(&rbsp);
+    match header.nal_unit_type {
+      HevcNalUnitHeader::VPS_NUT => {
+        let vps = VideoParameterSet::decode_rbsp(&mut data)?;
+        log!(File@"{}{vps:#?}", Self::nal_msg(&header, unit.len()));
+        self.vps.insert(vps.id, vps);
+      }
+      HevcNalUnitHeader::SPS_NUT => {
+        let sps = HevcSequenceParameterSet::decode_rbsp(&mut data)?;
+        log!(File@"{}{sps:#?}", Self::nal_msg(&header, unit.len()));
+        self.sps.insert(sps.id, sps);
+      }
+      HevcNalUnitHeader::PPS_NUT => {
+        // The 4:4:4 scaling lists depend on the chroma format of the referenced SPS
+        let mut ids = BitStream::rbsp(&rbsp);
+        ids.exponential_golomb::<u16>();
+        let sps_id = ids.exponential_golomb::<u16>();
+        let chroma_format_idc = self
+          .sps
+          .get(&(sps_id as u8))
+          .map(|sps| sps.chroma_format_idc)
+          .unwrap_or(1);
+        let pps = HevcPictureParameterSet::decode_rbsp(&mut data, chroma_format_idc)?;
+        log!(File@"{}{pps:#?}", Self::nal_msg(&header, unit.len()));
+        self.pps.insert(pps.id, pps);
+      }
+      _ if header.is_slice_segment() => {
+        self.decode_slice_segment(header, data, unit.len())?;
+      }
+      HevcNalUnitHeader::EOS_NUT => {
+        log!(File@"{} END OF SEQUENCE", Self::nal_msg(&header, unit.len()));
+        self.dpb.sequence_started = false;
+      }
+      _ => log!(File@"{} [UNUSED]", Self::nal_msg(&header, unit.len())),
+    }
+    Ok(())
+  }
+
+  fn decode_slice_segment(
+    &mut self,
+    nal: HevcNalUnitHeader,
+    mut data: BitStream,
+    size: usize,
+  ) -> HevcResult {
+    let previous = self
+      .picture
+      .as_ref()
+      .and_then(|picture| picture.slice_header.as_ref());
+    let header = SliceSegmentHeader::decode(&mut data, nal, &self.sps, &self.pps, previous)?;
+    log!(File@"{}{header:#?}", Self::nal_msg(&nal, size));
+    if header.first_slice_segment_in_pic_flag {
+      self.finish_picture();
+    }
+    let pps_id = header.slice_pic_parameter_set_id;
+    let pps = self
+      .pps
+      .get(&pps_id)
+      .ok_or(HevcError::MissingParameterSet("PPS", pps_id as u32))?;
+    let sps = self
+      .sps
+      .get(&pps.seq_parameter_set_id)
+      .ok_or(HevcError::MissingParameterSet(
+        "SPS",
+        pps.seq_parameter_set_id as u32,
+      ))?;
+    if self.dpb.skips(&nal) {
+      log!(File@"SKIPPED RASL PICTURE");
+      return Ok(());
+    }
+    if header.first_slice_segment_in_pic_flag {
+      let mut picture = Picture::new(sps, pps)?;
+      picture.pic_order_cnt_val = self.dpb.start_picture(&nal, &header, sps, pps)?;
+      picture.pic_output_flag =
+        !(nal.is_rasl() && self.dpb.no_rasl_output_flag) && header.pic_output_flag;
+      self.picture = Some(picture);
+    }
+    let Some(picture) = self.picture.as_mut() else {
+      return Err(HevcError::Invalid("slice segment without a first one"));
+    };
+    if !header.dependent_slice_segment_flag {
+      picture.slice_header = Some(header.clone());
+    }
+    let ref_pic_list = self.dpb.ref_pic_lists(&header)?;
+    let mut slice = Slice::new(header, sps, pps, data, picture, ref_pic_list)?;
+    slice.data()?;
+    let coding_units = std::mem::take(&mut slice.coding_units);
+    log!(File@"SLICE SEGMENT {} CODING UNITS", coding_units.len());
+    for cu in &coding_units {
+      log!(File@"{cu:?}");
+    }
+    for cu in &coding_units {
+      slice.reconstruct(cu)?;
+    }
+    Ok(())
+  }
+
+  /// 8.7 Applies the in-loop filters to the current picture once all of its slice segments are
+  /// decoded and stores it in the DPB, returns the filtered picture
+  pub fn finish_picture(&mut self) -> Option<&Picture> {
+    let mut picture = self.picture.take()?;
+    picture.deblocking_filter();
+    picture.sample_adaptive_offset();
+    self.dpb.store(picture);
+    self.dpb.pictures.last().map(|decoded| &decoded.picture)
+  }
+
+  fn nal_msg(header: &HevcNalUnitHeader, size: usize) -> String {
+    format!(
+      "[HEVC type={} tid={}] ({size} bytes) => ",
+      header.nal_unit_type,
+      header.nuh_temporal_id_plus1.saturating_sub(1)
+    )
+  }
+}
@@ -0,0 +1,427 @@
+use super::{
+  dpb::DecodedPicture,
+  picture::MvField,
+  slice::{
+    coding_unit::{CodingUnit, InterPredIdc, PartMode, PredictionUnit},
+    Slice,
+  },
+  HevcError, HevcResult,
+};
+
+/// Table 8-7 l0CandIdx and l1CandIdx of the combined bi-predictive merging candidates
+#[rustfmt::skip]
+const COMBINED_CANDIDATES: [(usize, usize); 12] = [
+  (0, 1), (1, 0), (0, 2), (2, 0), (1, 2), (2, 1), (0, 3), (3, 0), (1, 3), (3, 1), (2, 3), (3, 2),
+];
+
+/// 8-179 to 8-183 Scales a motion vector by the ratio of the POC distances `tb` over `td`
+fn scale_mv(mv: [i32; 2], td: i32, tb: i32) -> [i32; 2] {
+  let td = td.clamp(-128, 127);
+  let tb = tb.clamp(-128, 127);
+  if td == 0 {
+    return mv;
+  }
+  let tx = (16384 + (td.abs() >> 1)) / td;
+  let dist_scale_factor = ((tb * tx + 32) >> 6).clamp(-4096, 4095);
+  mv.map(|mv| {
+    let scaled = dist_scale_factor * mv;
+    (scaled.signum() * ((scaled.abs() + 127) >> 8)).clamp(-32768, 32767)
+  })
+}
+
+impl Slice<'_> {
+  /// Entry `ref_idx` of RefPicList0 or RefPicList1
+  pub fn ref_pic(&self, x: usize, ref_idx: u8) -> HevcResult<&DecodedPicture> {
+    self.ref_pic_list[x]
+      .get(ref_idx as usize)
+      .copied()
+      .ok_or(HevcError::SliceHeader(
+        if x == 0 { "ref_idx_l0" } else { "ref_idx_l1" },
+        ref_idx as u32,
+      ))
+  }
+
+  /// 8.5.3.2.1 Motion vectors and reference indices of the prediction unit `part_idx` of an
+  /// inter coding unit
+  pub fn mv_field(
+    &self,
+    cu: &CodingUnit,
+    pu: &PredictionUnit,
+    part_idx: usize,
+  ) -> HevcResult<MvField> {
+    if pu.merge_flag {
+      return self.merge_mode(cu, pu, part_idx);
+    }
+    let mut pred_flag = match pu.inter_pred_idc {
+      InterPredIdc::PredL0 => [true, false],
+      InterPredIdc::PredL1 => [false, true],
+      InterPredIdc::PredBi => [true, true],
+    };
+    if !self.slice_type.is_bidirectional() {
+      pred_flag = [true, false];
+    }
+    let mut mv = [[0; 2]; 2];
+    for x in 0..2 {
+      if pred_flag[x] {
+        let mvp_list = self.luma_mv_prediction(cu, pu, part_idx, x, pu.ref_idx[x])?;
+        let mvp = mvp_list[pu.mvp_flag[x] as usize];
+        // 8-194 to 8-197 the sum wraps around to 16 bits
+        mv[x] = [0, 1].map(|c| (mvp[c] + pu.mvd[x][c]) as i16 as i32);
+      }
+    }
+    self.with_references(pred_flag, pu.ref_idx, mv)
+  }
+
+  /// Motion of the used lists with the PicOrderCntVal and marking of their reference pictures,
+  /// the fields of an unused list are zero
+  fn with_references(
+    &self,
+    pred_flag: [bool; 2],
+    ref_idx: [u8; 2],
+    mv: [[i32; 2]; 2],
+  ) -> HevcResult<MvField> {
+    let mut mv_field = MvField {
+      pred_flag,
+      ..Default::default()
+    };
+    for x in 0..2 {
+      if pred_flag[x] {
+        let reference = self.ref_pic(x, ref_idx[x])?;
+        mv_field.ref_idx[x] = ref_idx[x];
+        mv_field.mv[x] = mv[x];
+        mv_field.ref_poc[x] = reference.poc();
+        mv_field.ref_long_term[x] = reference.is_long_term();
+      }
+    }
+    Ok(mv_field)
+  }
+
+  /// 6.4.2 Motion of the neighbouring prediction block covering (`x_nb`, `y_nb`), `None` when
+  /// it is not available or intra coded
+  fn neighbouring_mv_field(
+    &self,
+    cu: &CodingUnit,
+    [x_pb, y_pb, width, height]: [u32; 4],
+    part_idx: usize,
+    x_nb: i32,
+    y_nb: i32,
+  ) -> Option<MvField> {
+    let size = 1 << cu.log2_cb_size;
+    let (x_cb, y_cb) = (cu.x0 as i32, cu.y0 as i32);
+    let same_cb = x_cb <= x_nb && y_cb <= y_nb && x_cb + size > x_nb && y_cb + size > y_nb;
+    let available = if !same_cb {
+      self.picture.available(x_pb, y_pb, x_nb, y_nb)
+    } else {
+      // The second NxN partition can't use the third one, decoded after it
+      !(width << 1 == size as u32
+        && height << 1 == size as u32
+        && part_idx == 1
+        && y_cb + height as i32 <= y_nb
+        && x_cb + width as i32 > x_nb)
+    };
+    if !available {
+      return None;
+    }
+    let block = self.picture.block(x_nb as u32, y_nb as u32);
+    if block.pred_mode.is_intra() {
+      None
+    } else {
+      Some(block.mv_field)
+    }
+  }
+
+  /// 8.5.3.2.2 Derivation process for luma motion vectors for merge mode
+  fn merge_mode(
+    &self,
+    cu: &CodingUnit,
+    pu: &PredictionUnit,
+    part_idx: usize,
+  ) -> HevcResult<MvField> {
+    let log2_par_mrg_level = self.pps.log2_parallel_merge_level_minus2 + 2;
+    // singleMCLFlag, the prediction units of 8x8 coding units share the merge candidates of
+    // the 2Nx2N one
+    let (pb, part_idx) = if log2_par_mrg_level > 2 && cu.log2_cb_size == 3 {
+      ([cu.x0, cu.y0, 8, 8], 0)
+    } else {
+      ([pu.x0, pu.y0, pu.width, pu.height], part_idx)
+    };
+    let mut candidates = self.spatial_merge_candidates(cu, pb, part_idx, log2_par_mrg_level);
+    let bidirectional = self.slice_type.is_bidirectional();
+
+    // 8.5.3.2.8 temporal candidate with the reference index 0
+    let mv_col = [0, 1].map(|x| {
+      if x == 0 || bidirectional {
+        self.temporal_luma_mv(pb, x, 0)
+      } else {
+        None
+      }
+    });
+    if mv_col.iter().any(Option::is_some) {
+      candidates.push(self.with_references(
+        mv_col.map(|mv| mv.is_some()),
+        [0, 0],
+        mv_col.map(Option::unwrap_or_default),
+      )?);
+    }
+
+    // 8.5.3.2.4 Derivation process for combined bi-predictive merging candidates
+    let max_num_merge_cand = self.max_num_merge_cand as usize;
+    let num_orig_merge_cand = candidates.len();
+    if bidirectional && num_orig_merge_cand > 1 && num_orig_merge_cand < max_num_merge_cand {
+      for &(l0_cand, l1_cand) in COMBINED_CANDIDATES
+        .iter()
+        .take(num_orig_merge_cand * (num_orig_merge_cand - 1))
+      {
+        if candidates.len() == max_num_merge_cand {
+          break;
+        }
+        let (l0_cand, l1_cand) = (candidates[l0_cand], candidates[l1_cand]);
+        if l0_cand.pred_flag[0]
+          && l1_cand.pred_flag[1]
+          && (l0_cand.ref_poc[0] != l1_cand.ref_poc[1] || l0_cand.mv[0] != l1_cand.mv[1])
+        {
+          candidates.push(self.with_references(
+            [true, true],
+            [l0_cand.ref_idx[0], l1_cand.ref_idx[1]],
+            [l0_cand.mv[0], l1_cand.mv[1]],
+          )?);
+        }
+      }
+    }
+
+    // 8.5.3.2.5 Derivation process for zero motion vector merging candidates
+    let num_ref_idx = if bidirectional {
+      self
+        .num_ref_idx_l0_active_minus1
+        .min(self.num_ref_idx_l1_active_minus1)
+        + 1
+    } else {
+      self.num_ref_idx_l0_active_minus1 + 1
+    };
+    let mut zero_idx = 0;
+    while candidates.len() < max_num_merge_cand {
+      let ref_idx = if zero_idx < num_ref_idx { zero_idx } else { 0 };
+      candidates.push(self.with_references([true, bidirectional], [ref_idx; 2], [[0; 2]; 2])?);
+      zero_idx += 1;
+    }
+
+    let mut mv_field = *candidates
+      .get(pu.merge_idx as usize)
+      .ok_or(HevcError::SliceHeader("merge_idx", pu.merge_idx as u32))?;
+    // 8x4 and 4x8 prediction units are restricted to uni-prediction
+    if mv_field.pred_flag == [true, true] && pu.width + pu.height == 12 {
+      mv_field = self.with_references([true, false], mv_field.ref_idx, mv_field.mv)?;
+    }
+    Ok(mv_field)
+  }
+
+  /// 8.5.3.2.3 Derivation process for spatial merging candidates, in the order A1, B1, B0, A0
+  /// and B2
+  fn spatial_merge_candidates(
+    &self,
+    cu: &CodingUnit,
+    pb: [u32; 4],
+    part_idx: usize,
+    log2_par_mrg_level: u8,
+  ) -> Vec<MvField> {
+    let [x_pb, y_pb, width, height] = pb.map(|v| v as i32);
+    let neighbour = |x_nb: i32, y_nb: i32| {
+      // Neighbours in the same merge estimation region are not used
+      if x_pb >> log2_par_mrg_level == x_nb >> log2_par_mrg_level
+        && y_pb >> log2_par_mrg_level == y_nb >> log2_par_mrg_level
+      {
+        None
+      } else {
+        self.neighbouring_mv_field(cu, pb, part_idx, x_nb, y_nb)
+      }
+    };
+    // The second prediction unit of a split coding unit would merge back into the first one
+    let second_vertical = part_idx == 1
+      && matches!(
+        cu.part_mode,
+        PartMode::PartNx2N | PartMode::PartnLx2N | PartMode::PartnRx2N
+      );
+    let second_horizontal = part_idx == 1
+      && matches!(
+        cu.part_mode,
+        PartMode::Part2NxN | PartMode::Part2NxnU | PartMode::Part2NxnD
+      );
+    let a1 = if second_vertical {
+      None
+    } else {
+      neighbour(x_pb - 1, y_pb + height - 1)
+    };
+    let b1 = if second_horizontal {
+      None
+    } else {
+      neighbour(x_pb + width - 1, y_pb - 1)
+    };
+    let b0 = neighbour(x_pb + width, y_pb - 1);
+    let a0 = neighbour(x_pb - 1, y_pb + height);
+    let b2 = neighbour(x_pb - 1, y_pb - 1);
+
+    let mut candidates = Vec::with_capacity(5);
+    candidates.extend(a1);
+    candidates.extend(b1.filter(|b1| Some(*b1) != a1));
+    candidates.extend(b0.filter(|b0| Some(*b0) != b1));
+    candidates.extend(a0.filter(|a0| Some(*a0) != a1));
+    if candidates.len() < 4 {
+      candidates.extend(b2.filter(|b2| Some(*b2) != a1 && Some(*b2) != b1));
+    }
+    candidates
+  }
+
+  /// 8.5.3.2.6 Derivation process for luma motion vector prediction, mvpListLX of the reference
+  /// index `ref_idx` of list `x`
+  fn luma_mv_prediction(
+    &self,
+    cu: &CodingUnit,
+    pu: &PredictionUnit,
+    part_idx: usize,
+    x: usize,
+    ref_idx: u8,
+  ) -> HevcResult<[[i32; 2]; 2]> {
+    let pb = [pu.x0, pu.y0, pu.width, pu.height];
+    let [x_pb, y_pb, width, height] = pb.map(|v| v as i32);
+    let reference = self.ref_pic(x, ref_idx)?;
+    let (ref_poc, long_term) = (reference.poc(), reference.is_long_term());
+    let poc = self.picture.pic_order_cnt_val;
+    let neighbour = |x_nb, y_nb| self.neighbouring_mv_field(cu, pb, part_idx, x_nb, y_nb);
+
+    // 8.5.3.2.7 Derivation process for motion vector predictor candidates. A neighbour
+    // predicted from the same picture in list X or Y is used as is
+    let same_picture = |n: MvField| {
+      [x, 1 - x]
+        .into_iter()
+        .find(|&l| n.pred_flag[l] && n.ref_poc[l] == ref_poc)
+        .map(|l| n.mv[l])
+    };
+    // Otherwise any reference with the same marking, scaled between short-term pictures
+    let scaled = |n: MvField| {
+      [x, 1 - x]
+        .into_iter()
+        .find(|&l| n.pred_flag[l] && n.ref_long_term[l] == long_term)
+        .map(|l| {
+          if long_term {
+            n.mv[l]
+          } else {
+            scale_mv(n.mv[l], poc - n.ref_poc[l], poc - ref_poc)
+          }
+        })
+    };
+
+    let a = [
+      neighbour(x_pb - 1, y_pb + height),
+      neighbour(x_pb - 1, y_pb + height - 1),
+    ];
+    let is_scaled_flag = a.iter().any(Option::is_some);
+    let mut mv_a = a
+      .into_iter()
+      .flatten()
+      .find_map(same_picture)
+      .or_else(|| a.into_iter().flatten().find_map(scaled));
+    let b = [
+      neighbour(x_pb + width, y_pb - 1),
+      neighbour(x_pb + width - 1, y_pb - 1),
+      neighbour(x_pb - 1, y_pb - 1),
+    ];
+    let mut mv_b = b.into_iter().flatten().find_map(same_picture);
+    if !is_scaled_flag {
+      mv_a = mv_a.or(mv_b);
+      mv_b = b.into_iter().flatten().find_map(scaled);
+    }
+
+    let mut mvp_list = Vec::with_capacity(3);
+    mvp_list.extend(mv_a);
+    mvp_list.extend(mv_b.filter(|mv_b| Some(*mv_b) != mv_a));
+    if mvp_list.len() < 2 {
+      mvp_list.extend(self.temporal_luma_mv(pb, x, ref_idx));
+    }
+    mvp_list.resize(2, [0; 2]);
+    Ok([mvp_list[0], mvp_list[1]])
+  }
+
+  /// 8.5.3.2.8 Derivation process for temporal luma motion vector prediction from the bottom
+  /// right or the center of the prediction block in the collocated picture
+  fn temporal_luma_mv(
+    &self,
+    [x_pb, y_pb, width, height]: [u32; 4],
+    x: usize,
+    ref_idx: u8,
+  ) -> Option<[i32; 2]> {
+    if !self.slice_temporal_mvp_enabled_flag {
+      return None;
+    }
+    let col_list = if self.slice_type.is_bidirectional() && !self.collocated_from_l0_flag {
+      1
+    } else {
+      0
+    };
+    let col_pic = *self.ref_pic_list[col_list].get(self.collocated_ref_idx as usize)?;
+    let (x_col_br, y_col_br) = (x_pb + width, y_pb + height);
+    let log2_ctb_size = self.picture.log2_ctb_size;
+    let bottom_right = if y_pb >> log2_ctb_size == y_col_br >> log2_ctb_size
+      && y_col_br < self.picture.height
+      && x_col_br < self.picture.width
+    {
+      self.collocated_mv(col_pic, x_col_br, y_col_br, x, ref_idx)
+    } else {
+      None
+    };
+    bottom_right.or_else(|| {
+      self.collocated_mv(
+        col_pic,
+        x_pb + (width >> 1),
+        y_pb + (height >> 1),
+        x,
+        ref_idx,
+      )
+    })
+  }
+
+  /// 8.5.3.2.9 Derivation process for collocated motion vectors, the motion of the collocated
+  /// picture is stored at a 16x16 granularity
+  fn collocated_mv(
+    &self,
+    col_pic: &DecodedPicture,
+    x_col: u32,
+    y_col: u32,
+    x: usize,
+    ref_idx: u8,
+  ) -> Option<[i32; 2]> {
+    let col = col_pic
+      .picture
+      .block((x_col >> 4) << 4, (y_col >> 4) << 4)
+      .mv_field;
+    let list_col = match col.pred_flag {
+      [false, false] => return None,
+      [false, true] => 1,
+      [true, false] => 0,
+      [true, true] => {
+        // NoBackwardPredFlag, no reference picture follows the current one
+        let poc = self.picture.pic_order_cnt_val;
+        let no_backward_pred_flag = self
+          .ref_pic_list
+          .iter()
+          .flatten()
+          .all(|reference| reference.poc() <= poc);
+        if no_backward_pred_flag {
+          x
+        } else {
+          self.collocated_from_l0_flag as usize
+        }
+      }
+    };
+    let reference = self.ref_pic(x, ref_idx).ok()?;
+    if reference.is_long_term() != col.ref_long_term[list_col] {
+      return None;
+    }
+    let col_poc_diff = col_pic.poc() - col.ref_poc[list_col];
+    let curr_poc_diff = self.picture.pic_order_cnt_val - reference.poc();
+    if reference.is_long_term() || col_poc_diff == curr_poc_diff {
+      Some(col.mv[list_col])
+    } else {
+      Some(scale_mv(col.mv[list_col], col_poc_diff, curr_poc_diff))
+    }
+  }
+}
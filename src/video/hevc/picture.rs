@@ -0,0 +1,432 @@
+use super::{
+  cabac::CabacContext,
+  slice::{coding_unit::PredMode, header::SliceSegmentHeader},
+  HevcError, HevcResult,
+};
+use crate::video::{
+  atom::{HevcPictureParameterSet, HevcSequenceParameterSet},
+  frame::Frame,
+};
+
+/// Picture being decoded: the tile and z-scan layout of 6.5 and the state slice segments share
+/// through the coding tree neighbours
+#[derive(Debug)]
+pub struct Picture {
+  pub width: u32,
+  pub height: u32,
+  pub log2_ctb_size: u8,
+  pub log2_min_tb_size: u8,
+  pub pic_width_in_ctbs: u32,
+  /// 6-5 CtbAddrRsToTs
+  pub ctb_addr_rs_to_ts: Box<[u32]>,
+  /// 6-6 CtbAddrTsToRs
+  pub ctb_addr_ts_to_rs: Box<[u32]>,
+  /// 6-7 TileId, indexed by the tile scan address
+  pub tile_id: Box<[u32]>,
+  /// 6-10 MinTbAddrZs of every minimum transform block of the CTB aligned picture
+  pub min_tb_addr_zs: Box<[u32]>,
+  /// SliceAddrRs of the slice containing every CTB, `None` until it is decoded
+  pub ctb_slice_addr: Box<[Option<u32>]>,
+  /// SAO parameters of every CTB for luma, Cb and Cr
+  pub sao: Box<[[SaoParameters; 3]]>,
+  /// In-loop filter parameters of the slice containing every CTB
+  pub loop_filter: Box<[LoopFilterParameters]>,
+  /// Coding tree information of every 4x4 luma block
+  pub blocks: Box<[BlockInfo]>,
+  /// Reconstructed samples of the luma and chroma components, or of the three colour planes when
+  /// they are coded separately
+  pub planes: Box<[Plane]>,
+  /// ChromaArrayType, 0 for monochrome pictures and separately coded colour planes
+  pub chroma_array_type: u8,
+  pub pcm_loop_filter_disabled_flag: bool,
+  pub loop_filter_across_tiles_enabled_flag: bool,
+  /// cQpPicOffset of Cb and Cr, pps_cb_qp_offset and pps_cr_qp_offset
+  pub c_qp_pic_offset: [i32; 2],
+  /// PicOrderCntVal
+  pub pic_order_cnt_val: i32,
+  /// PicOutputFlag
+  pub pic_output_flag: bool,
+  /// Header of the last independent slice segment
+  pub slice_header: Option<SliceSegmentHeader>,
+  /// qPY_PREV of the next quantization group, carried over to dependent slice segments
+  pub qp_y_prev: i8,
+  /// 9.3.2.4 context variables stored after the second CTB of a row for WPP
+  pub wpp_contexts: Option<CabacContext>,
+  /// Context variables stored at the end of a slice segment for the next dependent one
+  pub dependent_contexts: Option<CabacContext>,
+}
+
+impl Picture {
+  pub fn new(sps: &HevcSequenceParameterSet, pps: &HevcPictureParameterSet) -> HevcResult<Self> {
+    let log2_ctb_size = sps.log2_ctb_size();
+    let log2_min_tb_size = sps.log2_min_luma_transform_block_size_minus2 + 2;
+    if log2_ctb_size > 6 || log2_min_tb_size >= sps.log2_min_cb_size() {
+      return Err(HevcError::Invalid("coding tree block size"));
+    }
+    let width = sps.pic_width_in_luma_samples;
+    let height = sps.pic_height_in_luma_samples;
+    let pic_width_in_ctbs = width.div_ceil(1 << log2_ctb_size);
+    let pic_height_in_ctbs = height.div_ceil(1 << log2_ctb_size);
+    if width == 0 || height == 0 {
+      return Err(HevcError::Invalid("picture size"));
+    }
+    let pic_size_in_ctbs = (pic_width_in_ctbs * pic_height_in_ctbs) as usize;
+
+    // 6.5.1 CTB raster and tile scanning conversion
+    let (col_width, row_height) = match &pps.tiles {
+      Some(tiles) => (
+        Self::tile_sizes(
+          pic_width_in_ctbs,
+          tiles.num_tile_columns_minus1 as u32 + 1,
+          tiles.column_width_minus1.as_deref(),
+        )?,
+        Self::tile_sizes(
+          pic_height_in_ctbs,
+          tiles.num_tile_rows_minus1 as u32 + 1,
+          tiles.row_height_minus1.as_deref(),
+        )?,
+      ),
+      None => (vec![pic_width_in_ctbs], vec![pic_height_in_ctbs]),
+    };
+    let boundaries = |sizes: &[u32]| {
+      std::iter::once(0)
+        .chain(sizes.iter().scan(0, |bd, size| {
+          *bd += size;
+          Some(*bd)
+        }))
+        .collect::<Box<[u32]>>()
+    };
+    // 6-3 colBd and 6-4 rowBd, the first CTB of every tile column and row and the picture size last
+    let col_bd = boundaries(&col_width);
+    let row_bd = boundaries(&row_height);
+
+    let mut ctb_addr_rs_to_ts = vec![0; pic_size_in_ctbs];
+    let mut ctb_addr_ts_to_rs = vec![0; pic_size_in_ctbs];
+    for (ctb_addr_rs, ctb_addr_ts) in ctb_addr_rs_to_ts.iter_mut().enumerate() {
+      let tb_x = ctb_addr_rs as u32 % pic_width_in_ctbs;
+      let tb_y = ctb_addr_rs as u32 / pic_width_in_ctbs;
+      let tile_x = col_bd
+        .iter()
+        .rposition(|&bd| tb_x >= bd)
+        .unwrap_or_default();
+      let tile_y = row_bd
+        .iter()
+        .rposition(|&bd| tb_y >= bd)
+        .unwrap_or_default();
+      *ctb_addr_ts = col_width[..tile_x]
+        .iter()
+        .map(|width| row_height[tile_y] * width)
+        .sum::<u32>()
+        + row_height[..tile_y]
+          .iter()
+          .map(|height| pic_width_in_ctbs * height)
+          .sum::<u32>()
+        + (tb_y - row_bd[tile_y]) * col_width[tile_x]
+        + tb_x
+        - col_bd[tile_x];
+      ctb_addr_ts_to_rs[*ctb_addr_ts as usize] = ctb_addr_rs as u32;
+    }
+    let mut tile_id = vec![0; pic_size_in_ctbs];
+    let mut tile_idx = 0;
+    for rows in row_bd.windows(2) {
+      for cols in col_bd.windows(2) {
+        for y in rows[0]..rows[1] {
+          for x in cols[0]..cols[1] {
+            tile_id[ctb_addr_rs_to_ts[(y * pic_width_in_ctbs + x) as usize] as usize] = tile_idx;
+          }
+        }
+        tile_idx += 1;
+      }
+    }
+
+    // 6.5.2 Z-scan order array initialization
+    let log2_diff = (log2_ctb_size - log2_min_tb_size) as u32;
+    let width_in_min_tbs = pic_width_in_ctbs << log2_diff;
+    let height_in_min_tbs = pic_height_in_ctbs << log2_diff;
+    let min_tb_addr_zs = (0..height_in_min_tbs)
+      .flat_map(|y| (0..width_in_min_tbs).map(move |x| (x, y)))
+      .map(|(x, y)| {
+        let ctb_addr_rs = pic_width_in_ctbs * (y >> log2_diff) + (x >> log2_diff);
+        let p = (0..log2_diff)
+          .map(|i| {
+            let m = 1 << i;
+            (if m & x != 0 { m * m } else { 0 }) + (if m & y != 0 { 2 * m * m } else { 0 })
+          })
+          .sum::<u32>();
+        (ctb_addr_rs_to_ts[ctb_addr_rs as usize] << (log2_diff * 2)) + p
+      })
+      .collect();
+
+    let (sub_width_c, sub_height_c) = sps.chroma_subsampling();
+    let planes = match sps.chroma_format_idc {
+      0 => vec![Plane::new(width, height, sps.bit_depth_luma())],
+      _ if sps.separate_colour_plane_flag => {
+        vec![Plane::new(width, height, sps.bit_depth_luma()); 3]
+      }
+      _ => vec![
+        Plane::new(width, height, sps.bit_depth_luma()),
+        Plane::new(
+          width / sub_width_c,
+          height / sub_height_c,
+          sps.bit_depth_chroma(),
+        ),
+        Plane::new(
+          width / sub_width_c,
+          height / sub_height_c,
+          sps.bit_depth_chroma(),
+        ),
+      ],
+    };
+
+    let blocks = ((pic_width_in_ctbs * pic_height_in_ctbs) << ((log2_ctb_size - 2) * 2)) as usize;
+    Ok(Self {
+      width,
+      height,
+      log2_ctb_size,
+      log2_min_tb_size,
+      pic_width_in_ctbs,
+      ctb_addr_rs_to_ts: ctb_addr_rs_to_ts.into_boxed_slice(),
+      ctb_addr_ts_to_rs: ctb_addr_ts_to_rs.into_boxed_slice(),
+      tile_id: tile_id.into_boxed_slice(),
+      min_tb_addr_zs,
+      ctb_slice_addr: vec![None; pic_size_in_ctbs].into_boxed_slice(),
+      sao: vec![[SaoParameters::default(); 3]; pic_size_in_ctbs].into_boxed_slice(),
+      loop_filter: vec![LoopFilterParameters::default(); pic_size_in_ctbs].into_boxed_slice(),
+      blocks: vec![BlockInfo::default(); blocks].into_boxed_slice(),
+      planes: planes.into_boxed_slice(),
+      chroma_array_type: if sps.separate_colour_plane_flag {
+        0
+      } else {
+        sps.chroma_format_idc
+      },
+      pcm_loop_filter_disabled_flag: sps
+        .pcm
+        .as_ref()
+        .is_some_and(|pcm| pcm.loop_filter_disabled_flag),
+      loop_filter_across_tiles_enabled_flag: pps
+        .tiles
+        .as_ref()
+        .is_none_or(|tiles| tiles.loop_filter_across_tiles_enabled_flag),
+      c_qp_pic_offset: [pps.cb_qp_offset as i32, pps.cr_qp_offset as i32],
+      pic_order_cnt_val: 0,
+      pic_output_flag: true,
+      slice_header: None,
+      qp_y_prev: 0,
+      wpp_contexts: None,
+      dependent_contexts: None,
+    })
+  }
+
+  /// Widths of the tile columns or heights of the tile rows in CTBs, uniformly spaced when
+  /// `explicit` is `None`
+  fn tile_sizes(size: u32, count: u32, explicit: Option<&[u16]>) -> HevcResult<Vec<u32>> {
+    if count > size {
+      return Err(HevcError::Invalid("tile count"));
+    }
+    let Some(explicit) = explicit else {
+      return Ok(
+        (0..count)
+          .map(|i| ((i + 1) * size) / count - (i * size) / count)
+          .collect(),
+      );
+    };
+    let mut sizes = explicit
+      .iter()
+      .map(|size_minus1| *size_minus1 as u32 + 1)
+      .collect::<Vec<_>>();
+    let last = size
+      .checked_sub(sizes.iter().sum())
+      .filter(|last| *last > 0)
+      .ok_or(HevcError::Invalid("tile sizes"))?;
+    sizes.push(last);
+    Ok(sizes)
+  }
+
+  pub fn pic_width_in_min_tbs(&self) -> u32 {
+    self.pic_width_in_ctbs << (self.log2_ctb_size - self.log2_min_tb_size)
+  }
+
+  fn min_tb_addr(&self, x: u32, y: u32) -> u32 {
+    let (x, y) = (x >> self.log2_min_tb_size, y >> self.log2_min_tb_size);
+    self.min_tb_addr_zs[(y * self.pic_width_in_min_tbs() + x) as usize]
+  }
+
+  /// Raster scan address of the CTB containing the luma location
+  pub fn ctb_addr_rs(&self, x: u32, y: u32) -> u32 {
+    (y >> self.log2_ctb_size) * self.pic_width_in_ctbs + (x >> self.log2_ctb_size)
+  }
+
+  /// 6.4.1 Availability of the neighbouring block at (`x_nb`, `y_nb`) for the current block at
+  /// (`x_curr`, `y_curr`) in z-scan order: decoded, in the same slice and in the same tile
+  pub fn available(&self, x_curr: u32, y_curr: u32, x_nb: i32, y_nb: i32) -> bool {
+    if x_nb < 0 || y_nb < 0 || x_nb as u32 >= self.width || y_nb as u32 >= self.height {
+      return false;
+    }
+    let (x_nb, y_nb) = (x_nb as u32, y_nb as u32);
+    if self.min_tb_addr(x_nb, y_nb) > self.min_tb_addr(x_curr, y_curr) {
+      return false;
+    }
+    let ctb_curr = self.ctb_addr_rs(x_curr, y_curr) as usize;
+    let ctb_nb = self.ctb_addr_rs(x_nb, y_nb) as usize;
+    match self.ctb_slice_addr[ctb_nb] {
+      Some(slice_addr) => {
+        Some(slice_addr) == self.ctb_slice_addr[ctb_curr]
+          && self.tile_id[self.ctb_addr_rs_to_ts[ctb_nb] as usize]
+            == self.tile_id[self.ctb_addr_rs_to_ts[ctb_curr] as usize]
+      }
+      None => false,
+    }
+  }
+
+  fn block_index(&self, x: u32, y: u32) -> usize {
+    ((y >> 2) * (self.pic_width_in_ctbs << (self.log2_ctb_size - 2)) + (x >> 2)) as usize
+  }
+
+  pub fn block(&self, x: u32, y: u32) -> &BlockInfo {
+    &self.blocks[self.block_index(x, y)]
+  }
+
+  pub fn block_mut(&mut self, x: u32, y: u32) -> &mut BlockInfo {
+    let index = self.block_index(x, y);
+    &mut self.blocks[index]
+  }
+
+  /// Sets the information of every 4x4 block of the `width` x `height` area at (`x0`, `y0`)
+  pub fn set_blocks(&mut self, x0: u32, y0: u32, width: u32, height: u32, block: BlockInfo) {
+    for y in (y0..y0 + height).step_by(4) {
+      for x in (x0..x0 + width).step_by(4) {
+        let index = self.block_index(x, y);
+        self.blocks[index] = block;
+      }
+    }
+  }
+
+  /// Sets the motion of every 4x4 block of a prediction block
+  pub fn set_mv_field(&mut self, x0: u32, y0: u32, width: u32, height: u32, mv_field: MvField) {
+    for y in (y0..y0 + height).step_by(4) {
+      for x in (x0..x0 + width).step_by(4) {
+        let index = self.block_index(x, y);
+        self.blocks[index].mv_field = mv_field;
+      }
+    }
+  }
+
+  /// SubWidthC and SubHeightC of the chroma planes
+  pub fn chroma_subsampling(&self) -> (u32, u32) {
+    match self.chroma_array_type {
+      1 => (2, 2),
+      2 => (2, 1),
+      _ => (1, 1),
+    }
+  }
+
+  /// Frame of the reconstructed samples reduced to 8 bits
+  pub fn frame(&self) -> Frame {
+    let luma = &self.planes[0];
+    let chroma = self.planes.get(1);
+    let mut frame = Frame::with_size(
+      luma.width as usize,
+      luma.height as usize,
+      chroma.map(|plane| plane.width).unwrap_or_default() as usize,
+      chroma.map(|plane| plane.height).unwrap_or_default() as usize,
+    );
+    let components = [
+      &mut frame.luma_data,
+      &mut frame.chroma_cb_data,
+      &mut frame.chroma_cr_data,
+    ];
+    for (plane, data) in self.planes.iter().zip(components) {
+      let shift = plane.bit_depth - 8;
+      for y in 0..plane.height {
+        for x in 0..plane.width {
+          data[x as usize][y as usize] = (plane.get(x, y) >> shift) as u8;
+        }
+      }
+    }
+    frame
+  }
+}
+
+/// Samples of a colour component in raster order
+#[derive(Debug, Clone)]
+pub struct Plane {
+  pub width: u32,
+  pub height: u32,
+  pub bit_depth: u8,
+  pub samples: Box<[u16]>,
+}
+
+impl Plane {
+  pub fn new(width: u32, height: u32, bit_depth: u8) -> Self {
+    Self {
+      width,
+      height,
+      bit_depth,
+      samples: vec![0; (width * height) as usize].into_boxed_slice(),
+    }
+  }
+
+  pub fn get(&self, x: u32, y: u32) -> u16 {
+    self.samples[(y * self.width + x) as usize]
+  }
+
+  pub fn set(&mut self, x: u32, y: u32, sample: u16) {
+    self.samples[(y * self.width + x) as usize] = sample;
+  }
+}
+
+/// Coding unit information the CABAC contexts and the predictions of neighbouring blocks depend on
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockInfo {
+  /// CtDepth
+  pub ct_depth: u8,
+  pub pred_mode: PredMode,
+  pub pcm_flag: bool,
+  pub cu_transquant_bypass_flag: bool,
+  /// IntraPredModeY, 1 (DC) for blocks that are not intra predicted
+  pub intra_pred_mode_y: u8,
+  /// QpY of the coding unit
+  pub qp_y: i8,
+  /// Motion of the prediction block, not predicted from any list for intra blocks
+  pub mv_field: MvField,
+  /// The luma transform block contains non-zero coefficients
+  pub cbf_luma: bool,
+  /// bS of the vertical edge on the left and of the horizontal edge on top of the block, 0 when
+  /// they are not deblocked
+  pub bs: [u8; 2],
+}
+
+/// Motion vectors and reference indices of a prediction block, with the PicOrderCntVal and
+/// long-term marking of its reference pictures for the temporal prediction of later pictures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MvField {
+  /// predFlagL0 and predFlagL1
+  pub pred_flag: [bool; 2],
+  /// refIdxL0 and refIdxL1
+  pub ref_idx: [u8; 2],
+  /// mvL0 and mvL1, horizontal component first
+  pub mv: [[i32; 2]; 2],
+  pub ref_poc: [i32; 2],
+  pub ref_long_term: [bool; 2],
+}
+
+/// Deblocking and SAO controls of the slice segment header
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoopFilterParameters {
+  pub slice_beta_offset_div2: i8,
+  pub slice_tc_offset_div2: i8,
+  pub slice_loop_filter_across_slices_enabled_flag: bool,
+}
+
+/// 7.3.8.3 sample_adaptive_offset syntax of a colour component with the offsets derived in
+/// 7.4.9.3.2
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SaoParameters {
+  /// SaoTypeIdx: 0 not applied, 1 band offset, 2 edge offset
+  pub type_idx: u8,
+  /// SaoOffsetVal, the first entry is always 0
+  pub offset_val: [i16; 5],
+  pub band_position: u8,
+  /// SaoEoClass
+  pub eo_class: u8,
+}
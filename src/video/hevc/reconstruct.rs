@@ -0,0 +1,197 @@
+use super::{
+  intra::intra_sample_prediction,
+  slice::{
+    coding_unit::{CodingUnit, PcmSample, TransformUnit},
+    Slice,
+  },
+  HevcResult,
+};
+
+impl Slice<'_> {
+  /// 8.4 and 8.5 Decodes the samples of a coding unit into the picture prior to the in-loop
+  /// filters and derives the boundary filtering strengths of its edges
+  pub fn reconstruct(&mut self, cu: &CodingUnit) -> HevcResult {
+    if let Some(pcm_sample) = &cu.pcm_sample {
+      self.pcm_sample_construction(cu, pcm_sample);
+      self.boundary_strengths(cu);
+      return Ok(());
+    }
+    if !cu.pred_mode.is_intra() {
+      // 8.5.3 The motion of every prediction unit is stored before the next one is derived
+      for (part_idx, pu) in cu.prediction_units.iter().enumerate() {
+        let mv_field = self.mv_field(cu, pu, part_idx)?;
+        self
+          .picture
+          .set_mv_field(pu.x0, pu.y0, pu.width, pu.height, mv_field);
+        self.inter_sample_prediction([pu.x0, pu.y0, pu.width, pu.height], &mv_field)?;
+      }
+    }
+    for tu in &cu.transform_units {
+      self.transform_unit(cu, tu);
+    }
+    self.boundary_strengths(cu);
+    Ok(())
+  }
+
+  /// Index of the picture plane of colour component `c_idx`
+  pub fn plane_index(&self, c_idx: u8) -> usize {
+    if self.sps.separate_colour_plane_flag {
+      self.colour_plane_id as usize
+    } else {
+      c_idx as usize
+    }
+  }
+
+  /// 8.4.4.1 and 8.5.2 Decodes the luma and chroma blocks of a transform unit
+  fn transform_unit(&mut self, cu: &CodingUnit, tu: &TransformUnit) {
+    let log2_trafo_size = tu.log2_trafo_size;
+    let partition = cu.partition_index(tu.x0, tu.y0);
+    let mode = cu.intra_pred_mode_y[partition];
+    let residual_y = self.transform_block_residual(cu, tu, 0, tu.y0, mode);
+    self.block_reconstruction(
+      cu,
+      0,
+      [tu.x0, tu.y0],
+      log2_trafo_size,
+      mode,
+      residual_y.as_deref(),
+    );
+    if self.chroma_array_type == 0 {
+      return;
+    }
+
+    // 4x4 luma blocks of 4:2:0 and 4:2:2 share the chroma blocks decoded with the fourth one
+    let ([x0, y0], log2_trafo_size_c) = if self.chroma_array_type == 3 {
+      ([tu.x0, tu.y0], log2_trafo_size)
+    } else if log2_trafo_size > 2 {
+      ([tu.x0, tu.y0], log2_trafo_size - 1)
+    } else if tu.blk_idx == 3 {
+      ([tu.x_base, tu.y_base], 2)
+    } else {
+      return;
+    };
+    let (sub_width_c, sub_height_c) = self.sps.chroma_subsampling();
+    let mode = cu.intra_pred_mode_c[partition];
+    for c_idx in 1..3 {
+      let blocks = if self.chroma_array_type == 2 { 2 } else { 1 };
+      for t_idx in 0..blocks {
+        let y = y0 + (t_idx << log2_trafo_size_c);
+        let mut residual = self.transform_block_residual(cu, tu, c_idx, y, mode);
+        // 8.6.6 Residual modification process for transform blocks using cross-component
+        // prediction
+        let res_scale_val = tu.res_scale_val[c_idx as usize - 1] as i32;
+        if let (true, Some(residual_y)) = (res_scale_val != 0, &residual_y) {
+          let bit_depth_y = self.sps.bit_depth_luma();
+          let bit_depth_c = self.sps.bit_depth_chroma();
+          let residual = residual.get_or_insert_with(|| vec![0; residual_y.len()]);
+          for (r, r_y) in residual.iter_mut().zip(residual_y) {
+            *r += (res_scale_val * ((r_y << bit_depth_c) >> bit_depth_y)) >> 3;
+          }
+        }
+        self.block_reconstruction(
+          cu,
+          c_idx,
+          [x0 / sub_width_c, y / sub_height_c],
+          log2_trafo_size_c,
+          mode,
+          residual.as_deref(),
+        );
+      }
+    }
+  }
+
+  /// Residual samples of the transform block of component `c_idx` coded at the luma row `y0` of
+  /// the transform unit, `None` when its coded block flag is 0
+  fn transform_block_residual(
+    &self,
+    cu: &CodingUnit,
+    tu: &TransformUnit,
+    c_idx: u8,
+    y0: u32,
+    pred_mode_intra: u8,
+  ) -> Option<Vec<i32>> {
+    tu.residuals
+      .iter()
+      .find(|block| block.c_idx == c_idx && block.y0 == y0)
+      .map(|block| self.residual_samples(cu, block, pred_mode_intra))
+  }
+
+  /// 8.4.4.2 and 8.6.7 Adds the residual samples to the prediction of a square block of component
+  /// samples at `[x, y]`, intra blocks are predicted first and inter ones already are in the
+  /// picture
+  fn block_reconstruction(
+    &mut self,
+    cu: &CodingUnit,
+    c_idx: u8,
+    [x_tb, y_tb]: [u32; 2],
+    log2_size: u8,
+    pred_mode_intra: u8,
+    residual: Option<&[i32]>,
+  ) {
+    let size = 1 << log2_size;
+    let plane_index = self.plane_index(c_idx);
+    let pred = if cu.pred_mode.is_intra() {
+      intra_sample_prediction(
+        self,
+        c_idx,
+        x_tb,
+        y_tb,
+        log2_size,
+        pred_mode_intra,
+        cu.cu_transquant_bypass_flag,
+      )
+    } else if residual.is_some() {
+      let plane = &self.picture.planes[plane_index];
+      (0..size * size)
+        .map(|i| plane.get(x_tb + (i % size) as u32, y_tb + (i / size) as u32) as i32)
+        .collect()
+    } else {
+      return;
+    };
+    let plane = &mut self.picture.planes[plane_index];
+    let max = (1 << plane.bit_depth) - 1;
+    for (i, pred) in pred.into_iter().enumerate() {
+      let sample = pred + residual.map(|r| r[i]).unwrap_or_default();
+      plane.set(
+        x_tb + (i % size) as u32,
+        y_tb + (i / size) as u32,
+        sample.clamp(0, max) as u16,
+      );
+    }
+  }
+
+  /// 8.4.4.1 Sample construction of a PCM coding unit
+  fn pcm_sample_construction(&mut self, cu: &CodingUnit, pcm_sample: &PcmSample) {
+    let Some(pcm) = &self.sps.pcm else {
+      return;
+    };
+    let size = 1 << cu.log2_cb_size;
+    let (sub_width_c, sub_height_c) = self.sps.chroma_subsampling();
+    let (width_c, height_c) = (size / sub_width_c, size / sub_height_c);
+    let mut components = vec![(0, size, &pcm_sample.luma[..])];
+    if self.chroma_array_type != 0 {
+      let (cb, cr) = pcm_sample.chroma.split_at((width_c * height_c) as usize);
+      components.push((1, width_c, cb));
+      components.push((2, width_c, cr));
+    }
+    for (c_idx, width, samples) in components {
+      let pcm_bit_depth = if c_idx == 0 {
+        pcm.sample_bit_depth_luma_minus1 + 1
+      } else {
+        pcm.sample_bit_depth_chroma_minus1 + 1
+      };
+      let (x0, y0) = if c_idx == 0 {
+        (cu.x0, cu.y0)
+      } else {
+        (cu.x0 / sub_width_c, cu.y0 / sub_height_c)
+      };
+      let plane_index = self.plane_index(c_idx);
+      let plane = &mut self.picture.planes[plane_index];
+      let shift = plane.bit_depth.saturating_sub(pcm_bit_depth);
+      for (i, sample) in samples.iter().enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        plane.set(x0 + x, y0 + y, sample << shift);
+      }
+    }
+  }
+}
@@ -0,0 +1,117 @@
+use super::picture::{Picture, Plane, SaoParameters};
+
+/// 8.7.3.2 hPos and vPos of the two neighbouring samples of every SaoEoClass
+#[rustfmt::skip]
+const EDGE_OFFSET_NEIGHBOURS: [[[i32; 2]; 2]; 4] = [
+  [[-1, 0], [1, 0]],
+  [[0, -1], [0, 1]],
+  [[-1, -1], [1, 1]],
+  [[1, -1], [-1, 1]],
+];
+
+impl Picture {
+  /// 8.7.3 Sample adaptive offset process of the deblocked picture
+  pub fn sample_adaptive_offset(&mut self) {
+    if self.sao.iter().flatten().all(|sao| sao.type_idx == 0) {
+      return;
+    }
+    let deblocked = self.planes.clone();
+    for ctb_addr_rs in 0..self.sao.len() {
+      for (plane_index, plane) in deblocked.iter().enumerate() {
+        // Separately coded colour planes use the parameters of luma
+        let c_idx = if self.chroma_array_type == 0 {
+          0
+        } else {
+          plane_index
+        };
+        let sao = self.sao[ctb_addr_rs][c_idx];
+        if sao.type_idx != 0 {
+          self.ctb_modification(plane_index, ctb_addr_rs as u32, &sao, plane);
+        }
+      }
+    }
+  }
+
+  /// 8.7.3.2 CTB modification process of the samples of a colour plane from the `deblocked` ones
+  fn ctb_modification(
+    &mut self,
+    plane_index: usize,
+    ctb_addr_rs: u32,
+    sao: &SaoParameters,
+    deblocked: &Plane,
+  ) {
+    let (sub_width, sub_height) = if plane_index == 0 || self.chroma_array_type == 0 {
+      (1, 1)
+    } else {
+      self.chroma_subsampling()
+    };
+    let ctb_size = 1 << self.log2_ctb_size;
+    let x_ctb = (ctb_addr_rs % self.pic_width_in_ctbs) * ctb_size / sub_width;
+    let y_ctb = (ctb_addr_rs / self.pic_width_in_ctbs) * ctb_size / sub_height;
+    let x_end = (x_ctb + ctb_size / sub_width).min(deblocked.width);
+    let y_end = (y_ctb + ctb_size / sub_height).min(deblocked.height);
+    let bit_depth = deblocked.bit_depth;
+    let max = (1 << bit_depth) - 1;
+
+    let mut band_table = [0; 32];
+    for k in 0..4 {
+      band_table[(k + sao.band_position as usize) & 31] = k + 1;
+    }
+    let neighbours = EDGE_OFFSET_NEIGHBOURS[sao.eo_class as usize];
+
+    for y in y_ctb..y_end {
+      for x in x_ctb..x_end {
+        let luma = [x * sub_width, y * sub_height];
+        let block = self.block(luma[0], luma[1]);
+        if (block.pcm_flag && self.pcm_loop_filter_disabled_flag) || block.cu_transquant_bypass_flag
+        {
+          continue;
+        }
+        let sample = deblocked.get(x, y) as i32;
+        let idx = if sao.type_idx == 1 {
+          band_table[(sample >> (bit_depth - 5)) as usize]
+        } else {
+          let neighbours = neighbours.map(|[h, v]| [x as i32 + h, y as i32 + v]);
+          if !neighbours.iter().all(|&[x_nb, y_nb]| {
+            self.edge_offset_neighbour(luma, [x_nb * sub_width as i32, y_nb * sub_height as i32])
+          }) {
+            continue;
+          }
+          let sign = |[x_nb, y_nb]: [i32; 2]| {
+            (sample - deblocked.get(x_nb as u32, y_nb as u32) as i32).signum()
+          };
+          // edgeIdx 0, 1 and 2 are mapped to 1, 2 and 0
+          match 2 + sign(neighbours[0]) + sign(neighbours[1]) {
+            2 => 0,
+            edge_idx @ (0 | 1) => edge_idx as usize + 1,
+            edge_idx => edge_idx as usize,
+          }
+        };
+        let offset = sao.offset_val[idx] as i32;
+        self.planes[plane_index].set(x, y, (sample + offset).clamp(0, max) as u16);
+      }
+    }
+  }
+
+  /// 8.7.3.2 The sample at the luma location `[x_nb, y_nb]` is inside the picture and is not
+  /// across a slice or tile boundary the in-loop filters are disabled on from the luma location
+  /// `[x, y]`
+  fn edge_offset_neighbour(&self, [x, y]: [u32; 2], [x_nb, y_nb]: [i32; 2]) -> bool {
+    if x_nb < 0 || y_nb < 0 || x_nb as u32 >= self.width || y_nb as u32 >= self.height {
+      return false;
+    }
+    let ctb_curr = self.ctb_addr_rs(x, y) as usize;
+    let ctb_nb = self.ctb_addr_rs(x_nb as u32, y_nb as u32) as usize;
+    let ctb_addr_ts = |ctb_addr_rs: usize| self.ctb_addr_rs_to_ts[ctb_addr_rs] as usize;
+    // The flag of the slice decoded last applies to the boundary
+    let later = if ctb_addr_ts(ctb_nb) > ctb_addr_ts(ctb_curr) {
+      ctb_nb
+    } else {
+      ctb_curr
+    };
+    (self.ctb_slice_addr[ctb_nb] == self.ctb_slice_addr[ctb_curr]
+      || self.loop_filter[later].slice_loop_filter_across_slices_enabled_flag)
+      && (self.loop_filter_across_tiles_enabled_flag
+        || self.tile_id[ctb_addr_ts(ctb_nb)] == self.tile_id[ctb_addr_ts(ctb_curr)])
+  }
+}
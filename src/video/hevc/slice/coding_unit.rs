@@ -0,0 +1,205 @@
+use std::fmt;
+
+/// 7.4.9.5 CuPredMode, MODE_SKIP coding units are inter predicted from merge candidates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PredMode {
+  #[default]
+  Intra,
+  Inter,
+  Skip,
+}
+
+impl PredMode {
+  pub fn is_intra(&self) -> bool {
+    matches!(self, Self::Intra)
+  }
+}
+
+/// Table 7-10 part_mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PartMode {
+  #[default]
+  Part2Nx2N,
+  Part2NxN,
+  PartNx2N,
+  PartNxN,
+  Part2NxnU,
+  Part2NxnD,
+  PartnLx2N,
+  PartnRx2N,
+}
+
+impl PartMode {
+  /// Location and size of every prediction block of a `1 << log2_cb_size` coding block, in the
+  /// order of the prediction_unit syntax
+  pub fn prediction_blocks(&self, x0: u32, y0: u32, log2_cb_size: u8) -> Vec<[u32; 4]> {
+    let size = 1 << log2_cb_size;
+    let (half, quarter) = (size / 2, size / 4);
+    match self {
+      Self::Part2Nx2N => vec![[x0, y0, size, size]],
+      Self::Part2NxN => vec![[x0, y0, size, half], [x0, y0 + half, size, half]],
+      Self::PartNx2N => vec![[x0, y0, half, size], [x0 + half, y0, half, size]],
+      Self::Part2NxnU => vec![
+        [x0, y0, size, quarter],
+        [x0, y0 + quarter, size, size - quarter],
+      ],
+      Self::Part2NxnD => vec![
+        [x0, y0, size, size - quarter],
+        [x0, y0 + size - quarter, size, quarter],
+      ],
+      Self::PartnLx2N => vec![
+        [x0, y0, quarter, size],
+        [x0 + quarter, y0, size - quarter, size],
+      ],
+      Self::PartnRx2N => vec![
+        [x0, y0, size - quarter, size],
+        [x0 + size - quarter, y0, quarter, size],
+      ],
+      Self::PartNxN => vec![
+        [x0, y0, half, half],
+        [x0 + half, y0, half, half],
+        [x0, y0 + half, half, half],
+        [x0 + half, y0 + half, half, half],
+      ],
+    }
+  }
+}
+
+/// 7.3.8.5 coding_unit with the derived intra prediction modes and quantization parameters
+#[derive(Debug, Default)]
+pub struct CodingUnit {
+  pub x0: u32,
+  pub y0: u32,
+  pub log2_cb_size: u8,
+  pub cu_transquant_bypass_flag: bool,
+  pub pred_mode: PredMode,
+  pub part_mode: PartMode,
+  pub pcm_sample: Option<PcmSample>,
+  /// IntraPredModeY of every NxN partition, or of the whole coding unit first
+  pub intra_pred_mode_y: [u8; 4],
+  /// intra_chroma_pred_mode syntax element of every partition, 4 derives from luma
+  pub intra_chroma_pred_mode: [u8; 4],
+  /// IntraPredModeC of every partition, after the 4:2:2 mapping of Table 8-3
+  pub intra_pred_mode_c: [u8; 4],
+  pub prediction_units: Vec<PredictionUnit>,
+  pub transform_units: Vec<TransformUnit>,
+  /// QpY
+  pub qp_y: i8,
+  /// CuQpOffsetCb
+  pub cu_qp_offset_cb: i8,
+  /// CuQpOffsetCr
+  pub cu_qp_offset_cr: i8,
+}
+
+impl CodingUnit {
+  /// Location, size and IntraPredModeY of the luma partitions
+  pub fn intra_partitions(&self) -> Vec<(u32, u32, u32, u32, u8)> {
+    let size = 1 << self.log2_cb_size;
+    if self.part_mode == PartMode::PartNxN && self.pred_mode.is_intra() {
+      let half = size / 2;
+      (0..4)
+        .map(|i| {
+          let x = self.x0 + (i as u32 & 1) * half;
+          let y = self.y0 + (i as u32 >> 1) * half;
+          (x, y, half, half, self.intra_pred_mode_y[i])
+        })
+        .collect()
+    } else {
+      vec![(self.x0, self.y0, size, size, self.intra_pred_mode_y[0])]
+    }
+  }
+
+  /// Index of the NxN partition covering the luma location (`x`, `y`)
+  pub fn partition_index(&self, x: u32, y: u32) -> usize {
+    let half = 1 << (self.log2_cb_size - 1);
+    ((x - self.x0 >= half) as usize) + 2 * ((y - self.y0 >= half) as usize)
+  }
+}
+
+/// 7.3.8.7 pcm_sample, samples in raster order with the PCM bit depths
+#[derive(Debug)]
+pub struct PcmSample {
+  pub luma: Box<[u16]>,
+  /// Cb samples followed by Cr samples
+  pub chroma: Box<[u16]>,
+}
+
+/// 7.3.8.6 prediction_unit, the motion vectors are derived from the merge index or the
+/// predictor flags and differences
+#[derive(Debug, Default)]
+pub struct PredictionUnit {
+  pub x0: u32,
+  pub y0: u32,
+  pub width: u32,
+  pub height: u32,
+  pub merge_flag: bool,
+  pub merge_idx: u8,
+  pub inter_pred_idc: InterPredIdc,
+  pub ref_idx: [u8; 2],
+  pub mvp_flag: [bool; 2],
+  /// MvdL0 and MvdL1, horizontal component first
+  pub mvd: [[i32; 2]; 2],
+}
+
+/// Table 7-15 inter_pred_idc
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InterPredIdc {
+  #[default]
+  PredL0,
+  PredL1,
+  PredBi,
+}
+
+/// 7.3.8.10 transform_unit
+#[derive(Debug, Default)]
+pub struct TransformUnit {
+  pub x0: u32,
+  pub y0: u32,
+  /// Location of the parent block carrying the chroma residuals of 4x4 luma blocks
+  pub x_base: u32,
+  pub y_base: u32,
+  pub log2_trafo_size: u8,
+  pub blk_idx: u8,
+  pub cbf_luma: bool,
+  /// cbf_cb of the first and, in 4:2:2, the second chroma block
+  pub cbf_cb: [bool; 2],
+  pub cbf_cr: [bool; 2],
+  /// ResScaleVal of the cross-component prediction of Cb and Cr
+  pub res_scale_val: [i8; 2],
+  pub residuals: Vec<ResidualBlock>,
+}
+
+/// 7.3.8.11 residual_coding of a transform block
+pub struct ResidualBlock {
+  pub c_idx: u8,
+  pub x0: u32,
+  pub y0: u32,
+  pub log2_trafo_size: u8,
+  pub transform_skip_flag: bool,
+  /// explicit_rdpcm_dir_flag when explicit_rdpcm_flag is set
+  pub explicit_rdpcm_dir_flag: Option<bool>,
+  /// TransCoeffLevel in raster order
+  pub coefficients: Box<[i32]>,
+}
+
+impl fmt::Debug for ResidualBlock {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let size = 1 << self.log2_trafo_size;
+    let levels = self
+      .coefficients
+      .iter()
+      .enumerate()
+      .filter(|(_, level)| **level != 0)
+      .map(|(i, level)| format!("({},{})={level}", i % size, i / size))
+      .collect::<Vec<_>>();
+    f.debug_struct("ResidualBlock")
+      .field("c_idx", &self.c_idx)
+      .field("x0", &self.x0)
+      .field("y0", &self.y0)
+      .field("log2_trafo_size", &self.log2_trafo_size)
+      .field("transform_skip_flag", &self.transform_skip_flag)
+      .field("explicit_rdpcm_dir_flag", &self.explicit_rdpcm_dir_flag)
+      .field("coefficients", &levels.join(" "))
+      .finish()
+  }
+}
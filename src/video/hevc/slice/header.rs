@@ -0,0 +1,581 @@
+use super::super::{HevcError, HevcResult};
+use crate::{
+  byte::BitStream,
+  video::atom::{
+    HevcNalUnitHeader, HevcPictureParameterSet, HevcSequenceParameterSet, ShortTermRefPicSet,
+  },
+};
+use std::collections::HashMap;
+
+/// 7.4.7.1 Table 7-7 slice_type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceType {
+  B,
+  P,
+  I,
+}
+
+impl SliceType {
+  pub fn is_intra(&self) -> bool {
+    matches!(self, Self::I)
+  }
+
+  pub fn is_bidirectional(&self) -> bool {
+    matches!(self, Self::B)
+  }
+}
+
+/// 7.3.6.1 General slice segment header. Dependent slice segments copy the values of the
+/// preceding independent slice segment
+#[derive(Debug, Clone)]
+pub struct SliceSegmentHeader {
+  pub first_slice_segment_in_pic_flag: bool,
+  pub no_output_of_prior_pics_flag: bool,
+  pub slice_pic_parameter_set_id: u8,
+  pub dependent_slice_segment_flag: bool,
+  pub slice_segment_address: u32,
+  pub slice_type: SliceType,
+  pub pic_output_flag: bool,
+  pub colour_plane_id: u8,
+  pub slice_pic_order_cnt_lsb: u32,
+  pub short_term_ref_pic_set_sps_flag: bool,
+  /// Short-term reference picture set of the picture, signalled in the header or selected
+  /// from the SPS, empty for IDR pictures
+  pub short_term_ref_pic_set: ShortTermRefPicSet,
+  pub short_term_ref_pic_set_idx: u8,
+  pub long_term_ref_pics: Box<[LongTermRefPic]>,
+  pub slice_temporal_mvp_enabled_flag: bool,
+  pub slice_sao_luma_flag: bool,
+  pub slice_sao_chroma_flag: bool,
+  pub num_ref_idx_l0_active_minus1: u8,
+  pub num_ref_idx_l1_active_minus1: u8,
+  pub ref_pic_lists_modification: Option<RefPicListModification>,
+  pub mvd_l1_zero_flag: bool,
+  pub cabac_init_flag: bool,
+  pub collocated_from_l0_flag: bool,
+  pub collocated_ref_idx: u8,
+  pub pred_weight_table: Option<PredWeightTable>,
+  /// MaxNumMergeCand, 5 - five_minus_max_num_merge_cand
+  pub max_num_merge_cand: u8,
+  pub slice_qp_delta: i8,
+  pub slice_cb_qp_offset: i8,
+  pub slice_cr_qp_offset: i8,
+  pub cu_chroma_qp_offset_enabled_flag: bool,
+  pub deblocking_filter_override_flag: bool,
+  pub slice_deblocking_filter_disabled_flag: bool,
+  pub slice_beta_offset_div2: i8,
+  pub slice_tc_offset_div2: i8,
+  pub slice_loop_filter_across_slices_enabled_flag: bool,
+  /// Byte offsets of the tile and WPP substreams, emulation prevention bytes included
+  pub entry_point_offset_minus1: Box<[u32]>,
+}
+
+impl SliceSegmentHeader {
+  /// Decodes the header of a slice segment NAL unit and the byte_alignment() after it,
+  /// `previous` is the header of the last independent slice segment of the picture
+  pub fn decode(
+    data: &mut BitStream,
+    nal: HevcNalUnitHeader,
+    sps: &HashMap<u8, HevcSequenceParameterSet>,
+    pps: &HashMap<u8, HevcPictureParameterSet>,
+    previous: Option<&Self>,
+  ) -> HevcResult<Self> {
+    let first_slice_segment_in_pic_flag = data.bit_flag();
+    let no_output_of_prior_pics_flag = nal.is_irap() && data.bit_flag();
+    let slice_pic_parameter_set_id = data.exponential_golomb::<u16>();
+    let pps = u8::try_from(slice_pic_parameter_set_id)
+      .ok()
+      .and_then(|id| pps.get(&id))
+      .ok_or(HevcError::MissingParameterSet(
+        "PPS",
+        slice_pic_parameter_set_id as u32,
+      ))?;
+    let sps = sps
+      .get(&pps.seq_parameter_set_id)
+      .ok_or(HevcError::MissingParameterSet(
+        "SPS",
+        pps.seq_parameter_set_id as u32,
+      ))?;
+    let log2_ctb_size = sps.log2_ctb_size();
+    let pic_size_in_ctbs = sps.pic_width_in_luma_samples.div_ceil(1 << log2_ctb_size)
+      * sps.pic_height_in_luma_samples.div_ceil(1 << log2_ctb_size);
+
+    let mut dependent_slice_segment_flag = false;
+    let mut slice_segment_address = 0;
+    if !first_slice_segment_in_pic_flag {
+      dependent_slice_segment_flag = pps.dependent_slice_segments_enabled_flag && data.bit_flag();
+      slice_segment_address = data.bits_into(ceil_log2(pic_size_in_ctbs));
+      if slice_segment_address >= pic_size_in_ctbs {
+        return Err(HevcError::SliceHeader(
+          "slice_segment_address",
+          slice_segment_address,
+        ));
+      }
+    }
+
+    let mut header = if dependent_slice_segment_flag {
+      let Some(previous) = previous else {
+        return Err(HevcError::Invalid(
+          "dependent slice segment without an independent slice segment",
+        ));
+      };
+      previous.clone()
+    } else {
+      Self::decode_independent(data, nal, sps, pps)?
+    };
+    header.first_slice_segment_in_pic_flag = first_slice_segment_in_pic_flag;
+    header.no_output_of_prior_pics_flag = no_output_of_prior_pics_flag;
+    header.slice_pic_parameter_set_id = pps.id;
+    header.dependent_slice_segment_flag = dependent_slice_segment_flag;
+    header.slice_segment_address = slice_segment_address;
+
+    header.entry_point_offset_minus1 = Box::default();
+    if pps.tiles.is_some() || pps.entropy_coding_sync_enabled_flag {
+      let num_entry_point_offsets = data.exponential_golomb::<u32>();
+      if num_entry_point_offsets >= pic_size_in_ctbs {
+        return Err(HevcError::SliceHeader(
+          "num_entry_point_offsets",
+          num_entry_point_offsets,
+        ));
+      }
+      if num_entry_point_offsets > 0 {
+        let offset_len_minus1 = data.exponential_golomb::<u16>().min(31) as usize;
+        header.entry_point_offset_minus1 = (0..num_entry_point_offsets)
+          .map(|_| data.bits_into(offset_len_minus1 + 1))
+          .collect();
+      }
+    }
+    if pps.slice_segment_header_extension_present_flag {
+      let slice_segment_header_extension_length = data.exponential_golomb::<u16>();
+      data.skip_trailing_bits();
+      data.skip_bytes(slice_segment_header_extension_length as usize);
+    }
+    // byte_alignment()
+    if !data.bit_flag() {
+      return Err(HevcError::Invalid("alignment_bit_equal_to_one"));
+    }
+    data.skip_trailing_bits();
+    Ok(header)
+  }
+
+  fn decode_independent(
+    data: &mut BitStream,
+    nal: HevcNalUnitHeader,
+    sps: &HevcSequenceParameterSet,
+    pps: &HevcPictureParameterSet,
+  ) -> HevcResult<Self> {
+    let chroma_array_type = if sps.separate_colour_plane_flag {
+      0
+    } else {
+      sps.chroma_format_idc
+    };
+    data.bits_into::<u8>(pps.num_extra_slice_header_bits as usize);
+    let slice_type = match data.exponential_golomb::<u16>() {
+      0 => SliceType::B,
+      1 => SliceType::P,
+      2 => SliceType::I,
+      slice_type => return Err(HevcError::SliceHeader("slice_type", slice_type as u32)),
+    };
+    let mut header = Self {
+      first_slice_segment_in_pic_flag: false,
+      no_output_of_prior_pics_flag: false,
+      slice_pic_parameter_set_id: pps.id,
+      dependent_slice_segment_flag: false,
+      slice_segment_address: 0,
+      slice_type,
+      pic_output_flag: !pps.output_flag_present_flag || data.bit_flag(),
+      colour_plane_id: if sps.separate_colour_plane_flag {
+        data.bits_into(2)
+      } else {
+        0
+      },
+      slice_pic_order_cnt_lsb: 0,
+      short_term_ref_pic_set_sps_flag: false,
+      short_term_ref_pic_set: ShortTermRefPicSet::default(),
+      short_term_ref_pic_set_idx: 0,
+      long_term_ref_pics: Box::default(),
+      slice_temporal_mvp_enabled_flag: false,
+      slice_sao_luma_flag: false,
+      slice_sao_chroma_flag: false,
+      num_ref_idx_l0_active_minus1: pps.num_ref_idx_l0_default_active_minus1,
+      num_ref_idx_l1_active_minus1: pps.num_ref_idx_l1_default_active_minus1,
+      ref_pic_lists_modification: None,
+      mvd_l1_zero_flag: false,
+      cabac_init_flag: false,
+      collocated_from_l0_flag: true,
+      collocated_ref_idx: 0,
+      pred_weight_table: None,
+      max_num_merge_cand: 5,
+      slice_qp_delta: 0,
+      slice_cb_qp_offset: 0,
+      slice_cr_qp_offset: 0,
+      cu_chroma_qp_offset_enabled_flag: false,
+      deblocking_filter_override_flag: false,
+      slice_deblocking_filter_disabled_flag: false,
+      slice_beta_offset_div2: 0,
+      slice_tc_offset_div2: 0,
+      slice_loop_filter_across_slices_enabled_flag: pps.loop_filter_across_slices_enabled_flag,
+      entry_point_offset_minus1: Box::default(),
+    };
+    if let Some(deblocking) = &pps.deblocking_filter_control {
+      header.slice_deblocking_filter_disabled_flag = deblocking.pps_deblocking_filter_disabled_flag;
+      header.slice_beta_offset_div2 = deblocking.beta_offset_div2;
+      header.slice_tc_offset_div2 = deblocking.tc_offset_div2;
+    }
+
+    if !nal.is_idr() {
+      let log2_max_pic_order_cnt_lsb = sps.log2_max_pic_order_cnt_lsb_minus4 as usize + 4;
+      header.slice_pic_order_cnt_lsb = data.bits_into(log2_max_pic_order_cnt_lsb);
+      header.short_term_ref_pic_set_sps_flag = data.bit_flag();
+      let sets = &sps.short_term_ref_pic_sets;
+      if !header.short_term_ref_pic_set_sps_flag {
+        header.short_term_ref_pic_set = ShortTermRefPicSet::decode(data, sets, sets.len())?;
+      } else {
+        header.short_term_ref_pic_set_idx = data.bits_into(ceil_log2(sets.len() as u32));
+        header.short_term_ref_pic_set = sets
+          .get(header.short_term_ref_pic_set_idx as usize)
+          .ok_or(HevcError::SliceHeader(
+            "short_term_ref_pic_set_idx",
+            header.short_term_ref_pic_set_idx as u32,
+          ))?
+          .clone();
+      }
+      if let Some(long_term) = &sps.long_term_ref_pics {
+        header.long_term_ref_pics =
+          LongTermRefPic::decode(data, long_term, log2_max_pic_order_cnt_lsb)?;
+      }
+      if sps.temporal_mvp_enabled_flag {
+        header.slice_temporal_mvp_enabled_flag = data.bit_flag();
+      }
+    }
+    if sps.sample_adaptive_offset_enabled_flag {
+      header.slice_sao_luma_flag = data.bit_flag();
+      header.slice_sao_chroma_flag = chroma_array_type != 0 && data.bit_flag();
+    }
+
+    if !slice_type.is_intra() {
+      if data.bit_flag() {
+        header.num_ref_idx_l0_active_minus1 = Self::num_ref_idx_active_minus1(data)?;
+        if slice_type.is_bidirectional() {
+          header.num_ref_idx_l1_active_minus1 = Self::num_ref_idx_active_minus1(data)?;
+        }
+      }
+      if !slice_type.is_bidirectional() {
+        header.num_ref_idx_l1_active_minus1 = 0;
+      }
+      let num_pic_total_curr = header.num_pic_total_curr();
+      if pps.lists_modification_present_flag && num_pic_total_curr > 1 {
+        header.ref_pic_lists_modification = Some(RefPicListModification::decode(
+          data,
+          &header,
+          num_pic_total_curr,
+        ));
+      }
+      header.mvd_l1_zero_flag = slice_type.is_bidirectional() && data.bit_flag();
+      header.cabac_init_flag = pps.cabac_init_present_flag && data.bit_flag();
+      if header.slice_temporal_mvp_enabled_flag {
+        header.collocated_from_l0_flag = !slice_type.is_bidirectional() || data.bit_flag();
+        let num_ref_idx_active_minus1 = if header.collocated_from_l0_flag {
+          header.num_ref_idx_l0_active_minus1
+        } else {
+          header.num_ref_idx_l1_active_minus1
+        };
+        if num_ref_idx_active_minus1 > 0 {
+          header.collocated_ref_idx = data.exponential_golomb::<u16>() as u8;
+          if header.collocated_ref_idx > num_ref_idx_active_minus1 {
+            return Err(HevcError::SliceHeader(
+              "collocated_ref_idx",
+              header.collocated_ref_idx as u32,
+            ));
+          }
+        }
+      }
+      if (pps.weighted_pred_flag && !slice_type.is_bidirectional())
+        || (pps.weighted_bipred_flag && slice_type.is_bidirectional())
+      {
+        header.pred_weight_table = Some(PredWeightTable::decode(
+          data,
+          &header,
+          sps,
+          chroma_array_type,
+        )?);
+      }
+      let five_minus_max_num_merge_cand = data.exponential_golomb::<u16>();
+      if five_minus_max_num_merge_cand > 4 {
+        return Err(HevcError::SliceHeader(
+          "five_minus_max_num_merge_cand",
+          five_minus_max_num_merge_cand as u32,
+        ));
+      }
+      header.max_num_merge_cand = 5 - five_minus_max_num_merge_cand as u8;
+    }
+
+    header.slice_qp_delta = data.signed_exponential_golomb::<i16>().clamp(-87, 51) as i8;
+    if pps.slice_chroma_qp_offsets_present_flag {
+      header.slice_cb_qp_offset = data.signed_exponential_golomb::<i16>().clamp(-12, 12) as i8;
+      header.slice_cr_qp_offset = data.signed_exponential_golomb::<i16>().clamp(-12, 12) as i8;
+    }
+    if !pps.range_extension.chroma_qp_offset_list.is_empty() {
+      header.cu_chroma_qp_offset_enabled_flag = data.bit_flag();
+    }
+    if pps
+      .deblocking_filter_control
+      .as_ref()
+      .is_some_and(|deblocking| deblocking.deblocking_filter_override_enabled_flag)
+    {
+      header.deblocking_filter_override_flag = data.bit_flag();
+    }
+    if header.deblocking_filter_override_flag {
+      header.slice_deblocking_filter_disabled_flag = data.bit_flag();
+      if !header.slice_deblocking_filter_disabled_flag {
+        header.slice_beta_offset_div2 = data.signed_exponential_golomb::<i16>().clamp(-6, 6) as i8;
+        header.slice_tc_offset_div2 = data.signed_exponential_golomb::<i16>().clamp(-6, 6) as i8;
+      }
+    }
+    if pps.loop_filter_across_slices_enabled_flag
+      && (header.slice_sao_luma_flag
+        || header.slice_sao_chroma_flag
+        || !header.slice_deblocking_filter_disabled_flag)
+    {
+      header.slice_loop_filter_across_slices_enabled_flag = data.bit_flag();
+    }
+    Ok(header)
+  }
+
+  fn num_ref_idx_active_minus1(data: &mut BitStream) -> HevcResult<u8> {
+    let num_ref_idx_active_minus1 = data.exponential_golomb::<u16>();
+    if num_ref_idx_active_minus1 > 14 {
+      return Err(HevcError::SliceHeader(
+        "num_ref_idx_active_minus1",
+        num_ref_idx_active_minus1 as u32,
+      ));
+    }
+    Ok(num_ref_idx_active_minus1 as u8)
+  }
+
+  /// 7-55 NumPicTotalCurr, the number of reference pictures used by the current picture
+  pub fn num_pic_total_curr(&self) -> usize {
+    let set = &self.short_term_ref_pic_set;
+    set
+      .used_by_curr_pic_s0
+      .iter()
+      .chain(&*set.used_by_curr_pic_s1)
+      .chain(
+        self
+          .long_term_ref_pics
+          .iter()
+          .map(|lt| &lt.used_by_curr_pic_lt_flag),
+      )
+      .filter(|used| **used)
+      .count()
+  }
+
+  /// 7-54 SliceQpY
+  pub fn slice_qp_y(&self, pps: &HevcPictureParameterSet) -> i8 {
+    (26 + pps.init_qp_minus26 + self.slice_qp_delta as i16) as i8
+  }
+}
+
+/// Ceil(Log2(n)), the number of bits of the fixed length codes indexing `n` values
+fn ceil_log2(n: u32) -> usize {
+  (u32::BITS - n.saturating_sub(1).leading_zeros()) as usize
+}
+
+/// Long-term reference picture of the slice header, either signalled or chosen from the SPS
+/// candidates
+#[derive(Debug, Clone, Copy)]
+pub struct LongTermRefPic {
+  /// PocLsbLt
+  pub poc_lsb_lt: u32,
+  /// UsedByCurrPicLt
+  pub used_by_curr_pic_lt_flag: bool,
+  /// DeltaPocMsbCycleLt, accumulated over the SPS and the slice header candidates as in 7-52
+  pub delta_poc_msb_cycle_lt: Option<u32>,
+}
+
+impl LongTermRefPic {
+  fn decode(
+    data: &mut BitStream,
+    sps_pics: &crate::video::atom::LongTermRefPics,
+    log2_max_pic_order_cnt_lsb: usize,
+  ) -> HevcResult<Box<[Self]>> {
+    let num_long_term_ref_pics_sps = sps_pics.lt_ref_pic_poc_lsb_sps.len() as u32;
+    let num_long_term_sps = if num_long_term_ref_pics_sps > 0 {
+      data.exponential_golomb::<u32>()
+    } else {
+      0
+    };
+    if num_long_term_sps > num_long_term_ref_pics_sps {
+      return Err(HevcError::SliceHeader(
+        "num_long_term_sps",
+        num_long_term_sps,
+      ));
+    }
+    let num_long_term_pics = data.exponential_golomb::<u32>();
+    if num_long_term_sps + num_long_term_pics > 32 {
+      return Err(HevcError::SliceHeader(
+        "num_long_term_pics",
+        num_long_term_pics,
+      ));
+    }
+    let mut delta_poc_msb_cycle_lt = 0;
+    (0..num_long_term_sps + num_long_term_pics)
+      .map(|i| {
+        let (poc_lsb_lt, used_by_curr_pic_lt_flag) = if i < num_long_term_sps {
+          let lt_idx_sps = data.bits_into::<u32>(ceil_log2(num_long_term_ref_pics_sps)) as usize;
+          match sps_pics.lt_ref_pic_poc_lsb_sps.get(lt_idx_sps) {
+            Some(&poc_lsb_lt) => (
+              poc_lsb_lt,
+              sps_pics.used_by_curr_pic_lt_sps_flag[lt_idx_sps],
+            ),
+            None => return Err(HevcError::SliceHeader("lt_idx_sps", lt_idx_sps as u32)),
+          }
+        } else {
+          (data.bits_into(log2_max_pic_order_cnt_lsb), data.bit_flag())
+        };
+        if i == 0 || i == num_long_term_sps {
+          delta_poc_msb_cycle_lt = 0;
+        }
+        let delta_poc_msb_present_flag = data.bit_flag();
+        let delta_poc_msb_cycle_lt = if delta_poc_msb_present_flag {
+          delta_poc_msb_cycle_lt += data.exponential_golomb::<u32>();
+          Some(delta_poc_msb_cycle_lt)
+        } else {
+          None
+        };
+        Ok(Self {
+          poc_lsb_lt,
+          used_by_curr_pic_lt_flag,
+          delta_poc_msb_cycle_lt,
+        })
+      })
+      .collect()
+  }
+}
+
+/// 7.3.6.2 ref_pic_lists_modification, `None` lists keep the initial order
+#[derive(Debug, Clone)]
+pub struct RefPicListModification {
+  pub list_entry_l0: Option<Box<[u8]>>,
+  pub list_entry_l1: Option<Box<[u8]>>,
+}
+
+impl RefPicListModification {
+  fn decode(data: &mut BitStream, header: &SliceSegmentHeader, num_pic_total_curr: usize) -> Self {
+    let bits = ceil_log2(num_pic_total_curr as u32);
+    let mut list_entries = |num_ref_idx_active_minus1: u8| {
+      data.bit_flag().then(|| {
+        (0..=num_ref_idx_active_minus1)
+          .map(|_| data.bits_into::<u8>(bits).min(num_pic_total_curr as u8 - 1))
+          .collect()
+      })
+    };
+    let list_entry_l0 = list_entries(header.num_ref_idx_l0_active_minus1);
+    let list_entry_l1 = if header.slice_type.is_bidirectional() {
+      list_entries(header.num_ref_idx_l1_active_minus1)
+    } else {
+      None
+    };
+    Self {
+      list_entry_l0,
+      list_entry_l1,
+    }
+  }
+}
+
+/// 7.3.6.3 pred_weight_table with the weights and offsets derived in 7.4.7.3
+#[derive(Debug, Clone)]
+pub struct PredWeightTable {
+  pub luma_log2_weight_denom: u8,
+  /// ChromaLog2WeightDenom
+  pub chroma_log2_weight_denom: u8,
+  /// Weights of every active reference picture of list 0 and list 1
+  pub weights: [Box<[PredWeight]>; 2],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PredWeight {
+  /// LumaWeightLX
+  pub luma_weight: i16,
+  /// luma_offset_lX, before the bit depth scaling of weighted prediction
+  pub luma_offset: i16,
+  /// ChromaWeightLX of Cb and Cr
+  pub chroma_weight: [i16; 2],
+  /// ChromaOffsetLX of Cb and Cr
+  pub chroma_offset: [i16; 2],
+}
+
+impl PredWeightTable {
+  fn decode(
+    data: &mut BitStream,
+    header: &SliceSegmentHeader,
+    sps: &HevcSequenceParameterSet,
+    chroma_array_type: u8,
+  ) -> HevcResult<Self> {
+    let luma_log2_weight_denom = data.exponential_golomb::<u16>();
+    if luma_log2_weight_denom > 7 {
+      return Err(HevcError::SliceHeader(
+        "luma_log2_weight_denom",
+        luma_log2_weight_denom as u32,
+      ));
+    }
+    let chroma_log2_weight_denom = if chroma_array_type != 0 {
+      (luma_log2_weight_denom as i16 + data.signed_exponential_golomb::<i16>()).clamp(0, 7)
+    } else {
+      0
+    } as u8;
+    let luma_log2_weight_denom = luma_log2_weight_denom as u8;
+    let wp_offset_half_range_c: i32 = if sps.range_extension.high_precision_offsets_enabled_flag {
+      1 << (sps.bit_depth_chroma() - 1)
+    } else {
+      1 << 7
+    };
+
+    let mut weights = |num_ref_idx_active_minus1: u8| {
+      let count = num_ref_idx_active_minus1 as usize + 1;
+      let luma_weight_flags = (0..count).map(|_| data.bit_flag()).collect::<Vec<_>>();
+      let chroma_weight_flags = (0..count)
+        .map(|_| chroma_array_type != 0 && data.bit_flag())
+        .collect::<Vec<_>>();
+      luma_weight_flags
+        .into_iter()
+        .zip(chroma_weight_flags)
+        .map(|(luma_weight_flag, chroma_weight_flag)| {
+          let mut weight = PredWeight {
+            luma_weight: 1 << luma_log2_weight_denom,
+            luma_offset: 0,
+            chroma_weight: [1 << chroma_log2_weight_denom; 2],
+            chroma_offset: [0; 2],
+          };
+          if luma_weight_flag {
+            weight.luma_weight += data.signed_exponential_golomb::<i16>().clamp(-128, 127);
+            weight.luma_offset = data.signed_exponential_golomb();
+          }
+          if chroma_weight_flag {
+            for j in 0..2 {
+              weight.chroma_weight[j] += data.signed_exponential_golomb::<i16>().clamp(-128, 127);
+              let delta_chroma_offset = data.signed_exponential_golomb::<i32>();
+              weight.chroma_offset[j] = (wp_offset_half_range_c + delta_chroma_offset
+                - ((wp_offset_half_range_c * weight.chroma_weight[j] as i32)
+                  >> chroma_log2_weight_denom))
+                .clamp(-wp_offset_half_range_c, wp_offset_half_range_c - 1)
+                as i16;
+            }
+          }
+          weight
+        })
+        .collect::<Box<[_]>>()
+    };
+    let weights_l0 = weights(header.num_ref_idx_l0_active_minus1);
+    let weights_l1 = if header.slice_type.is_bidirectional() {
+      weights(header.num_ref_idx_l1_active_minus1)
+    } else {
+      Box::default()
+    };
+    Ok(Self {
+      luma_log2_weight_denom,
+      chroma_log2_weight_denom,
+      weights: [weights_l0, weights_l1],
+    })
+  }
+}
@@ -0,0 +1,289 @@
+pub mod coding_unit;
+pub mod header;
+
+use super::{
+  cabac::CabacContext,
+  dpb::DecodedPicture,
+  picture::{LoopFilterParameters, Picture},
+  HevcError, HevcResult,
+};
+use crate::{
+  byte::BitStream,
+  video::atom::{HevcPictureParameterSet, HevcSequenceParameterSet, ScalingListData},
+};
+use coding_unit::CodingUnit;
+use header::{SliceSegmentHeader, SliceType};
+use std::ops::Deref;
+
+/// Slice segment being parsed into the coding units of `picture`
+pub struct Slice<'a> {
+  pub header: SliceSegmentHeader,
+  pub sps: &'a HevcSequenceParameterSet,
+  pub pps: &'a HevcPictureParameterSet,
+  /// slice_segment_data() after the header byte alignment
+  pub stream: BitStream<'a>,
+  pub picture: &'a mut Picture,
+  /// Coding units of the slice segment in decoding order
+  pub coding_units: Vec<CodingUnit>,
+  /// SliceAddrRs, address of the first CTB of the independent slice segment
+  pub slice_addr_rs: u32,
+  /// 7-54 SliceQpY
+  pub slice_qp_y: i8,
+  /// 9.3.2.2 initType of the context variables
+  pub init_type: usize,
+  pub chroma_array_type: u8,
+  /// QpBdOffsetY
+  pub qp_bd_offset_y: i8,
+  /// Log2MinCuQpDeltaSize
+  pub log2_min_cu_qp_delta_size: u8,
+  /// Log2MinCuChromaQpOffsetSize
+  pub log2_min_cu_chroma_qp_offset_size: u8,
+  pub ctb_addr_in_rs: u32,
+  pub ctb_addr_in_ts: u32,
+  pub is_cu_qp_delta_coded: bool,
+  pub cu_qp_delta_val: i32,
+  pub is_cu_chroma_qp_offset_coded: bool,
+  /// CuQpOffsetCb
+  pub cu_qp_offset_cb: i8,
+  /// CuQpOffsetCr
+  pub cu_qp_offset_cr: i8,
+  /// qPY_PRED of the current quantization group
+  pub qp_y_pred: i8,
+  /// Scaling lists of the PPS, the SPS or the default ones, `None` when they are disabled
+  pub scaling_list: Option<ScalingListData>,
+  /// RefPicList0 and RefPicList1
+  pub ref_pic_list: [Vec<&'a DecodedPicture>; 2],
+}
+
+impl<'a> Slice<'a> {
+  pub fn new(
+    header: SliceSegmentHeader,
+    sps: &'a HevcSequenceParameterSet,
+    pps: &'a HevcPictureParameterSet,
+    stream: BitStream<'a>,
+    picture: &'a mut Picture,
+    ref_pic_list: [Vec<&'a DecodedPicture>; 2],
+  ) -> HevcResult<Self> {
+    if sps.range_extension.extended_precision_processing_flag {
+      return Err(HevcError::Unsupported("extended_precision_processing_flag"));
+    }
+    if sps.range_extension.cabac_bypass_alignment_enabled_flag {
+      return Err(HevcError::Unsupported(
+        "cabac_bypass_alignment_enabled_flag",
+      ));
+    }
+    let slice_addr_rs = if header.dependent_slice_segment_flag {
+      picture
+        .slice_header
+        .as_ref()
+        .map(|header| header.slice_segment_address)
+        .unwrap_or_default()
+    } else {
+      header.slice_segment_address
+    };
+    let init_type = match header.slice_type {
+      SliceType::I => 0,
+      SliceType::P => 1 + header.cabac_init_flag as usize,
+      SliceType::B => 2 - header.cabac_init_flag as usize,
+    };
+    let log2_ctb_size = sps.log2_ctb_size();
+    let scaling_list = if sps.scaling_list_enabled_flag {
+      Some(
+        pps
+          .scaling_list
+          .as_ref()
+          .or(sps.scaling_list.as_ref())
+          .cloned()
+          .unwrap_or_default(),
+      )
+    } else {
+      None
+    };
+    Ok(Self {
+      slice_qp_y: header.slice_qp_y(pps),
+      ctb_addr_in_rs: header.slice_segment_address,
+      ctb_addr_in_ts: picture.ctb_addr_rs_to_ts[header.slice_segment_address as usize],
+      header,
+      sps,
+      pps,
+      stream,
+      picture,
+      coding_units: Vec::new(),
+      slice_addr_rs,
+      init_type,
+      chroma_array_type: if sps.separate_colour_plane_flag {
+        0
+      } else {
+        sps.chroma_format_idc
+      },
+      qp_bd_offset_y: 6 * sps.bit_depth_luma_minus8 as i8,
+      log2_min_cu_qp_delta_size: log2_ctb_size
+        .saturating_sub(pps.diff_cu_qp_delta_depth.unwrap_or_default()),
+      log2_min_cu_chroma_qp_offset_size: log2_ctb_size
+        .saturating_sub(pps.range_extension.diff_cu_chroma_qp_offset_depth),
+      is_cu_qp_delta_coded: false,
+      cu_qp_delta_val: 0,
+      is_cu_chroma_qp_offset_coded: false,
+      cu_qp_offset_cb: 0,
+      cu_qp_offset_cr: 0,
+      qp_y_pred: 0,
+      scaling_list,
+      ref_pic_list,
+    })
+  }
+
+  /// 7.3.8.1 General slice segment data syntax
+  pub fn data(&mut self) -> HevcResult {
+    let pic_size_in_ctbs = self.picture.ctb_addr_ts_to_rs.len() as u32;
+    let pic_width_in_ctbs = self.picture.pic_width_in_ctbs;
+    let tiles_enabled_flag = self.pps.tiles.is_some();
+    let entropy_coding_sync_enabled_flag = self.pps.entropy_coding_sync_enabled_flag;
+    let mut cabac = CabacContext::new(self)?;
+    let mut first_ctb_in_segment = true;
+    loop {
+      self.start_coding_tree_unit(&mut cabac, first_ctb_in_segment)?;
+      first_ctb_in_segment = false;
+      cabac.coding_tree_unit(self)?;
+
+      let ctb_addr_in_rs = self.ctb_addr_in_rs;
+      let tile_id = &self.picture.tile_id;
+      if entropy_coding_sync_enabled_flag
+        && (ctb_addr_in_rs % pic_width_in_ctbs == 1
+          || (ctb_addr_in_rs > 1
+            && tile_id[self.ctb_addr_in_ts as usize]
+              != tile_id[self.picture.ctb_addr_rs_to_ts[ctb_addr_in_rs as usize - 2] as usize]))
+      {
+        self.picture.wpp_contexts = Some(cabac.clone());
+      }
+      let end_of_slice_segment_flag = cabac.terminate(self);
+      if end_of_slice_segment_flag {
+        if self.pps.dependent_slice_segments_enabled_flag {
+          self.picture.dependent_contexts = Some(cabac);
+        }
+        return Ok(());
+      }
+
+      self.ctb_addr_in_ts += 1;
+      if self.ctb_addr_in_ts >= pic_size_in_ctbs {
+        return Err(HevcError::SliceHeader("CtbAddrInTs", self.ctb_addr_in_ts));
+      }
+      self.ctb_addr_in_rs = self.picture.ctb_addr_ts_to_rs[self.ctb_addr_in_ts as usize];
+      let tile_id = &self.picture.tile_id;
+      let ctb_addr_in_ts = self.ctb_addr_in_ts as usize;
+      if (tiles_enabled_flag && tile_id[ctb_addr_in_ts] != tile_id[ctb_addr_in_ts - 1])
+        || (entropy_coding_sync_enabled_flag
+          && (self.ctb_addr_in_rs.is_multiple_of(pic_width_in_ctbs)
+            || tile_id[ctb_addr_in_ts]
+              != tile_id
+                [self.picture.ctb_addr_rs_to_ts[self.ctb_addr_in_rs as usize - 1] as usize]))
+      {
+        // The arithmetic decoder reads the alignment_bit_equal_to_one of the substream end
+        let end_of_subset_one_bit = cabac.terminate(self);
+        if !end_of_subset_one_bit {
+          return Err(HevcError::Invalid("end_of_subset_one_bit"));
+        }
+        self.stream.skip_trailing_bits();
+        cabac.init_decoding_engine(self)?;
+      }
+    }
+  }
+
+  /// 9.3.1 Initializes or synchronizes the context variables of the CTU at `ctb_addr_in_ts`
+  /// and resets qPY_PREV at the start of slices, tiles and WPP rows
+  fn start_coding_tree_unit(
+    &mut self,
+    cabac: &mut CabacContext,
+    first_ctb_in_segment: bool,
+  ) -> HevcResult {
+    let ctb_addr_in_ts = self.ctb_addr_in_ts as usize;
+    let ctb_addr_in_rs = self.ctb_addr_in_rs;
+    self.picture.ctb_slice_addr[ctb_addr_in_rs as usize] = Some(self.slice_addr_rs);
+    self.picture.loop_filter[ctb_addr_in_rs as usize] = LoopFilterParameters {
+      slice_beta_offset_div2: self.slice_beta_offset_div2,
+      slice_tc_offset_div2: self.slice_tc_offset_div2,
+      slice_loop_filter_across_slices_enabled_flag: self
+        .slice_loop_filter_across_slices_enabled_flag,
+    };
+    let picture = &*self.picture;
+
+    let first_ctb_in_tile =
+      ctb_addr_in_ts == 0 || picture.tile_id[ctb_addr_in_ts] != picture.tile_id[ctb_addr_in_ts - 1];
+    let first_ctb_in_row = self.pps.entropy_coding_sync_enabled_flag
+      && (ctb_addr_in_rs.is_multiple_of(picture.pic_width_in_ctbs)
+        || picture.tile_id[ctb_addr_in_ts]
+          != picture.tile_id[picture.ctb_addr_rs_to_ts[ctb_addr_in_rs as usize - 1] as usize]);
+    if first_ctb_in_tile {
+      if !first_ctb_in_segment {
+        cabac.init_context_variables(self);
+      }
+    } else if first_ctb_in_row {
+      let ctb_size = 1 << picture.log2_ctb_size;
+      let x_ctb = (ctb_addr_in_rs % picture.pic_width_in_ctbs) * ctb_size;
+      let y_ctb = (ctb_addr_in_rs / picture.pic_width_in_ctbs) * ctb_size;
+      let stored = picture.wpp_contexts.as_ref().filter(|_| {
+        picture.available(
+          x_ctb,
+          y_ctb,
+          (x_ctb + ctb_size) as i32,
+          y_ctb as i32 - ctb_size as i32,
+        )
+      });
+      match stored {
+        Some(stored) => cabac.sync(stored),
+        None => cabac.init_context_variables(self),
+      }
+    } else if first_ctb_in_segment && self.header.dependent_slice_segment_flag {
+      let stored = picture
+        .dependent_contexts
+        .as_ref()
+        .ok_or(HevcError::Invalid(
+          "dependent slice segment context variables",
+        ))?;
+      cabac.sync(stored);
+    }
+    if first_ctb_in_tile
+      || first_ctb_in_row
+      || (first_ctb_in_segment && !self.header.dependent_slice_segment_flag)
+    {
+      self.picture.qp_y_prev = self.slice_qp_y;
+    }
+    Ok(())
+  }
+
+  /// 8.6.1 qPY_PRED of the quantization group at (`x_qg`, `y_qg`), the neighbouring QpY are
+  /// only used inside the current CTB
+  pub fn start_quantization_group(&mut self, x_qg: u32, y_qg: u32) {
+    self.is_cu_qp_delta_coded = false;
+    self.cu_qp_delta_val = 0;
+    let qp_y_prev = self.picture.qp_y_prev;
+    let ctb_mask = (1 << self.picture.log2_ctb_size) - 1;
+    let qp_y_a = if x_qg & ctb_mask != 0 {
+      self.picture.block(x_qg - 1, y_qg).qp_y
+    } else {
+      qp_y_prev
+    };
+    let qp_y_b = if y_qg & ctb_mask != 0 {
+      self.picture.block(x_qg, y_qg - 1).qp_y
+    } else {
+      qp_y_prev
+    };
+    self.qp_y_pred = ((qp_y_a as i16 + qp_y_b as i16 + 1) >> 1) as i8;
+  }
+
+  /// 8-283 QpY of the current coding unit, which is qPY_PREV of the next quantization group
+  pub fn qp_y(&mut self) -> i8 {
+    let qp_bd_offset_y = self.qp_bd_offset_y as i32;
+    let qp_y = (self.qp_y_pred as i32 + self.cu_qp_delta_val + 52 + 2 * qp_bd_offset_y)
+      % (52 + qp_bd_offset_y)
+      - qp_bd_offset_y;
+    self.picture.qp_y_prev = qp_y as i8;
+    qp_y as i8
+  }
+}
+
+impl<'a> Deref for Slice<'a> {
+  type Target = SliceSegmentHeader;
+  fn deref(&self) -> &Self::Target {
+    &self.header
+  }
+}
@@ -0,0 +1,223 @@
+use super::{
+  cabac::table::{SCAN_4X4, SCAN_8X8_DIAGONAL},
+  slice::{
+    coding_unit::{CodingUnit, ResidualBlock},
+    Slice,
+  },
+};
+
+/// 8.6.2 levelScale
+const LEVEL_SCALE: [i64; 6] = [40, 45, 51, 57, 64, 72];
+
+/// Table 8-10 QpC of qPi from 30 to 43 when ChromaArrayType is 1
+const QP_C: [i32; 14] = [29, 30, 31, 32, 33, 33, 34, 34, 35, 35, 36, 36, 37, 37];
+
+/// 8.6.4.2 transMatrix coefficients of the 32x32 DCT, indexed by the angle of the cosine in
+/// multiples of pi / 64 up to pi / 2
+#[rustfmt::skip]
+const DCT_COEFFICIENTS: [i32; 33] = [
+  64, 90, 90, 90, 89, 88, 87, 85, 83, 82, 80, 78, 75, 73, 70, 67,
+  64, 61, 57, 54, 50, 46, 43, 38, 36, 31, 25, 22, 18, 13, 9, 4, 0,
+];
+
+/// 8-316 transMatrix of the 4x4 DST, every row is a basis function
+#[rustfmt::skip]
+const DST_MATRIX: [[i32; 4]; 4] = [
+  [29, 55, 74, 84],
+  [74, 74, 0, -74],
+  [84, -29, -74, 55],
+  [55, -84, 74, -29],
+];
+
+const COEFF_MIN: i32 = -(1 << 15);
+const COEFF_MAX: i32 = (1 << 15) - 1;
+
+/// Coefficient of the basis function `k` of the 32x32 DCT at sample `n`
+fn dct_coefficient(k: usize, n: usize) -> i32 {
+  if k == 0 {
+    return 64;
+  }
+  let angle = ((2 * n + 1) * k) % 128;
+  let angle = if angle > 64 { 128 - angle } else { angle };
+  if angle <= 32 {
+    DCT_COEFFICIENTS[angle]
+  } else {
+    -DCT_COEFFICIENTS[64 - angle]
+  }
+}
+
+/// Table 8-10 QpC of the index qPi
+pub fn qp_c(qp_i: i32, chroma_array_type: u8) -> i32 {
+  if chroma_array_type != 1 {
+    qp_i.min(51)
+  } else if qp_i < 30 {
+    qp_i
+  } else if qp_i > 43 {
+    qp_i - 6
+  } else {
+    QP_C[qp_i as usize - 30]
+  }
+}
+
+impl Slice<'_> {
+  /// 8.6.1 Qp′Y, Qp′Cb or Qp′Cr of the transform blocks of colour component `c_idx`
+  pub fn qp_prime(&self, cu: &CodingUnit, c_idx: u8) -> i32 {
+    let qp_y = cu.qp_y as i32;
+    if c_idx == 0 {
+      return qp_y + self.qp_bd_offset_y as i32;
+    }
+    let qp_bd_offset_c = 6 * self.sps.bit_depth_chroma_minus8 as i32;
+    let offset = if c_idx == 1 {
+      self.pps.cb_qp_offset as i32 + self.slice_cb_qp_offset as i32 + cu.cu_qp_offset_cb as i32
+    } else {
+      self.pps.cr_qp_offset as i32 + self.slice_cr_qp_offset as i32 + cu.cu_qp_offset_cr as i32
+    };
+    let qp_i = (qp_y + offset).clamp(-qp_bd_offset_c, 57);
+    qp_c(qp_i, self.chroma_array_type) + qp_bd_offset_c
+  }
+
+  /// 8.6.2 Residual samples of a transform block in raster order, before the cross-component
+  /// prediction. `pred_mode_intra` selects the implicit residual DPCM of intra blocks
+  pub fn residual_samples(
+    &self,
+    cu: &CodingUnit,
+    block: &ResidualBlock,
+    pred_mode_intra: u8,
+  ) -> Vec<i32> {
+    let log2_size = block.log2_trafo_size;
+    let size = 1usize << log2_size;
+    let bit_depth = self.picture.planes[self.plane_index(block.c_idx)].bit_depth;
+    let range_extension = &self.sps.range_extension;
+    let intra = cu.pred_mode.is_intra();
+    let rotation = range_extension.transform_skip_rotation_enabled_flag && size == 4 && intra;
+    let rotate = |coefficients: &[i32]| -> Vec<i32> {
+      if rotation {
+        coefficients.iter().rev().copied().collect()
+      } else {
+        coefficients.to_vec()
+      }
+    };
+    // Direction of the residual DPCM, true when vertical
+    let rdpcm = if intra {
+      if range_extension.implicit_rdpcm_enabled_flag
+        && (block.transform_skip_flag || cu.cu_transquant_bypass_flag)
+        && (pred_mode_intra == 10 || pred_mode_intra == 26)
+      {
+        Some(pred_mode_intra == 26)
+      } else {
+        None
+      }
+    } else {
+      block.explicit_rdpcm_dir_flag
+    };
+
+    let mut r = if cu.cu_transquant_bypass_flag {
+      rotate(&block.coefficients)
+    } else {
+      let d = self.scaled_coefficients(cu, block);
+      let r = if block.transform_skip_flag {
+        // 8.6.4.2 Residual modification process for transform skip
+        let ts_shift = 5 + log2_size;
+        rotate(&d).into_iter().map(|d| d << ts_shift).collect()
+      } else {
+        let dst = intra && size == 4 && block.c_idx == 0;
+        transformation(&d, log2_size, dst)
+      };
+      let bd_shift = 20 - bit_depth as i32;
+      r.into_iter()
+        .map(|r| (r + (1 << (bd_shift - 1))) >> bd_shift)
+        .collect()
+    };
+
+    // 8.6.8 Directional residual modification process
+    if let Some(vertical) = rdpcm {
+      for y in 0..size {
+        for x in 0..size {
+          if vertical && y > 0 {
+            r[y * size + x] += r[(y - 1) * size + x];
+          } else if !vertical && x > 0 {
+            r[y * size + x] += r[y * size + x - 1];
+          }
+        }
+      }
+    }
+    r
+  }
+
+  /// 8.6.3 Scaling process for transform coefficients
+  fn scaled_coefficients(&self, cu: &CodingUnit, block: &ResidualBlock) -> Vec<i32> {
+    let log2_size = block.log2_trafo_size;
+    let size = 1usize << log2_size;
+    let bit_depth = self.picture.planes[self.plane_index(block.c_idx)].bit_depth;
+    let qp = self.qp_prime(cu, block.c_idx);
+    let bd_shift = bit_depth as i64 + log2_size as i64 - 5;
+    let level_scale = LEVEL_SCALE[(qp % 6) as usize] << (qp / 6);
+    let scaling_list = self
+      .scaling_list
+      .as_ref()
+      .filter(|_| !(block.transform_skip_flag && size > 4));
+    let size_id = (log2_size - 2) as usize;
+    let matrix_id = block.c_idx as usize + if cu.pred_mode.is_intra() { 0 } else { 3 };
+
+    let mut d = vec![0; size * size];
+    for (i, (level, d)) in block.coefficients.iter().zip(&mut d).enumerate() {
+      if *level == 0 {
+        continue;
+      }
+      // 7.4.5 ScalingFactor, upsampled from the 8x8 lists for the larger blocks
+      let m = match scaling_list {
+        None => 16,
+        Some(list) if size_id >= 2 && i == 0 => list.dc_coef[size_id - 2][matrix_id] as i64,
+        Some(list) => {
+          let (x, y) = (i % size, i / size);
+          let position = if size_id == 0 {
+            SCAN_4X4[0]
+              .iter()
+              .position(|&[sx, sy]| (sx as usize, sy as usize) == (x, y))
+          } else {
+            let ratio = size / 8;
+            SCAN_8X8_DIAGONAL
+              .iter()
+              .position(|&[sx, sy]| (sx as usize, sy as usize) == (x / ratio, y / ratio))
+          };
+          list.lists[size_id][matrix_id][position.unwrap_or_default()] as i64
+        }
+      };
+      let scaled = (*level as i64 * m * level_scale + (1 << (bd_shift - 1))) >> bd_shift;
+      *d = scaled.clamp(COEFF_MIN as i64, COEFF_MAX as i64) as i32;
+    }
+    d
+  }
+}
+
+/// 8.6.4.2 Two-stage inverse DCT, or DST of intra 4x4 luma blocks
+fn transformation(d: &[i32], log2_size: u8, dst: bool) -> Vec<i32> {
+  let size = 1usize << log2_size;
+  let basis = |k: usize, n: usize| {
+    if dst {
+      DST_MATRIX[k][n]
+    } else {
+      dct_coefficient(k << (5 - log2_size), n)
+    }
+  };
+  // 8.6.4.3 One-dimensional transform of every column then every row
+  let mut g = vec![0; size * size];
+  for x in 0..size {
+    for y in 0..size {
+      let e = (0..size)
+        .filter(|k| d[k * size + x] != 0)
+        .map(|k| basis(k, y) * d[k * size + x])
+        .sum::<i32>();
+      g[y * size + x] = ((e + 64) >> 7).clamp(COEFF_MIN, COEFF_MAX);
+    }
+  }
+  let mut r = vec![0; size * size];
+  for y in 0..size {
+    for x in 0..size {
+      r[y * size + x] = (0..size)
+        .map(|k| basis(k, x) * g[y * size + k])
+        .sum::<i32>();
+    }
+  }
+  r
+}
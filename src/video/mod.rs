@@ -1,22 +1,43 @@
+pub mod aac;
 pub mod atom;
+pub mod av1;
 pub mod cabac;
+pub mod camera;
 pub mod codec;
 pub mod decoder;
 pub mod frame;
+pub mod hevc;
 pub mod sample;
 pub mod slice;
+pub mod timecode;
+pub mod track;
+pub mod vp9;
 
 use crate::{
-  ascii::{Color, RESET},
-  display::Duration,
+  ascii::{Color, LogDisplay, RESET},
+  display::{Date, Duration},
   log,
   math::Matrix3x3,
 };
-use atom::AtomError;
-use codec::VideoCodec;
+use atom::{
+  item_name, AtomError, CodecData, ColrAtom, DataValue, ElstAtom, MetaAtom, SidxAtom, StblAtom,
+  TfraAtom, TrackReference, VideoSignalType, VisualExtensions,
+};
+use camera::CameraMetadata;
+use codec::{ColourInfo, VideoCodec};
 use decoder::{Decoder, DecoderError};
-use std::{fmt, path::Path, str::FromStr};
+use sample::{SampleIndex, SubtitleFormat, SubtitleWriter, TextSample};
+use std::{
+  fmt,
+  fs::File,
+  io::{BufWriter, Write},
+  path::Path,
+  rc::Rc,
+  str::FromStr,
+};
 use thiserror::Error;
+use timecode::{Timecode, TimecodeTrack};
+use track::Track;
 
 #[derive(Debug, Error)]
 pub enum VideoError {
@@ -26,6 +47,20 @@ pub enum VideoError {
   AtomDecoding(#[from] AtomError),
   #[error("Could not find video codec")]
   VideoCodec,
+  #[error("Could not find video track")]
+  VideoTrack,
+  #[error("Could not find AAC audio track")]
+  AudioTrack,
+  #[error("Could not find PCM or AAC audio track")]
+  WavTrack,
+  #[error("Could not find cover art")]
+  CoverArt,
+  #[error("Could not find tx3g or QuickTime text track")]
+  TextTrack,
+  #[error("Could not find video track with id {0}")]
+  Track(u32),
+  #[error("Video IO Error\n{0}")]
+  IO(#[from] std::io::Error),
 }
 
 pub type VideoResult<T = ()> = Result<T, VideoError>;
@@ -34,15 +69,51 @@ pub type VideoResult<T = ()> = Result<T, VideoError>;
 pub struct Video {
   pub timescale: u32,
   pub duration: Duration,
+  /// Presentation time of the decoded frame
+  pub frame_time: Duration,
   pub height: f32,
   pub width: f32,
   pub matrix: Matrix3x3,
   pub video_codec: VideoCodec,
+  /// Colour of the samples, the container `colr` atom takes precedence over the bitstream
+  pub colour: Option<ColourInfo>,
+  /// `colr`, `pasp`, `clap`, `btrt`, `fiel`, `gama`, `mdcv` and `clli` atoms of the sample entry
+  pub visual: VisualExtensions,
+  pub creation_time: Date,
+  pub modification_time: Date,
+  /// `ilst` values of the movie and user data `meta` atoms, sorted by key
+  pub metadata: Box<[(Rc<str>, DataValue)]>,
+  /// Device, capture date and location from the metadata
+  pub camera: CameraMetadata,
+  /// Id of the decoded video track
+  pub track_id: u32,
+  pub tracks: Box<[Track]>,
+  /// Chapters of the first `chap` referenced text track
+  pub chapters: Box<[Chapter]>,
+  /// Timecode of the decoded frame
+  pub timecode: Option<Timecode>,
+  /// `tmcd` track referenced by the video track, or the first one if it has no reference
+  pub timecode_track: Option<TimecodeTrack>,
+  /// Number of `moof` boxes
+  pub fragments: usize,
+  /// Top level `sidx` boxes
+  pub segment_index: Box<[SidxAtom]>,
+  /// Random access points of every track in the `mfra` box
+  pub random_access: Box<[TfraAtom]>,
 }
 
 impl Video {
-  pub fn open<P: AsRef<Path>>(path: P) -> VideoResult<Self> {
+  /// Reads the metadata of every track of the video at `path` and decodes the frame at `seek`
+  /// of the video track `track_id`, or of the first video track if `None`
+  pub fn open<P: AsRef<Path>>(
+    path: P,
+    seek: SeekPosition,
+    track_id: Option<u32>,
+  ) -> VideoResult<Self> {
     let mut decoder = Decoder::open(path)?;
+    if decoder.brand.is_annex_b() {
+      return Self::open_annex_b(&mut decoder);
+    }
     let mut root = decoder.decode_root()?;
 
     let mut timescale = 0;
@@ -51,55 +122,596 @@ impl Video {
     let mut width = 0.;
     let mut matrix = None;
     let mut video_codec = None;
+    let mut colour = None;
+    let mut visual = None;
+
+    let mvhd = root.moov.mvhd.decode(&mut decoder)?;
+    log!(File@"MOOV.MVHD {:#?}", mvhd);
+    let creation_time = Date(mvhd.creation_time);
+    let modification_time = Date(mvhd.modification_time);
+    let movie_timescale = mvhd.timescale;
+    let mut frame_time = None;
 
-    decoder.decode_udta_meta(&mut root)?;
+    let mut metadata = Vec::new();
+    for meta in decoder.decode_udta_meta(&mut root)? {
+      Self::collect_tags(meta, &mut metadata);
+    }
     log!(File@"MOOV.META TAGS {:#?}", root.moov.meta.as_mut().map(|meta| meta.tags()));
+    if let Some(meta) = &mut root.moov.meta {
+      Self::collect_tags(meta, &mut metadata);
+    }
+    metadata.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let udta = root
+      .moov
+      .udta
+      .as_mut()
+      .map(|udta| udta.decode(&mut decoder))
+      .transpose()?;
+    let camera = CameraMetadata::new(&metadata, udta.as_deref());
+    log!(File@"CAMERA {camera:#?}");
     log!(File@"MOOV.META {:#?}", root.moov.meta);
+    let mvex = root
+      .moov
+      .mvex
+      .as_mut()
+      .map(|mvex| mvex.decode(&mut decoder))
+      .transpose()?;
+    log!(File@"MOOV.MVEX {:#?}", mvex);
+    log!(File@"ROOT.MOOF {} ROOT.SIDX {:#?}", root.moof.len(), root.sidx);
+    log!(File@"ROOT.MFRA {:#?}", root.mfra);
+    let mut tracks = Vec::new();
+    let mut text_tracks = Vec::new();
+    let mut timecode_tracks = Vec::new();
+    let mut frame_seconds = None;
+    let mut selected = None;
     for trak in &mut *root.moov.trak {
       let trak = trak.decode(&mut decoder)?;
+      let tkhd = trak.tkhd.decode(&mut decoder)?;
       let mdia = trak.mdia.decode(&mut decoder)?;
       let hdlr = mdia.hdlr.decode(&mut decoder)?;
       log!(File@"{:-^100}", hdlr.component_subtype.as_str());
-      let minf = mdia.minf.decode(&mut decoder)?;
-      if let Some(edts) = &mut trak.edts {
-        edts.decode(&mut decoder)?;
-      }
+      let mdhd = mdia.mdhd.decode(&mut decoder)?;
+      log!(File@"ROOT.TRAK.MDIA.MDHD {:#?}", mdhd);
+      let elst = match &mut trak.edts {
+        Some(edts) => Some(&*edts.decode(&mut decoder)?.elst.decode(&mut decoder)?),
+        None => None,
+      };
+      log!(File@"ROOT.TRAK.EDTS.ELST {:#?}", elst);
+      let tref = match &mut trak.tref {
+        Some(tref) => Some(&*tref.decode(&mut decoder)?),
+        None => None,
+      };
+      log!(File@"ROOT.TRAK.TREF {:#?}", tref);
 
-      if *hdlr.component_subtype == *b"vide" {
-        let tkhd = trak.tkhd.decode(&mut decoder)?;
-        let mdhd = mdia.mdhd.decode(&mut decoder)?;
-        log!(File@"ROOT.TRAK.MDIA.MDHD {:#?}", mdhd);
+      let stbl = mdia.minf.decode(&mut decoder)?.stbl.decode(&mut decoder)?;
+      let index = SampleIndex::new(&mut decoder, stbl, &root.moof, mvex.as_deref(), tkhd.track_id)
+        .map_err(DecoderError::from)?;
+      log!(File@"SAMPLE INDEX {} {:#?}", index.len(), index.iter().take(4).collect::<Vec<_>>());
+      let track = Track::new(
+        tkhd,
+        hdlr,
+        mdhd,
+        stbl.stsd.decode(&mut decoder)?,
+        &index,
+        elst,
+        tref,
+        movie_timescale,
+      );
+      log!(File@"TRACK {:#?}", track);
+      if track.is_text() {
+        match Self::text_cues(
+          &mut decoder,
+          stbl,
+          &index,
+          elst,
+          track.timescale,
+          movie_timescale,
+        ) {
+          Ok(cues) => text_tracks.push((track.id, cues)),
+          Err(e) => log!(warn@"#[text] Track #{}: {e}", track.id),
+        }
+      }
+      if *track.handler == *b"tmcd" {
+        match Self::timecode_track(&mut decoder, stbl, &index, elst, &track, movie_timescale) {
+          Ok(timecode_track) => timecode_tracks.push(timecode_track),
+          Err(e) => log!(warn@"#[tmcd] Track #{}: {e}", track.id),
+        }
+      }
 
-        timescale = mdhd.timescale;
-        duration = Some(Duration::from_secs_f32(
-          mdhd.duration as f32 / timescale as f32,
-        ));
+      let is_selected = match track_id {
+        Some(id) => id == track.id,
+        None => selected.is_none() && track.is_video(),
+      };
+      if is_selected {
+        if !track.is_video() {
+          return Err(VideoError::Track(track.id));
+        }
+        timescale = track.timescale;
+        duration = Some(track.duration());
         width = tkhd.width;
         height = tkhd.height;
         matrix = Some(tkhd.matrix);
-        video_codec = minf
-          .stbl
-          .decode(&mut decoder)?
+        video_codec = track.video_codec();
+        selected = Some(track.id);
+        let codec = stbl
           .stsd
           .decode(&mut decoder)?
           .sample_description_table
-          .get(0)
-          .map(|sample| VideoCodec::from(sample.data_format));
+          .first()
+          .map(|entry| &entry.data);
+        colour = codec.and_then(CodecData::colour);
+        visual = codec.and_then(CodecData::visual_extensions).cloned();
 
-        let stbl = mdia.minf.decode(&mut decoder)?.stbl.decode(&mut decoder)?;
-        decoder.decode_sample(stbl)?;
+        let time = seek.media_time(timescale, track.duration);
+        let media_time = match elst {
+          Some(elst) => elst.media_time(time, movie_timescale, timescale),
+          None => Some(time),
+        };
+        log!(File@"PRESENTATION TIME {time} => MEDIA TIME {media_time:?}");
+        if let Some(sample) = media_time
+          .map(|media_time| decoder.seek(stbl, &index, media_time))
+          .transpose()?
+          .flatten()
+        {
+          let composition_time = index[sample].composition_time;
+          frame_seconds = match elst {
+            Some(elst) => elst.presentation_time(composition_time, movie_timescale, timescale),
+            None => Some(composition_time),
+          }
+          .map(|time| time as f64 / timescale as f64);
+          frame_time = frame_seconds.map(|seconds| Duration::from_secs_f32(seconds as f32));
+        }
       }
+      tracks.push(track);
 
-      log!(File@"TRAK.MDIA.MDHD {:#?}", mdia.mdhd);
       decoder.decode_stbl(trak)?;
     }
+    if let Some(id) = track_id.filter(|_| selected.is_none()) {
+      return Err(VideoError::Track(id));
+    }
+    let chapters = tracks
+      .iter()
+      .flat_map(|track| track.referenced_tracks(&TrackReference::CHAPTER))
+      .find_map(|id| text_tracks.iter().position(|(text_id, _)| *text_id == id))
+      .map(|i| {
+        text_tracks
+          .swap_remove(i)
+          .1
+          .into_iter()
+          .map(|(start, _, sample)| Chapter {
+            start: Duration::from_secs_f32(start as f32 / 1000.),
+            title: sample.text.trim().into(),
+          })
+          .collect()
+      })
+      .unwrap_or_default();
+    let timecode_ids: Vec<_> = tracks
+      .iter()
+      .filter(|track| Some(track.id) == selected)
+      .flat_map(|track| track.referenced_tracks(&TrackReference::TIMECODE))
+      .collect();
+    let timecode_track = timecode_ids
+      .iter()
+      .find_map(|id| {
+        timecode_tracks
+          .iter()
+          .position(|track| track.track_id == *id)
+      })
+      .or((!timecode_tracks.is_empty()).then_some(0))
+      .map(|i| timecode_tracks.swap_remove(i));
+    let timecode = timecode_track
+      .as_ref()
+      .zip(frame_seconds)
+      .and_then(|(track, seconds)| track.timecode_at(seconds));
     Ok(Self {
       timescale,
       duration: duration.unwrap_or_default(),
+      frame_time: frame_time.unwrap_or_default(),
       width,
       height,
       matrix: matrix.unwrap_or_default(),
       video_codec: video_codec.ok_or(VideoError::VideoCodec)?,
+      colour,
+      visual: visual.unwrap_or_default(),
+      creation_time,
+      modification_time,
+      metadata: metadata.into_boxed_slice(),
+      camera,
+      track_id: selected.unwrap_or_default(),
+      tracks: tracks.into_boxed_slice(),
+      chapters,
+      timecode,
+      timecode_track,
+      fragments: root.moof.len(),
+      segment_index: std::mem::take(&mut root.sidx),
+      random_access: root.mfra.take().map(|mfra| mfra.tfra).unwrap_or_default(),
+    })
+  }
+}
+
+impl Video {
+  /// Writes the H.264 samples of the video track `track_id`, or of the first video track if
+  /// `None`, to `output` as an Annex B byte stream, returns the number of samples written
+  pub fn extract_annex_b<P: AsRef<Path>, O: AsRef<Path>>(
+    path: P,
+    output: O,
+    insert_aud: bool,
+    track_id: Option<u32>,
+  ) -> VideoResult<usize> {
+    let mut decoder = Decoder::open(path)?;
+    let mut root = decoder.decode_root()?;
+    let mvex = root
+      .moov
+      .mvex
+      .as_mut()
+      .map(|mvex| mvex.decode(&mut decoder))
+      .transpose()?;
+    for trak in &mut *root.moov.trak {
+      let trak = trak.decode(&mut decoder)?;
+      let id = trak.tkhd.decode(&mut decoder)?.track_id;
+      if track_id.is_some_and(|track_id| track_id != id) {
+        continue;
+      }
+      let mdia = trak.mdia.decode(&mut decoder)?;
+      if *mdia.hdlr.decode(&mut decoder)?.component_subtype != *b"vide" {
+        match track_id {
+          Some(id) => return Err(VideoError::Track(id)),
+          None => continue,
+        }
+      }
+      let stbl = mdia.minf.decode(&mut decoder)?.stbl.decode(&mut decoder)?;
+      let index = SampleIndex::new(&mut decoder, stbl, &root.moof, mvex.as_deref(), id)
+        .map_err(DecoderError::from)?;
+      let writer = BufWriter::new(File::create(output)?);
+      return Ok(decoder.extract_annex_b(stbl, &index, writer, insert_aud)?);
+    }
+    Err(VideoError::VideoTrack)
+  }
+
+  /// Writes the samples of the text track `track_id`, or of the first text track that is not a
+  /// chapter list if `None`, to `output` as WebVTT cues for `.vtt` files and SubRip otherwise,
+  /// returns the number of cues written
+  pub fn extract_subtitles<P: AsRef<Path>, O: AsRef<Path>>(
+    path: P,
+    output: O,
+    track_id: Option<u32>,
+  ) -> VideoResult<usize> {
+    let mut decoder = Decoder::open(path)?;
+    let mut root = decoder.decode_root()?;
+    let movie_timescale = root.moov.mvhd.decode(&mut decoder)?.timescale;
+    let mvex = root
+      .moov
+      .mvex
+      .as_mut()
+      .map(|mvex| mvex.decode(&mut decoder))
+      .transpose()?;
+    let mut chapter_ids = Vec::new();
+    for trak in &mut *root.moov.trak {
+      if let Some(tref) = &mut trak.decode(&mut decoder)?.tref {
+        chapter_ids.extend(
+          tref
+            .decode(&mut decoder)?
+            .references
+            .iter()
+            .filter(|reference| *reference.reference_type == TrackReference::CHAPTER)
+            .flat_map(|reference| reference.track_ids.iter().copied()),
+        );
+      }
+    }
+    for trak in &mut *root.moov.trak {
+      let trak = trak.decode(&mut decoder)?;
+      let id = trak.tkhd.decode(&mut decoder)?.track_id;
+      match track_id {
+        Some(track_id) if track_id != id => continue,
+        None if chapter_ids.contains(&id) => continue,
+        _ => {}
+      }
+      let mdia = trak.mdia.decode(&mut decoder)?;
+      let hdlr = mdia.hdlr.decode(&mut decoder)?;
+      if !matches!(&*hdlr.component_subtype, b"text" | b"sbtl") {
+        match track_id {
+          Some(_) => return Err(VideoError::TextTrack),
+          None => continue,
+        }
+      }
+      let timescale = mdia.mdhd.decode(&mut decoder)?.timescale;
+      let elst = match &mut trak.edts {
+        Some(edts) => Some(&*edts.decode(&mut decoder)?.elst.decode(&mut decoder)?),
+        None => None,
+      };
+      let stbl = mdia.minf.decode(&mut decoder)?.stbl.decode(&mut decoder)?;
+      let index = SampleIndex::new(&mut decoder, stbl, &root.moof, mvex.as_deref(), id)
+        .map_err(DecoderError::from)?;
+      let cues = match Self::text_cues(&mut decoder, stbl, &index, elst, timescale, movie_timescale)
+      {
+        Err(VideoError::Decoding(DecoderError::MissingConfig)) if track_id.is_none() => continue,
+        Err(VideoError::Decoding(DecoderError::MissingConfig)) => {
+          return Err(VideoError::TextTrack)
+        }
+        cues => cues?,
+      };
+      let format = SubtitleFormat::from_path(&output);
+      let mut writer = SubtitleWriter::new(BufWriter::new(File::create(output)?), format)
+        .map_err(DecoderError::from)?;
+      for (start, end, sample) in &cues {
+        writer
+          .write_cue(*start, *end, sample)
+          .map_err(DecoderError::from)?;
+      }
+      writer.flush().map_err(DecoderError::from)?;
+      log!(File@"EXTRACTED {} CUES OF TRACK #{id}", writer.cues());
+      return Ok(writer.cues());
+    }
+    Err(VideoError::TextTrack)
+  }
+
+  /// Presentation interval in milliseconds and decoded text of the samples of a text track,
+  /// samples trimmed by the edit list are skipped
+  fn text_cues(
+    decoder: &mut Decoder,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    elst: Option<&ElstAtom>,
+    timescale: u32,
+    movie_timescale: u32,
+  ) -> VideoResult<Vec<(u64, u64, TextSample)>> {
+    let samples = decoder.text_samples(stbl, index)?;
+    let ms = |time: i64| time.max(0) as u64 * 1000 / timescale.max(1) as u64;
+    Ok(
+      index
+        .iter()
+        .zip(samples)
+        .filter_map(|(sample, text)| {
+          let start = match elst {
+            Some(elst) => {
+              elst.presentation_time(sample.composition_time, movie_timescale, timescale)?
+            }
+            None => sample.composition_time,
+          };
+          Some((ms(start), ms(start + sample.duration as i64), text))
+        })
+        .collect(),
+    )
+  }
+
+  /// Frame numbers of the samples of a `tmcd` track with their presentation times, samples
+  /// trimmed by the edit list are skipped
+  fn timecode_track(
+    decoder: &mut Decoder,
+    stbl: &mut StblAtom,
+    index: &SampleIndex,
+    elst: Option<&ElstAtom>,
+    track: &Track,
+    movie_timescale: u32,
+  ) -> VideoResult<TimecodeTrack> {
+    let frames = decoder.timecode_samples(stbl, index)?;
+    let Some(CodecData::Tmcd(tmcd)) = stbl
+      .stsd
+      .decode(decoder)?
+      .sample_description_table
+      .first()
+      .map(|entry| &entry.data)
+    else {
+      return Err(DecoderError::MissingConfig.into());
+    };
+    let samples = index.iter().zip(frames).filter_map(|(sample, frame)| {
+      let start = match elst {
+        Some(elst) => {
+          elst.presentation_time(sample.composition_time, movie_timescale, track.timescale)?
+        }
+        None => sample.composition_time,
+      };
+      Some((start as f64 / track.timescale.max(1) as f64, frame))
+    });
+    Ok(TimecodeTrack::new(track.id, tmcd, samples))
+  }
+
+  /// Appends the tags of `meta` to `tags`, metadata that can't be read is skipped
+  fn collect_tags(meta: &mut MetaAtom, tags: &mut Vec<(Rc<str>, DataValue)>) {
+    match meta.tags() {
+      Ok(meta_tags) => tags.extend(meta_tags),
+      Err(e) => log!(warn@"#[meta] {e}"),
+    }
+  }
+
+  /// Writes the first `covr` image of the movie or user data metadata to `output`, returns the
+  /// file extension of the image format and the number of bytes written
+  pub fn extract_cover_art<P: AsRef<Path>, O: AsRef<Path>>(
+    path: P,
+    output: O,
+  ) -> VideoResult<(&'static str, usize)> {
+    let mut decoder = Decoder::open(path)?;
+    let mut root = decoder.decode_root()?;
+    let mut image = decoder
+      .decode_udta_meta(&mut root)?
+      .into_iter()
+      .find_map(|meta| meta.cover_art().and_then(DataValue::image))
+      .map(|(extension, data)| (extension, Box::<[u8]>::from(data)));
+    if image.is_none() {
+      image = root
+        .moov
+        .meta
+        .as_ref()
+        .and_then(MetaAtom::cover_art)
+        .and_then(DataValue::image)
+        .map(|(extension, data)| (extension, data.into()));
+    }
+    let (extension, data) = image.ok_or(VideoError::CoverArt)?;
+    File::create(output)?.write_all(&data)?;
+    Ok((extension, data.len()))
+  }
+
+  /// Writes the AAC samples of the audio track `track_id`, or of the first AAC track if `None`,
+  /// presented between `start` and `end` to `output` as ADTS frames, returns the number of
+  /// frames written
+  pub fn extract_adts<P: AsRef<Path>, O: AsRef<Path>>(
+    path: P,
+    output: O,
+    track_id: Option<u32>,
+    start: SeekPosition,
+    end: SeekPosition,
+  ) -> VideoResult<usize> {
+    let mut decoder = Decoder::open(path)?;
+    let mut root = decoder.decode_root()?;
+    let movie_timescale = root.moov.mvhd.decode(&mut decoder)?.timescale;
+    let mvex = root
+      .moov
+      .mvex
+      .as_mut()
+      .map(|mvex| mvex.decode(&mut decoder))
+      .transpose()?;
+    for trak in &mut *root.moov.trak {
+      let trak = trak.decode(&mut decoder)?;
+      let tkhd = trak.tkhd.decode(&mut decoder)?;
+      if track_id.is_some_and(|track_id| track_id != tkhd.track_id) {
+        continue;
+      }
+      let mdia = trak.mdia.decode(&mut decoder)?;
+      let hdlr = mdia.hdlr.decode(&mut decoder)?;
+      let stbl = mdia.minf.decode(&mut decoder)?.stbl.decode(&mut decoder)?;
+      let is_aac = stbl
+        .stsd
+        .decode(&mut decoder)?
+        .sample_description_table
+        .first()
+        .is_some_and(|entry| match &entry.data {
+          CodecData::Mp4a(mp4a) => mp4a.audio_specific_config().is_some(),
+          _ => false,
+        });
+      if *hdlr.component_subtype != *b"soun" || !is_aac {
+        match track_id {
+          Some(_) => return Err(VideoError::AudioTrack),
+          None => continue,
+        }
+      }
+      let elst = match &mut trak.edts {
+        Some(edts) => Some(&*edts.decode(&mut decoder)?.elst.decode(&mut decoder)?),
+        None => None,
+      };
+      let index = SampleIndex::new(&mut decoder, stbl, &root.moof, mvex.as_deref(), tkhd.track_id)
+        .map_err(DecoderError::from)?;
+      let mdhd = mdia.mdhd.decode(&mut decoder)?;
+      let track = Track::new(
+        tkhd,
+        hdlr,
+        mdhd,
+        stbl.stsd.decode(&mut decoder)?,
+        &index,
+        elst,
+        None,
+        movie_timescale,
+      );
+      let media_time = |position: SeekPosition| {
+        let time = position.media_time(track.timescale, track.duration);
+        match elst {
+          Some(elst) => elst.media_time(time, movie_timescale, track.timescale),
+          None => Some(time),
+        }
+      };
+      let start = media_time(start).unwrap_or(i64::MAX);
+      let end = media_time(end).unwrap_or(i64::MAX);
+      log!(File@"ADTS TRACK #{} MEDIA TIME {start}..{end}", track.id);
+      let writer = BufWriter::new(File::create(output)?);
+      return Ok(decoder.extract_adts(stbl, &index, start, end, writer)?);
+    }
+    Err(VideoError::AudioTrack)
+  }
+
+  /// Writes the audio samples of the track `track_id`, or of the first PCM or AAC track if `None`,
+  /// to `output` as a WAV file, returns the number of bytes of audio data written
+  pub fn extract_wav<P: AsRef<Path>, O: AsRef<Path>>(
+    path: P,
+    output: O,
+    track_id: Option<u32>,
+  ) -> VideoResult<u64> {
+    let mut decoder = Decoder::open(path)?;
+    let mut root = decoder.decode_root()?;
+    let mvex = root
+      .moov
+      .mvex
+      .as_mut()
+      .map(|mvex| mvex.decode(&mut decoder))
+      .transpose()?;
+    for trak in &mut *root.moov.trak {
+      let trak = trak.decode(&mut decoder)?;
+      let tkhd = trak.tkhd.decode(&mut decoder)?;
+      if track_id.is_some_and(|track_id| track_id != tkhd.track_id) {
+        continue;
+      }
+      let mdia = trak.mdia.decode(&mut decoder)?;
+      let hdlr = mdia.hdlr.decode(&mut decoder)?;
+      let stbl = mdia.minf.decode(&mut decoder)?.stbl.decode(&mut decoder)?;
+      let is_supported = stbl
+        .stsd
+        .decode(&mut decoder)?
+        .sample_description_table
+        .first()
+        .is_some_and(|entry| match &entry.data {
+          CodecData::Pcm(_) => true,
+          CodecData::Mp4a(mp4a) => mp4a.audio_specific_config().is_some(),
+          _ => false,
+        });
+      if *hdlr.component_subtype != *b"soun" || !is_supported {
+        match track_id {
+          Some(_) => return Err(VideoError::WavTrack),
+          None => continue,
+        }
+      }
+      let index = SampleIndex::new(&mut decoder, stbl, &root.moof, mvex.as_deref(), tkhd.track_id)
+        .map_err(DecoderError::from)?;
+      log!(File@"WAV TRACK #{}", tkhd.track_id);
+      let writer = BufWriter::new(File::create(output)?);
+      return Ok(decoder.extract_wav(stbl, &index, writer)?);
+    }
+    Err(VideoError::WavTrack)
+  }
+
+  /// Opens a raw H.264 Annex B byte stream, the timing is taken from the SPS VUI if present
+  fn open_annex_b(decoder: &mut Decoder) -> VideoResult<Self> {
+    let stream = decoder.decode_annex_b()?;
+    let (width, height) = stream.sps.frame_size();
+    let timing_info = stream
+      .sps
+      .vui_parameters
+      .as_ref()
+      .and_then(|vui| vui.timing_info.as_ref());
+    Ok(Self {
+      timescale: timing_info
+        .map(|timing| timing.time_scale)
+        .unwrap_or_default(),
+      duration: timing_info
+        .filter(|timing| timing.time_scale != 0)
+        .map(|timing| {
+          Duration::from_secs_f32(
+            stream.access_units as f32 * 2. * timing.num_units_in_tick as f32
+              / timing.time_scale as f32,
+          )
+        })
+        .unwrap_or_default(),
+      frame_time: Duration::default(),
+      width: width as f32,
+      height: height as f32,
+      matrix: Matrix3x3::identity(),
+      video_codec: VideoCodec::H264,
+      colour: stream
+        .sps
+        .vui_parameters
+        .as_ref()
+        .and_then(|vui| vui.video_signal_type.as_ref())
+        .map(VideoSignalType::colour),
+      visual: VisualExtensions::default(),
+      creation_time: Date::default(),
+      modification_time: Date::default(),
+      metadata: Box::default(),
+      camera: CameraMetadata::default(),
+      track_id: 0,
+      tracks: Box::default(),
+      chapters: Box::default(),
+      timecode: None,
+      timecode_track: None,
+      fragments: 0,
+      segment_index: Box::default(),
+      random_access: Box::default(),
     })
   }
 }
@@ -109,25 +721,157 @@ impl fmt::Display for Video {
     write!(
       f,
       "{title}VIDEO INFO{RESET}\n\
+      - {title}Video Track:{RESET} #{}\n\
       - {title}Video Codec:{RESET} {:?}\n\
       - {title}Matrix:{RESET}\n{}\
       - {title}Rotation:{RESET} {}°\n\
       - {title}Width:{RESET} {}\n\
       - {title}Height:{RESET} {}\n\
       - {title}Duration:{RESET} {}\n\
-      - {title}Timescale:{RESET} {:?}",
+      - {title}Frame Time:{RESET} {}\n\
+      - {title}Timescale:{RESET} {:?}\n\
+      - {title}Created:{RESET} {}\n\
+      - {title}Modified:{RESET} {}",
+      self.track_id,
       self.video_codec,
       self.matrix,
       self.matrix.rotation(),
       self.width,
       self.height,
       self.duration,
+      self.frame_time,
       self.timescale,
+      self.creation_time,
+      self.modification_time,
       title = "".rgb(75, 205, 94).bold(),
-    )
+    )?;
+    if let Some(timecode) = &self.timecode {
+      write!(
+        f,
+        "\n- {}Timecode:{RESET} {timecode}",
+        "".rgb(75, 205, 94).bold()
+      )?;
+    }
+    if let Some(track) = &self.timecode_track {
+      let title = "".rgb(75, 205, 94).bold();
+      if let Some(start) = track.start() {
+        write!(
+          f,
+          "\n- {title}Start Timecode:{RESET} {start} (track #{})",
+          track.track_id
+        )?;
+      }
+      if let Some(source_name) = &track.source_name {
+        write!(f, "\n- {title}Timecode Source:{RESET} {source_name}")?;
+      }
+    }
+    self.fmt_fragments(f)?;
+    self.fmt_visual(f)?;
+    if !self.camera.is_empty() {
+      let title = "".rgb(75, 205, 94).bold();
+      write!(f, "\n{title}CAMERA{RESET}")?;
+      let camera = &self.camera;
+      if let Some(make) = &camera.make {
+        write!(f, "\n- {title}Make:{RESET} {make}")?;
+      }
+      if let Some(model) = &camera.model {
+        write!(f, "\n- {title}Model:{RESET} {model}")?;
+      }
+      if let Some(software) = &camera.software {
+        write!(f, "\n- {title}Software:{RESET} {software}")?;
+      }
+      if let Some(date) = &camera.creation_date {
+        write!(f, "\n- {title}Captured:{RESET} {date}")?;
+      }
+      if let Some(location) = &camera.location {
+        write!(f, "\n- {title}Location:{RESET} {location}")?;
+      }
+    }
+    if !self.metadata.is_empty() {
+      write!(f, "\n{}METADATA{RESET}", "".rgb(75, 205, 94).bold())?;
+      for (key, value) in &*self.metadata {
+        let title = "".rgb(75, 205, 94).bold();
+        let name = item_name(key).unwrap_or(key);
+        write!(f, "\n- {title}{name}:{RESET} {value}")?;
+      }
+    }
+    if !self.chapters.is_empty() {
+      write!(f, "\n{}CHAPTERS{RESET}", "".rgb(75, 205, 94).bold())?;
+      for chapter in &*self.chapters {
+        let title = "".rgb(75, 205, 94).bold();
+        write!(f, "\n- {title}{}:{RESET} {}", chapter.start, chapter.title)?;
+      }
+    }
+    if !self.tracks.is_empty() {
+      write!(f, "\n{}TRACKS{RESET}", "".rgb(75, 205, 94).bold())?;
+      for track in &*self.tracks {
+        write!(f, "\n{track}")?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Video {
+  /// Fragment count and the `sidx` and `mfra` indexes of fragmented files
+  fn fmt_fragments(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let title = "".rgb(75, 205, 94).bold();
+    if self.fragments > 0 {
+      write!(f, "\n- {title}Fragments:{RESET} {}", self.fragments)?;
+    }
+    for sidx in &*self.segment_index {
+      write!(f, "\n- {title}Segment Index:{RESET} {sidx}")?;
+    }
+    for tfra in &*self.random_access {
+      write!(f, "\n- {title}Random Access Points:{RESET} {tfra}")?;
+    }
+    Ok(())
+  }
+
+  /// Colour and picture properties of the sample entry, only the ones that are known
+  fn fmt_visual(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let title = "".rgb(75, 205, 94).bold();
+    let visual = &self.visual;
+    if let Some(colour) = &self.colour {
+      write!(f, "\n- {title}Colour:{RESET} {colour}")?;
+    }
+    for colr in &*visual.colr {
+      if let ColrAtom::Icc { .. } = colr {
+        write!(f, "\n- {title}Colour Profile:{RESET} {colr}")?;
+      }
+    }
+    if let Some(pasp) = &visual.pasp {
+      write!(f, "\n- {title}Pixel Aspect Ratio:{RESET} {pasp}")?;
+    }
+    if let Some(clap) = &visual.clap {
+      write!(f, "\n- {title}Clean Aperture:{RESET} {clap}")?;
+    }
+    if let Some(fiel) = &visual.fiel {
+      write!(f, "\n- {title}Fields:{RESET} {fiel}")?;
+    }
+    if let Some(gama) = visual.gama {
+      write!(f, "\n- {title}Gamma:{RESET} {gama}")?;
+    }
+    if let Some(btrt) = &visual.btrt {
+      write!(f, "\n- {title}Bitrate:{RESET} {btrt}")?;
+    }
+    if let Some(mdcv) = &visual.mdcv {
+      write!(f, "\n- {title}Mastering Display:{RESET} {mdcv}")?;
+    }
+    if let Some(clli) = &visual.clli {
+      write!(f, "\n- {title}Content Light Level:{RESET} {clli}")?;
+    }
+    Ok(())
   }
 }
 
+/// Title of the chapter presented from `start`
+#[derive(Debug)]
+pub struct Chapter {
+  pub start: Duration,
+  pub title: Box<str>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum SeekPosition {
   Seconds(i64),
@@ -153,6 +897,18 @@ impl FromStr for SeekPosition {
   }
 }
 
+impl SeekPosition {
+  /// Converts the position to media time given the track `timescale` and `duration`
+  pub fn media_time(&self, timescale: u32, duration: u64) -> i64 {
+    match *self {
+      Self::Seconds(s) => s * timescale as i64,
+      Self::Milliseconds(ms) => ms * timescale as i64 / 1000,
+      Self::Percentage(p) => (duration as f64 * p) as i64,
+      Self::TimeBase(ts) => ts,
+    }
+  }
+}
+
 impl Default for SeekPosition {
   fn default() -> Self {
     Self::TimeBase(0)
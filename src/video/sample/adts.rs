@@ -0,0 +1,108 @@
+use super::*;
+use std::io::Write;
+
+/// ADTS header size without CRC
+const HEADER_SIZE: usize = 7;
+const MAX_FRAME_LENGTH: usize = 0x1FFF;
+
+/// Wraps raw AAC access units in ADTS frames, ISO/IEC 13818-7 6.2 / ISO/IEC 14496-3 1.A.2
+pub struct AdtsWriter<W: Write> {
+  writer: W,
+  header: [u8; HEADER_SIZE],
+}
+
+impl<W: Write> AdtsWriter<W> {
+  /// Builds the fixed part of the header from `config`, HE-AAC is signalled implicitly so the
+  /// core object type and sampling frequency are used
+  pub fn new(writer: W, config: &AudioSpecificConfig) -> SampleResult<Self> {
+    if !(1..=4).contains(&config.audio_object_type) {
+      return Err(SampleError::Adts(
+        "audio object type",
+        config.audio_object_type as u32,
+      ));
+    }
+    if config.sampling_frequency_index > 12 {
+      return Err(SampleError::Adts(
+        "sampling frequency",
+        config.sampling_frequency,
+      ));
+    }
+    if config.channel_configuration > 7 {
+      return Err(SampleError::Adts(
+        "channel configuration",
+        config.channel_configuration as u32,
+      ));
+    }
+    let profile = config.audio_object_type - 1;
+    let channels = config.channel_configuration;
+    Ok(Self {
+      writer,
+      header: [
+        0xFF,
+        // syncword, MPEG-4, layer 0, protection_absent
+        0xF1,
+        profile << 6 | config.sampling_frequency_index << 2 | channels >> 2,
+        (channels & 0b11) << 6,
+        0,
+        // adts_buffer_fullness 0x7FF signals a variable bitrate stream
+        0x1F,
+        0xFC,
+      ],
+    })
+  }
+
+  /// Writes the header with the frame length of `sample` followed by the access unit
+  pub fn write_sample(&mut self, sample: &[u8]) -> SampleResult {
+    let frame_length = sample.len() + HEADER_SIZE;
+    if frame_length > MAX_FRAME_LENGTH {
+      return Err(SampleError::Adts("frame length", frame_length as u32));
+    }
+    self.header[3] = self.header[3] & 0xFC | (frame_length >> 11) as u8 & 0b11;
+    self.header[4] = (frame_length >> 3) as u8;
+    self.header[5] = ((frame_length & 0b111) as u8) << 5 | 0x1F;
+    self.writer.write_all(&self.header)?;
+    self.writer.write_all(sample)?;
+    Ok(())
+  }
+
+  pub fn flush(&mut self) -> SampleResult {
+    Ok(self.writer.flush()?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn adts_header() {
+    // AAC LC, 44.1 kHz, stereo
+    let config = AudioSpecificConfig::decode(&[0x12, 0x10]).unwrap();
+    let mut writer = AdtsWriter::new(Vec::new(), &config).unwrap();
+    writer.write_sample(&[0xAA; 10]).unwrap();
+    writer.write_sample(&[0xBB; 0x800]).unwrap();
+    let output = writer.writer;
+    assert_eq!(output[..7], [0xFF, 0xF1, 0x50, 0x80, 0x02, 0x3F, 0xFC]);
+    assert_eq!(output[7..17], [0xAA; 10]);
+    assert_eq!(output[17..24], [0xFF, 0xF1, 0x50, 0x81, 0x00, 0xFF, 0xFC]);
+    assert_eq!(output.len(), 24 + 0x800);
+  }
+
+  #[test]
+  fn unsupported_adts() {
+    let config = AudioSpecificConfig {
+      audio_object_type: 39,
+      ..Default::default()
+    };
+    assert!(matches!(
+      AdtsWriter::new(Vec::new(), &config),
+      Err(SampleError::Adts("audio object type", 39))
+    ));
+    let config = AudioSpecificConfig::decode(&[0x12, 0x10]).unwrap();
+    let mut writer = AdtsWriter::new(Vec::new(), &config).unwrap();
+    assert!(matches!(
+      writer.write_sample(&[0; MAX_FRAME_LENGTH]),
+      Err(SampleError::Adts("frame length", _))
+    ));
+  }
+}
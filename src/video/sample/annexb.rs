@@ -0,0 +1,504 @@
+use super::*;
+use std::collections::HashMap;
+use std::io::Write;
+
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// Number of bytes read from the byte stream at a time
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Reads the NAL units of an Annex B byte stream, B.2 Byte stream NAL unit decoding process.
+/// The stream is buffered a chunk at a time, only the NAL unit being read is kept in memory
+#[derive(Debug)]
+pub struct AnnexBIter<R: Read> {
+  reader: R,
+  buffer: Vec<u8>,
+  /// Start of the unread bytes of `buffer`
+  offset: usize,
+  eof: bool,
+}
+
+impl<R: Read> AnnexBIter<R> {
+  pub fn new(reader: R) -> Self {
+    Self {
+      reader,
+      buffer: Vec::new(),
+      offset: 0,
+      eof: false,
+    }
+  }
+
+  /// Drops the read bytes of the buffer and appends the next chunk of the stream, returns false
+  /// at the end of the stream. Indices into the buffer are shifted back by the previous `offset`
+  fn fill(&mut self) -> SampleResult<bool> {
+    if self.eof {
+      return Ok(false);
+    }
+    self.buffer.drain(..self.offset);
+    self.offset = 0;
+    let len = self.buffer.len();
+    self.buffer.resize(len + CHUNK_SIZE, 0);
+    let read = loop {
+      match self.reader.read(&mut self.buffer[len..]) {
+        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+        read => break read?,
+      }
+    };
+    self.buffer.truncate(len + read);
+    self.eof = read == 0;
+    Ok(!self.eof)
+  }
+
+  /// Index of the first window of 3 bytes at or after `from` matching `pattern`
+  fn find_window(&self, from: usize, pattern: fn(&[u8]) -> bool) -> Option<usize> {
+    self.buffer[from..]
+      .windows(3)
+      .position(pattern)
+      .map(|i| from + i)
+  }
+
+  /// Moves `offset` to the first byte after the next `0x000001` start code prefix
+  fn next_start_code(&mut self) -> SampleResult<bool> {
+    loop {
+      if let Some(i) = self.find_window(self.offset, |window| window == [0, 0, 1]) {
+        self.offset = i + 3;
+        return Ok(true);
+      }
+      // The last 2 bytes may be the start of a start code split across chunks
+      self.offset = self.buffer.len().saturating_sub(2).max(self.offset);
+      if !self.fill()? {
+        return Ok(false);
+      }
+    }
+  }
+
+  /// Index of the end of the NAL unit starting at `offset`, trailing_zero_8bits and the
+  /// zero_byte of a following 4 byte start code are not part of the NAL unit
+  fn nal_end(&mut self) -> SampleResult<usize> {
+    let mut from = self.offset;
+    loop {
+      let end = self.find_window(from, |window| {
+        window[0] == 0 && window[1] == 0 && window[2] <= 1
+      });
+      if let Some(end) = end {
+        return Ok(end);
+      }
+      let searched = self.buffer.len().saturating_sub(2).max(self.offset) - self.offset;
+      if !self.fill()? {
+        let trailing_zeros = self.buffer[self.offset..]
+          .iter()
+          .rev()
+          .take_while(|byte| **byte == 0)
+          .count();
+        return Ok(self.buffer.len() - trailing_zeros);
+      }
+      from = self.offset + searched;
+    }
+  }
+}
+
+impl<R: Read> Iterator for AnnexBIter<R> {
+  /// NAL unit with its header byte
+  type Item = SampleResult<Box<[u8]>>;
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      match self.next_start_code() {
+        Ok(true) => (),
+        Ok(false) => return None,
+        Err(e) => return Some(Err(e)),
+      }
+      let end = match self.nal_end() {
+        Ok(end) => end,
+        Err(e) => return Some(Err(e)),
+      };
+      let unit = &self.buffer[self.offset..end.max(self.offset)];
+      self.offset = end.max(self.offset);
+      match unit.first() {
+        None => continue,
+        Some(header) if header & 0x80 != 0 => return Some(Err(SampleError::NALForbiddenZeroBit)),
+        Some(_) => return Some(Ok(unit.into())),
+      }
+    }
+  }
+}
+
+/// NAL unit view of a unit read by [`AnnexBIter`]
+fn nal_unit(unit: &[u8]) -> NALUnit<'_> {
+  NALUnit {
+    header: unit[0],
+    idc: unit[0] >> 5,
+    unit_type: NALUnitType::new(unit[0] & 0x1F),
+    size: unit.len(),
+    data: &unit[1..],
+  }
+}
+
+#[derive(Debug)]
+pub struct AccessUnit {
+  units: Box<[Box<[u8]>]>,
+}
+
+impl AccessUnit {
+  pub fn units(&self) -> impl Iterator<Item = NALUnit<'_>> {
+    self.units.iter().map(|unit| nal_unit(unit))
+  }
+
+  pub fn len(&self) -> usize {
+    self.units.len()
+  }
+
+  pub fn is_idr(&self) -> bool {
+    self.units().any(|nal| nal.unit_type.is_idr())
+  }
+}
+
+/// Slice header fields which differ between the first VCL NAL units of two primary coded
+/// pictures, 7.4.1.2.4 Detection of the first VCL NAL unit of a primary coded picture
+#[derive(Debug, PartialEq)]
+struct PictureId {
+  pps_id: u16,
+  frame_num: u16,
+  field_pic_flag: bool,
+  bottom_field_flag: bool,
+  /// Whether `nal_ref_idc` is not 0
+  is_reference: bool,
+  /// Only set for IDR pictures
+  idr_pic_id: Option<u16>,
+  pic_order_cnt_lsb: Option<u16>,
+  delta_pic_order_cnt_bottom: Option<i16>,
+  delta_pic_order_cnt: Option<(i16, Option<i16>)>,
+}
+
+/// Groups the NAL units of an Annex B byte stream into access units
+#[derive(Debug)]
+pub struct AccessUnitIter<R: Read> {
+  units: AnnexBIter<R>,
+  pending: Option<Box<[u8]>>,
+  sps: HashMap<u16, SequenceParameterSet>,
+  pps: HashMap<u16, PictureParameterSet>,
+  /// Picture of the last VCL NAL unit of the current access unit
+  picture: Option<PictureId>,
+}
+
+impl<R: Read> AccessUnitIter<R> {
+  pub fn new(reader: R) -> Self {
+    Self {
+      units: AnnexBIter::new(reader),
+      pending: None,
+      sps: HashMap::new(),
+      pps: HashMap::new(),
+      picture: None,
+    }
+  }
+
+  /// Keeps the parameter sets needed to read the slice headers
+  fn parameter_set(&mut self, nal: &NALUnit) {
+    let mut data = BitStream::new(nal.rbsp());
+    match nal.unit_type {
+      NALUnitType::Sps => {
+        let sps = SequenceParameterSet::decode_rbsp(nal.size as u16, nal.header, &mut data);
+        self.sps.insert(sps.id, sps);
+      }
+      NALUnitType::Pps => {
+        let chroma_format_idc = self
+          .sps
+          .get(&PictureParameterSet::seq_parameter_set_id(nal.rbsp()))
+          .map_or(1, |sps| sps.chroma_format_idc);
+        let pps = PictureParameterSet::decode_rbsp(
+          nal.size as u16,
+          nal.header,
+          &mut data,
+          chroma_format_idc,
+        );
+        self.pps.insert(pps.id, pps);
+      }
+      _ => (),
+    }
+  }
+
+  /// Reads the fields of a slice header compared by 7.4.1.2.4, `None` if its parameter sets
+  /// have not been seen
+  fn picture_id(&self, nal: &NALUnit) -> Option<PictureId> {
+    let mut data = BitStream::new(nal.rbsp());
+    data.exponential_golomb::<u16>();
+    data.exponential_golomb::<u16>();
+    let pps_id = data.exponential_golomb();
+    let pps = self.pps.get(&pps_id)?;
+    let sps = self.sps.get(&pps.seq_parameter_set_id)?;
+    if sps.separate_color_plane_flag {
+      data.bits_into::<u8>(2);
+    }
+    let frame_num = data.bits_into(sps.log2_max_frame_num_minus4 as usize + 4);
+    let field_pic_flag = !sps.frame_mbs_only_flag && data.bit_flag();
+    let bottom_field_flag = field_pic_flag && data.bit_flag();
+    let idr_pic_id = nal.unit_type.is_idr().then(|| data.exponential_golomb());
+    let pic_order_cnt_lsb = sps
+      .log2_max_pic_order_cnt_lsb_minus4
+      .map(|size| data.bits_into(size as usize + 4));
+    let delta_pic_order_cnt_bottom = (pic_order_cnt_lsb.is_some()
+      && pps.bottom_field_pic_order_in_frame_present_flag
+      && !field_pic_flag)
+      .then(|| data.signed_exponential_golomb());
+    let delta_pic_order_cnt = sps
+      .pic_order_cnt_type_one
+      .as_ref()
+      .is_some_and(|type_one| !type_one.delta_pic_order_always_zero_flag)
+      .then(|| {
+        let delta = data.signed_exponential_golomb();
+        let bottom = (pps.bottom_field_pic_order_in_frame_present_flag && !field_pic_flag)
+          .then(|| data.signed_exponential_golomb());
+        (delta, bottom)
+      });
+    Some(PictureId {
+      pps_id,
+      frame_num,
+      field_pic_flag,
+      bottom_field_flag,
+      is_reference: nal.idc != 0,
+      idr_pic_id,
+      pic_order_cnt_lsb,
+      delta_pic_order_cnt_bottom,
+      delta_pic_order_cnt,
+    })
+  }
+
+  /// 7.4.1.2.3 Order of NAL units and coded pictures and association to access units
+  ///
+  /// Only called once the current access unit has a VCL NAL unit. Parameter sets, SEI and
+  /// access unit delimiters start the next access unit, the first slice of the next primary
+  /// coded picture is detected by the slice header fields of 7.4.1.2.4. Slices with unknown
+  /// parameter sets fall back to their `first_mb_in_slice` being 0
+  fn starts_access_unit(&self, nal: &NALUnit, picture: Option<&PictureId>) -> bool {
+    match nal.unit_type {
+      NALUnitType::AccessUnitDelimeter
+      | NALUnitType::Sps
+      | NALUnitType::Pps
+      | NALUnitType::Sei
+      | NALUnitType::PrefixNALUnit
+      | NALUnitType::SubsetSPS
+      | NALUnitType::DepthParameterSet
+      | NALUnitType::Reserved => true,
+      NALUnitType::NonIDRPicture | NALUnitType::IDRPicture | NALUnitType::DataPartitionA => {
+        match (picture, &self.picture) {
+          (Some(picture), Some(previous)) => picture != previous,
+          _ => BitStream::new(nal.rbsp()).exponential_golomb::<u32>() == 0,
+        }
+      }
+      _ => false,
+    }
+  }
+}
+
+impl<R: Read> Iterator for AccessUnitIter<R> {
+  type Item = SampleResult<AccessUnit>;
+  fn next(&mut self) -> Option<Self::Item> {
+    let mut units = Vec::new();
+    let mut has_vcl = false;
+    loop {
+      let unit = match self.pending.take().map(Ok).or_else(|| self.units.next()) {
+        Some(Ok(unit)) => unit,
+        Some(Err(e)) => return Some(Err(e)),
+        None => break,
+      };
+      let nal = nal_unit(&unit);
+      let picture = matches!(
+        nal.unit_type,
+        NALUnitType::NonIDRPicture | NALUnitType::IDRPicture | NALUnitType::DataPartitionA
+      )
+      .then(|| self.picture_id(&nal))
+      .flatten();
+      if has_vcl && self.starts_access_unit(&nal, picture.as_ref()) {
+        self.pending = Some(unit);
+        break;
+      }
+      self.parameter_set(&nal);
+      has_vcl |= nal.unit_type.is_vcl();
+      if picture.is_some() {
+        self.picture = picture;
+      }
+      units.push(unit);
+    }
+
+    (!units.is_empty()).then(|| {
+      Ok(AccessUnit {
+        units: units.into_boxed_slice(),
+      })
+    })
+  }
+}
+
+/// Rewrites length prefixed samples into an Annex B byte stream
+pub struct AnnexBWriter<W: Write> {
+  writer: W,
+  sps_units: ParameterSetUnits,
+  pps_units: ParameterSetUnits,
+  insert_aud: bool,
+}
+
+impl<W: Write> AnnexBWriter<W> {
+  pub fn new(
+    writer: W,
+    sps_units: ParameterSetUnits,
+    pps_units: ParameterSetUnits,
+    insert_aud: bool,
+  ) -> Self {
+    Self {
+      writer,
+      sps_units,
+      pps_units,
+      insert_aud,
+    }
+  }
+
+  /// Writes every NAL unit of the sample behind a start code, NAL units are copied verbatim so
+  /// emulation prevention bytes are preserved. The out-of-band parameter sets are injected
+  /// at the start of IDR access units unless the sample already carries them
+  pub fn write_sample(&mut self, sample: &Sample, nal_length_size: usize) -> SampleResult {
+    let units = sample
+      .units(nal_length_size)
+      .collect::<SampleResult<Vec<_>>>()?;
+
+    let has_aud = units
+      .iter()
+      .any(|nal| matches!(nal.unit_type, NALUnitType::AccessUnitDelimeter));
+    if self.insert_aud && !has_aud {
+      let primary_pic_type = Self::primary_pic_type(&units);
+      self.write_unit(&[NALUnitType::AUD_HEADER, primary_pic_type << 5 | 0x10])?;
+    }
+
+    let is_idr = units.iter().any(|nal| nal.unit_type.is_idr());
+    let has_parameter_sets = units
+      .iter()
+      .any(|nal| matches!(nal.unit_type, NALUnitType::Sps));
+    let mut inject_parameter_sets = is_idr && !has_parameter_sets;
+    for nal in &units {
+      let is_aud = matches!(nal.unit_type, NALUnitType::AccessUnitDelimeter);
+      if inject_parameter_sets && !is_aud {
+        for i in 0..self.sps_units.len() {
+          self.writer.write_all(&START_CODE)?;
+          self.writer.write_all(&self.sps_units[i])?;
+        }
+        for i in 0..self.pps_units.len() {
+          self.writer.write_all(&START_CODE)?;
+          self.writer.write_all(&self.pps_units[i])?;
+        }
+        inject_parameter_sets = false;
+      }
+      self.writer.write_all(&START_CODE)?;
+      self.writer.write_all(&[nal.header])?;
+      self.writer.write_all(nal.rbsp())?;
+    }
+    Ok(())
+  }
+
+  pub fn flush(&mut self) -> SampleResult {
+    Ok(self.writer.flush()?)
+  }
+
+  fn write_unit(&mut self, unit: &[u8]) -> SampleResult {
+    self.writer.write_all(&START_CODE)?;
+    Ok(self.writer.write_all(unit)?)
+  }
+
+  /// 7.4.2.4 Table 7-5 smallest primary_pic_type containing every slice type of the sample
+  fn primary_pic_type(units: &[NALUnit]) -> u8 {
+    const SLICE_TYPES: [&[u8]; 8] = [
+      &[2],
+      &[0, 2],
+      &[0, 1, 2],
+      &[4],
+      &[3, 4],
+      &[2, 4],
+      &[0, 2, 3, 4],
+      &[0, 1, 2, 3, 4],
+    ];
+    let slice_types = units.iter().filter_map(|nal| nal.slice_type());
+    SLICE_TYPES
+      .iter()
+      .position(|allowed| slice_types.clone().all(|t| allowed.contains(&t)))
+      .unwrap_or(7) as u8
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Reads one byte at a time so start codes are split across every chunk boundary
+  struct ByteReader<'a>(&'a [u8]);
+
+  impl Read for ByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+      let Some((byte, rest)) = self.0.split_first() else {
+        return Ok(0);
+      };
+      buf[0] = *byte;
+      self.0 = rest;
+      Ok(1)
+    }
+  }
+
+  fn units<R: Read>(reader: R) -> Vec<Box<[u8]>> {
+    AnnexBIter::new(reader)
+      .collect::<SampleResult<_>>()
+      .unwrap()
+  }
+
+  #[test]
+  fn split_nal_units() {
+    let data = [
+      0xFF, 0, 0, 0, 1, 0x09, 0xF0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0x68, 0xCE, 0,
+      0,
+    ];
+    let expected: [&[u8]; 3] = [&[0x09, 0xF0], &[0x67, 0x42], &[0x68, 0xCE]];
+    assert_eq!(units(&data[..]), expected.map(Box::from));
+    assert_eq!(units(ByteReader(&data)), expected.map(Box::from));
+  }
+
+  #[test]
+  fn forbidden_zero_bit() {
+    let mut units = AnnexBIter::new(&[0, 0, 1, 0x80, 0xFF][..]);
+    assert!(matches!(
+      units.next(),
+      Some(Err(SampleError::NALForbiddenZeroBit))
+    ));
+  }
+
+  #[test]
+  fn group_access_units() {
+    // Baseline SPS with frame_num of 4 bits and pic_order_cnt_type 2, and its PPS
+    let sps: &[u8] = &[0x67, 0x42, 0x00, 0x1E, 0xDA, 0x79];
+    let pps: &[u8] = &[0x68, 0xCE, 0x38, 0x80];
+    // IDR slices, first_mb_in_slice 0 and 1 with idr_pic_id 0, then first_mb_in_slice 0 with
+    // idr_pic_id 1
+    let idr: &[u8] = &[0x65, 0x88, 0x86];
+    let idr_second_slice: &[u8] = &[0x65, 0x42, 0x21, 0x80];
+    let next_idr: &[u8] = &[0x65, 0x88, 0x82, 0x80];
+    // P slices with frame_num 1, first_mb_in_slice 1 and then 0 (arbitrary slice order)
+    let p_slice: &[u8] = &[0x41, 0x46, 0x8C];
+    let p_first_slice: &[u8] = &[0x41, 0x9A, 0x30];
+    let sei: &[u8] = &[0x06, 0x80];
+
+    let units = [
+      sps,
+      pps,
+      idr,
+      idr_second_slice,
+      next_idr,
+      p_slice,
+      p_first_slice,
+      sei,
+      p_first_slice,
+    ];
+    let mut data = Vec::new();
+    for unit in units {
+      data.extend(START_CODE);
+      data.extend(unit);
+    }
+    let access_units = AccessUnitIter::new(&data[..])
+      .map(|access_unit| access_unit.map(|access_unit| access_unit.len()))
+      .collect::<SampleResult<Vec<_>>>()
+      .unwrap();
+    assert_eq!(access_units, [4, 1, 2, 2]);
+  }
+}
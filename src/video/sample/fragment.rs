@@ -0,0 +1,122 @@
+use super::*;
+
+/// Location and timing of a sample stored in a movie fragment
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FragmentSample {
+  pub offset: u64,
+  pub size: u32,
+  pub duration: u32,
+  pub decode_time: u64,
+  pub composition_time_offset: i32,
+  pub flags: SampleFlags,
+  pub sample_description_index: u32,
+  /// Distance of the `roll` sample group of the sample
+  pub roll_distance: Option<i16>,
+}
+
+/// Walks the samples of a track across every movie fragment in file order
+#[derive(Debug, Clone)]
+pub struct FragmentIter {
+  samples: std::vec::IntoIter<FragmentSample>,
+}
+
+impl FragmentIter {
+  /// Resolves the offset, size, duration and flags of every sample of `track_id`, missing
+  /// fields fall back to the `tfhd` defaults and then to the `trex` defaults. Fragments without
+  /// a `tfdt` continue from `decode_time`, the end of the samples before them. `roll` sample
+  /// groups are looked up in the `traf` and then in the movie `sgpd`
+  pub fn new(
+    moofs: &[MoofAtom],
+    mvex: Option<&MvexAtom>,
+    track_id: u32,
+    decode_time: u64,
+    sgpd: &[SgpdAtom],
+  ) -> Self {
+    let mut samples = Vec::new();
+    let mut decode_time = decode_time;
+    for moof in moofs {
+      let mut data_end = moof.start();
+      for traf in &*moof.traf {
+        let tfhd = &traf.tfhd;
+        let trex = mvex.and_then(|mvex| mvex.trex(tfhd.track_id));
+        let is_track = tfhd.track_id == track_id;
+        let base_data_offset = tfhd
+          .base_data_offset
+          .unwrap_or(if tfhd.default_base_is_moof {
+            moof.start()
+          } else {
+            data_end
+          });
+        if let Some(tfdt) = traf.tfdt.as_ref().filter(|_| is_track) {
+          decode_time = tfdt.base_media_decode_time;
+        }
+        let mut roll_groups = traf
+          .sbgp
+          .iter()
+          .find(|sbgp| *sbgp.grouping_type == *b"roll")
+          .map(SbgpAtom::group_description_indices);
+
+        let mut offset = base_data_offset;
+        for trun in &*traf.trun {
+          if let Some(data_offset) = trun.data_offset {
+            offset = base_data_offset.wrapping_add_signed(data_offset as i64);
+          }
+          for (i, sample) in trun.samples.iter().enumerate() {
+            let size = sample
+              .size
+              .or(tfhd.default_sample_size)
+              .or(trex.map(|trex| trex.default_sample_size))
+              .unwrap_or_default();
+            if is_track {
+              let duration = sample
+                .duration
+                .or(tfhd.default_sample_duration)
+                .or(trex.map(|trex| trex.default_sample_duration))
+                .unwrap_or_default();
+              samples.push(FragmentSample {
+                offset,
+                size,
+                duration,
+                decode_time,
+                composition_time_offset: sample.composition_time_offset.unwrap_or_default(),
+                flags: trun
+                  .first_sample_flags
+                  .filter(|_| i == 0)
+                  .or(sample.flags)
+                  .or(tfhd.default_sample_flags)
+                  .or(trex.map(|trex| trex.default_sample_flags))
+                  .unwrap_or_default(),
+                sample_description_index: tfhd
+                  .sample_description_index
+                  .or(trex.map(|trex| trex.default_sample_description_index))
+                  .unwrap_or(1),
+                roll_distance: roll_groups
+                  .as_mut()
+                  .and_then(Iterator::next)
+                  .and_then(|index| SgpdAtom::roll_distance(sgpd, &traf.sgpd, index)),
+              });
+              decode_time += duration as u64;
+            }
+            offset += size as u64;
+          }
+        }
+        data_end = offset;
+      }
+    }
+
+    Self {
+      samples: samples.into_iter(),
+    }
+  }
+}
+
+impl Iterator for FragmentIter {
+  type Item = FragmentSample;
+  fn next(&mut self) -> Option<Self::Item> {
+    self.samples.next()
+  }
+
+  fn nth(&mut self, n: usize) -> Option<Self::Item> {
+    self.samples.nth(n)
+  }
+}
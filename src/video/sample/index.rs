@@ -0,0 +1,207 @@
+use super::*;
+
+/// Location, timing and sync flag of a single sample, times are in media timescale units
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IndexedSample {
+  pub offset: u64,
+  pub size: u32,
+  pub decode_time: u64,
+  pub composition_time: i64,
+  pub duration: u32,
+  pub is_sync: bool,
+  pub sample_description_id: u32,
+  /// Distance of the `roll` sample group of the sample
+  pub roll_distance: Option<i16>,
+}
+
+impl IndexedSample {
+  pub fn read(&self, reader: &mut File) -> SampleResult<Sample> {
+    Sample::read_at(reader, self.offset, self.size as u64)
+  }
+}
+
+/// Every sample of a track in decoding order
+#[derive(Debug, Default)]
+pub struct SampleIndex {
+  samples: Box<[IndexedSample]>,
+  /// Sample indices sorted by composition time
+  presentation_order: Box<[usize]>,
+}
+
+impl SampleIndex {
+  /// Builds the index of the track `track_id` from the sample tables of `stbl`, followed by the
+  /// samples of the track in the movie fragments `moofs`
+  pub fn new(
+    decoder: &mut Decoder,
+    stbl: &mut StblAtom,
+    moofs: &[MoofAtom],
+    mvex: Option<&MvexAtom>,
+    track_id: u32,
+  ) -> SampleResult<Self> {
+    let mut samples = Self::sample_tables(decoder, stbl)?;
+    let decode_time = samples
+      .last()
+      .map_or(0, |sample| sample.decode_time + sample.duration as u64);
+    samples.extend(
+      FragmentIter::new(moofs, mvex, track_id, decode_time, &stbl.sgpd).map(|sample| {
+        IndexedSample {
+          offset: sample.offset,
+          size: sample.size,
+          decode_time: sample.decode_time,
+          composition_time: sample.decode_time as i64 + sample.composition_time_offset as i64,
+          duration: sample.duration,
+          is_sync: sample.flags.is_sync(),
+          sample_description_id: sample.sample_description_index,
+          roll_distance: sample.roll_distance,
+        }
+      }),
+    );
+    Ok(Self::from_samples(samples.into_boxed_slice()))
+  }
+
+  /// Samples of the stts, ctts, stsc, stco, stsz and stss tables of `stbl`
+  fn sample_tables(decoder: &mut Decoder, stbl: &mut StblAtom) -> SampleResult<Vec<IndexedSample>> {
+    let sample_count = stbl.stsz.number_of_entries as usize;
+    let mut samples = vec![IndexedSample::default(); sample_count];
+
+    if stbl.stsz.sample_size != 0 {
+      for sample in &mut samples {
+        sample.size = stbl.stsz.sample_size;
+      }
+    } else {
+      for (sample, size) in samples
+        .iter_mut()
+        .zip(stbl.stsz.sample_size_table(decoder)?)
+      {
+        sample.size = size as u32;
+      }
+    }
+
+    let mut decode_time = 0;
+    let mut durations = stbl
+      .stts
+      .time_to_sample_table(decoder)?
+      .flat_map(|stts| (0..stts.sample_count).map(move |_| stts.sample_duration));
+    for sample in &mut samples {
+      sample.duration = durations.next().unwrap_or_default();
+      sample.decode_time = decode_time;
+      sample.composition_time = decode_time as i64;
+      decode_time += sample.duration as u64;
+    }
+
+    if let Some(ctts) = &stbl.ctts {
+      let offsets = ctts
+        .composition_offset_table(decoder)?
+        .flat_map(|ctts| (0..ctts.sample_count).map(move |_| ctts.sample_offset));
+      for (sample, offset) in samples.iter_mut().zip(offsets) {
+        sample.composition_time += offset as i64;
+      }
+    }
+
+    match &stbl.stss {
+      Some(stss) => {
+        for sync_sample in stss.sync_sample_table(decoder)? {
+          if let Some(sample) = (sync_sample as usize)
+            .checked_sub(1)
+            .and_then(|i| samples.get_mut(i))
+          {
+            sample.is_sync = true;
+          }
+        }
+      }
+      None => {
+        for sample in &mut samples {
+          sample.is_sync = true;
+        }
+      }
+    }
+
+    if let Some(sbgp) = stbl
+      .sbgp
+      .iter()
+      .find(|sbgp| *sbgp.grouping_type == *b"roll")
+    {
+      for (sample, index) in samples.iter_mut().zip(sbgp.group_description_indices()) {
+        sample.roll_distance = SgpdAtom::roll_distance(&stbl.sgpd, &[], index);
+      }
+    }
+
+    let stsc = stbl.stsc.decode(decoder)?;
+    let sample_to_chunk = stsc.sample_to_chunk_table(decoder)?.collect::<Vec<_>>();
+    let mut stsc_index = 0;
+    let mut samples_iter = samples.iter_mut();
+    'chunks: for (chunk, chunk_offset) in stbl.stco.chunk_offset_table(decoder)?.enumerate() {
+      while sample_to_chunk
+        .get(stsc_index + 1)
+        .is_some_and(|stsc| chunk + 1 >= stsc.first_chunk as usize)
+      {
+        stsc_index += 1;
+      }
+      let Some(stsc) = sample_to_chunk.get(stsc_index) else {
+        break;
+      };
+      let mut offset = chunk_offset;
+      for _ in 0..stsc.samples_per_chunk {
+        let Some(sample) = samples_iter.next() else {
+          break 'chunks;
+        };
+        sample.offset = offset;
+        sample.sample_description_id = stsc.sample_description_id;
+        offset += sample.size as u64;
+      }
+    }
+
+    Ok(samples)
+  }
+
+  fn from_samples(samples: Box<[IndexedSample]>) -> Self {
+    let mut presentation_order = (0..samples.len()).collect::<Box<_>>();
+    presentation_order.sort_by_key(|&i| samples[i].composition_time);
+    Self {
+      samples,
+      presentation_order,
+    }
+  }
+}
+
+impl SampleIndex {
+  /// Index of the first sample presented at or after `time`
+  pub fn sample_presented_from(&self, time: i64) -> Option<usize> {
+    let position = self
+      .presentation_order
+      .partition_point(|&i| self.samples[i].composition_time < time);
+    self.presentation_order.get(position).copied()
+  }
+
+  /// Presentation end of the last sample
+  pub fn duration(&self) -> u64 {
+    self
+      .samples
+      .iter()
+      .map(|sample| sample.composition_time.max(0) as u64 + sample.duration as u64)
+      .max()
+      .unwrap_or_default()
+  }
+
+  /// Whether any sample is a member of a `roll` sample group
+  pub fn has_roll_groups(&self) -> bool {
+    self
+      .samples
+      .iter()
+      .any(|sample| sample.roll_distance.is_some())
+  }
+
+  /// Index of the closest sync sample at or before `sample`
+  pub fn sync_sample(&self, sample: usize) -> Option<usize> {
+    self.samples[..=sample.min(self.samples.len().checked_sub(1)?)]
+      .iter()
+      .rposition(|sample| sample.is_sync)
+  }
+}
+
+impl Deref for SampleIndex {
+  type Target = [IndexedSample];
+  fn deref(&self) -> &Self::Target {
+    &self.samples
+  }
+}
@@ -1,8 +1,21 @@
+mod adts;
+mod annexb;
+mod fragment;
+mod index;
 mod nal;
+mod subtitle;
+mod wav;
 
 use super::atom::*;
 use super::decoder::*;
+use crate::byte::BitStream;
+pub use adts::*;
+pub use annexb::*;
+pub use fragment::*;
+pub use index::*;
 pub use nal::*;
+pub use subtitle::*;
+pub use wav::*;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
@@ -19,6 +32,16 @@ pub enum SampleError {
   Bit(#[from] TryFromIntError),
   #[error("NAL forbidden_zero_bit is not 0")]
   NALForbiddenZeroBit,
+  #[error("ADTS does not support {0} {1}")]
+  Adts(&'static str, u32),
+  #[error("WAV data size {0} exceeds the 4 GiB RIFF limit")]
+  WavSize(u64),
+  #[error("Wrote {0} bytes of WAV data, expected {1}")]
+  WavData(u64, u32),
+  #[error("Text sample of {0} bytes has {1} bytes of data")]
+  TextLength(usize, usize),
+  #[error("Timecode sample has {0} bytes, expected 4")]
+  TimecodeSize(usize),
 }
 
 pub type SampleResult<T = ()> = Result<T, SampleError>;
@@ -28,83 +51,60 @@ pub struct Sample {
 }
 
 impl Sample {
-  pub fn units(&self, nal_length_size: usize) -> NALUnitIter {
-    NALUnitIter::new(&self.data, nal_length_size)
+  /// Reads `size` bytes at file `offset`
+  pub fn read_at(reader: &mut File, offset: u64, size: u64) -> SampleResult<Self> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0; size as usize];
+    reader.read_exact(&mut buffer)?;
+    Ok(Self {
+      data: buffer.into_boxed_slice(),
+    })
   }
-}
 
-impl Deref for Sample {
-  type Target = Box<[u8]>;
-  fn deref(&self) -> &Self::Target {
-    &self.data
+  pub fn units(&self, nal_length_size: usize) -> NALUnitIter {
+    NALUnitIter::new(&self.data, nal_length_size)
   }
-}
 
-#[derive(Debug)]
-pub struct SampleIter {
-  chunk_index: usize,
-  chunk_offset: u64,
-  sample_offset: u64,
-  sample_size_offset: u64,
-  samples_in_chunk: u64,
-  next_stsc: Option<StscItem>,
-  reader: File,
-  chunk_offsets: std::iter::Enumerate<SampleTable>,
-  sample_sizes: SampleTable,
-  sample_to_chunk_table: SampleTable<StscItem>,
-}
-
-impl SampleIter {
-  pub fn new(decoder: &mut Decoder, stbl: &mut StblAtom) -> SampleResult<Self> {
-    Ok(Self {
-      chunk_index: 0,
-      chunk_offset: 0,
-      sample_offset: 1,
-      sample_size_offset: 0,
-      samples_in_chunk: 0,
-      next_stsc: None,
-      reader: decoder.file.try_clone()?,
-      chunk_offsets: stbl.stco.chunk_offset_table(decoder)?.enumerate(),
-      sample_sizes: stbl.stsz.sample_size_table(decoder)?,
-      sample_to_chunk_table: stbl.stsc.decode(decoder)?.sample_to_chunk_table(decoder)?,
+  /// Length prefixed NAL units of the sample with their headers, stops at a truncated unit
+  pub fn raw_units(&self, nal_length_size: usize) -> impl Iterator<Item = &[u8]> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+      let start = offset + nal_length_size;
+      let prefix = self.data.get(offset..start)?;
+      let size = prefix.iter().fold(0, |size, b| (size << 8) | *b as usize);
+      let unit = self.data.get(start..start + size)?;
+      offset = start + size;
+      Some(unit)
     })
   }
-}
 
-impl Iterator for SampleIter {
-  type Item = Sample;
-  fn next(&mut self) -> Option<Self::Item> {
-    if self.sample_offset >= self.samples_in_chunk {
-      self.sample_offset = 0;
-      self.sample_size_offset = 0;
-      (self.chunk_index, self.chunk_offset) = self.chunk_offsets.next()?;
-      if self
-        .next_stsc
-        .as_ref()
-        .is_some_and(|stsc| self.chunk_index >= stsc.first_chunk as usize - 1)
-        || self.next_stsc.is_none()
-      {
-        let stsc = self
-          .next_stsc
-          .take()
-          .or_else(|| self.sample_to_chunk_table.next())?;
-        self.samples_in_chunk = stsc.samples_per_chunk as u64;
-        self.next_stsc = self.sample_to_chunk_table.next();
+  /// Returns the `recovery_frame_cnt` of the first recovery point SEI message in the sample
+  pub fn recovery_point(&self, nal_length_size: usize) -> SampleResult<Option<u16>> {
+    for nal in self.units(nal_length_size) {
+      let nal = nal?;
+      if !matches!(nal.unit_type, NALUnitType::Sei) {
+        continue;
+      }
+      let mut data = BitStream::new(nal.rbsp());
+      let recovery_frame_cnt = SeiMessage::decode_all(nal.size, &mut data)
+        .iter()
+        .find_map(|sei| match sei.payload {
+          SeiPayload::RecoveryPoint {
+            recovery_frame_cnt, ..
+          } => Some(recovery_frame_cnt),
+          _ => None,
+        });
+      if recovery_frame_cnt.is_some() {
+        return Ok(recovery_frame_cnt);
       }
     }
-    let sample_size = self.sample_sizes.next()?;
-
-    self
-      .reader
-      .seek(SeekFrom::Start(self.chunk_offset + self.sample_size_offset))
-      .ok()?;
-    self.sample_offset += 1;
-    self.sample_size_offset += sample_size;
-    let mut buffer = vec![0; sample_size as usize];
-    self.reader.read_exact(&mut buffer).ok()?;
+    Ok(None)
+  }
+}
 
-    Some(Sample {
-      data: buffer.into_boxed_slice(),
-    })
+impl Deref for Sample {
+  type Target = Box<[u8]>;
+  fn deref(&self) -> &Self::Target {
+    &self.data
   }
 }
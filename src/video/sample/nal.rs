@@ -25,10 +25,21 @@ impl SeiMessage {
     }
     payload_size += data.byte() as u32;
 
-    Self {
-      size,
-      payload: SeiPayload::new(payload_type, payload_size, data),
+    let payload_end = std::cmp::min(payload_size as usize, data.len());
+    let mut payload_data = BitStream::new(&data[..payload_end]);
+    let payload = SeiPayload::new(payload_type, payload_size, &mut payload_data);
+    data.skip_bytes(payload_size as usize);
+
+    Self { size, payload }
+  }
+
+  /// 7.3.2.3 Supplemental enhancement information RBSP syntax
+  pub fn decode_all(size: usize, data: &mut BitStream) -> Box<[Self]> {
+    let mut messages = Vec::new();
+    while data.len() > 1 {
+      messages.push(Self::decode(size, data));
     }
+    messages.into_boxed_slice()
   }
 }
 
@@ -39,6 +50,10 @@ pub enum SeiPayload {
     uuid_iso_iec_11578: u128,
     data: Box<[u8]>,
   },
+  /// D.2.8 Recovery point SEI message semantics
+  RecoveryPoint {
+    recovery_frame_cnt: u16,
+  },
 }
 
 impl SeiPayload {
@@ -46,7 +61,10 @@ impl SeiPayload {
     match payload_type {
       5 => Self::UserDataUnregistered {
         uuid_iso_iec_11578: data.next_into(),
-        data: (&**data)[..payload_size as usize - 16].into(),
+        data: (&**data)[..(payload_size as usize).saturating_sub(16).min(data.len())].into(),
+      },
+      6 => Self::RecoveryPoint {
+        recovery_frame_cnt: data.exponential_golomb(),
       },
       n => Self::Unknown(n),
     }
@@ -162,6 +180,9 @@ pub enum NALUnitType {
 }
 
 impl NALUnitType {
+  /// Header byte of an access unit delimiter with nal_ref_idc 0
+  pub const AUD_HEADER: u8 = 9;
+
   pub fn new(nal_unit_type: u8) -> Self {
     match nal_unit_type {
       1 => Self::NonIDRPicture,
@@ -191,15 +212,49 @@ impl NALUnitType {
   pub fn is_idr(&self) -> bool {
     matches!(self, NALUnitType::IDRPicture)
   }
+
+  /// Video Coding Layer NAL units carry the coded slice data
+  pub fn is_vcl(&self) -> bool {
+    matches!(
+      self,
+      NALUnitType::NonIDRPicture
+        | NALUnitType::DataPartitionA
+        | NALUnitType::DataPartitionB
+        | NALUnitType::DataPartitionC
+        | NALUnitType::IDRPicture
+    )
+  }
 }
 
 pub struct NALUnit<'a> {
+  pub header: u8,
   pub idc: u8,
   pub unit_type: NALUnitType,
   pub size: usize,
   pub data: &'a [u8],
 }
 
+impl<'a> NALUnit<'a> {
+  /// The NAL unit payload without the header byte, bounded to the unit's size
+  pub fn rbsp(&self) -> &'a [u8] {
+    &self.data[..self.size.saturating_sub(1).min(self.data.len())]
+  }
+
+  /// `slice_type` of a slice NAL unit, 7.4.3 Table 7-6 (values 5..9 are mapped to 0..4). Data
+  /// partitions B and C start with slice_id instead of a slice header and have none
+  pub fn slice_type(&self) -> Option<u8> {
+    matches!(
+      self.unit_type,
+      NALUnitType::NonIDRPicture | NALUnitType::DataPartitionA | NALUnitType::IDRPicture
+    )
+    .then(|| {
+      let mut data = BitStream::new(self.rbsp());
+      data.exponential_golomb::<u32>();
+      (data.exponential_golomb::<u32>() % 5) as u8
+    })
+  }
+}
+
 impl<'a> Debug for NALUnit<'a> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("NALUnit")
@@ -242,6 +297,7 @@ impl<'a> Iterator for NALUnitIter<'a> {
       let idc = self.data[self.offset] >> 5;
       let nal_type = self.data[self.offset] & 0x1F;
       let nal_unit = NALUnit {
+        header: byte,
         idc,
         unit_type: NALUnitType::new(nal_type),
         size: nal_size,
@@ -0,0 +1,300 @@
+use super::*;
+use crate::log;
+use std::{io::Write, path::Path};
+
+/// Text and style records of a `tx3g` or QuickTime `text` sample, an empty text clears the
+/// previous one
+#[derive(Debug, Default)]
+pub struct TextSample {
+  pub text: Box<str>,
+  pub styles: Box<[StyleRecord]>,
+}
+
+impl TextSample {
+  /// Size of a QuickTime `styl` entry
+  const QUICKTIME_STYLE_SIZE: usize = 20;
+
+  /// Decodes the 16-bit length prefixed text and its modifier atoms, `quicktime` samples use
+  /// the QuickTime `styl` layout
+  pub fn decode(data: &[u8], quicktime: bool) -> SampleResult<Self> {
+    let Some((length, rest)) = data.split_first_chunk::<2>() else {
+      return Ok(Self::default());
+    };
+    let length = u16::from_be_bytes(*length) as usize;
+    let text = rest
+      .get(..length)
+      .ok_or(SampleError::TextLength(length, rest.len()))?;
+    let text = match text.strip_prefix(&[0xFE, 0xFF]) {
+      Some(utf16) => String::from_utf16_lossy(
+        &utf16
+          .chunks_exact(2)
+          .map(|c| u16::from_be_bytes([c[0], c[1]]))
+          .collect::<Vec<_>>(),
+      ),
+      None => String::from_utf8_lossy(text).into_owned(),
+    };
+    let mut sample = Self {
+      text: text.replace("\r\n", "\n").replace('\r', "\n").into(),
+      styles: Box::default(),
+    };
+    for atom in AtomDataIter::new(&rest[length..], 0) {
+      match atom {
+        Ok((atom, data)) => match &*atom.name {
+          b"styl" if quicktime => sample.styles = sample.quicktime_styles(data)?,
+          b"styl" => sample.styles = Self::styles(data)?,
+          _ => log!(File@"#[text] Unused modifier {atom:?}"),
+        },
+        Err(e) => log!(File@"#[text] {e}"),
+      }
+    }
+    Ok(sample)
+  }
+
+  /// 3GPP `styl` modifier, a 16-bit entry count and the style records
+  fn styles(data: &[u8]) -> SampleResult<Box<[StyleRecord]>> {
+    let Some((count, records)) = data.split_first_chunk::<2>() else {
+      return Ok(Box::default());
+    };
+    let count = (u16::from_be_bytes(*count) as usize).min(records.len() / StyleRecord::SIZE);
+    let mut records = AtomData::new(records, 0);
+    (0..count)
+      .map(|_| Ok(StyleRecord::decode(&mut records)?))
+      .collect()
+  }
+
+  /// QuickTime `styl` modifier, each entry starts a run that ends at the next one
+  fn quicktime_styles(&self, data: &[u8]) -> SampleResult<Box<[StyleRecord]>> {
+    let Some((count, entries)) = data.split_first_chunk::<2>() else {
+      return Ok(Box::default());
+    };
+    let entries: Vec<_> = entries
+      .chunks_exact(Self::QUICKTIME_STYLE_SIZE)
+      .take(u16::from_be_bytes(*count) as usize)
+      .collect();
+    let start = |entry: &[u8]| u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]);
+    let length = self.text.chars().count() as u32;
+    Ok(
+      entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| StyleRecord {
+          start_char: start(entry) as u16,
+          end_char: entries.get(i + 1).map_or(length, |next| start(next)) as u16,
+          font_id: u16::from_be_bytes([entry[8], entry[9]]),
+          face_style_flags: entry[11],
+        })
+        .collect(),
+    )
+  }
+
+  /// Text with the bold, italic and underline styles as `<b>`, `<i>` and `<u>` tags, `escape`
+  /// replaces the characters WebVTT reserves. Blank lines are removed so the text fits a cue
+  pub fn markup(&self, escape: bool) -> String {
+    let mut markup = String::new();
+    let mut open = Vec::new();
+    for (i, c) in self.text.chars().enumerate() {
+      let tags = self.tags(i as u16);
+      if open != tags {
+        for tag in open.iter().rev() {
+          markup.push_str(&format!("</{tag}>"));
+        }
+        for tag in &tags {
+          markup.push_str(&format!("<{tag}>"));
+        }
+        open = tags;
+      }
+      match c {
+        '&' if escape => markup.push_str("&amp;"),
+        '<' if escape => markup.push_str("&lt;"),
+        '>' if escape => markup.push_str("&gt;"),
+        c => markup.push(c),
+      }
+    }
+    for tag in open.iter().rev() {
+      markup.push_str(&format!("</{tag}>"));
+    }
+    markup
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  /// Tags of the style of the character at index `char`
+  fn tags(&self, char: u16) -> Vec<&'static str> {
+    let Some(style) = self
+      .styles
+      .iter()
+      .find(|style| (style.start_char..style.end_char).contains(&char))
+    else {
+      return Vec::new();
+    };
+    [
+      (style.is_bold(), "b"),
+      (style.is_italic(), "i"),
+      (style.is_underlined(), "u"),
+    ]
+    .into_iter()
+    .filter_map(|(set, tag)| set.then_some(tag))
+    .collect()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubtitleFormat {
+  Srt,
+  WebVtt,
+}
+
+impl SubtitleFormat {
+  /// WebVTT for `.vtt` files and SubRip otherwise
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+    match path.as_ref().extension() {
+      Some(extension) if extension.eq_ignore_ascii_case("vtt") => Self::WebVtt,
+      _ => Self::Srt,
+    }
+  }
+}
+
+/// Writes timed text samples as SubRip or WebVTT cues, samples without text are skipped
+pub struct SubtitleWriter<W: Write> {
+  writer: W,
+  format: SubtitleFormat,
+  cues: usize,
+}
+
+impl<W: Write> SubtitleWriter<W> {
+  pub fn new(mut writer: W, format: SubtitleFormat) -> SampleResult<Self> {
+    if format == SubtitleFormat::WebVtt {
+      writer.write_all(b"WEBVTT\n\n")?;
+    }
+    Ok(Self {
+      writer,
+      format,
+      cues: 0,
+    })
+  }
+
+  /// Writes `sample` presented from `start` to `end` milliseconds
+  pub fn write_cue(&mut self, start: u64, end: u64, sample: &TextSample) -> SampleResult {
+    let text = sample.markup(self.format == SubtitleFormat::WebVtt);
+    if text.is_empty() || end <= start {
+      return Ok(());
+    }
+    self.cues += 1;
+    if self.format == SubtitleFormat::Srt {
+      writeln!(self.writer, "{}", self.cues)?;
+    }
+    writeln!(
+      self.writer,
+      "{} --> {}\n{text}\n",
+      self.timestamp(start),
+      self.timestamp(end)
+    )?;
+    Ok(())
+  }
+
+  /// `HH:MM:SS,mmm` for SubRip and `HH:MM:SS.mmm` for WebVTT
+  fn timestamp(&self, ms: u64) -> String {
+    let separator = match self.format {
+      SubtitleFormat::Srt => ',',
+      SubtitleFormat::WebVtt => '.',
+    };
+    format!(
+      "{:02}:{:02}:{:02}{separator}{:03}",
+      ms / 3_600_000,
+      ms / 60_000 % 60,
+      ms / 1_000 % 60,
+      ms % 1_000
+    )
+  }
+
+  /// Number of cues written
+  pub fn cues(&self) -> usize {
+    self.cues
+  }
+
+  pub fn flush(&mut self) -> SampleResult {
+    Ok(self.writer.flush()?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// `tx3g` sample of `text` with a 3GPP `styl` modifier of `styles`
+  fn tx3g(text: &str, styles: &[(u16, u16, u8)]) -> Vec<u8> {
+    let mut data = (text.len() as u16).to_be_bytes().to_vec();
+    data.extend(text.as_bytes());
+    data.extend((10 + styles.len() as u32 * 12).to_be_bytes());
+    data.extend(b"styl");
+    data.extend((styles.len() as u16).to_be_bytes());
+    for (start, end, flags) in styles {
+      data.extend(start.to_be_bytes());
+      data.extend(end.to_be_bytes());
+      data.extend([0, 1, *flags, 18, 0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+    data
+  }
+
+  #[test]
+  fn styled_markup() {
+    let sample =
+      TextSample::decode(&tx3g("Hello <world>", &[(0, 5, 1), (6, 13, 2)]), false).unwrap();
+    assert_eq!(sample.markup(false), "<b>Hello</b> <i><world></i>");
+    assert_eq!(sample.markup(true), "<b>Hello</b> <i>&lt;world&gt;</i>");
+    let sample = TextSample::decode(&tx3g("One\r\n\r\nTwo", &[]), false).unwrap();
+    assert_eq!(sample.markup(false), "One\nTwo");
+  }
+
+  #[test]
+  fn text_length() {
+    assert!(matches!(
+      TextSample::decode(&[0, 4, b'a'], false),
+      Err(SampleError::TextLength(4, 1))
+    ));
+    assert!(TextSample::decode(&[], false).unwrap().text.is_empty());
+  }
+
+  fn cues(format: SubtitleFormat) -> String {
+    let mut writer = SubtitleWriter::new(Vec::new(), format).unwrap();
+    let text = TextSample::decode(&tx3g("A & B", &[]), false).unwrap();
+    writer.write_cue(1_500, 3_723_004, &text).unwrap();
+    writer.write_cue(4_000_000, 4_000_000, &text).unwrap();
+    writer
+      .write_cue(4_000_000, 4_001_000, &TextSample::default())
+      .unwrap();
+    writer.write_cue(0, 1, &text).unwrap();
+    assert_eq!(writer.cues(), 2);
+    String::from_utf8(writer.writer).unwrap()
+  }
+
+  #[test]
+  fn srt_cues() {
+    assert_eq!(
+      cues(SubtitleFormat::Srt),
+      concat!(
+        "1\n00:00:01,500 --> 01:02:03,004\nA & B\n\n",
+        "2\n00:00:00,000 --> 00:00:00,001\nA & B\n\n",
+      )
+    );
+  }
+
+  #[test]
+  fn webvtt_cues() {
+    assert_eq!(
+      cues(SubtitleFormat::WebVtt),
+      concat!(
+        "WEBVTT\n\n",
+        "00:00:01.500 --> 01:02:03.004\nA &amp; B\n\n",
+        "00:00:00.000 --> 00:00:00.001\nA &amp; B\n\n",
+      )
+    );
+    assert_eq!(
+      SubtitleFormat::from_path("cues.VTT"),
+      SubtitleFormat::WebVtt
+    );
+    assert_eq!(SubtitleFormat::from_path("cues.srt"), SubtitleFormat::Srt);
+  }
+}
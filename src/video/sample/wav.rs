@@ -0,0 +1,118 @@
+use super::*;
+use std::io::Write;
+
+/// Size of the RIFF, fmt and data chunk headers with a WAVE_FORMAT_EXTENSIBLE fmt chunk
+const EXTENSIBLE_HEADER_SIZE: u32 = 68;
+/// Size of the RIFF, fmt and data chunk headers with a 16 byte fmt chunk
+const HEADER_SIZE: u32 = 44;
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// KSDATAFORMAT_SUBTYPE GUID without its leading format tag
+const SUBFORMAT_GUID: [u8; 14] = [
+  0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// Writes uncompressed audio to a RIFF WAVE file, samples are converted to the little endian
+/// layout WAV expects, with 8-bit samples unsigned and wider ones signed
+pub struct WavWriter<W: Write> {
+  writer: W,
+  format: PcmFormat,
+  data_size: u32,
+  written: u64,
+}
+
+impl<W: Write> WavWriter<W> {
+  /// Writes the RIFF header for `data_size` bytes of `channels` interleaved channels, a
+  /// WAVE_FORMAT_EXTENSIBLE fmt chunk is used for more than 2 channels or 16 bits
+  pub fn new(
+    mut writer: W,
+    format: PcmFormat,
+    channels: u16,
+    sample_rate: u32,
+    channel_mask: u32,
+    data_size: u64,
+  ) -> SampleResult<Self> {
+    let extensible = channels > 2 || format.bits_per_sample > 16;
+    let header_size = if extensible {
+      EXTENSIBLE_HEADER_SIZE
+    } else {
+      HEADER_SIZE
+    };
+    let riff_size = data_size + (data_size & 1) + header_size as u64 - 8;
+    let (Ok(data_size), Ok(riff_size)) = (u32::try_from(data_size), u32::try_from(riff_size)) else {
+      return Err(SampleError::WavSize(data_size));
+    };
+    let format_tag = if format.float {
+      WAVE_FORMAT_IEEE_FLOAT
+    } else {
+      WAVE_FORMAT_PCM
+    };
+    let bytes_per_sample = format.bytes_per_sample();
+    let block_align = channels * bytes_per_sample;
+
+    let mut header = Vec::with_capacity(header_size as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&riff_size.to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&(header_size - 28).to_le_bytes());
+    header.extend_from_slice(
+      &(if extensible {
+        WAVE_FORMAT_EXTENSIBLE
+      } else {
+        format_tag
+      })
+      .to_le_bytes(),
+    );
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&(bytes_per_sample * 8).to_le_bytes());
+    if extensible {
+      header.extend_from_slice(&22u16.to_le_bytes());
+      header.extend_from_slice(&format.bits_per_sample.to_le_bytes());
+      header.extend_from_slice(&channel_mask.to_le_bytes());
+      header.extend_from_slice(&format_tag.to_le_bytes());
+      header.extend_from_slice(&SUBFORMAT_GUID);
+    }
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    writer.write_all(&header)?;
+
+    Ok(Self {
+      writer,
+      format,
+      data_size,
+      written: 0,
+    })
+  }
+
+  /// Converts `samples` in place and writes them, `samples` must hold whole samples
+  pub fn write_samples(&mut self, samples: &mut [u8]) -> SampleResult {
+    let bytes_per_sample = self.format.bytes_per_sample() as usize;
+    let unsigned = !self.format.float && (bytes_per_sample == 1) == self.format.signed;
+    for sample in samples.chunks_exact_mut(bytes_per_sample) {
+      if self.format.big_endian {
+        sample.reverse();
+      }
+      if unsigned {
+        sample[bytes_per_sample - 1] ^= 0x80;
+      }
+    }
+    self.written += samples.len() as u64;
+    self.writer.write_all(samples)?;
+    Ok(())
+  }
+
+  /// Pads the data chunk to an even size and flushes the writer
+  pub fn finish(&mut self) -> SampleResult {
+    if self.written != self.data_size as u64 {
+      return Err(SampleError::WavData(self.written, self.data_size));
+    }
+    if self.data_size & 1 != 0 {
+      self.writer.write_all(&[0])?;
+    }
+    Ok(self.writer.flush()?)
+  }
+}
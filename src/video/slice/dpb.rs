@@ -11,6 +11,10 @@ pub struct DecodedPictureBuffer {
   pub buffer: Vec<Picture>,
   pub ref_pic_list0: Vec<Picture>,
   pub ref_pic_list1: Vec<Picture>,
+  pub prev_ref_frame_num: u16,
+  /// Set when decoding starts at a non-IDR random access point, the references
+  /// preceding it are inferred as "non-existing" frames
+  pub random_access: bool,
 }
 
 impl DecodedPictureBuffer {
@@ -20,6 +24,8 @@ impl DecodedPictureBuffer {
       buffer: Vec::with_capacity(16),
       ref_pic_list0: Vec::with_capacity(16),
       ref_pic_list1: Vec::with_capacity(16),
+      prev_ref_frame_num: 0,
+      random_access: false,
     }
   }
 
@@ -27,6 +33,41 @@ impl DecodedPictureBuffer {
     if slice.nal_idc != 0 {
       let pic = self.new_picture(slice);
       self.buffer.push(pic);
+      self.prev_ref_frame_num = slice.frame_num;
+    }
+  }
+
+  /// 8.2.5.2 Decoding process for gaps in frame_num
+  pub fn frame_num_gap(&mut self, slice: &Slice) {
+    if slice.nal_unit_type.is_idr() {
+      self.random_access = false;
+      return;
+    }
+    let max_frame_num = slice.max_frame_num;
+    if self.random_access {
+      self.random_access = false;
+      self.prev_ref_frame_num =
+        (slice.frame_num + max_frame_num - (slice.sps.max_num_ref_frames % max_frame_num) - 1)
+          % max_frame_num;
+    } else if !slice.sps.gaps_in_frame_num_value_allowed_flag {
+      return;
+    }
+    if slice.frame_num == self.prev_ref_frame_num {
+      return;
+    }
+
+    let mut unused_short_term_frame_num = (self.prev_ref_frame_num + 1) % max_frame_num;
+    while unused_short_term_frame_num != slice.frame_num {
+      self.sliding_window(slice.sps.max_num_ref_frames);
+      let mut pic = Picture::unknown();
+      pic.frame_num = unused_short_term_frame_num as i16;
+      pic.frame_num_wrap = pic.frame_num;
+      pic.max_frame_num = max_frame_num as i16;
+      pic.reference_marked_type = PictureMarking::ShortTermReference;
+      pic.non_existing = true;
+      self.buffer.push(pic);
+      self.prev_ref_frame_num = unused_short_term_frame_num;
+      unused_short_term_frame_num = (unused_short_term_frame_num + 1) % max_frame_num;
     }
   }
 
@@ -415,6 +456,9 @@ impl DecodedPictureBuffer {
   /// 8.2.5 Decoded reference picture marking process
   pub fn new_picture(&mut self, slice: &Slice) -> Picture {
     let mut pic = Picture::from_poc(&self.poc);
+    pic.frame_num = slice.frame_num as i16;
+    pic.frame_num_wrap = pic.frame_num;
+    pic.max_frame_num = slice.max_frame_num as i16;
     if slice.nal_unit_type.is_idr() {
       self.buffer.clear();
       self.ref_pic_list0.clear();
@@ -812,6 +856,8 @@ pub struct Picture {
   pub frame_num_wrap: i16,
   pub memory_management_control_operation_5_flag: bool,
   pub memory_management_control_operation_6_flag: bool,
+  /// Inferred reference for a frame_num gap, it has no sample data
+  pub non_existing: bool,
 }
 
 impl Deref for Picture {
@@ -848,6 +894,7 @@ impl Picture {
       frame_num_wrap: 0,
       memory_management_control_operation_5_flag: false,
       memory_management_control_operation_6_flag: false,
+      non_existing: false,
     }
   }
 
@@ -190,6 +190,8 @@ impl<'a> Slice<'a> {
       SliceType::B => MB_TYPE_B_SKIP,
       _ => MB_TYPE_P_SKIP,
     };
+    // Gaps in frame_num insert "non-existing" frames for both entropy coding modes
+    dpb.frame_num_gap(self);
     if self.pps.entropy_coding_mode_flag {
       let mut cabac = CabacContext::new(self)?;
       dpb.decode_pic_order_cnt_type(self);
@@ -239,7 +241,7 @@ impl<'a> Slice<'a> {
           if end_of_slice_flag != 0 {
             self.last_mb_in_slice = self.curr_mb_addr;
             self.stream.is_byte_aligned(0);
-            return Ok(());
+            break;
           }
         }
         self.prev_mb_addr = self.curr_mb_addr;
@@ -312,7 +314,9 @@ impl<'a> Slice<'a> {
         // }
       }
     }
-    dpb.push(self);
+    if self.last_mb_in_slice + 1 >= self.pic_size_in_mbs as isize {
+      dpb.push(self);
+    }
     Ok(())
   }
 
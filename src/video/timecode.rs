@@ -0,0 +1,180 @@
+use super::atom::TmcdAtom;
+use std::fmt;
+
+/// SMPTE timecode, drop-frame timecodes separate the frames with `;`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timecode {
+  pub negative: bool,
+  pub hours: u32,
+  pub minutes: u8,
+  pub seconds: u8,
+  pub frames: u8,
+  pub drop_frame: bool,
+}
+
+impl Timecode {
+  /// Timecode of `frame` counted at `number_of_frames` per second. Drop-frame timecodes skip the
+  /// first 2 frame numbers (4 at 60 frames) of every minute except each tenth minute
+  pub fn from_frame(
+    frame: i64,
+    number_of_frames: u8,
+    drop_frame: bool,
+    max_24_hours: bool,
+  ) -> Self {
+    let fps = number_of_frames.max(1) as i64;
+    let negative = frame < 0;
+    let mut frame = frame.abs();
+    let dropped = if drop_frame { fps / 15 } else { 0 };
+    if dropped > 0 {
+      let frames_per_minute = fps * 60 - dropped;
+      let frames_per_ten_minutes = fps * 600 - dropped * 9;
+      let tens = frame / frames_per_ten_minutes;
+      let rest = frame % frames_per_ten_minutes;
+      frame += dropped * 9 * tens;
+      if rest > dropped {
+        frame += dropped * ((rest - dropped) / frames_per_minute);
+      }
+    }
+    let seconds = frame / fps;
+    let mut hours = seconds / 3_600;
+    if max_24_hours {
+      hours %= 24;
+    }
+    Self {
+      negative,
+      hours: hours as u32,
+      minutes: (seconds / 60 % 60) as u8,
+      seconds: (seconds % 60) as u8,
+      frames: (frame % fps) as u8,
+      drop_frame: dropped > 0,
+    }
+  }
+}
+
+impl fmt::Display for Timecode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}{:02}:{:02}:{:02}{}{:02}",
+      if self.negative { "-" } else { "" },
+      self.hours,
+      self.minutes,
+      self.seconds,
+      if self.drop_frame { ';' } else { ':' },
+      self.frames
+    )
+  }
+}
+
+/// Timecode samples of a `tmcd` track, each one gives the frame number of the timecode at its
+/// presentation time and the timecode counts frames from there
+#[derive(Debug)]
+pub struct TimecodeTrack {
+  pub track_id: u32,
+  pub flags: u32,
+  pub timescale: u32,
+  pub frame_duration: u32,
+  pub number_of_frames: u8,
+  pub source_name: Option<Box<str>>,
+  /// Presentation time in seconds and frame number of each sample
+  pub samples: Box<[(f64, i64)]>,
+}
+
+impl TimecodeTrack {
+  pub fn new<I: Iterator<Item = (f64, u32)>>(track_id: u32, tmcd: &TmcdAtom, samples: I) -> Self {
+    let negative_times = tmcd.flags & TmcdAtom::NEGATIVE_TIMES != 0;
+    Self {
+      track_id,
+      flags: tmcd.flags,
+      timescale: tmcd.timescale,
+      frame_duration: tmcd.frame_duration,
+      number_of_frames: tmcd.number_of_frames,
+      source_name: tmcd.source_name.clone(),
+      samples: samples
+        .map(|(time, frame)| match negative_times {
+          true => (time, frame as i32 as i64),
+          false => (time, frame as i64),
+        })
+        .collect(),
+    }
+  }
+
+  /// Timecode of the frame presented at `time` seconds, counted from the last sample presented
+  /// at or before it
+  pub fn timecode_at(&self, time: f64) -> Option<Timecode> {
+    let (start, frame) = self
+      .samples
+      .iter()
+      .rev()
+      .find(|(start, _)| *start <= time)
+      .or(self.samples.first())?;
+    // Tolerates the rounding of times that fall on a frame boundary
+    let elapsed = (time - start) * self.timescale as f64 / self.frame_duration.max(1) as f64;
+    Some(self.timecode(frame + (elapsed + 1e-3).floor() as i64))
+  }
+
+  /// Timecode of the first sample
+  pub fn start(&self) -> Option<Timecode> {
+    self.samples.first().map(|(_, frame)| self.timecode(*frame))
+  }
+
+  fn timecode(&self, frame: i64) -> Timecode {
+    Timecode::from_frame(
+      frame,
+      self.number_of_frames,
+      self.flags & TmcdAtom::DROP_FRAME != 0,
+      self.flags & TmcdAtom::MAX_24_HOURS != 0,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn timecode(frame: i64, number_of_frames: u8, drop_frame: bool) -> String {
+    Timecode::from_frame(frame, number_of_frames, drop_frame, false).to_string()
+  }
+
+  #[test]
+  fn non_drop_frame() {
+    assert_eq!(timecode(0, 25, false), "00:00:00:00");
+    assert_eq!(timecode(1_799, 30, false), "00:00:59:29");
+    assert_eq!(timecode(90_000, 25, false), "01:00:00:00");
+    assert_eq!(timecode(-26, 25, false), "-00:00:01:01");
+    assert_eq!(
+      Timecode::from_frame(25 * 3_600 * 25, 25, false, true).to_string(),
+      "01:00:00:00"
+    );
+  }
+
+  #[test]
+  fn drop_frame() {
+    assert_eq!(timecode(1_799, 30, true), "00:00:59;29");
+    assert_eq!(timecode(1_800, 30, true), "00:01:00;02");
+    assert_eq!(timecode(3_597, 30, true), "00:01:59;29");
+    assert_eq!(timecode(3_598, 30, true), "00:02:00;02");
+    assert_eq!(timecode(17_982, 30, true), "00:10:00;00");
+    assert_eq!(timecode(107_892, 30, true), "01:00:00;00");
+    assert_eq!(timecode(3_600, 60, true), "00:01:00;04");
+  }
+
+  #[test]
+  fn timecode_track() {
+    let tmcd = TmcdAtom {
+      flags: TmcdAtom::DROP_FRAME,
+      timescale: 30_000,
+      frame_duration: 1_001,
+      number_of_frames: 30,
+      source_name: None,
+    };
+    let track = TimecodeTrack::new(1, &tmcd, [(0., 1_800), (10., 0)].into_iter());
+    assert_eq!(track.start().unwrap().to_string(), "00:01:00;02");
+    let frame_time = 1_001. / 30_000.;
+    assert_eq!(
+      track.timecode_at(3. * frame_time).unwrap().to_string(),
+      "00:01:00;05"
+    );
+    assert_eq!(track.timecode_at(10.).unwrap().to_string(), "00:00:00;00");
+  }
+}
@@ -0,0 +1,281 @@
+use super::atom::*;
+use super::codec::VideoCodec;
+use super::sample::SampleIndex;
+use crate::{
+  ascii::{Color, RESET},
+  byte::Str,
+  display::Duration,
+};
+use std::fmt;
+
+/// Properties of a single `trak` of the movie
+#[derive(Debug)]
+pub struct Track {
+  pub id: u32,
+  /// `hdlr` component subtype, `vide`, `soun`, `text`, `tmcd`, `meta`...
+  pub handler: Str<4>,
+  pub handler_name: Box<str>,
+  pub language: Str<3>,
+  pub timescale: u32,
+  /// Presentation duration in `timescale` units, with the edit list applied
+  pub duration: u64,
+  pub sample_count: usize,
+  pub sample_entries: Box<[SampleEntry]>,
+  /// `tref` references to other tracks
+  pub references: Box<[TrackReference]>,
+}
+
+impl Track {
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(
+    tkhd: &TkhdAtom,
+    hdlr: &HdlrAtom,
+    mdhd: &MdhdAtom,
+    stsd: &StsdAtom,
+    index: &SampleIndex,
+    elst: Option<&ElstAtom>,
+    tref: Option<&TrefAtom>,
+    movie_timescale: u32,
+  ) -> Self {
+    let mut duration = match mdhd.duration {
+      0 => index.duration(),
+      duration => duration,
+    };
+    if let Some(elst) = elst {
+      let edits_duration = elst.duration(movie_timescale, mdhd.timescale);
+      if edits_duration != i64::MAX {
+        duration = edits_duration as u64;
+      }
+    }
+    Self {
+      id: tkhd.track_id,
+      handler: hdlr.component_subtype,
+      handler_name: hdlr.component_name.clone(),
+      language: mdhd.language,
+      timescale: mdhd.timescale,
+      duration,
+      sample_count: index.len(),
+      sample_entries: stsd
+        .sample_description_table
+        .iter()
+        .map(SampleEntry::from)
+        .collect(),
+      references: tref.map(|tref| tref.references.clone()).unwrap_or_default(),
+    }
+  }
+
+  pub fn is_video(&self) -> bool {
+    *self.handler == *b"vide"
+  }
+
+  /// `text` QuickTime text or `sbtl` subtitle track
+  pub fn is_text(&self) -> bool {
+    matches!(&*self.handler, b"text" | b"sbtl")
+  }
+
+  /// Ids of the tracks referenced with `reference_type`
+  pub fn referenced_tracks<'a>(
+    &'a self,
+    reference_type: &'a [u8; 4],
+  ) -> impl Iterator<Item = u32> + 'a {
+    self
+      .references
+      .iter()
+      .filter(move |reference| *reference.reference_type == *reference_type)
+      .flat_map(|reference| reference.track_ids.iter().copied())
+  }
+
+  /// Codec of the first sample entry
+  pub fn codec(&self) -> Option<Str<4>> {
+    self.sample_entries.first().map(|entry| entry.format)
+  }
+
+  pub fn video_codec(&self) -> Option<VideoCodec> {
+    self.codec().map(VideoCodec::from)
+  }
+
+  pub fn duration(&self) -> Duration {
+    Duration::from_secs_f32(self.duration as f32 / self.timescale.max(1) as f32)
+  }
+}
+
+impl fmt::Display for Track {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let title = "".rgb(75, 205, 94).bold();
+    write!(
+      f,
+      "- {title}Track #{}:{RESET} {} \"{}\" ({})\n  \
+      - {title}Timescale:{RESET} {}\n  \
+      - {title}Duration:{RESET} {}\n  \
+      - {title}Samples:{RESET} {}",
+      self.id,
+      self.handler,
+      self.handler_name,
+      self.language,
+      self.timescale,
+      self.duration(),
+      self.sample_count,
+    )?;
+    for entry in &*self.sample_entries {
+      write!(f, "\n  - {title}Sample Entry:{RESET} {entry}")?;
+    }
+    for reference in &*self.references {
+      write!(
+        f,
+        "\n  - {title}Reference:{RESET} {}",
+        reference.reference_type
+      )?;
+      for id in &*reference.track_ids {
+        write!(f, " #{id}")?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Summary of a `stsd` sample description
+#[derive(Debug)]
+pub struct SampleEntry {
+  pub format: Str<4>,
+  pub details: SampleEntryDetails,
+}
+
+#[derive(Debug)]
+pub enum SampleEntryDetails {
+  Video {
+    width: u16,
+    height: u16,
+    depth: i16,
+    compressor_name: Box<str>,
+    /// Profile, tier, level and colour description from the decoder configuration
+    profile: Option<Box<str>>,
+  },
+  Audio {
+    number_of_channels: u32,
+    sample_size: u16,
+    sample_rate: f64,
+    /// AAC profile from the AudioSpecificConfig
+    profile: Option<&'static str>,
+  },
+  Text {
+    font_name: Option<Box<str>>,
+  },
+  Timecode {
+    frame_rate: f64,
+    number_of_frames: u8,
+    drop_frame: bool,
+  },
+  Unknown,
+}
+
+impl From<&StsdCodec> for SampleEntry {
+  fn from(codec: &StsdCodec) -> Self {
+    Self {
+      format: codec.data_format,
+      details: match &codec.data {
+        CodecData::Avc1(avc1) => SampleEntryDetails::Video {
+          width: avc1.width,
+          height: avc1.height,
+          depth: avc1.depth,
+          compressor_name: avc1.compressor_name.clone(),
+          profile: None,
+        },
+        CodecData::Hvc1(hvc1) => SampleEntryDetails::Video {
+          width: hvc1.width,
+          height: hvc1.height,
+          depth: hvc1.depth,
+          compressor_name: hvc1.compressor_name.clone(),
+          profile: Some(hvc1.hvcc.description().into()),
+        },
+        CodecData::Av01(av01) => SampleEntryDetails::Video {
+          width: av01.width,
+          height: av01.height,
+          depth: av01.depth,
+          compressor_name: av01.compressor_name.clone(),
+          profile: Some(av01.av1c.description().into()),
+        },
+        CodecData::Vp09(vp09) => SampleEntryDetails::Video {
+          width: vp09.width,
+          height: vp09.height,
+          depth: vp09.depth,
+          compressor_name: vp09.compressor_name.clone(),
+          profile: Some(vp09.vpcc.description().into()),
+        },
+        CodecData::Mp4a(mp4a) => SampleEntryDetails::Audio {
+          number_of_channels: mp4a.output_channels(),
+          sample_size: mp4a.sample_size,
+          sample_rate: mp4a.output_sample_rate(),
+          profile: mp4a.audio_specific_config().map(|config| config.profile()),
+        },
+        CodecData::Pcm(pcm) => SampleEntryDetails::Audio {
+          number_of_channels: pcm.sound.output_channels(),
+          sample_size: pcm.format.bits_per_sample,
+          sample_rate: pcm.sound.output_sample_rate(),
+          profile: None,
+        },
+        CodecData::Tx3g(tx3g) => SampleEntryDetails::Text {
+          font_name: tx3g.font_name().map(Box::from),
+        },
+        CodecData::Text(text) => SampleEntryDetails::Text {
+          font_name: (!text.font_name.is_empty()).then(|| text.font_name.clone()),
+        },
+        CodecData::Tmcd(tmcd) => SampleEntryDetails::Timecode {
+          frame_rate: tmcd.frame_rate(),
+          number_of_frames: tmcd.number_of_frames,
+          drop_frame: tmcd.is_drop_frame(),
+        },
+        CodecData::Unknown(_) => SampleEntryDetails::Unknown,
+      },
+    }
+  }
+}
+
+impl fmt::Display for SampleEntry {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.format)?;
+    match &self.details {
+      SampleEntryDetails::Video {
+        width,
+        height,
+        depth,
+        compressor_name,
+        profile,
+      } => {
+        write!(f, " {width}x{height} {depth}-bit \"{compressor_name}\"")?;
+        if let Some(profile) = profile {
+          write!(f, " ({profile})")?;
+        }
+        Ok(())
+      }
+      SampleEntryDetails::Audio {
+        number_of_channels,
+        sample_size,
+        sample_rate,
+        profile,
+      } => {
+        if let Some(profile) = profile {
+          write!(f, " {profile}")?;
+        }
+        write!(
+          f,
+          " {number_of_channels} channels {sample_size}-bit {sample_rate} Hz"
+        )
+      }
+      SampleEntryDetails::Text {
+        font_name: Some(font_name),
+      } => write!(f, " \"{font_name}\""),
+      SampleEntryDetails::Timecode {
+        frame_rate,
+        number_of_frames,
+        drop_frame,
+      } => {
+        write!(f, " {frame_rate:.3} fps {number_of_frames} frames")?;
+        if *drop_frame {
+          write!(f, " drop-frame")?;
+        }
+        Ok(())
+      }
+      SampleEntryDetails::Text { font_name: None } | SampleEntryDetails::Unknown => Ok(()),
+    }
+  }
+}
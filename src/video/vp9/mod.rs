@@ -0,0 +1,322 @@
+use crate::byte::BitStream;
+use crate::log;
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Vp9Error {
+  #[error("VP9 frame_marker is not 2")]
+  FrameMarker,
+  #[error("Invalid VP9 frame_sync_code")]
+  SyncCode,
+  #[error("Invalid VP9 {0}")]
+  Invalid(&'static str),
+}
+
+pub type Vp9Result<T = ()> = Result<T, Vp9Error>;
+
+/// VP9 color_config
+#[derive(Debug, Clone, Copy)]
+pub struct Vp9ColorConfig {
+  /// BitDepth
+  pub bit_depth: u8,
+  pub color_space: u8,
+  pub color_range: bool,
+  pub subsampling_x: bool,
+  pub subsampling_y: bool,
+}
+
+impl Vp9ColorConfig {
+  /// CS_BT_601
+  pub const CS_BT_601: u8 = 1;
+  /// CS_RGB
+  pub const CS_RGB: u8 = 7;
+
+  fn decode(data: &mut BitStream, profile: u8) -> Vp9Result<Self> {
+    let bit_depth = match profile {
+      0 | 1 => 8,
+      _ if data.bit_flag() => 12,
+      _ => 10,
+    };
+    let color_space = data.bits_into(3);
+    let odd_profile = profile & 1 != 0;
+    let (color_range, subsampling_x, subsampling_y) = if color_space != Self::CS_RGB {
+      let color_range = data.bit_flag();
+      if odd_profile {
+        let subsampling = (data.bit_flag(), data.bit_flag());
+        Self::reserved_zero(data)?;
+        (color_range, subsampling.0, subsampling.1)
+      } else {
+        (color_range, true, true)
+      }
+    } else {
+      if odd_profile {
+        Self::reserved_zero(data)?;
+      }
+      (true, false, false)
+    };
+    Ok(Self {
+      bit_depth,
+      color_space,
+      color_range,
+      subsampling_x,
+      subsampling_y,
+    })
+  }
+
+  fn reserved_zero(data: &mut BitStream) -> Vp9Result {
+    if data.bit_flag() {
+      return Err(Vp9Error::Invalid("reserved_zero"));
+    }
+    Ok(())
+  }
+
+  pub fn color_space_name(&self) -> &'static str {
+    match self.color_space {
+      1 => "BT.601",
+      2 => "BT.709",
+      3 => "SMPTE 170",
+      4 => "SMPTE 240",
+      5 => "BT.2020",
+      7 => "sRGB",
+      _ => "Unknown",
+    }
+  }
+
+  /// Chroma format of the subsampling flags, e.g. `4:2:0`
+  pub fn chroma_format(&self) -> &'static str {
+    match (self.subsampling_x, self.subsampling_y) {
+      (true, true) => "4:2:0",
+      (true, false) => "4:2:2",
+      (false, true) => "4:4:0",
+      (false, false) => "4:4:4",
+    }
+  }
+}
+
+/// VP9 uncompressed_header up to the frame size
+#[derive(Debug)]
+pub struct UncompressedHeader {
+  pub show_existing_frame: bool,
+  pub frame_to_show_map_idx: u8,
+  /// frame_type is KEY_FRAME
+  pub key_frame: bool,
+  pub show_frame: bool,
+  pub error_resilient_mode: bool,
+  pub intra_only: bool,
+  pub reset_frame_context: u8,
+  /// Colour configuration of key frames and of intra only frames, profile 0 intra only frames
+  /// use 8-bit BT.601 4:2:0
+  pub color_config: Option<Vp9ColorConfig>,
+  pub refresh_frame_flags: u8,
+  /// FrameWidth and FrameHeight, `None` when they are copied from an unknown reference frame
+  pub frame_size: Option<(u16, u16)>,
+  /// Render width and height, the frame size unless they differ
+  pub render_size: Option<(u16, u16)>,
+}
+
+impl UncompressedHeader {
+  /// frame_sync_code
+  const SYNC_CODE: u32 = 0x498342;
+
+  /// Frame size shown to the viewer
+  pub fn display_size(&self) -> Option<(u16, u16)> {
+    self.render_size.or(self.frame_size)
+  }
+}
+
+impl fmt::Display for UncompressedHeader {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match (self.key_frame, self.intra_only) {
+      (true, _) => "KEY_FRAME",
+      (_, true) => "INTRA_ONLY_FRAME",
+      _ => "NON_KEY_FRAME",
+    })?;
+    if let Some((width, height)) = self.display_size() {
+      write!(f, " {width}x{height}")?;
+    }
+    if let Some(color_config) = &self.color_config {
+      write!(
+        f,
+        " {}-bit {} {} {} range",
+        color_config.bit_depth,
+        color_config.color_space_name(),
+        color_config.chroma_format(),
+        if color_config.color_range {
+          "full"
+        } else {
+          "limited"
+        }
+      )?;
+    }
+    if self.show_existing_frame {
+      write!(f, " (shows frame {})", self.frame_to_show_map_idx)
+    } else if !self.show_frame {
+      write!(f, " (hidden)")
+    } else {
+      Ok(())
+    }
+  }
+}
+
+/// VP9 uncompressed header parser of every sample. Frames are not decoded, only the sizes of
+/// the reference frames are tracked
+#[derive(Debug, Default)]
+pub struct Vp9Parser {
+  /// RefFrameWidth and RefFrameHeight of every slot
+  ref_frame_size: [Option<(u16, u16)>; 8],
+}
+
+impl Vp9Parser {
+  /// REFS_PER_FRAME
+  const REFS_PER_FRAME: usize = 3;
+
+  /// Parses the headers of the frames of a sample, a superframe holds several of them
+  pub fn parse_sample(&mut self, data: &[u8]) -> Vp9Result<Vec<UncompressedHeader>> {
+    superframe(data)
+      .into_iter()
+      .map(|frame| {
+        let header = self.uncompressed_header(&mut BitStream::rbsp(frame))?;
+        log!(File@"VP9 {header:#?}");
+        Ok(header)
+      })
+      .collect()
+  }
+
+  /// uncompressed_header, stops once the frame size is known and updates the sizes of the
+  /// reference frames it refreshes
+  fn uncompressed_header(&mut self, data: &mut BitStream) -> Vp9Result<UncompressedHeader> {
+    if data.bits_into::<u8>(2) != 2 {
+      return Err(Vp9Error::FrameMarker);
+    }
+    let profile_low_bit = data.bit();
+    let profile = (data.bit() << 1) | profile_low_bit;
+    if profile == 3 && data.bit_flag() {
+      return Err(Vp9Error::Invalid("reserved_zero"));
+    }
+    let mut header = UncompressedHeader {
+      show_existing_frame: data.bit_flag(),
+      frame_to_show_map_idx: 0,
+      key_frame: false,
+      show_frame: true,
+      error_resilient_mode: false,
+      intra_only: false,
+      reset_frame_context: 0,
+      color_config: None,
+      refresh_frame_flags: 0,
+      frame_size: None,
+      render_size: None,
+    };
+    if header.show_existing_frame {
+      header.frame_to_show_map_idx = data.bits_into::<u8>(3);
+      header.frame_size = self.ref_frame_size[header.frame_to_show_map_idx as usize];
+      return Ok(header);
+    }
+    header.key_frame = !data.bit_flag();
+    header.show_frame = data.bit_flag();
+    header.error_resilient_mode = data.bit_flag();
+    if header.key_frame {
+      Self::frame_sync_code(data)?;
+      header.color_config = Some(Vp9ColorConfig::decode(data, profile)?);
+      header.refresh_frame_flags = 0xFF;
+      header.frame_size = Some(Self::frame_size(data));
+      header.render_size = Self::render_size(data);
+    } else {
+      header.intra_only = !header.show_frame && data.bit_flag();
+      if !header.error_resilient_mode {
+        header.reset_frame_context = data.bits_into::<u8>(2);
+      }
+      if header.intra_only {
+        Self::frame_sync_code(data)?;
+        header.color_config = Some(if profile > 0 {
+          Vp9ColorConfig::decode(data, profile)?
+        } else {
+          Vp9ColorConfig {
+            bit_depth: 8,
+            color_space: Vp9ColorConfig::CS_BT_601,
+            color_range: false,
+            subsampling_x: true,
+            subsampling_y: true,
+          }
+        });
+        header.refresh_frame_flags = data.byte();
+        header.frame_size = Some(Self::frame_size(data));
+        header.render_size = Self::render_size(data);
+      } else {
+        header.refresh_frame_flags = data.byte();
+        let mut ref_frame_idx = [0; Self::REFS_PER_FRAME];
+        for idx in &mut ref_frame_idx {
+          *idx = data.bits_into::<u8>(3);
+          // ref_frame_sign_bias
+          data.bit();
+        }
+        // frame_size_with_refs
+        let found_ref = ref_frame_idx.iter().find(|_| data.bit_flag());
+        header.frame_size = match found_ref {
+          Some(&idx) => self.ref_frame_size[idx as usize],
+          None => Some(Self::frame_size(data)),
+        };
+        header.render_size = Self::render_size(data);
+      }
+    }
+    for (i, slot) in self.ref_frame_size.iter_mut().enumerate() {
+      if header.refresh_frame_flags & (1 << i) != 0 {
+        *slot = header.frame_size;
+      }
+    }
+    Ok(header)
+  }
+
+  fn frame_sync_code(data: &mut BitStream) -> Vp9Result {
+    if data.bits_into::<u32>(24) != UncompressedHeader::SYNC_CODE {
+      return Err(Vp9Error::SyncCode);
+    }
+    Ok(())
+  }
+
+  /// frame_size, FrameWidth and FrameHeight
+  fn frame_size(data: &mut BitStream) -> (u16, u16) {
+    let width = data.bits_into::<u16>(16) + 1;
+    (width, data.bits_into::<u16>(16) + 1)
+  }
+
+  /// render_size, `None` when it is the frame size
+  fn render_size(data: &mut BitStream) -> Option<(u16, u16)> {
+    data.bit_flag().then(|| Self::frame_size(data))
+  }
+}
+
+/// Annex B Splits a superframe into its frames using the superframe index at its end, a
+/// sample without an index holds a single frame
+pub fn superframe(data: &[u8]) -> Vec<&[u8]> {
+  let Some(&marker) = data.last() else {
+    return Vec::new();
+  };
+  if marker & 0xE0 != 0xC0 {
+    return vec![data];
+  }
+  let bytes_per_framesize = ((marker >> 3) & 0b11) as usize + 1;
+  let frames = (marker & 0b111) as usize + 1;
+  let index_size = 2 + bytes_per_framesize * frames;
+  let Some(index_start) = data.len().checked_sub(index_size) else {
+    return vec![data];
+  };
+  if data[index_start] != marker {
+    return vec![data];
+  }
+  let mut offset = 0;
+  data[index_start + 1..data.len() - 1]
+    .chunks_exact(bytes_per_framesize)
+    .map_while(|size| {
+      let size = size
+        .iter()
+        .rev()
+        .fold(0, |size, byte| (size << 8) | *byte as usize);
+      let frame = data[..index_start].get(offset..offset + size)?;
+      offset += size;
+      Some(frame)
+    })
+    // Empty frames have no header to parse
+    .filter(|frame| !frame.is_empty())
+    .collect()
+}